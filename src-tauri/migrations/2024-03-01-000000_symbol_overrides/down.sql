@@ -0,0 +1 @@
+DROP TABLE "symbol_overrides";
@@ -0,0 +1,12 @@
+-- CreateTable
+CREATE TABLE "symbol_overrides" (
+    "id" TEXT NOT NULL PRIMARY KEY,
+    "symbol" TEXT NOT NULL,
+    "account_id" TEXT,
+    "provider_symbol" TEXT NOT NULL,
+    "created_at" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT "symbol_overrides_account_id_fkey" FOREIGN KEY ("account_id") REFERENCES "accounts" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateIndex
+CREATE INDEX "symbol_overrides_symbol_idx" ON "symbol_overrides"("symbol");
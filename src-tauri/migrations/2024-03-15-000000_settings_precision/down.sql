@@ -0,0 +1,2 @@
+ALTER TABLE settings DROP COLUMN price_precision;
+ALTER TABLE settings DROP COLUMN quantity_precision;
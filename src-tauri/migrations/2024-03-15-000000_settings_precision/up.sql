@@ -0,0 +1,2 @@
+ALTER TABLE settings ADD COLUMN quantity_precision INTEGER NOT NULL DEFAULT 8;
+ALTER TABLE settings ADD COLUMN price_precision INTEGER NOT NULL DEFAULT 2;
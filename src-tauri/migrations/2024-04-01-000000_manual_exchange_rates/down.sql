@@ -0,0 +1 @@
+DROP TABLE "manual_exchange_rates";
@@ -0,0 +1,13 @@
+-- CreateTable
+CREATE TABLE "manual_exchange_rates" (
+    "id" TEXT NOT NULL PRIMARY KEY,
+    "from_currency" TEXT NOT NULL,
+    "to_currency" TEXT NOT NULL,
+    "rate" NUMERIC NOT NULL,
+    "start_date" DATE NOT NULL,
+    "end_date" DATE NOT NULL,
+    "created_at" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- CreateIndex
+CREATE INDEX "manual_exchange_rates_currencies_idx" ON "manual_exchange_rates"("from_currency", "to_currency");
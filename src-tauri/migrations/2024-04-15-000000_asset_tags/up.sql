@@ -0,0 +1,10 @@
+-- CreateTable
+CREATE TABLE "asset_tags" (
+    "asset_id" TEXT NOT NULL,
+    "tag" TEXT NOT NULL,
+    PRIMARY KEY ("asset_id", "tag"),
+    CONSTRAINT "asset_tags_asset_id_fkey" FOREIGN KEY ("asset_id") REFERENCES "assets" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateIndex
+CREATE INDEX "asset_tags_tag_idx" ON "asset_tags"("tag");
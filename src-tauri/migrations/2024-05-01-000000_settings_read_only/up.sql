@@ -0,0 +1 @@
+ALTER TABLE settings ADD COLUMN read_only BOOLEAN NOT NULL DEFAULT 0;
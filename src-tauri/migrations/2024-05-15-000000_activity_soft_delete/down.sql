@@ -0,0 +1,3 @@
+ALTER TABLE settings DROP COLUMN activity_undo_days;
+DELETE FROM activities WHERE deleted_at IS NOT NULL;
+ALTER TABLE activities DROP COLUMN deleted_at;
@@ -0,0 +1,2 @@
+ALTER TABLE activities ADD COLUMN deleted_at TIMESTAMP;
+ALTER TABLE settings ADD COLUMN activity_undo_days INTEGER NOT NULL DEFAULT 7;
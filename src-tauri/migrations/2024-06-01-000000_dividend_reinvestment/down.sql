@@ -0,0 +1,2 @@
+ALTER TABLE activities DROP COLUMN source_activity_id;
+DROP TABLE drip_settings;
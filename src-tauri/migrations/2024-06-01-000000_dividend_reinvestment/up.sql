@@ -0,0 +1,11 @@
+-- CreateTable
+CREATE TABLE "drip_settings" (
+    "account_id" TEXT NOT NULL,
+    "asset_id" TEXT NOT NULL,
+    PRIMARY KEY ("account_id", "asset_id"),
+    CONSTRAINT "drip_settings_account_id_fkey" FOREIGN KEY ("account_id") REFERENCES "accounts" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "drip_settings_asset_id_fkey" FOREIGN KEY ("asset_id") REFERENCES "assets" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- The dividend a reinvestment buy was generated from
+ALTER TABLE activities ADD COLUMN source_activity_id TEXT;
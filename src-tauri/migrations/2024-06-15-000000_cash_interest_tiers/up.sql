@@ -0,0 +1,11 @@
+-- CreateTable
+CREATE TABLE "cash_interest_tiers" (
+    "id" TEXT NOT NULL PRIMARY KEY,
+    "account_id" TEXT NOT NULL,
+    "min_balance" REAL NOT NULL DEFAULT 0,
+    "annual_rate" REAL NOT NULL,
+    CONSTRAINT "cash_interest_tiers_account_id_fkey" FOREIGN KEY ("account_id") REFERENCES "accounts" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateIndex
+CREATE INDEX "cash_interest_tiers_account_id_idx" ON "cash_interest_tiers"("account_id");
@@ -0,0 +1 @@
+DROP TABLE option_contracts;
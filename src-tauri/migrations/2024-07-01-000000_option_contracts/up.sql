@@ -0,0 +1,13 @@
+-- CreateTable
+CREATE TABLE "option_contracts" (
+    "asset_id" TEXT NOT NULL PRIMARY KEY,
+    "underlying_asset_id" TEXT NOT NULL,
+    "option_type" TEXT NOT NULL,
+    "strike_price" REAL NOT NULL,
+    "expiration_date" DATE NOT NULL,
+    CONSTRAINT "option_contracts_asset_id_fkey" FOREIGN KEY ("asset_id") REFERENCES "assets" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "option_contracts_underlying_asset_id_fkey" FOREIGN KEY ("underlying_asset_id") REFERENCES "assets" ("id") ON DELETE RESTRICT ON UPDATE CASCADE
+);
+
+-- CreateIndex
+CREATE INDEX "option_contracts_underlying_asset_id_idx" ON "option_contracts"("underlying_asset_id");
@@ -0,0 +1,8 @@
+-- CreateTable
+CREATE TABLE "import_mappings" (
+    "account_id" TEXT NOT NULL PRIMARY KEY,
+    "decimal_separator" TEXT NOT NULL,
+    "thousands_separator" TEXT NOT NULL,
+    "date_format" TEXT NOT NULL,
+    CONSTRAINT "import_mappings_account_id_fkey" FOREIGN KEY ("account_id") REFERENCES "accounts" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
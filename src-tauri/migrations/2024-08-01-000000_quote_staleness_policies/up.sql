@@ -0,0 +1,5 @@
+-- CreateTable
+CREATE TABLE "quote_staleness_policies" (
+    "asset_type" TEXT NOT NULL PRIMARY KEY,
+    "max_age_days" INTEGER NOT NULL
+);
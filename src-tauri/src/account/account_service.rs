@@ -1,7 +1,7 @@
 use crate::account::AccountRepository;
 use crate::asset::asset_service::AssetService;
 use crate::models::{Account, AccountUpdate, NewAccount};
-use crate::settings::SettingsService;
+use crate::settings::{read_only_mode, SettingsService};
 use diesel::prelude::*;
 use diesel::SqliteConnection;
 
@@ -39,6 +39,7 @@ impl AccountService {
         conn: &mut SqliteConnection,
         new_account: NewAccount,
     ) -> Result<Account, diesel::result::Error> {
+        read_only_mode::ensure_writable()?;
         //get base currency
         let settings_service = SettingsService::new();
         let settings = settings_service.get_settings(conn)?;
@@ -92,6 +93,7 @@ impl AccountService {
         conn: &mut SqliteConnection,
         updated_account_data: AccountUpdate,
     ) -> Result<Account, diesel::result::Error> {
+        read_only_mode::ensure_writable()?;
         self.account_repo.update_account(conn, updated_account_data)
     }
 
@@ -100,6 +102,7 @@ impl AccountService {
         conn: &mut SqliteConnection,
         account_id_to_delete: String, // ID of the account to delete
     ) -> Result<usize, diesel::result::Error> {
+        read_only_mode::ensure_writable()?;
         self.account_repo.delete_account(conn, account_id_to_delete)
     }
 }
@@ -1,9 +1,13 @@
+use crate::activity::activity_import::ImportPreset;
 use crate::activity::activity_service;
 use crate::models::{
-    Activity, ActivityImport, ActivitySearchResponse, ActivityUpdate, NewActivity, Sort,
+    ActivitiesChanged, Activity, ActivityBulkFilter, ActivityBulkUpdate, ActivityExportFilter,
+    ActivityImportPreview, ActivitySearchFilter, ActivitySearchResponse, ActivityUpdate,
+    DripSetting, ImportActivitiesResult, ImportDryRunReport, ImportLocale, ImportProgress,
+    ImportSelection, NewActivity, Sort,
 };
 use crate::AppState;
-use tauri::State;
+use tauri::{AppHandle, Manager, State};
 
 // #[tauri::command]
 // pub fn get_activities(state: State<AppState>) -> Result<Vec<ActivityDetails>, String> {
@@ -17,12 +21,15 @@ use tauri::State;
 // }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub fn search_activities(
     page: i64,                                 // Page number, 1-based
     page_size: i64,                            // Number of items per page
     account_id_filter: Option<Vec<String>>,    // Optional account_id filter
     activity_type_filter: Option<Vec<String>>, // Optional activity_type filter
     asset_id_keyword: Option<String>,          // Optional asset_id keyword for search
+    tag_filter: Option<Vec<String>>,           // Optional asset tag filter
+    filter: Option<ActivitySearchFilter>,      // Optional date, amount and comment filters
     sort: Option<Sort>,
     state: State<AppState>,
 ) -> Result<ActivitySearchResponse, String> {
@@ -38,6 +45,8 @@ pub fn search_activities(
             account_id_filter,
             activity_type_filter,
             asset_id_keyword,
+            tag_filter,
+            filter,
             sort,
         )
         .map_err(|e| format!("Seach activities: {}", e))
@@ -60,8 +69,10 @@ pub fn create_activity(activity: NewActivity, state: State<AppState>) -> Result<
 pub fn check_activities_import(
     account_id: String,
     file_path: String,
+    preset: Option<ImportPreset>,
+    locale: Option<ImportLocale>,
     state: State<AppState>,
-) -> Result<Vec<ActivityImport>, String> {
+) -> Result<ActivityImportPreview, String> {
     println!(
         "Checking activities import...: {}, {}",
         account_id, file_path
@@ -71,7 +82,7 @@ pub fn check_activities_import(
         let mut conn = state.conn.lock().unwrap();
         let service = activity_service::ActivityService::new();
         service
-            .check_activities_import(&mut *conn, account_id, file_path)
+            .check_activities_import(&mut *conn, account_id, file_path, preset, locale)
             .await
     });
 
@@ -79,16 +90,101 @@ pub fn check_activities_import(
 }
 
 #[tauri::command]
-pub fn create_activities(
-    activities: Vec<NewActivity>,
+pub fn dry_run_activities_import(
+    account_id: String,
+    file_path: String,
+    preset: Option<ImportPreset>,
+    locale: Option<ImportLocale>,
     state: State<AppState>,
+) -> Result<ImportDryRunReport, String> {
+    println!(
+        "Dry run of activities import...: {}, {}",
+        account_id, file_path
+    );
+
+    let result = tauri::async_runtime::block_on(async {
+        let mut conn = state.conn.lock().unwrap();
+        let service = activity_service::ActivityService::new();
+        service
+            .dry_run_activities_import(&mut *conn, account_id, file_path, preset, locale)
+            .await
+    });
+
+    result.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn export_activities(
+    file_path: String,
+    filter: Option<ActivityExportFilter>,
+    currency: Option<String>,
+    state: State<AppState>,
+) -> Result<usize, String> {
+    println!("Exporting activities to: {}", file_path);
+    let mut conn = state.conn.lock().unwrap();
+    let service = activity_service::ActivityService::new();
+    service
+        .export_activities(&mut conn, &file_path, filter.unwrap_or_default(), currency)
+        .map_err(|e| format!("Failed to export activities: {}", e))
+}
+
+fn emit_import_progress(app_handle: &AppHandle, processed: usize, total: usize) {
+    app_handle
+        .emit_all(
+            "ACTIVITIES_IMPORT_PROGRESS",
+            ImportProgress { processed, total },
+        )
+        .unwrap_or_else(|e| println!("Failed to emit import progress: {}", e));
+}
+
+// async so it runs off the main thread and the progress events reach the window while
+// importing. The import awaits symbol lookups while holding the connection, so it runs on
+// a blocking thread.
+#[tauri::command]
+pub async fn import_activities(
+    account_id: String,
+    file_path: String,
+    preset: Option<ImportPreset>,
+    locale: Option<ImportLocale>,
+    selection: Option<ImportSelection>,
+    app_handle: AppHandle,
+) -> Result<ImportActivitiesResult, String> {
+    println!("Importing activities from: {}", file_path);
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app_handle.state::<AppState>();
+        let mut conn = state.conn.lock().unwrap();
+        let service = activity_service::ActivityService::new();
+        let result = tauri::async_runtime::block_on(service.import_activities(
+            &mut conn,
+            account_id,
+            file_path,
+            preset,
+            locale,
+            selection.unwrap_or_default(),
+            |processed, total| emit_import_progress(&app_handle, processed, total),
+        ));
+        result
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| format!("Failed to import activities: {}", e))
+}
+
+// async so it runs off the main thread and the progress events reach the window while importing
+#[tauri::command]
+pub async fn create_activities(
+    activities: Vec<NewActivity>,
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
 ) -> Result<usize, String> {
     // Return a Result with the count or an error message
     println!("Importing activities...");
     let mut conn = state.conn.lock().unwrap();
     let service = activity_service::ActivityService::new();
     service
-        .create_activities(&mut *conn, activities)
+        .create_activities(&mut *conn, activities, |processed, total| {
+            emit_import_progress(&app_handle, processed, total)
+        })
         .map_err(|err| format!("Failed to import activities: {}", err))
         .map(|count| count) // You can directly return the count here
 }
@@ -126,6 +222,63 @@ pub fn update_activity(
 //         .map_err(|e| format!("Failed to update activity: {}", e))
 // }
 
+// A single event is sent once the whole batch is written, so the portfolio is recalculated once
+fn emit_activities_changed(app_handle: &AppHandle, changed: ActivitiesChanged) {
+    app_handle
+        .emit_all("ACTIVITIES_CHANGED", changed)
+        .unwrap_or_else(|e| println!("Failed to emit activities changed: {}", e));
+}
+
+#[tauri::command]
+pub fn delete_activities_by_filter(
+    filter: ActivityBulkFilter,
+    state: State<AppState>,
+    app_handle: AppHandle,
+) -> Result<usize, String> {
+    println!("Deleting activities by filter: {:?}", filter);
+    let mut conn = state.conn.lock().unwrap();
+    let service = activity_service::ActivityService::new();
+    let deleted = service
+        .delete_activities_by_filter(&mut conn, filter)
+        .map_err(|e| format!("Failed to delete activities: {}", e))?;
+
+    emit_activities_changed(
+        &app_handle,
+        ActivitiesChanged {
+            updated: 0,
+            deleted,
+        },
+    );
+    Ok(deleted)
+}
+
+#[tauri::command]
+pub fn update_activities_by_filter(
+    filter: ActivityBulkFilter,
+    changes: ActivityBulkUpdate,
+    state: State<AppState>,
+    app_handle: AppHandle,
+) -> Result<usize, String> {
+    println!(
+        "Updating activities by filter: {:?} with {:?}",
+        filter, changes
+    );
+    let mut conn = state.conn.lock().unwrap();
+    let service = activity_service::ActivityService::new();
+    let updated = service
+        .update_activities_by_filter(&mut conn, filter, changes)
+        .map_err(|e| format!("Failed to update activities: {}", e))?;
+
+    emit_activities_changed(
+        &app_handle,
+        ActivitiesChanged {
+            updated,
+            deleted: 0,
+        },
+    );
+    Ok(updated)
+}
+
 #[tauri::command]
 pub fn delete_activity(activity_id: String, state: State<AppState>) -> Result<usize, String> {
     println!("Deleting activity..."); // Log message
@@ -135,3 +288,56 @@ pub fn delete_activity(activity_id: String, state: State<AppState>) -> Result<us
         .delete_activity(&mut *conn, activity_id)
         .map_err(|e| format!("Failed to delete activity: {}", e))
 }
+
+#[tauri::command]
+pub fn get_import_locale(
+    account_id: String,
+    state: State<AppState>,
+) -> Result<Option<ImportLocale>, String> {
+    println!("Fetching import locale of account: {}", account_id);
+    let mut conn = state.conn.lock().unwrap();
+    let service = activity_service::ActivityService::new();
+    service
+        .get_import_locale(&mut conn, &account_id)
+        .map_err(|e| format!("Failed to load import locale: {}", e))
+}
+
+#[tauri::command]
+pub fn get_drip_settings(state: State<AppState>) -> Result<Vec<DripSetting>, String> {
+    println!("Fetching dividend reinvestment settings...");
+    let mut conn = state.conn.lock().unwrap();
+    let service = activity_service::ActivityService::new();
+    service
+        .get_drip_settings(&mut conn)
+        .map_err(|e| format!("Failed to load dividend reinvestment settings: {}", e))
+}
+
+#[tauri::command]
+pub fn set_drip_enabled(
+    drip_setting: DripSetting,
+    enabled: bool,
+    state: State<AppState>,
+) -> Result<Vec<DripSetting>, String> {
+    println!(
+        "Setting dividend reinvestment of {:?}: {}",
+        drip_setting, enabled
+    );
+    let mut conn = state.conn.lock().unwrap();
+    let service = activity_service::ActivityService::new();
+    service
+        .set_drip_enabled(&mut conn, drip_setting, enabled)
+        .map_err(|e| format!("Failed to update dividend reinvestment: {}", e))?;
+    service
+        .get_drip_settings(&mut conn)
+        .map_err(|e| format!("Failed to load dividend reinvestment settings: {}", e))
+}
+
+#[tauri::command]
+pub fn restore_activity(activity_id: String, state: State<AppState>) -> Result<Activity, String> {
+    println!("Restoring activity: {}", activity_id);
+    let mut conn = state.conn.lock().unwrap();
+    let service = activity_service::ActivityService::new();
+    service
+        .restore_activity(&mut conn, &activity_id)
+        .map_err(|e| format!("Failed to restore activity: {}", e))
+}
@@ -0,0 +1,1091 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::io::Read;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::NaiveDate;
+use csv::{ReaderBuilder, StringRecord, StringRecordsIntoIter};
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Activity, ActivityImport, DuplicateStatus, ImportLocale};
+
+// Relative difference in amount under which a matching trade is a possible duplicate
+const DUPLICATE_AMOUNT_TOLERANCE: f64 = 0.01;
+// Activities dated before this are most likely typos, e.g. 1024 for 2024. Only used for
+// an account without activities, otherwise its first activity is the earliest date.
+const EARLIEST_ACTIVITY_DATE: (i32, u32, u32) = (1970, 1, 1);
+
+const WEALTHFOLIO_HEADERS: [&str; 4] = ["date", "symbol", "activityType", "quantity"];
+// Columns of the generic layout read with the import locale
+const WEALTHFOLIO_NUMBER_HEADERS: [&str; 4] = ["quantity", "unitPrice", "fee", "accruedInterest"];
+const WEALTHFOLIO_DATE_HEADER: &str = "date";
+// Date formats tried by the locale detection, the first one reading every date wins
+const DETECTED_DATE_FORMATS: [&str; 6] = [
+    "%Y-%m-%d", "%m/%d/%Y", "%d/%m/%Y", "%d.%m.%Y", "%d-%m-%Y", "%Y/%m/%d",
+];
+const SCHWAB_HEADERS: [&str; 7] = [
+    "Date",
+    "Action",
+    "Symbol",
+    "Quantity",
+    "Price",
+    "Fees & Comm",
+    "Amount",
+];
+const DEGIRO_HEADERS: [&str; 6] = ["Date", "Product", "ISIN", "Quantity", "Price", "Order ID"];
+// Records read ahead of the others to detect the locale of a file
+const LOCALE_SAMPLE_ROWS: usize = 100;
+
+/// CSV layouts understood by the activity import
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ImportPreset {
+    Wealthfolio,
+    Schwab,
+    Degiro,
+}
+
+impl ImportPreset {
+    fn required_headers(&self) -> &'static [&'static str] {
+        match self {
+            ImportPreset::Wealthfolio => &WEALTHFOLIO_HEADERS,
+            ImportPreset::Schwab => &SCHWAB_HEADERS,
+            ImportPreset::Degiro => &DEGIRO_HEADERS,
+        }
+    }
+
+    // Whether a csv row is the header row of this preset
+    pub fn matches_headers(&self, record: &StringRecord) -> bool {
+        self.required_headers()
+            .iter()
+            .all(|header| record.iter().any(|field| field.trim() == *header))
+    }
+
+    pub fn detect(record: &StringRecord) -> Option<ImportPreset> {
+        [
+            ImportPreset::Wealthfolio,
+            ImportPreset::Schwab,
+            ImportPreset::Degiro,
+        ]
+        .into_iter()
+        .find(|preset| preset.matches_headers(record))
+    }
+
+    // Convert a csv row into the activities it describes. The locale only applies to the
+    // generic layout, the broker layouts have fixed formats.
+    pub fn parse_record(
+        &self,
+        record: &StringRecord,
+        headers: &StringRecord,
+        currency: &str,
+        locale: &ImportLocale,
+    ) -> Result<Vec<ActivityImport>, String> {
+        let activities = match self {
+            ImportPreset::Wealthfolio => {
+                let activity_import: ActivityImport = apply_locale(record, headers, locale)
+                    .deserialize(Some(headers))
+                    .map_err(|e| e.to_string())?;
+                vec![activity_import]
+            }
+            ImportPreset::Schwab => parse_schwab_record(record, headers, currency)?,
+            ImportPreset::Degiro => parse_degiro_record(record, headers, currency)?,
+        };
+
+        Ok(activities
+            .into_iter()
+            .flat_map(|activity_import| {
+                let accrued_interest = accrued_interest_activity(&activity_import);
+                std::iter::once(activity_import).chain(accrued_interest)
+            })
+            .collect())
+    }
+}
+
+pub fn validate_locale(locale: &ImportLocale) -> Result<(), String> {
+    if locale.decimal_separator != "." && locale.decimal_separator != "," {
+        return Err(format!(
+            "Unsupported decimal separator: {}",
+            locale.decimal_separator
+        ));
+    }
+    if !["", ",", ".", " ", "'"].contains(&locale.thousands_separator.as_str())
+        || locale.thousands_separator == locale.decimal_separator
+    {
+        return Err(format!(
+            "Unsupported thousands separator: {}",
+            locale.thousands_separator
+        ));
+    }
+    // The format must write and read back a whole date, without time fields
+    let date = NaiveDate::from_ymd_opt(2024, 12, 31).unwrap();
+    let mut formatted = String::new();
+    let is_date_format = !StrftimeItems::new(&locale.date_format).any(|item| item == Item::Error)
+        && write!(formatted, "{}", date.format(&locale.date_format)).is_ok()
+        && NaiveDate::parse_from_str(&formatted, &locale.date_format) == Ok(date);
+    if !is_date_format {
+        return Err(format!("Unsupported date format: {}", locale.date_format));
+    }
+    Ok(())
+}
+
+fn parse_locale_number(value: &str, locale: &ImportLocale) -> Option<f64> {
+    let mut value = value.trim().to_string();
+    if !locale.thousands_separator.is_empty() {
+        value = value.replace(&locale.thousands_separator, "");
+    }
+    value
+        .replace(&locale.decimal_separator, ".")
+        .parse::<f64>()
+        .ok()
+}
+
+// Rewrite the number and date fields of a generic row in the formats it is deserialized
+// from. Fields the locale cannot read are kept, e.g. dates with a time.
+fn apply_locale(
+    record: &StringRecord,
+    headers: &StringRecord,
+    locale: &ImportLocale,
+) -> StringRecord {
+    record
+        .iter()
+        .zip(headers.iter().map(str::trim))
+        .map(|(field, header)| {
+            let normalized = if WEALTHFOLIO_NUMBER_HEADERS.contains(&header) {
+                parse_locale_number(field, locale).map(|number| number.to_string())
+            } else if header == WEALTHFOLIO_DATE_HEADER {
+                NaiveDate::parse_from_str(field.trim(), &locale.date_format)
+                    .ok()
+                    .map(|date| date.format("%Y-%m-%d").to_string())
+            } else {
+                None
+            };
+            normalized.unwrap_or_else(|| field.to_string())
+        })
+        .collect()
+}
+
+// Best effort guess of the locale of generic rows. A comma is the decimal separator when
+// some number can only be read that way and none contradicts it, e.g. "1.234,56" or "0,5".
+// Day first dates are preferred over month first ones with a decimal comma.
+pub fn detect_locale(records: &[StringRecord], headers: &StringRecord) -> ImportLocale {
+    let column = |name: &str| headers.iter().position(|header| header.trim() == name);
+    let values = |index: Option<usize>| -> Vec<&str> {
+        index
+            .map(|index| {
+                records
+                    .iter()
+                    .filter_map(|record| record.get(index))
+                    .map(str::trim)
+                    .filter(|value| !value.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let numbers: Vec<&str> = WEALTHFOLIO_NUMBER_HEADERS
+        .iter()
+        .flat_map(|header| values(column(header)))
+        .collect();
+    let mut has_decimal_comma = false;
+    let mut has_decimal_point = false;
+    for number in &numbers {
+        match (number.rfind(','), number.rfind('.')) {
+            (Some(comma), Some(point)) if comma > point => has_decimal_comma = true,
+            (Some(_), Some(_)) => has_decimal_point = true,
+            // A single separator followed by three digits may be a thousands one
+            (Some(comma), None) if number.len() - comma - 1 != 3 => has_decimal_comma = true,
+            (None, Some(point)) if number.len() - point - 1 != 3 => has_decimal_point = true,
+            _ => {}
+        }
+    }
+    let is_decimal_comma = has_decimal_comma && !has_decimal_point;
+
+    let dates = values(column(WEALTHFOLIO_DATE_HEADER));
+    let mut date_formats: Vec<&str> = DETECTED_DATE_FORMATS.to_vec();
+    if is_decimal_comma {
+        date_formats.sort_by_key(|format| !format.starts_with("%d"));
+    }
+    let date_format = match date_formats.into_iter().find(|format| {
+        dates
+            .iter()
+            .all(|date| NaiveDate::parse_from_str(date, format).is_ok())
+    }) {
+        Some(format) if !dates.is_empty() => format.to_string(),
+        _ => ImportLocale::default().date_format,
+    };
+
+    if is_decimal_comma {
+        ImportLocale {
+            decimal_separator: ",".to_string(),
+            thousands_separator: ".".to_string(),
+            date_format,
+        }
+    } else {
+        ImportLocale {
+            date_format,
+            ..Default::default()
+        }
+    }
+}
+
+// Accrued interest paid on a bond purchase is a cash cost, received on a sale it is income,
+// so it is booked as its own cash activity instead of being added to the trade price
+fn accrued_interest_activity(activity_import: &ActivityImport) -> Option<ActivityImport> {
+    let accrued_interest = activity_import
+        .accrued_interest
+        .filter(|a| *a != 0.0)?
+        .abs();
+    let (activity_type, quantity, fee) = match activity_import.activity_type.as_str() {
+        "BUY" => ("FEE", 0.0, accrued_interest),
+        "SELL" => ("INTEREST", accrued_interest, 0.0),
+        _ => return None,
+    };
+
+    Some(ActivityImport {
+        date: activity_import.date.clone(),
+        symbol: format!("$CASH-{}", activity_import.currency),
+        activity_type: activity_type.to_string(),
+        quantity,
+        unit_price: 1.0,
+        currency: activity_import.currency.clone(),
+        fee,
+        comment: Some(format!("Accrued interest on {}", activity_import.symbol)),
+        ..Default::default()
+    })
+}
+
+fn get_field<'a>(record: &'a StringRecord, headers: &StringRecord, name: &str) -> &'a str {
+    headers
+        .iter()
+        .position(|header| header.trim() == name)
+        .and_then(|index| record.get(index))
+        .map(str::trim)
+        .unwrap_or("")
+}
+
+// Field of the unnamed column right after `name`, where DEGIRO puts the currency of an amount
+fn get_next_field<'a>(record: &'a StringRecord, headers: &StringRecord, name: &str) -> &'a str {
+    headers
+        .iter()
+        .position(|header| header.trim() == name)
+        .and_then(|index| record.get(index + 1))
+        .map(str::trim)
+        .unwrap_or("")
+}
+
+// Parse broker amounts such as "$1,234.56", "-$4.95" or "($4.95)"
+fn parse_amount(value: &str) -> Option<f64> {
+    let is_negative = value.starts_with('-') || value.starts_with('(');
+    let digits: String = value
+        .chars()
+        .filter(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+
+    digits
+        .parse::<f64>()
+        .ok()
+        .map(|amount| if is_negative { -amount } else { amount })
+}
+
+// Schwab dates may carry a settlement note, e.g. "01/15/2024 as of 01/12/2024"
+fn parse_schwab_date(value: &str) -> Result<String, String> {
+    let date = value.split_whitespace().next().unwrap_or("");
+    NaiveDate::parse_from_str(date, "%m/%d/%Y")
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .map_err(|_| format!("Invalid date: {}", value))
+}
+
+fn parse_schwab_record(
+    record: &StringRecord,
+    headers: &StringRecord,
+    currency: &str,
+) -> Result<Vec<ActivityImport>, String> {
+    let action = get_field(record, headers, "Action");
+
+    // The totals row at the end of the export has no action
+    if action.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let date = parse_schwab_date(get_field(record, headers, "Date"))?;
+    let symbol = get_field(record, headers, "Symbol");
+    let description = get_field(record, headers, "Description");
+    let quantity = parse_amount(get_field(record, headers, "Quantity"))
+        .unwrap_or(0.0)
+        .abs();
+    let price = parse_amount(get_field(record, headers, "Price")).unwrap_or(0.0);
+    let fee = parse_amount(get_field(record, headers, "Fees & Comm"))
+        .unwrap_or(0.0)
+        .abs();
+    let amount = parse_amount(get_field(record, headers, "Amount")).unwrap_or(0.0);
+    let cash_symbol = format!("$CASH-{}", currency);
+
+    let activity = |activity_type: &str, symbol: &str, quantity, unit_price, fee| ActivityImport {
+        date: date.clone(),
+        symbol: symbol.to_string(),
+        activity_type: activity_type.to_string(),
+        quantity,
+        unit_price,
+        currency: currency.to_string(),
+        fee,
+        comment: (!description.is_empty()).then(|| description.to_string()),
+        ..Default::default()
+    };
+
+    let activities = match action {
+        "Buy" | "Reinvest Shares" => vec![activity("BUY", symbol, quantity, price, fee)],
+        "Sell" => vec![activity("SELL", symbol, quantity, price, fee)],
+        "Reinvest Dividend" | "Qual Div Reinvest" | "Pr Yr Div Reinvest" => {
+            let mut activities = vec![activity("DIVIDEND", symbol, amount.abs(), 1.0, 0.0)];
+            // Some exports report the reinvested shares on the dividend row itself
+            if quantity > 0.0 && price > 0.0 {
+                activities.push(activity("BUY", symbol, quantity, price, fee));
+            }
+            activities
+        }
+        "Qualified Dividend"
+        | "Cash Dividend"
+        | "Non-Qualified Div"
+        | "Special Dividend"
+        | "Special Qual Div"
+        | "Pr Yr Cash Div"
+        | "Long Term Cap Gain"
+        | "Short Term Cap Gain" => {
+            vec![activity("DIVIDEND", symbol, amount.abs(), 1.0, 0.0)]
+        }
+        "Bank Interest" | "Credit Interest" | "Bond Interest" => {
+            vec![activity("INTEREST", &cash_symbol, amount.abs(), 1.0, 0.0)]
+        }
+        "MoneyLink Transfer" | "MoneyLink Deposit" | "Wire Funds" | "Wire Received"
+        | "Wire Sent" | "Funds Received" | "Journal" => {
+            let activity_type = if amount < 0.0 {
+                "WITHDRAWAL"
+            } else {
+                "DEPOSIT"
+            };
+            vec![activity(
+                activity_type,
+                &cash_symbol,
+                amount.abs(),
+                1.0,
+                0.0,
+            )]
+        }
+        "Service Fee" | "ADR Mgmt Fee" | "Margin Interest" => {
+            vec![activity("FEE", &cash_symbol, 0.0, 1.0, amount.abs())]
+        }
+        "Foreign Tax Paid" | "NRA Tax Adj" => {
+            vec![activity("TAX", &cash_symbol, 0.0, 1.0, amount.abs())]
+        }
+        _ => return Err(format!("Unsupported Schwab action: {}", action)),
+    };
+
+    Ok(activities)
+}
+
+// Parse European amounts such as "1.234,56" or "-4,90", plain "-4.90" is accepted too
+fn parse_decimal_comma_amount(value: &str) -> Option<f64> {
+    let value = value.trim();
+    if value.contains(',') {
+        value.replace('.', "").replace(',', ".").parse::<f64>().ok()
+    } else {
+        value.parse::<f64>().ok()
+    }
+}
+
+fn parse_degiro_date(value: &str) -> Result<String, String> {
+    NaiveDate::parse_from_str(value, "%d-%m-%Y")
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .map_err(|_| format!("Invalid date: {}", value))
+}
+
+// Rows of the DEGIRO Transactions.csv export. A sale has a negative quantity, and a row
+// without quantity only carries the fee of the order it shares its id with.
+fn parse_degiro_record(
+    record: &StringRecord,
+    headers: &StringRecord,
+    currency: &str,
+) -> Result<Vec<ActivityImport>, String> {
+    let date_field = get_field(record, headers, "Date");
+    // Blank separator lines carry no date
+    if date_field.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let date = parse_degiro_date(date_field)?;
+    let product = get_field(record, headers, "Product");
+    let isin = get_field(record, headers, "ISIN");
+    let quantity =
+        parse_decimal_comma_amount(get_field(record, headers, "Quantity")).unwrap_or(0.0);
+    let price = parse_decimal_comma_amount(get_field(record, headers, "Price")).unwrap_or(0.0);
+    let order_id = Some(get_field(record, headers, "Order ID"))
+        .filter(|order_id| !order_id.is_empty())
+        .map(str::to_string);
+
+    // The fee header is cut differently across export versions, e.g. "Transaction and/or
+    // third" or "Transaction costs"
+    let fee_header = headers
+        .iter()
+        .map(str::trim)
+        .find(|header| header.starts_with("Transaction"));
+    let fee = fee_header
+        .and_then(|fee_header| parse_decimal_comma_amount(get_field(record, headers, fee_header)))
+        .unwrap_or(0.0)
+        .abs();
+    let fee_currency = fee_header
+        .map(|fee_header| get_next_field(record, headers, fee_header))
+        .filter(|fee_currency| !fee_currency.is_empty())
+        .unwrap_or(currency);
+    let trade_currency = Some(get_next_field(record, headers, "Price"))
+        .filter(|trade_currency| !trade_currency.is_empty())
+        .unwrap_or(currency);
+
+    let fee_activity = |order_id: Option<String>| ActivityImport {
+        date: date.clone(),
+        symbol: format!("$CASH-{}", fee_currency),
+        activity_type: "FEE".to_string(),
+        quantity: 0.0,
+        unit_price: 1.0,
+        currency: fee_currency.to_string(),
+        fee,
+        comment: Some(format!("Transaction fee {}", product)),
+        order_id,
+        ..Default::default()
+    };
+
+    if quantity == 0.0 {
+        if fee == 0.0 {
+            return Err(format!(
+                "Unsupported DEGIRO row without quantity or fee: {}",
+                product
+            ));
+        }
+        return Ok(vec![fee_activity(order_id)]);
+    }
+
+    // A fee in another currency than the trade is booked on its own
+    let is_fee_separate = fee != 0.0 && fee_currency != trade_currency;
+    let mut activities = vec![ActivityImport {
+        date: date.clone(),
+        symbol: isin.to_string(),
+        activity_type: if quantity < 0.0 { "SELL" } else { "BUY" }.to_string(),
+        quantity: quantity.abs(),
+        unit_price: price,
+        currency: trade_currency.to_string(),
+        fee: if is_fee_separate { 0.0 } else { fee },
+        comment: (!product.is_empty()).then(|| product.to_string()),
+        order_id,
+        ..Default::default()
+    }];
+    if is_fee_separate {
+        activities.push(fee_activity(None));
+    }
+    Ok(activities)
+}
+
+// Add the fee rows of an order to its first trade of the same currency, the ones left
+// without a trade stay cash fees
+pub fn merge_order_fees(activity_imports: Vec<ActivityImport>) -> Vec<ActivityImport> {
+    let (fee_rows, mut merged): (Vec<ActivityImport>, Vec<ActivityImport>) =
+        activity_imports.into_iter().partition(|activity_import| {
+            activity_import.activity_type == "FEE" && activity_import.order_id.is_some()
+        });
+
+    for fee_row in fee_rows {
+        let trade = merged.iter_mut().find(|activity_import| {
+            (activity_import.activity_type == "BUY" || activity_import.activity_type == "SELL")
+                && activity_import.order_id == fee_row.order_id
+                && activity_import.currency == fee_row.currency
+        });
+        match trade {
+            Some(trade) => trade.fee += fee_row.fee,
+            None => merged.push(fee_row),
+        }
+    }
+    merged
+}
+
+// Activities of a csv file parsed one record at a time, so a large file is never held in
+// memory. The fee rows of an order are merged into its trade, brokers list them on
+// consecutive rows.
+pub struct CsvActivityReader<R: Read> {
+    records: StringRecordsIntoIter<R>,
+    preset: ImportPreset,
+    headers: StringRecord,
+    locale: ImportLocale,
+    currency: String,
+    // Records read to detect the locale, parsed before the rest of the file
+    sample: VecDeque<StringRecord>,
+    // Activities of the order being read, merged once the next order starts
+    order: Vec<ActivityImport>,
+    parsed: VecDeque<ActivityImport>,
+}
+
+impl<R: Read> CsvActivityReader<R> {
+    // Without a locale, the one detected from the first rows applies
+    pub fn new(
+        reader: R,
+        preset: Option<ImportPreset>,
+        locale: Option<ImportLocale>,
+        currency: &str,
+    ) -> Result<Self, String> {
+        let mut records = ReaderBuilder::new()
+            .delimiter(b',')
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(reader)
+            .into_records();
+
+        // Brokers may put title lines above the header row, skip until it is found
+        let (preset, headers) = loop {
+            let record = records
+                .next()
+                .ok_or_else(|| "No supported header row found in the CSV file".to_string())?
+                .map_err(|e| e.to_string())?;
+
+            let detected = match preset {
+                Some(preset) if preset.matches_headers(&record) => Some(preset),
+                Some(_) => None,
+                None => ImportPreset::detect(&record),
+            };
+
+            if let Some(detected) = detected {
+                break (detected, record);
+            }
+        };
+
+        let sample = records
+            .by_ref()
+            .take(LOCALE_SAMPLE_ROWS)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        let locale = locale.unwrap_or_else(|| detect_locale(&sample, &headers));
+
+        Ok(CsvActivityReader {
+            records,
+            preset,
+            headers,
+            locale,
+            currency: currency.to_string(),
+            sample: sample.into(),
+            order: Vec::new(),
+            parsed: VecDeque::new(),
+        })
+    }
+
+    fn end_order(&mut self) {
+        let order = std::mem::take(&mut self.order);
+        self.parsed.extend(merge_order_fees(order));
+    }
+
+    #[cfg(test)]
+    fn buffered_len(&self) -> usize {
+        self.sample.len() + self.order.len() + self.parsed.len()
+    }
+}
+
+impl<R: Read> Iterator for CsvActivityReader<R> {
+    type Item = Result<ActivityImport, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(activity_import) = self.parsed.pop_front() {
+                return Some(Ok(activity_import));
+            }
+
+            let record = match self.sample.pop_front() {
+                Some(record) => record,
+                None => match self.records.next() {
+                    Some(Ok(record)) => record,
+                    Some(Err(e)) => return Some(Err(e.to_string())),
+                    None if self.order.is_empty() => return None,
+                    None => {
+                        self.end_order();
+                        continue;
+                    }
+                },
+            };
+
+            let line_number = record.position().map_or(0, |position| position.line());
+            let parsed =
+                match self
+                    .preset
+                    .parse_record(&record, &self.headers, &self.currency, &self.locale)
+                {
+                    Ok(parsed) => parsed,
+                    Err(e) => return Some(Err(format!("{}. Line: {}", e, line_number))),
+                };
+
+            for mut activity_import in parsed {
+                activity_import.line_number = Some(line_number as i32);
+                if activity_import.order_id.is_none() {
+                    self.end_order();
+                    self.parsed.push_back(activity_import);
+                    continue;
+                }
+                if self
+                    .order
+                    .first()
+                    .is_some_and(|first| first.order_id != activity_import.order_id)
+                {
+                    self.end_order();
+                }
+                self.order.push(activity_import);
+            }
+        }
+    }
+}
+
+// Earliest sensible date of a new activity of the account, the date of its first activity
+pub fn get_earliest_activity_date(existing_activities: &[Activity]) -> NaiveDate {
+    existing_activities
+        .iter()
+        .map(|activity| activity.activity_date.date())
+        .min()
+        .unwrap_or_else(|| {
+            let (year, month, day) = EARLIEST_ACTIVITY_DATE;
+            NaiveDate::from_ymd_opt(year, month, day).unwrap_or(NaiveDate::MIN)
+        })
+}
+
+// Warn about a row dated after `today` or before `earliest_date`, a wrong year would
+// otherwise silently distort the valuation history
+pub fn check_activity_date(
+    activity_import: &ActivityImport,
+    earliest_date: NaiveDate,
+    today: NaiveDate,
+) -> Option<String> {
+    let date = activity_import
+        .date
+        .get(..10)
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())?;
+
+    if date > today {
+        Some(format!("Date {} is in the future", date))
+    } else if date < earliest_date {
+        Some(format!("Date {} is before {}", date, earliest_date))
+    } else {
+        None
+    }
+}
+
+fn is_same_amount(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-9
+}
+
+// Compare an imported row against the existing activities of the account, matching on
+// date, symbol, type and quantity, then on price and fee
+pub fn find_duplicate(
+    activity_import: &ActivityImport,
+    existing_activities: &[Activity],
+) -> Option<DuplicateStatus> {
+    let import_date = activity_import
+        .date
+        .get(..10)
+        .unwrap_or(&activity_import.date);
+    let import_amount = activity_import.quantity * activity_import.unit_price + activity_import.fee;
+
+    existing_activities
+        .iter()
+        .filter(|activity| {
+            activity.activity_date.format("%Y-%m-%d").to_string() == import_date
+                && activity.asset_id == activity_import.symbol
+                && activity.activity_type == activity_import.activity_type
+                && is_same_amount(activity.quantity, activity_import.quantity)
+        })
+        .filter_map(|activity| {
+            if is_same_amount(activity.unit_price, activity_import.unit_price)
+                && is_same_amount(activity.fee, activity_import.fee)
+            {
+                return Some(DuplicateStatus::Exact);
+            }
+
+            let amount = activity.quantity * activity.unit_price + activity.fee;
+            let tolerance = DUPLICATE_AMOUNT_TOLERANCE * amount.abs().max(import_amount.abs());
+            ((amount - import_amount).abs() <= tolerance).then_some(DuplicateStatus::Possible)
+        })
+        .min_by_key(|status| match status {
+            DuplicateStatus::Exact => 0,
+            DuplicateStatus::Possible => 1,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    const GENERATED_HEADER: &str = "date,symbol,activityType,quantity,unitPrice,currency,fee\n";
+
+    // Csv of numbered buys written while it is read, the file never exists as a whole
+    struct GeneratedCsv {
+        rows: usize,
+        next_row: usize,
+        line: Vec<u8>,
+        position: usize,
+    }
+
+    impl GeneratedCsv {
+        fn new(rows: usize) -> Self {
+            GeneratedCsv {
+                rows,
+                next_row: 0,
+                line: Vec::new(),
+                position: 0,
+            }
+        }
+    }
+
+    impl Read for GeneratedCsv {
+        fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+            if self.position == self.line.len() {
+                self.line = match self.next_row {
+                    0 => GENERATED_HEADER.as_bytes().to_vec(),
+                    row if row <= self.rows => {
+                        format!("2024-01-02,AAPL,BUY,{},150.25,USD,1\n", row).into_bytes()
+                    }
+                    _ => return Ok(0),
+                };
+                self.next_row += 1;
+                self.position = 0;
+            }
+
+            let length = out.len().min(self.line.len() - self.position);
+            out[..length].copy_from_slice(&self.line[self.position..self.position + length]);
+            self.position += length;
+            Ok(length)
+        }
+    }
+
+    #[test]
+    fn reads_a_large_csv_one_record_at_a_time() {
+        let rows = 50_000;
+        let mut reader =
+            CsvActivityReader::new(GeneratedCsv::new(rows), None, None, "USD").unwrap();
+
+        let mut count = 0;
+        let mut max_buffered = 0;
+        while let Some(activity_import) = reader.next() {
+            let activity_import = activity_import.unwrap();
+            count += 1;
+            max_buffered = max_buffered.max(reader.buffered_len());
+            assert_eq!(activity_import.quantity, count as f64);
+            assert_eq!(activity_import.line_number, Some(count as i32 + 1));
+        }
+
+        assert_eq!(count, rows);
+        assert!(max_buffered <= LOCALE_SAMPLE_ROWS);
+    }
+
+    fn read_activities(content: &str, currency: &str) -> Vec<ActivityImport> {
+        CsvActivityReader::new(content.as_bytes(), None, None, currency)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn a_schwab_export_is_detected_below_its_title_line() {
+        let content = r#""Transactions  for account XXXX-1234 as of 01/31/2024"
+"Date","Action","Symbol","Description","Quantity","Price","Fees & Comm","Amount"
+"01/15/2024 as of 01/12/2024","Buy","AAPL","APPLE INC","10","$185.50","$1.00","-$1,856.00"
+"01/20/2024","Reinvest Dividend","VTI","VANGUARD TOTAL","","","","$25.00"
+"01/20/2024","Reinvest Shares","VTI","VANGUARD TOTAL","0.1","$250.00","","-$25.00"
+"01/31/2024","Service Fee","","ACCOUNT FEE","","","","($4.95)"
+"Transactions Total","","","","","","","-$1,860.95"
+"#;
+
+        let activities = read_activities(content, "USD");
+
+        assert_eq!(activities.len(), 4);
+        let buy = &activities[0];
+        assert_eq!(buy.activity_type, "BUY");
+        assert_eq!(buy.symbol, "AAPL");
+        assert_eq!(buy.date, "2024-01-15");
+        assert_eq!(buy.quantity, 10.0);
+        assert_eq!(buy.unit_price, 185.5);
+        assert_eq!(buy.fee, 1.0);
+        assert_eq!(buy.line_number, Some(3));
+        assert_eq!(activities[1].activity_type, "DIVIDEND");
+        assert_eq!(activities[1].quantity, 25.0);
+        assert_eq!(activities[2].activity_type, "BUY");
+        assert_eq!(activities[2].quantity, 0.1);
+        assert_eq!(activities[2].unit_price, 250.0);
+        let fee = &activities[3];
+        assert_eq!(fee.activity_type, "FEE");
+        assert_eq!(fee.symbol, "$CASH-USD");
+        assert_eq!(fee.fee, 4.95);
+    }
+
+    #[test]
+    fn an_unsupported_schwab_action_is_reported_with_its_line() {
+        let content = r#""Date","Action","Symbol","Description","Quantity","Price","Fees & Comm","Amount"
+"01/15/2024","Stock Split","AAPL","APPLE INC","10","","",""
+"#;
+        let mut reader = CsvActivityReader::new(content.as_bytes(), None, None, "USD").unwrap();
+
+        assert_eq!(
+            reader.next().unwrap().unwrap_err(),
+            "Unsupported Schwab action: Stock Split. Line: 2"
+        );
+    }
+
+    #[test]
+    fn degiro_fee_rows_are_merged_into_their_order() {
+        let content = r#"Date,Time,Product,ISIN,Reference,Venue,Quantity,Price,,Local value,,Value,,Exchange rate,Transaction and/or third,,Total,,Order ID
+15-01-2024,09:04,VANGUARD FTSE AW,IE00B3RBWM25,AEB,XAMS,10,"105,20",EUR,"-1052,00",EUR,"-1052,00",EUR,,"-2,00",EUR,"-1054,00",EUR,abc-1
+15-01-2024,09:04,VANGUARD FTSE AW,IE00B3RBWM25,AEB,XAMS,,,,,,,,,"-0,50",EUR,"-0,50",EUR,abc-1
+16-01-2024,15:30,APPLE INC,US0378331005,NDQ,XNAS,-5,"190,00",USD,"950,00",USD,"868,00",EUR,"1,0945","-1,00",EUR,"867,00",EUR,def-2
+"#;
+
+        let activities = read_activities(content, "EUR");
+
+        assert_eq!(activities.len(), 3);
+        let buy = &activities[0];
+        assert_eq!(buy.activity_type, "BUY");
+        assert_eq!(buy.symbol, "IE00B3RBWM25");
+        assert_eq!(buy.date, "2024-01-15");
+        assert_eq!(buy.quantity, 10.0);
+        assert_eq!(buy.unit_price, 105.2);
+        assert_eq!(buy.currency, "EUR");
+        assert_eq!(buy.fee, 2.5);
+        // The fee paid in euros of a trade in dollars is booked on its own
+        let sell = &activities[1];
+        assert_eq!(sell.activity_type, "SELL");
+        assert_eq!(sell.quantity, 5.0);
+        assert_eq!(sell.unit_price, 190.0);
+        assert_eq!(sell.currency, "USD");
+        assert_eq!(sell.fee, 0.0);
+        let fee = &activities[2];
+        assert_eq!(fee.activity_type, "FEE");
+        assert_eq!(fee.symbol, "$CASH-EUR");
+        assert_eq!(fee.fee, 1.0);
+    }
+
+    #[test]
+    fn european_amounts_are_read_with_a_decimal_comma() {
+        assert_eq!(parse_decimal_comma_amount("1.234,56"), Some(1234.56));
+        assert_eq!(parse_decimal_comma_amount("-4,90"), Some(-4.9));
+        assert_eq!(parse_decimal_comma_amount("-4.90"), Some(-4.9));
+        assert_eq!(parse_decimal_comma_amount(""), None);
+    }
+
+    fn records(rows: &[&str]) -> Vec<StringRecord> {
+        rows.iter()
+            .map(|row| StringRecord::from(row.split(';').collect::<Vec<_>>()))
+            .collect()
+    }
+
+    #[test]
+    fn a_decimal_comma_and_day_first_dates_are_detected() {
+        let headers = StringRecord::from(vec!["date", "symbol", "quantity", "unitPrice", "fee"]);
+        let records = records(&["15.01.2024;SAP;10;1.234,56;0,5", "02.02.2024;SAP;5;120;1"]);
+
+        let locale = detect_locale(&records, &headers);
+
+        assert_eq!(
+            locale,
+            ImportLocale {
+                decimal_separator: ",".to_string(),
+                thousands_separator: ".".to_string(),
+                date_format: "%d.%m.%Y".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn ambiguous_numbers_keep_the_default_separators() {
+        let headers = StringRecord::from(vec!["date", "symbol", "quantity", "unitPrice"]);
+
+        // "1,234" may be a thousand, month first dates are read as such
+        let locale = detect_locale(
+            &records(&["01/15/2024;AAPL;1,234;150", "02/01/2024;AAPL;2;150.25"]),
+            &headers,
+        );
+        assert_eq!(locale.decimal_separator, ".");
+        assert_eq!(locale.date_format, "%m/%d/%Y");
+
+        // A number contradicting the decimal comma wins
+        let locale = detect_locale(
+            &records(&["2024-01-15;AAPL;0,5;150", "2024-02-01;AAPL;1,234.50;150"]),
+            &headers,
+        );
+        assert_eq!(locale, ImportLocale::default());
+    }
+
+    #[test]
+    fn generic_rows_are_read_with_the_detected_locale() {
+        let content = "date,symbol,activityType,quantity,unitPrice,currency,fee\n\
+                       15.01.2024,SAP,BUY,10,\"1.234,56\",EUR,\"0,5\"\n";
+
+        let activities = read_activities(content, "EUR");
+
+        assert_eq!(activities[0].date, "2024-01-15");
+        assert_eq!(activities[0].unit_price, 1234.56);
+        assert_eq!(activities[0].fee, 0.5);
+    }
+
+    #[test]
+    fn unsupported_locales_are_rejected() {
+        let locale =
+            |decimal_separator: &str, thousands_separator: &str, date_format: &str| ImportLocale {
+                decimal_separator: decimal_separator.to_string(),
+                thousands_separator: thousands_separator.to_string(),
+                date_format: date_format.to_string(),
+            };
+
+        assert!(validate_locale(&locale(",", " ", "%d/%m/%Y")).is_ok());
+        assert!(validate_locale(&locale(";", ",", "%d/%m/%Y")).is_err());
+        assert!(validate_locale(&locale(",", ",", "%d/%m/%Y")).is_err());
+        assert!(validate_locale(&locale(".", ",", "%d/%m")).is_err());
+    }
+
+    #[test]
+    fn reports_the_line_of_a_malformed_record() {
+        let content = format!("{}2024-01-02,AAPL,BUY,abc,150.25,USD,1\n", GENERATED_HEADER);
+        let mut reader = CsvActivityReader::new(content.as_bytes(), None, None, "USD").unwrap();
+
+        let error = reader.next().unwrap().unwrap_err();
+        assert!(error.ends_with("Line: 2"), "{}", error);
+    }
+
+    #[test]
+    fn dates_before_the_first_activity_or_in_the_future_are_flagged() {
+        let date = |date: &str| NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+        let row = |row_date: &str| ActivityImport {
+            date: row_date.to_string(),
+            ..Default::default()
+        };
+        let today = date("2024-06-03");
+        let first_activity =
+            crate::test_utils::activity("account", "AAPL", "BUY", "2020-03-02", 1.0, 100.0);
+        let earliest_date = get_earliest_activity_date(&[first_activity]);
+
+        assert_eq!(earliest_date, date("2020-03-02"));
+        assert!(check_activity_date(&row("2022-05-10"), earliest_date, today).is_none());
+        assert!(check_activity_date(&row("2205-05-10"), earliest_date, today).is_some());
+        assert!(check_activity_date(&row("2020-02-28"), earliest_date, today).is_some());
+    }
+
+    #[test]
+    fn near_identical_trades_are_possible_duplicates() {
+        let mut existing =
+            crate::test_utils::activity("account", "AAPL", "BUY", "2024-01-02", 10.0, 150.0);
+        existing.fee = 1.0;
+        let row = |unit_price: f64, fee: f64| ActivityImport {
+            date: "2024-01-02T15:30:00.000Z".to_string(),
+            symbol: "AAPL".to_string(),
+            activity_type: "BUY".to_string(),
+            quantity: 10.0,
+            unit_price,
+            fee,
+            ..Default::default()
+        };
+        let existing = [existing];
+
+        assert_eq!(
+            find_duplicate(&row(150.0, 1.0), &existing),
+            Some(DuplicateStatus::Exact)
+        );
+        // 1501.00 against 1502.50, within the 1% tolerance
+        assert_eq!(
+            find_duplicate(&row(150.15, 1.0), &existing),
+            Some(DuplicateStatus::Possible)
+        );
+        assert_eq!(
+            find_duplicate(&row(150.0, 0.0), &existing),
+            Some(DuplicateStatus::Possible)
+        );
+        // 1501.00 against 1531.00, outside of it
+        assert_eq!(find_duplicate(&row(153.0, 1.0), &existing), None);
+    }
+
+    #[test]
+    fn only_the_same_day_symbol_type_and_quantity_are_compared() {
+        let existing = [crate::test_utils::activity(
+            "account",
+            "AAPL",
+            "BUY",
+            "2024-01-02",
+            10.0,
+            150.0,
+        )];
+        let row = |date: &str, symbol: &str, activity_type: &str, quantity: f64| ActivityImport {
+            date: date.to_string(),
+            symbol: symbol.to_string(),
+            activity_type: activity_type.to_string(),
+            quantity,
+            unit_price: 1500.0 / quantity,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            find_duplicate(&row("2024-01-02", "AAPL", "BUY", 10.0), &existing),
+            Some(DuplicateStatus::Exact)
+        );
+        assert_eq!(
+            find_duplicate(&row("2024-01-03", "AAPL", "BUY", 10.0), &existing),
+            None
+        );
+        assert_eq!(
+            find_duplicate(&row("2024-01-02", "MSFT", "BUY", 10.0), &existing),
+            None
+        );
+        assert_eq!(
+            find_duplicate(&row("2024-01-02", "AAPL", "SELL", 10.0), &existing),
+            None
+        );
+        // Same amount, but a different quantity is another trade
+        assert_eq!(
+            find_duplicate(&row("2024-01-02", "AAPL", "BUY", 12.0), &existing),
+            None
+        );
+    }
+
+    #[test]
+    fn an_account_without_activities_falls_back_to_the_fixed_floor() {
+        let today = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+        let earliest_date = get_earliest_activity_date(&[]);
+        let row = |row_date: &str| ActivityImport {
+            date: row_date.to_string(),
+            ..Default::default()
+        };
+
+        assert!(check_activity_date(&row("1998-01-05"), earliest_date, today).is_none());
+        assert!(check_activity_date(&row("1024-01-05"), earliest_date, today).is_some());
+    }
+
+    #[test]
+    fn a_bond_trade_keeps_its_nominal_and_books_the_accrued_interest_apart() {
+        let content = "date,symbol,activityType,quantity,unitPrice,currency,fee,accruedInterest
+2024-02-01,IT0005436693,BUY,3000,98.50,EUR,2,12.34
+2024-05-02,IT0005436693,SELL,1000,99.10,EUR,2,4.50
+";
+
+        let activities = read_activities(content, "EUR");
+
+        assert_eq!(activities.len(), 4);
+        let buy = &activities[0];
+        assert_eq!(buy.activity_type, "BUY");
+        assert_eq!(buy.quantity, 3000.0);
+        assert_eq!(buy.unit_price, 98.5);
+        assert_eq!(buy.fee, 2.0);
+        // The clean price is a percentage of the nominal
+        assert!((buy.quantity * buy.unit_price / 100.0 - 2955.0).abs() < 1e-9);
+        let paid_interest = &activities[1];
+        assert_eq!(paid_interest.activity_type, "FEE");
+        assert_eq!(paid_interest.symbol, "$CASH-EUR");
+        assert_eq!(paid_interest.date, "2024-02-01");
+        assert_eq!(paid_interest.fee, 12.34);
+        assert_eq!(
+            paid_interest.comment.as_deref(),
+            Some("Accrued interest on IT0005436693")
+        );
+        assert_eq!(activities[2].activity_type, "SELL");
+        let received_interest = &activities[3];
+        assert_eq!(received_interest.activity_type, "INTEREST");
+        assert_eq!(received_interest.quantity, 4.5);
+        assert_eq!(received_interest.unit_price, 1.0);
+        assert_eq!(received_interest.fee, 0.0);
+    }
+}
@@ -1,11 +1,15 @@
 use crate::{
     models::{
-        Activity, ActivityDetails, ActivitySearchResponse, ActivitySearchResponseMeta,
-        ActivityUpdate, NewActivity, Sort,
+        Activity, ActivityBulkFilter, ActivityBulkUpdate, ActivityDetails, ActivityExportFilter,
+        ActivitySearchFilter, ActivitySearchResponse, ActivitySearchResponseMeta, ActivityUpdate,
+        DripSetting, ImportMapping, NewActivity, Sort,
     },
-    schema::{accounts, activities, assets},
+    schema::{accounts, activities, asset_tags, assets, drip_settings, import_mappings},
 };
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::connection::DefaultLoadingMode;
 use diesel::prelude::*;
+use diesel::sqlite::Sqlite;
 use uuid::Uuid;
 
 pub struct ActivityRepository;
@@ -22,7 +26,14 @@ impl ActivityRepository {
         activities::table
             .inner_join(accounts::table.on(accounts::id.eq(activities::account_id)))
             .filter(accounts::is_active.eq(true))
-            .filter(activities::activity_type.eq_any(vec!["BUY", "SELL", "SPLIT"]))
+            .filter(activities::deleted_at.is_null())
+            .filter(activities::activity_type.eq_any(vec![
+                "BUY",
+                "SELL",
+                "SPLIT",
+                "SPIN_OFF_OUT",
+                "SPIN_OFF_IN",
+            ]))
             .select(activities::all_columns)
             .order(activities::activity_date.asc())
             .load::<Activity>(conn)
@@ -35,11 +46,62 @@ impl ActivityRepository {
         activities::table
             .inner_join(accounts::table.on(accounts::id.eq(activities::account_id)))
             .filter(accounts::is_active.eq(true))
+            .filter(activities::deleted_at.is_null())
             .select(activities::all_columns)
             .order(activities::activity_date.asc())
             .load::<Activity>(conn)
     }
 
+    pub fn get_account_activities(
+        &self,
+        conn: &mut SqliteConnection,
+        account_id: &str,
+    ) -> Result<Vec<Activity>, diesel::result::Error> {
+        activities::table
+            .filter(activities::account_id.eq(account_id))
+            .filter(activities::deleted_at.is_null())
+            .order(activities::activity_date.asc())
+            .load::<Activity>(conn)
+    }
+
+    // Stream the activities matching the filter to `on_activity` in date order,
+    // without loading them all in memory
+    pub fn for_each_filtered_activity(
+        &self,
+        conn: &mut SqliteConnection,
+        filter: &ActivityExportFilter,
+        mut on_activity: impl FnMut(Activity) -> Result<(), diesel::result::Error>,
+    ) -> Result<usize, diesel::result::Error> {
+        let mut query = activities::table
+            .filter(activities::deleted_at.is_null())
+            .into_boxed::<Sqlite>();
+
+        if let Some(account_ids) = &filter.account_ids {
+            query = query.filter(activities::account_id.eq_any(account_ids));
+        }
+        if let Some(activity_types) = &filter.activity_types {
+            query = query.filter(activities::activity_type.eq_any(activity_types));
+        }
+        if let Some(start_date) = parse_filter_date(&filter.start_date, 0)? {
+            query = query.filter(activities::activity_date.ge(start_date));
+        }
+        if let Some(end_date) = parse_filter_date(&filter.end_date, 1)? {
+            query = query.filter(activities::activity_date.lt(end_date));
+        }
+
+        let mut count = 0;
+        for activity in query
+            .order(activities::activity_date.asc())
+            .load_iter::<Activity, DefaultLoadingMode>(conn)?
+        {
+            on_activity(activity?)?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn search_activities(
         &self,
         conn: &mut SqliteConnection,
@@ -48,9 +110,14 @@ impl ActivityRepository {
         account_id_filter: Option<Vec<String>>,    // Optional account_id filter
         activity_type_filter: Option<Vec<String>>, // Optional activity_type filter
         asset_id_keyword: Option<String>,          // Optional asset_id keyword for search
+        tag_filter: Option<Vec<String>>,           // Optional asset tag filter
+        filter: Option<ActivitySearchFilter>,      // Optional date, amount and comment filters
         sort: Option<Sort>,                        // Optional sort
     ) -> Result<ActivitySearchResponse, diesel::result::Error> {
         let offset = page * page_size;
+        let filter = filter.unwrap_or_default();
+        let start_date = parse_filter_date(&filter.start_date, 0)?;
+        let end_date = parse_filter_date(&filter.end_date, 1)?;
 
         // Function to create base query
         let create_base_query = |_conn: &SqliteConnection| {
@@ -58,6 +125,7 @@ impl ActivityRepository {
                 .inner_join(accounts::table.on(activities::account_id.eq(accounts::id)))
                 .inner_join(assets::table.on(activities::asset_id.eq(assets::id)))
                 .filter(accounts::is_active.eq(true))
+                .filter(activities::deleted_at.is_null())
                 .into_boxed();
 
             if let Some(ref account_ids) = account_id_filter {
@@ -69,6 +137,35 @@ impl ActivityRepository {
             if let Some(ref keyword) = asset_id_keyword {
                 query = query.filter(assets::id.like(format!("%{}%", keyword)));
             }
+            if let Some(ref tags) = tag_filter {
+                query = query.filter(
+                    activities::asset_id.eq_any(
+                        asset_tags::table
+                            .filter(asset_tags::tag.eq_any(tags))
+                            .select(asset_tags::asset_id),
+                    ),
+                );
+            }
+            if let Some(ref symbols) = filter.symbols {
+                query = query.filter(activities::asset_id.eq_any(symbols));
+            }
+            if let Some(start_date) = start_date {
+                query = query.filter(activities::activity_date.ge(start_date));
+            }
+            if let Some(end_date) = end_date {
+                query = query.filter(activities::activity_date.lt(end_date));
+            }
+            if let Some(min_amount) = filter.min_amount {
+                query =
+                    query.filter((activities::quantity * activities::unit_price).ge(min_amount));
+            }
+            if let Some(max_amount) = filter.max_amount {
+                query =
+                    query.filter((activities::quantity * activities::unit_price).le(max_amount));
+            }
+            if let Some(ref keyword) = filter.comment_keyword {
+                query = query.filter(activities::comment.like(format!("%{}%", keyword)));
+            }
 
             // Apply sorting
             if let Some(ref sort) = sort {
@@ -107,7 +204,8 @@ impl ActivityRepository {
                 query = query.order(activities::activity_date.desc()); // Default order
             }
 
-            query
+            // Ties are broken by id so consecutive pages never overlap
+            query.then_order_by(activities::id.asc())
         };
 
         // Count query
@@ -172,11 +270,408 @@ impl ActivityRepository {
             .expect("Error saving activity"))
     }
 
+    // Deleted activities are only marked, so the deletion can be undone until they are purged.
+    // The activities generated from the deleted one, like a dividend reinvestment, go with it.
     pub fn delete_activity(
         &self,
         conn: &mut SqliteConnection,
         activity_id: String,
     ) -> Result<usize, diesel::result::Error> {
-        diesel::delete(activities::table.filter(activities::id.eq(activity_id))).execute(conn)
+        diesel::update(
+            activities::table
+                .filter(
+                    activities::id
+                        .eq(&activity_id)
+                        .or(activities::source_activity_id.eq(&activity_id)),
+                )
+                .filter(activities::deleted_at.is_null()),
+        )
+        .set(activities::deleted_at.eq(chrono::Utc::now().naive_utc()))
+        .execute(conn)
+    }
+
+    // Restore an activity deleted after `deleted_since`
+    pub fn restore_activity(
+        &self,
+        conn: &mut SqliteConnection,
+        activity_id: &str,
+        deleted_since: NaiveDateTime,
+    ) -> Result<Activity, diesel::result::Error> {
+        let activity = diesel::update(
+            activities::table
+                .filter(activities::id.eq(activity_id))
+                .filter(activities::deleted_at.ge(deleted_since)),
+        )
+        .set(activities::deleted_at.eq(None::<NaiveDateTime>))
+        .get_result(conn)?;
+
+        diesel::update(
+            activities::table
+                .filter(activities::source_activity_id.eq(activity_id))
+                .filter(activities::deleted_at.ge(deleted_since)),
+        )
+        .set(activities::deleted_at.eq(None::<NaiveDateTime>))
+        .execute(conn)?;
+
+        Ok(activity)
+    }
+
+    pub fn get_drip_settings(
+        &self,
+        conn: &mut SqliteConnection,
+    ) -> Result<Vec<DripSetting>, diesel::result::Error> {
+        drip_settings::table
+            .order((
+                drip_settings::account_id.asc(),
+                drip_settings::asset_id.asc(),
+            ))
+            .load::<DripSetting>(conn)
+    }
+
+    pub fn is_drip_enabled(
+        &self,
+        conn: &mut SqliteConnection,
+        account_id: &str,
+        asset_id: &str,
+    ) -> Result<bool, diesel::result::Error> {
+        diesel::select(diesel::dsl::exists(
+            drip_settings::table.find((account_id, asset_id)),
+        ))
+        .get_result(conn)
+    }
+
+    pub fn add_drip_setting(
+        &self,
+        conn: &mut SqliteConnection,
+        drip_setting: &DripSetting,
+    ) -> Result<usize, diesel::result::Error> {
+        diesel::insert_or_ignore_into(drip_settings::table)
+            .values(drip_setting)
+            .execute(conn)
+    }
+
+    pub fn remove_drip_setting(
+        &self,
+        conn: &mut SqliteConnection,
+        drip_setting: &DripSetting,
+    ) -> Result<usize, diesel::result::Error> {
+        diesel::delete(
+            drip_settings::table.find((&drip_setting.account_id, &drip_setting.asset_id)),
+        )
+        .execute(conn)
+    }
+
+    pub fn get_import_mapping(
+        &self,
+        conn: &mut SqliteConnection,
+        account_id: &str,
+    ) -> Result<Option<ImportMapping>, diesel::result::Error> {
+        import_mappings::table
+            .find(account_id)
+            .first::<ImportMapping>(conn)
+            .optional()
+    }
+
+    pub fn save_import_mapping(
+        &self,
+        conn: &mut SqliteConnection,
+        import_mapping: &ImportMapping,
+    ) -> Result<usize, diesel::result::Error> {
+        diesel::insert_into(import_mappings::table)
+            .values(import_mapping)
+            .on_conflict(import_mappings::account_id)
+            .do_update()
+            .set(import_mapping)
+            .execute(conn)
+    }
+
+    // Remove for good the activities deleted before `deleted_before`
+    pub fn purge_deleted_activities(
+        &self,
+        conn: &mut SqliteConnection,
+        deleted_before: NaiveDateTime,
+    ) -> Result<usize, diesel::result::Error> {
+        diesel::delete(activities::table.filter(activities::deleted_at.lt(deleted_before)))
+            .execute(conn)
+    }
+
+    // Ids of every activity matching the bulk filter, of active and inactive accounts alike
+    pub fn get_filtered_activity_ids(
+        &self,
+        conn: &mut SqliteConnection,
+        filter: &ActivityBulkFilter,
+    ) -> Result<Vec<String>, diesel::result::Error> {
+        let mut query = activities::table
+            .filter(activities::deleted_at.is_null())
+            .select(activities::id)
+            .into_boxed::<Sqlite>();
+
+        if let Some(account_ids) = &filter.account_ids {
+            query = query.filter(activities::account_id.eq_any(account_ids));
+        }
+        if let Some(activity_types) = &filter.activity_types {
+            query = query.filter(activities::activity_type.eq_any(activity_types));
+        }
+        if let Some(asset_ids) = &filter.asset_ids {
+            query = query.filter(activities::asset_id.eq_any(asset_ids));
+        }
+        if let Some(start_date) = parse_filter_date(&filter.start_date, 0)? {
+            query = query.filter(activities::activity_date.ge(start_date));
+        }
+        if let Some(end_date) = parse_filter_date(&filter.end_date, 1)? {
+            query = query.filter(activities::activity_date.lt(end_date));
+        }
+
+        query.load::<String>(conn)
+    }
+
+    // Like `delete_activity`, the activities generated from the deleted ones go with them
+    pub fn delete_activities(
+        &self,
+        conn: &mut SqliteConnection,
+        activity_ids: &[String],
+    ) -> Result<usize, diesel::result::Error> {
+        diesel::update(
+            activities::table
+                .filter(
+                    activities::id
+                        .eq_any(activity_ids)
+                        .or(activities::source_activity_id.eq_any(activity_ids)),
+                )
+                .filter(activities::deleted_at.is_null()),
+        )
+        .set(activities::deleted_at.eq(chrono::Utc::now().naive_utc()))
+        .execute(conn)
+    }
+
+    pub fn update_activities(
+        &self,
+        conn: &mut SqliteConnection,
+        activity_ids: &[String],
+        changes: &ActivityBulkUpdate,
+    ) -> Result<usize, diesel::result::Error> {
+        diesel::update(activities::table.filter(activities::id.eq_any(activity_ids)))
+            .set((
+                changes,
+                activities::updated_at.eq(chrono::Utc::now().naive_utc()),
+            ))
+            .execute(conn)
+    }
+}
+
+// Midnight of a YYYY-MM-DD filter date, moved by `days_offset` days. A date that can't be
+// parsed is refused, ignoring it would widen the filter to every activity.
+fn parse_filter_date(
+    date: &Option<String>,
+    days_offset: i64,
+) -> Result<Option<NaiveDateTime>, diesel::result::Error> {
+    let date = match date.as_deref() {
+        Some(date) => date,
+        None => return Ok(None),
+    };
+    let parsed_date = NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| {
+        diesel::result::Error::QueryBuilderError(format!("Invalid filter date: {}", date).into())
+    })?;
+    Ok((parsed_date + chrono::Duration::days(days_offset)).and_hms_opt(0, 0, 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::asset_service::AssetService;
+    use crate::test_utils::{self, TestDatabase};
+
+    fn setup() -> TestDatabase {
+        let mut db = TestDatabase::new();
+        test_utils::insert_account(&mut db.conn, "account", "USD");
+        test_utils::insert_asset(&mut db.conn, "AAPL", "USD");
+        test_utils::insert_asset(&mut db.conn, "MSFT", "USD");
+        let activities = [
+            ("AAPL", "BUY", "2024-01-02", 10.0, 150.0, None),
+            ("AAPL", "BUY", "2024-01-02", 5.0, 150.0, None),
+            ("MSFT", "BUY", "2024-02-01", 2.0, 400.0, Some("Rebalance")),
+            ("AAPL", "SELL", "2024-03-01", 4.0, 175.0, None),
+            (
+                "MSFT",
+                "DIVIDEND",
+                "2024-03-15",
+                1.0,
+                12.0,
+                Some("Quarterly dividend"),
+            ),
+        ];
+        for (asset_id, activity_type, date, quantity, unit_price, comment) in activities {
+            let mut new_activity = test_utils::new_activity(
+                "account",
+                asset_id,
+                activity_type,
+                date,
+                quantity,
+                unit_price,
+            );
+            new_activity.comment = comment.map(str::to_string);
+            test_utils::insert_activity(&mut db.conn, new_activity);
+        }
+        db
+    }
+
+    fn search(
+        conn: &mut SqliteConnection,
+        page: i64,
+        page_size: i64,
+        filter: ActivitySearchFilter,
+    ) -> ActivitySearchResponse {
+        ActivityRepository::new()
+            .search_activities(
+                conn,
+                page,
+                page_size,
+                None,
+                None,
+                None,
+                None,
+                Some(filter),
+                None,
+            )
+            .unwrap()
+    }
+
+    // Symbol and amount of each activity found, sorted
+    fn found(conn: &mut SqliteConnection, filter: ActivitySearchFilter) -> Vec<(String, f64)> {
+        let mut found: Vec<(String, f64)> = search(conn, 0, 50, filter)
+            .data
+            .into_iter()
+            .map(|activity| (activity.asset_id, activity.quantity * activity.unit_price))
+            .collect();
+        found.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)));
+        found
+    }
+
+    fn amounts(found: &[(&str, f64)]) -> Vec<(String, f64)> {
+        found
+            .iter()
+            .map(|(asset_id, amount)| (asset_id.to_string(), *amount))
+            .collect()
+    }
+
+    #[test]
+    fn consecutive_pages_do_not_overlap() {
+        let mut db = setup();
+
+        let mut ids = Vec::new();
+        for page in 0..3 {
+            let response = search(&mut db.conn, page, 2, ActivitySearchFilter::default());
+            assert_eq!(response.meta.total_row_count, 5);
+            ids.extend(response.data.into_iter().map(|activity| activity.id));
+        }
+
+        assert_eq!(ids.len(), 5);
+        let mut unique_ids = ids.clone();
+        unique_ids.sort();
+        unique_ids.dedup();
+        assert_eq!(unique_ids.len(), 5);
+    }
+
+    #[test]
+    fn each_filter_narrows_the_results() {
+        let mut db = setup();
+
+        let by_date = ActivitySearchFilter {
+            start_date: Some("2024-02-01".to_string()),
+            end_date: Some("2024-03-01".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            found(&mut db.conn, by_date),
+            amounts(&[("AAPL", 700.0), ("MSFT", 800.0)])
+        );
+
+        let by_amount = ActivitySearchFilter {
+            min_amount: Some(700.0),
+            max_amount: Some(800.0),
+            ..Default::default()
+        };
+        assert_eq!(
+            found(&mut db.conn, by_amount),
+            amounts(&[("AAPL", 700.0), ("AAPL", 750.0), ("MSFT", 800.0)])
+        );
+
+        let by_comment = ActivitySearchFilter {
+            comment_keyword: Some("dividend".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(found(&mut db.conn, by_comment), amounts(&[("MSFT", 12.0)]));
+
+        let by_symbol = ActivitySearchFilter {
+            symbols: Some(vec!["MSFT".to_string()]),
+            ..Default::default()
+        };
+        assert_eq!(
+            found(&mut db.conn, by_symbol),
+            amounts(&[("MSFT", 12.0), ("MSFT", 800.0)])
+        );
+
+        let invalid_date = ActivitySearchFilter {
+            start_date: Some("02/01/2024".to_string()),
+            ..Default::default()
+        };
+        assert!(ActivityRepository::new()
+            .search_activities(
+                &mut db.conn,
+                0,
+                50,
+                None,
+                None,
+                None,
+                None,
+                Some(invalid_date),
+                None
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn the_tag_filter_keeps_the_activities_of_any_tagged_asset() {
+        let mut db = setup();
+        let asset_service = AssetService::new();
+        asset_service
+            .add_asset_tag(&mut db.conn, "AAPL", "Tech")
+            .unwrap();
+        asset_service
+            .add_asset_tag(&mut db.conn, "MSFT", "Tech")
+            .unwrap();
+        asset_service
+            .add_asset_tag(&mut db.conn, "MSFT", "Income")
+            .unwrap();
+
+        let mut search_tags = |tags: &[&str]| -> Vec<String> {
+            let mut asset_ids: Vec<String> = ActivityRepository::new()
+                .search_activities(
+                    &mut db.conn,
+                    0,
+                    50,
+                    None,
+                    None,
+                    None,
+                    Some(tags.iter().map(|tag| tag.to_string()).collect()),
+                    None,
+                    None,
+                )
+                .unwrap()
+                .data
+                .into_iter()
+                .map(|activity| activity.asset_id)
+                .collect();
+            asset_ids.sort();
+            asset_ids
+        };
+
+        assert_eq!(search_tags(&["Income"]), ["MSFT", "MSFT"]);
+        // An asset carrying several of the tags is not counted twice
+        assert_eq!(
+            search_tags(&["Tech", "Income"]),
+            ["AAPL", "AAPL", "AAPL", "MSFT", "MSFT"]
+        );
+        assert!(search_tags(&["Bonds"]).is_empty());
     }
 }
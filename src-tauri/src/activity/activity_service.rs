@@ -1,23 +1,110 @@
+use std::collections::HashMap;
 use std::fs::File;
+use std::io::BufReader;
+use std::ops::{Deref, DerefMut};
 
 use crate::account::AccountService;
+use crate::activity::activity_import::{self, CsvActivityReader, ImportPreset};
+use crate::activity::ofx_import;
 use crate::activity::ActivityRepository;
 use crate::asset::asset_service::AssetService;
+use crate::asset::security_identifier::SecurityIdentifier;
+use crate::asset::symbol_normalization;
+use crate::fx::FxService;
 use crate::models::{
-    Activity, ActivityImport, ActivitySearchResponse, ActivityUpdate, NewActivity, Sort,
+    Account, Activity, ActivityBulkFilter, ActivityBulkUpdate, ActivityExportFilter,
+    ActivityImport, ActivityImportPreview, ActivitySearchFilter, ActivitySearchResponse,
+    ActivityUpdate, AssetLookup, DripSetting, ImportActivitiesResult, ImportDryRunReport,
+    ImportLocale, ImportMapping, ImportSelection, NewActivity, NewAsset, Sort,
 };
-use crate::schema::activities;
+use crate::schema::{activities, assets};
+use crate::settings::{read_only_mode, SettingsService};
 
-use csv::ReaderBuilder;
+use chrono::NaiveDate;
+use diesel::connection::TransactionManager;
 use diesel::prelude::*;
 use uuid::Uuid;
 
+// Number of activities inserted between two progress notifications, each chunk is
+// committed on its own
+const IMPORT_CHUNK_SIZE: usize = 500;
+// Rows of an import file checked and shown for review before importing it
+const IMPORT_PREVIEW_ROWS: usize = 200;
+
+type SqliteTransactionManager = <SqliteConnection as Connection>::TransactionManager;
+
 pub struct ActivityService {
     repo: ActivityRepository,
     asset_service: AssetService,
     account_service: AccountService,
 }
 
+// Lookups shared by the rows of an import, large files repeat the same few symbols
+struct ImportContext {
+    account: Account,
+    existing_activities: Vec<Activity>,
+    symbol_overrides: HashMap<String, String>,
+    symbol_names: HashMap<String, Option<Option<String>>>,
+    resolved_symbols: HashMap<String, Result<String, String>>,
+    earliest_date: NaiveDate,
+    today: NaiveDate,
+    // Off when checking a file in read-only mode, nothing learned from the file is saved
+    persist: bool,
+    // Profiles of the new symbols fetched without saving them
+    fetched_assets: Vec<NewAsset>,
+}
+
+// Transaction of an import dry run, rolled back when dropped whichever way the run ends
+struct RollbackGuard<'a> {
+    conn: &'a mut SqliteConnection,
+}
+
+impl<'a> RollbackGuard<'a> {
+    fn begin(conn: &'a mut SqliteConnection) -> Result<Self, diesel::result::Error> {
+        SqliteTransactionManager::begin_transaction(conn)?;
+        Ok(RollbackGuard { conn })
+    }
+}
+
+impl Deref for RollbackGuard<'_> {
+    type Target = SqliteConnection;
+
+    fn deref(&self) -> &SqliteConnection {
+        self.conn
+    }
+}
+
+impl DerefMut for RollbackGuard<'_> {
+    fn deref_mut(&mut self) -> &mut SqliteConnection {
+        self.conn
+    }
+}
+
+impl Drop for RollbackGuard<'_> {
+    fn drop(&mut self) {
+        if let Err(e) = SqliteTransactionManager::rollback_transaction(self.conn) {
+            println!("Failed to roll back the import dry run: {}", e);
+        }
+    }
+}
+
+fn to_new_activity(activity_import: &ActivityImport) -> NewActivity {
+    NewActivity {
+        id: activity_import.id.clone(),
+        account_id: activity_import.account_id.clone().unwrap_or_default(),
+        asset_id: activity_import.symbol.clone(),
+        activity_type: activity_import.activity_type.clone(),
+        activity_date: activity_import.date.clone(),
+        quantity: activity_import.quantity,
+        unit_price: activity_import.unit_price,
+        currency: activity_import.currency.clone(),
+        fee: activity_import.fee,
+        is_draft: activity_import.is_draft.as_deref() == Some("true"),
+        comment: activity_import.comment.clone(),
+        source_activity_id: None,
+    }
+}
+
 impl ActivityService {
     pub fn new() -> Self {
         ActivityService {
@@ -33,9 +120,41 @@ impl ActivityService {
         conn: &mut SqliteConnection,
         activity_id: String,
     ) -> Result<usize, diesel::result::Error> {
+        read_only_mode::ensure_writable()?;
         self.repo.delete_activity(conn, activity_id)
     }
 
+    // restore an activity deleted within the undo window of the settings
+    pub fn restore_activity(
+        &self,
+        conn: &mut SqliteConnection,
+        activity_id: &str,
+    ) -> Result<Activity, diesel::result::Error> {
+        read_only_mode::ensure_writable()?;
+        let deleted_since = self.get_undo_window_start(conn)?;
+        self.repo.restore_activity(conn, activity_id, deleted_since)
+    }
+
+    // remove for good the activities deleted before the undo window
+    pub fn purge_deleted_activities(
+        &self,
+        conn: &mut SqliteConnection,
+    ) -> Result<usize, diesel::result::Error> {
+        read_only_mode::ensure_writable()?;
+        let deleted_before = self.get_undo_window_start(conn)?;
+        self.repo.purge_deleted_activities(conn, deleted_before)
+    }
+
+    fn get_undo_window_start(
+        &self,
+        conn: &mut SqliteConnection,
+    ) -> Result<chrono::NaiveDateTime, diesel::result::Error> {
+        let undo_days = SettingsService::new()
+            .get_settings(conn)?
+            .activity_undo_days;
+        Ok(chrono::Utc::now().naive_utc() - chrono::Duration::days(undo_days.max(0) as i64))
+    }
+
     //load all activities
     pub fn get_activities(
         &self,
@@ -51,6 +170,15 @@ impl ActivityService {
         self.repo.get_trading_activities(conn)
     }
 
+    pub fn get_account_activities(
+        &self,
+        conn: &mut SqliteConnection,
+        account_id: &str,
+    ) -> Result<Vec<Activity>, diesel::result::Error> {
+        self.repo.get_account_activities(conn, account_id)
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn search_activities(
         &self,
         conn: &mut SqliteConnection,
@@ -59,6 +187,8 @@ impl ActivityService {
         account_id_filter: Option<Vec<String>>,    // Optional account_id filter
         activity_type_filter: Option<Vec<String>>, // Optional activity_type filter
         asset_id_keyword: Option<String>,          // Optional asset_id keyword for search
+        tag_filter: Option<Vec<String>>,           // Optional asset tag filter
+        filter: Option<ActivitySearchFilter>,      // Optional date, amount and comment filters
         sort: Option<Sort>,                        // Optional sort
     ) -> Result<ActivitySearchResponse, diesel::result::Error> {
         self.repo.search_activities(
@@ -68,6 +198,8 @@ impl ActivityService {
             account_id_filter,
             activity_type_filter,
             asset_id_keyword,
+            tag_filter,
+            filter,
             sort,
         )
     }
@@ -78,6 +210,7 @@ impl ActivityService {
         conn: &mut SqliteConnection,
         mut activity: NewActivity,
     ) -> Result<Activity, diesel::result::Error> {
+        read_only_mode::ensure_writable()?;
         // Clone asset_id to avoid moving it
         let asset_id = activity.asset_id.clone();
 
@@ -94,93 +227,1310 @@ impl ActivityService {
             activity.unit_price = 1.0;
         }
 
-        // Insert the new activity into the database
-        self.repo.insert_new_activity(conn, activity)
+        // Insert the new activity into the database, with its reinvestment for a dividend
+        conn.transaction(|conn| {
+            self.link_spin_off(conn, &mut activity)?;
+            let activity = self.repo.insert_new_activity(conn, activity)?;
+            self.reinvest_if_enabled(conn, &activity)?;
+            Ok(activity)
+        })
     }
 
-    // verify the activities import from csv file
-    pub async fn check_activities_import(
+    // Point received spin-off shares to the parent side of the same day when no parent is
+    // given. An account with several spin-offs that day needs the parent to tell them apart.
+    fn link_spin_off(
         &self,
         conn: &mut SqliteConnection,
-        _account_id: String,
-        file_path: String,
-    ) -> Result<Vec<ActivityImport>, String> {
+        new_activity: &mut NewActivity,
+    ) -> Result<(), diesel::result::Error> {
+        if new_activity.activity_type != "SPIN_OFF_IN" || new_activity.source_activity_id.is_some()
+        {
+            return Ok(());
+        }
+        let spin_off_date = new_activity
+            .activity_date
+            .get(..10)
+            .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+            .ok_or_else(|| {
+                diesel::result::Error::QueryBuilderError(
+                    format!("Invalid spin-off date: {}", new_activity.activity_date).into(),
+                )
+            })?;
+
+        let account_activities = self
+            .repo
+            .get_account_activities(conn, &new_activity.account_id)?;
+        let linked_parents: Vec<&str> = account_activities
+            .iter()
+            .filter(|activity| activity.activity_type == "SPIN_OFF_IN")
+            .filter_map(|activity| activity.source_activity_id.as_deref())
+            .collect();
+        let parents: Vec<&Activity> = account_activities
+            .iter()
+            .filter(|activity| {
+                activity.activity_type == "SPIN_OFF_OUT"
+                    && activity.activity_date.date() == spin_off_date
+                    && !linked_parents.contains(&activity.id.as_str())
+            })
+            .collect();
+
+        match parents.as_slice() {
+            [parent] => {
+                new_activity.source_activity_id = Some(parent.id.clone());
+                Ok(())
+            }
+            [] => Err(diesel::result::Error::QueryBuilderError(
+                format!(
+                    "No spin-off of a parent on {} for the received shares",
+                    spin_off_date
+                )
+                .into(),
+            )),
+            _ => Err(diesel::result::Error::QueryBuilderError(
+                format!(
+                    "Several spin-offs on {}, the parent of the received shares is required",
+                    spin_off_date
+                )
+                .into(),
+            )),
+        }
+    }
+
+    // Reinvest a new dividend when its asset is opted in for the account
+    fn reinvest_if_enabled(
+        &self,
+        conn: &mut SqliteConnection,
+        activity: &Activity,
+    ) -> Result<Option<Activity>, diesel::result::Error> {
+        if activity.activity_type == "DIVIDEND"
+            && self
+                .repo
+                .is_drip_enabled(conn, &activity.account_id, &activity.asset_id)?
+        {
+            self.reinvest_dividend(conn, activity)
+        } else {
+            Ok(None)
+        }
+    }
+
+    // Buy the asset for the net amount of the dividend at the close of its pay date, in
+    // fractional shares. The buy points to the dividend so it can be traced back to it.
+    fn reinvest_dividend(
+        &self,
+        conn: &mut SqliteConnection,
+        dividend: &Activity,
+    ) -> Result<Option<Activity>, diesel::result::Error> {
+        let amount = dividend.quantity * dividend.unit_price - dividend.fee;
+        let asset = self
+            .asset_service
+            .get_asset_by_id(conn, &dividend.asset_id)?;
+        if amount <= 0.0 || asset.currency != dividend.currency {
+            println!(
+                "Dividend {} not reinvested: amount {} {} for an asset quoted in {}",
+                dividend.id, amount, dividend.currency, asset.currency
+            );
+            return Ok(None);
+        }
+
+        let quote = match self.asset_service.get_quote_on_date(
+            conn,
+            &dividend.asset_id,
+            dividend.activity_date.date(),
+        ) {
+            Ok(quote) if quote.close > 0.0 => quote,
+            Ok(_) | Err(diesel::result::Error::NotFound) => {
+                println!(
+                    "Dividend {} not reinvested: no price of {} on {}",
+                    dividend.id,
+                    dividend.asset_id,
+                    dividend.activity_date.date()
+                );
+                return Ok(None);
+            }
+            Err(e) => return Err(e),
+        };
+
+        let reinvestment = NewActivity {
+            id: None,
+            account_id: dividend.account_id.clone(),
+            asset_id: dividend.asset_id.clone(),
+            activity_type: "BUY".to_string(),
+            activity_date: dividend
+                .activity_date
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string(),
+            quantity: amount / quote.close,
+            unit_price: quote.close,
+            currency: dividend.currency.clone(),
+            fee: 0.0,
+            is_draft: false,
+            comment: Some("Dividend reinvestment".to_string()),
+            source_activity_id: Some(dividend.id.clone()),
+        };
+        self.repo.insert_new_activity(conn, reinvestment).map(Some)
+    }
+
+    pub fn get_drip_settings(
+        &self,
+        conn: &mut SqliteConnection,
+    ) -> Result<Vec<DripSetting>, diesel::result::Error> {
+        self.repo.get_drip_settings(conn)
+    }
+
+    // opt the asset of the account in or out of dividend reinvestment, only the
+    // dividends recorded afterwards are reinvested
+    pub fn set_drip_enabled(
+        &self,
+        conn: &mut SqliteConnection,
+        drip_setting: DripSetting,
+        enabled: bool,
+    ) -> Result<usize, diesel::result::Error> {
+        read_only_mode::ensure_writable()?;
+        if enabled {
+            self.repo.add_drip_setting(conn, &drip_setting)
+        } else {
+            self.repo.remove_drip_setting(conn, &drip_setting)
+        }
+    }
+
+    // Locale saved by the last import of the account that was given one
+    pub fn get_import_locale(
+        &self,
+        conn: &mut SqliteConnection,
+        account_id: &str,
+    ) -> Result<Option<ImportLocale>, diesel::result::Error> {
+        Ok(self
+            .repo
+            .get_import_mapping(conn, account_id)?
+            .map(|import_mapping| import_mapping.locale()))
+    }
+
+    // What the rows of an import are checked against, loaded once per import. A given
+    // locale is kept for the next imports of the account when `persist` is set, without
+    // one the saved locale applies, or the one detected from the file.
+    fn prepare_import(
+        &self,
+        conn: &mut SqliteConnection,
+        account_id: &str,
+        locale: Option<ImportLocale>,
+        persist: bool,
+    ) -> Result<(ImportContext, Option<ImportLocale>), String> {
         let account = self
             .account_service
-            .get_account_by_id(conn, &_account_id)
+            .get_account_by_id(conn, account_id)
             .map_err(|e| e.to_string())?;
 
-        let file = File::open(&file_path).map_err(|e| e.to_string())?;
-        let mut rdr = ReaderBuilder::new()
-            .delimiter(b',')
-            .has_headers(true)
-            .from_reader(file);
-        let mut activities_with_status: Vec<ActivityImport> = Vec::new();
-
-        for (line_number, result) in rdr.deserialize().enumerate() {
-            let line_number = line_number + 1; // Adjust for human-readable line number
-            let mut activity_import: ActivityImport = result.map_err(|e| e.to_string())?;
-
-            // Load the symbol profile here, now awaiting the async call
-            let symbol_profile_result = self
-                .asset_service
-                .get_asset_profile(conn, &activity_import.symbol)
-                .await;
-
-            // Check if symbol profile is valid
-            let (is_valid, error) = match symbol_profile_result {
-                Ok(profile) => {
-                    activity_import.symbol_name = profile.name;
-                    (Some("true".to_string()), None)
+        let locale = match locale {
+            Some(locale) => {
+                activity_import::validate_locale(&locale)?;
+                if persist {
+                    self.repo
+                        .save_import_mapping(conn, &ImportMapping::new(&account.id, &locale))
+                        .map_err(|e| e.to_string())?;
                 }
-                Err(_) => {
-                    let error_msg = format!(
-                        "Symbol {} not found. Line: {}",
-                        &activity_import.symbol, line_number
-                    );
-                    (Some("false".to_string()), Some(error_msg))
+                Some(locale)
+            }
+            None => self
+                .get_import_locale(conn, &account.id)
+                .map_err(|e| e.to_string())?,
+        };
+        let existing_activities = self
+            .repo
+            .get_account_activities(conn, &account.id)
+            .map_err(|e| e.to_string())?;
+        let symbol_overrides = self
+            .asset_service
+            .get_account_symbol_overrides(conn, &account.id)
+            .map_err(|e| e.to_string())?;
+
+        let context = ImportContext {
+            account,
+            earliest_date: activity_import::get_earliest_activity_date(&existing_activities),
+            existing_activities,
+            symbol_overrides,
+            symbol_names: HashMap::new(),
+            resolved_symbols: HashMap::new(),
+            today: chrono::Local::now().date_naive(),
+            persist,
+            fetched_assets: Vec::new(),
+        };
+        Ok((context, locale))
+    }
+
+    // The activities of an import file in the order of its rows, read lazily
+    fn read_activity_imports(
+        &self,
+        file_path: &str,
+        preset: Option<ImportPreset>,
+        locale: Option<ImportLocale>,
+        currency: &str,
+    ) -> Result<Box<dyn Iterator<Item = Result<ActivityImport, String>>>, String> {
+        if ofx_import::is_ofx_file(file_path) {
+            let content = std::fs::read_to_string(file_path).map_err(|e| e.to_string())?;
+            let activity_imports = ofx_import::parse_ofx(&content, currency)?;
+            Ok(Box::new(activity_imports.into_iter().map(Ok)))
+        } else {
+            let file = File::open(file_path).map_err(|e| e.to_string())?;
+            let reader = CsvActivityReader::new(BufReader::new(file), preset, locale, currency)?;
+            Ok(Box::new(reader))
+        }
+    }
+
+    // Resolve the symbol of an imported row and check it against the existing activities
+    async fn check_activity_import(
+        &self,
+        conn: &mut SqliteConnection,
+        context: &mut ImportContext,
+        mut activity_import: ActivityImport,
+    ) -> Result<ActivityImport, String> {
+        let line_number = activity_import.line_number.unwrap_or(0);
+
+        // Symbols pinned by the user skip any other resolution, otherwise securities
+        // may be identified by their ISIN or CUSIP instead of a ticker
+        let symbol_override =
+            context
+                .symbol_overrides
+                .get(&symbol_normalization::normalize_symbol(
+                    &activity_import.symbol,
+                ));
+        let identifier = match symbol_override {
+            Some(provider_symbol) => {
+                activity_import.symbol = provider_symbol.clone();
+                None
+            }
+            None => SecurityIdentifier::parse(&activity_import.symbol),
+        };
+        let mut resolution_error = None;
+        if let Some(identifier) = &identifier {
+            let resolved = match context.resolved_symbols.get(identifier.value()) {
+                Some(resolved) => resolved.clone(),
+                None => {
+                    let resolved = self
+                        .asset_service
+                        .resolve_security_identifier(conn, identifier)
+                        .await;
+                    context
+                        .resolved_symbols
+                        .insert(identifier.value().to_string(), resolved.clone());
+                    resolved
                 }
             };
+            match resolved {
+                Ok(symbol) => activity_import.symbol = symbol,
+                Err(e) => resolution_error = Some(e),
+            }
+        }
 
-            // Update the activity_import with the loaded symbol profile and status
-            activity_import.is_draft = Some("true".to_string());
-            activity_import.is_valid = is_valid.clone();
-            activity_import.error = error.clone();
-            activity_import.line_number = Some(line_number as i32);
-            activity_import.id = Some(Uuid::new_v4().to_string());
-            activity_import.account_id = Some(account.id.clone());
-            activity_import.account_name = Some(account.name.clone());
-            activities_with_status.push(activity_import);
+        // Load the symbol profile here, now awaiting the async call. A symbol unknown to
+        // the provider may be spelled differently by the broker, e.g. "BRK.B" for "BRK-B".
+        // Profiles fetched for new symbols are only saved when the context persists.
+        let mut symbol_profile_result = None;
+        if resolution_error.is_none() {
+            for candidate in symbol_normalization::get_symbol_candidates(&activity_import.symbol) {
+                let symbol_name = match context.symbol_names.get(&candidate) {
+                    Some(symbol_name) => symbol_name.clone(),
+                    None => {
+                        let symbol_name = if context.persist {
+                            self.asset_service
+                                .get_asset_profile(conn, &candidate)
+                                .await
+                                .ok()
+                                .map(|profile| profile.name)
+                        } else {
+                            match self
+                                .asset_service
+                                .lookup_asset_profile(conn, &candidate)
+                                .await
+                            {
+                                Ok(AssetLookup::Saved(asset)) => Some(asset.name),
+                                Ok(AssetLookup::Fetched(new_asset)) => {
+                                    let name = new_asset.name.clone();
+                                    context.fetched_assets.push(new_asset);
+                                    Some(name)
+                                }
+                                Err(_) => None,
+                            }
+                        };
+                        context
+                            .symbol_names
+                            .insert(candidate.clone(), symbol_name.clone());
+                        symbol_name
+                    }
+                };
+                if symbol_name.is_some() {
+                    activity_import.symbol = candidate;
+                    symbol_profile_result = symbol_name;
+                    break;
+                }
+            }
+        }
+
+        // Check if symbol profile is valid
+        let (is_valid, error) = match symbol_profile_result {
+            Some(symbol_name) => {
+                let saved_identifier = identifier.as_ref().filter(|_| context.persist);
+                if let Some(SecurityIdentifier::Isin(isin)) = saved_identifier {
+                    self.asset_service
+                        .set_asset_isin(conn, &activity_import.symbol, isin)
+                        .map_err(|e| e.to_string())?;
+                }
+                activity_import.symbol_name = symbol_name;
+                (Some("true".to_string()), None)
+            }
+            None => {
+                let error_msg = match &resolution_error {
+                    Some(e) => format!("{}. Line: {}", e, line_number),
+                    None => format!(
+                        "Symbol {} not found. Line: {}",
+                        &activity_import.symbol, line_number
+                    ),
+                };
+                (Some("false".to_string()), Some(error_msg))
+            }
+        };
+
+        // Update the activity_import with the loaded symbol profile and status
+        activity_import.is_draft = Some("true".to_string());
+        activity_import.is_valid = is_valid;
+        activity_import.error = error;
+        activity_import.line_number = Some(line_number);
+        activity_import.updated_activity_id = activity_import.id.take().filter(|activity_id| {
+            context
+                .existing_activities
+                .iter()
+                .any(|activity| &activity.id == activity_id)
+        });
+        activity_import.id = Some(Uuid::new_v4().to_string());
+        activity_import.account_id = Some(context.account.id.clone());
+        activity_import.account_name = Some(context.account.name.clone());
+        // A row replacing an activity is never its duplicate
+        if activity_import.updated_activity_id.is_none() {
+            activity_import.duplicate =
+                activity_import::find_duplicate(&activity_import, &context.existing_activities);
+        }
+        activity_import.warning = activity_import::check_activity_date(
+            &activity_import,
+            context.earliest_date,
+            context.today,
+        )
+        .map(|warning| format!("{}. Line: {}", warning, line_number));
+        Ok(activity_import)
+    }
+
+    // verify the activities import from csv file. Only the first rows are checked for
+    // review, the rest of the file is read to count its activities without being kept.
+    // Allowed in read-only mode, which only skips saving the locale, profiles and ISINs.
+    pub async fn check_activities_import(
+        &self,
+        conn: &mut SqliteConnection,
+        account_id: String,
+        file_path: String,
+        preset: Option<ImportPreset>,
+        locale: Option<ImportLocale>,
+    ) -> Result<ActivityImportPreview, String> {
+        let persist = !read_only_mode::is_enabled();
+        let (mut context, locale) = self.prepare_import(conn, &account_id, locale, persist)?;
+        let activity_imports =
+            self.read_activity_imports(&file_path, preset, locale, &context.account.currency)?;
+
+        let mut preview = ActivityImportPreview::default();
+        for activity_import in activity_imports {
+            let activity_import = activity_import?;
+            if preview.activities.len() < IMPORT_PREVIEW_ROWS {
+                let activity_import = self
+                    .check_activity_import(conn, &mut context, activity_import)
+                    .await?;
+                preview.activities.push(activity_import);
+            }
+            preview.total += 1;
         }
 
-        Ok(activities_with_status)
+        Ok(preview)
     }
 
-    // create activities used after the import is verified
+    // Import the activities of a file read again one row at a time, committed by chunks so
+    // memory use stays flat. Rows outside of the reviewed preview that have an error, a
+    // duplicate or a warning are skipped. A chunk that fails leaves the previous chunks
+    // imported, the check of the file has already parsed every row.
+    // `on_progress` is called with the processed and total row counts after each chunk.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn import_activities(
+        &self,
+        conn: &mut SqliteConnection,
+        account_id: String,
+        file_path: String,
+        preset: Option<ImportPreset>,
+        locale: Option<ImportLocale>,
+        selection: ImportSelection,
+        on_progress: impl Fn(usize, usize),
+    ) -> Result<ImportActivitiesResult, String> {
+        read_only_mode::ensure_writable().map_err(|e| e.to_string())?;
+        let (mut context, locale) = self.prepare_import(conn, &account_id, locale, true)?;
+        let currency = context.account.currency.clone();
+        let total = self
+            .read_activity_imports(&file_path, preset, locale.clone(), &currency)?
+            .count();
+
+        let mut result = ImportActivitiesResult::default();
+        let mut processed = 0;
+        let mut chunk = Vec::with_capacity(IMPORT_CHUNK_SIZE);
+        for activity_import in self.read_activity_imports(&file_path, preset, locale, &currency)? {
+            let activity_import = self
+                .check_activity_import(conn, &mut context, activity_import?)
+                .await?;
+            if selection.includes(&activity_import) {
+                chunk.push(activity_import);
+            } else {
+                result.skipped += 1;
+            }
+
+            processed += 1;
+            if processed % IMPORT_CHUNK_SIZE == 0 {
+                self.write_activity_imports(conn, &chunk, &mut result)
+                    .map_err(|e| e.to_string())?;
+                chunk.clear();
+                on_progress(processed, total);
+            }
+        }
+        self.write_activity_imports(conn, &chunk, &mut result)
+            .map_err(|e| e.to_string())?;
+        on_progress(processed, total);
+
+        Ok(result)
+    }
+
+    // Run the whole import of a file without writing anything. The rows are checked
+    // first, fetching the profiles of new symbols without saving them, then written in a
+    // short transaction that is always rolled back, dividend reinvestments included.
+    pub async fn dry_run_activities_import(
+        &self,
+        conn: &mut SqliteConnection,
+        account_id: String,
+        file_path: String,
+        preset: Option<ImportPreset>,
+        locale: Option<ImportLocale>,
+    ) -> Result<ImportDryRunReport, String> {
+        let (mut context, locale) = self.prepare_import(conn, &account_id, locale, false)?;
+        let mut activity_imports = Vec::new();
+        for activity_import in
+            self.read_activity_imports(&file_path, preset, locale, &context.account.currency)?
+        {
+            let activity_import = self
+                .check_activity_import(conn, &mut context, activity_import?)
+                .await?;
+            activity_imports.push(activity_import);
+        }
+
+        let mut conn = RollbackGuard::begin(conn).map_err(|e| e.to_string())?;
+        for new_asset in &context.fetched_assets {
+            diesel::insert_into(assets::table)
+                .values(new_asset)
+                .execute(&mut *conn)
+                .map_err(|e| e.to_string())?;
+        }
+
+        let mut report = ImportDryRunReport::default();
+        for mut activity_import in activity_imports {
+            if activity_import.error.is_some() {
+                report.errors += 1;
+            } else if activity_import.duplicate.is_some() {
+                report.duplicates += 1;
+            } else {
+                match self.write_activity_import(&mut conn, &activity_import) {
+                    Ok(reinvestment) => {
+                        if activity_import.updated_activity_id.is_some() {
+                            report.updates += 1;
+                        } else {
+                            report.creates += 1;
+                        }
+                        if activity_import.warning.is_some() {
+                            report.warnings += 1;
+                        }
+                        if reinvestment.is_some() {
+                            report.reinvestments += 1;
+                        }
+                    }
+                    Err(e) => {
+                        activity_import.is_valid = Some("false".to_string());
+                        activity_import.error = Some(format!(
+                            "{}. Line: {}",
+                            e,
+                            activity_import.line_number.unwrap_or_default()
+                        ));
+                        report.errors += 1;
+                    }
+                }
+            }
+            report.activities.push(activity_import);
+        }
+
+        Ok(report)
+    }
+
+    // create activities used after the import is verified, committed by chunks so a large
+    // import never holds the database in one long transaction.
+    // `on_progress` is called with the inserted and total counts after each chunk
     pub fn create_activities(
         &self,
         conn: &mut SqliteConnection,
         activities: Vec<NewActivity>,
+        on_progress: impl Fn(usize, usize),
+    ) -> Result<usize, diesel::result::Error> {
+        read_only_mode::ensure_writable()?;
+        let total = activities.len();
+
+        let mut insert_count = 0;
+        for chunk in activities.chunks(IMPORT_CHUNK_SIZE) {
+            insert_count += self.insert_activities(conn, chunk)?;
+            on_progress(insert_count, total);
+        }
+        Ok(insert_count)
+    }
+
+    // Write a chunk of checked import rows in one transaction
+    fn write_activity_imports(
+        &self,
+        conn: &mut SqliteConnection,
+        activity_imports: &[ActivityImport],
+        result: &mut ImportActivitiesResult,
+    ) -> Result<(), diesel::result::Error> {
+        let updated = conn.transaction(|conn| {
+            let mut updated = 0;
+            for activity_import in activity_imports {
+                self.write_activity_import(conn, activity_import)?;
+                if activity_import.updated_activity_id.is_some() {
+                    updated += 1;
+                }
+            }
+            Ok(updated)
+        })?;
+        result.imported += activity_imports.len() - updated;
+        result.updated += updated;
+        Ok(())
+    }
+
+    // Replace the existing activity a row names, or create the row along with its
+    // reinvestment for a dividend. Returns the reinvestment bought.
+    fn write_activity_import(
+        &self,
+        conn: &mut SqliteConnection,
+        activity_import: &ActivityImport,
+    ) -> Result<Option<Activity>, diesel::result::Error> {
+        let mut new_activity = to_new_activity(activity_import);
+        if let Some(activity_id) = &activity_import.updated_activity_id {
+            new_activity.id = Some(activity_id.clone());
+            diesel::update(activities::table.find(activity_id))
+                .set(&new_activity)
+                .execute(conn)?;
+            return Ok(None);
+        }
+
+        self.link_spin_off(conn, &mut new_activity)?;
+        let activity = diesel::insert_into(activities::table)
+            .values(&new_activity)
+            .returning(Activity::as_returning())
+            .get_result(conn)?;
+        self.reinvest_if_enabled(conn, &activity)
+    }
+
+    // Insert a chunk of imported activities in one transaction, along with the
+    // reinvestments of their dividends
+    fn insert_activities(
+        &self,
+        conn: &mut SqliteConnection,
+        new_activities: &[NewActivity],
     ) -> Result<usize, diesel::result::Error> {
         conn.transaction(|conn| {
-            let mut insert_count = 0;
-            for new_activity in activities {
-                diesel::insert_into(activities::table)
+            for new_activity in new_activities {
+                let mut new_activity = new_activity.clone();
+                self.link_spin_off(conn, &mut new_activity)?;
+                let activity = diesel::insert_into(activities::table)
                     .values(&new_activity)
-                    .execute(conn)?;
-                insert_count += 1;
+                    .returning(Activity::as_returning())
+                    .get_result(conn)?;
+                self.reinvest_if_enabled(conn, &activity)?;
             }
-
-            Ok(insert_count)
+            Ok(new_activities.len())
         })
     }
 
+    // Write the activities matching `filter` to a csv file in the import format, so the
+    // file can be imported back. Amounts are converted to `currency` when given.
+    pub fn export_activities(
+        &self,
+        conn: &mut SqliteConnection,
+        file_path: &str,
+        filter: ActivityExportFilter,
+        currency: Option<String>,
+    ) -> Result<usize, String> {
+        let fx_service = match &currency {
+            Some(_) => {
+                let settings = SettingsService::new()
+                    .get_settings(conn)
+                    .map_err(|e| e.to_string())?;
+                let exchange_rates = self
+                    .asset_service
+                    .load_exchange_rates(conn)
+                    .map_err(|e| e.to_string())?;
+                let manual_rates = self
+                    .asset_service
+                    .load_manual_exchange_rates(conn)
+                    .map_err(|e| e.to_string())?;
+                Some(
+                    FxService::new(&settings.base_currency, exchange_rates)
+                        .with_manual_rates(manual_rates),
+                )
+            }
+            None => None,
+        };
+
+        let mut writer = csv::Writer::from_path(file_path).map_err(|e| e.to_string())?;
+        writer
+            .write_record([
+                "date",
+                "symbol",
+                "quantity",
+                "activityType",
+                "unitPrice",
+                "currency",
+                "fee",
+                "comment",
+            ])
+            .map_err(|e| e.to_string())?;
+
+        let mut export_error: Option<String> = None;
+        let exported = self
+            .repo
+            .for_each_filtered_activity(conn, &filter, |activity| {
+                let (unit_price, fee, activity_currency) = match (&fx_service, &currency) {
+                    (Some(fx_service), Some(currency)) => {
+                        match fx_service.get_exchange_rate_for_date(
+                            &activity.currency,
+                            currency,
+                            activity.activity_date.date(),
+                        ) {
+                            Ok(rate) => (activity.unit_price * rate, activity.fee * rate, currency),
+                            Err(e) => {
+                                export_error = Some(e.to_string());
+                                return Err(diesel::result::Error::RollbackTransaction);
+                            }
+                        }
+                    }
+                    _ => (activity.unit_price, activity.fee, &activity.currency),
+                };
+
+                writer
+                    .write_record([
+                        activity
+                            .activity_date
+                            .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+                            .to_string(),
+                        activity.asset_id.clone(),
+                        activity.quantity.to_string(),
+                        activity.activity_type.clone(),
+                        unit_price.to_string(),
+                        activity_currency.clone(),
+                        fee.to_string(),
+                        activity.comment.clone().unwrap_or_default(),
+                    ])
+                    .map_err(|e| {
+                        export_error = Some(e.to_string());
+                        diesel::result::Error::RollbackTransaction
+                    })
+            })
+            .map_err(|e| export_error.take().unwrap_or_else(|| e.to_string()))?;
+
+        writer.flush().map_err(|e| e.to_string())?;
+        Ok(exported)
+    }
+
     // update an activity
     pub fn update_activity(
         &self,
         conn: &mut SqliteConnection,
         activity: ActivityUpdate,
     ) -> Result<Activity, diesel::result::Error> {
+        read_only_mode::ensure_writable()?;
         self.repo.update_activity(conn, activity)
     }
+
+    // delete every activity matching the filter in one transaction, an empty filter is
+    // refused so a mistake never clears the whole table
+    pub fn delete_activities_by_filter(
+        &self,
+        conn: &mut SqliteConnection,
+        filter: ActivityBulkFilter,
+    ) -> Result<usize, String> {
+        read_only_mode::ensure_writable().map_err(|e| e.to_string())?;
+        if filter.is_empty() {
+            return Err("At least one filter is required to delete activities".to_string());
+        }
+
+        conn.transaction(|conn| {
+            let activity_ids = self.repo.get_filtered_activity_ids(conn, &filter)?;
+            let mut deleted = 0;
+            for chunk in activity_ids.chunks(IMPORT_CHUNK_SIZE) {
+                deleted += self.repo.delete_activities(conn, chunk)?;
+            }
+            Ok(deleted)
+        })
+        .map_err(|e: diesel::result::Error| e.to_string())
+    }
+
+    // apply the same changes to every activity matching the filter in one transaction
+    pub fn update_activities_by_filter(
+        &self,
+        conn: &mut SqliteConnection,
+        filter: ActivityBulkFilter,
+        changes: ActivityBulkUpdate,
+    ) -> Result<usize, String> {
+        read_only_mode::ensure_writable().map_err(|e| e.to_string())?;
+        if filter.is_empty() {
+            return Err("At least one filter is required to update activities".to_string());
+        }
+        if changes.account_id.is_none() && changes.currency.is_none() {
+            return Err("No changes to apply".to_string());
+        }
+        if let Some(account_id) = &changes.account_id {
+            self.account_service
+                .get_account_by_id(conn, account_id)
+                .map_err(|_| format!("Account {} not found", account_id))?;
+        }
+
+        conn.transaction(|conn| {
+            let activity_ids = self.repo.get_filtered_activity_ids(conn, &filter)?;
+            let mut updated = 0;
+            for chunk in activity_ids.chunks(IMPORT_CHUNK_SIZE) {
+                updated += self.repo.update_activities(conn, chunk, &changes)?;
+            }
+            Ok(updated)
+        })
+        .map_err(|e: diesel::result::Error| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{DuplicateStatus, NewSymbolOverride};
+    use crate::test_utils::{self, TestDatabase};
+
+    fn setup() -> TestDatabase {
+        let mut db = TestDatabase::new();
+        test_utils::insert_account(&mut db.conn, "account", "USD");
+        test_utils::insert_asset(&mut db.conn, "AAPL", "USD");
+        db
+    }
+
+    fn count_activities(conn: &mut SqliteConnection) -> i64 {
+        activities::table
+            .filter(activities::deleted_at.is_null())
+            .count()
+            .get_result(conn)
+            .unwrap()
+    }
+
+    fn write_buys_csv(rows: usize) -> String {
+        let mut content =
+            String::from("date,symbol,activityType,quantity,unitPrice,currency,fee\n");
+        for row in 1..=rows {
+            content.push_str(&format!("2024-01-02,AAPL,BUY,{},150.25,USD,1\n", row));
+        }
+        test_utils::write_temp_file("csv", &content)
+    }
+
+    #[test]
+    fn check_previews_the_first_rows_and_counts_the_file() {
+        let mut db = setup();
+        let file_path = write_buys_csv(IMPORT_PREVIEW_ROWS * 3);
+
+        let preview =
+            tauri::async_runtime::block_on(ActivityService::new().check_activities_import(
+                &mut db.conn,
+                "account".to_string(),
+                file_path,
+                None,
+                None,
+            ))
+            .unwrap();
+
+        assert_eq!(preview.activities.len(), IMPORT_PREVIEW_ROWS);
+        assert_eq!(preview.total, IMPORT_PREVIEW_ROWS * 3);
+        assert!(preview.activities.iter().all(|a| a.error.is_none()));
+        assert_eq!(count_activities(&mut db.conn), 0);
+    }
+
+    #[test]
+    fn import_commits_the_file_by_chunks() {
+        let mut db = setup();
+        let rows = IMPORT_CHUNK_SIZE * 2 + 10;
+        let file_path = write_buys_csv(rows);
+        let selection = ImportSelection {
+            skipped_lines: vec![2],
+            included_lines: Vec::new(),
+        };
+
+        let progress = std::cell::RefCell::new(Vec::new());
+        let result = tauri::async_runtime::block_on(ActivityService::new().import_activities(
+            &mut db.conn,
+            "account".to_string(),
+            file_path,
+            None,
+            None,
+            selection,
+            |processed, total| progress.borrow_mut().push((processed, total)),
+        ))
+        .unwrap();
+
+        assert_eq!(result.imported, rows - 1);
+        assert_eq!(result.skipped, 1);
+        assert_eq!(count_activities(&mut db.conn), (rows - 1) as i64);
+        assert_eq!(
+            progress.into_inner(),
+            vec![
+                (IMPORT_CHUNK_SIZE, rows),
+                (IMPORT_CHUNK_SIZE * 2, rows),
+                (rows, rows)
+            ]
+        );
+    }
+
+    #[test]
+    fn bulk_delete_refuses_a_malformed_date() {
+        let mut db = setup();
+        test_utils::insert_activity(
+            &mut db.conn,
+            test_utils::new_activity("account", "AAPL", "BUY", "2024-01-02", 1.0, 100.0),
+        );
+
+        let filter = ActivityBulkFilter {
+            start_date: Some("2024-13-45".to_string()),
+            ..Default::default()
+        };
+        let result = ActivityService::new().delete_activities_by_filter(&mut db.conn, filter);
+
+        assert!(result.unwrap_err().contains("2024-13-45"));
+        assert_eq!(count_activities(&mut db.conn), 1);
+    }
+
+    #[test]
+    fn bulk_update_refuses_a_malformed_date() {
+        let mut db = setup();
+        test_utils::insert_activity(
+            &mut db.conn,
+            test_utils::new_activity("account", "AAPL", "BUY", "2024-01-02", 1.0, 100.0),
+        );
+
+        let filter = ActivityBulkFilter {
+            end_date: Some("02/01/2024".to_string()),
+            ..Default::default()
+        };
+        let changes = ActivityBulkUpdate {
+            currency: Some("EUR".to_string()),
+            ..Default::default()
+        };
+        let result =
+            ActivityService::new().update_activities_by_filter(&mut db.conn, filter, changes);
+
+        assert!(result.is_err());
+        let currency: String = activities::table
+            .select(activities::currency)
+            .first(&mut db.conn)
+            .unwrap();
+        assert_eq!(currency, "USD");
+    }
+
+    #[test]
+    fn bulk_delete_refuses_a_filter_of_empty_lists() {
+        let mut db = setup();
+        test_utils::insert_activity(
+            &mut db.conn,
+            test_utils::new_activity("account", "AAPL", "BUY", "2024-01-02", 1.0, 100.0),
+        );
+
+        let filter = ActivityBulkFilter {
+            account_ids: Some(Vec::new()),
+            activity_types: Some(Vec::new()),
+            ..Default::default()
+        };
+        assert!(filter.is_empty());
+        let result = ActivityService::new().delete_activities_by_filter(&mut db.conn, filter);
+
+        assert!(result.is_err());
+        assert_eq!(count_activities(&mut db.conn), 1);
+    }
+
+    #[test]
+    fn bulk_delete_cascades_to_the_dividend_reinvestments() {
+        let mut db = setup();
+        let dividend = test_utils::insert_activity(
+            &mut db.conn,
+            test_utils::new_activity("account", "AAPL", "DIVIDEND", "2024-03-01", 1.0, 50.0),
+        );
+        let mut reinvestment =
+            test_utils::new_activity("account", "AAPL", "BUY", "2024-03-01", 0.5, 100.0);
+        reinvestment.source_activity_id = Some(dividend.id.clone());
+        test_utils::insert_activity(&mut db.conn, reinvestment);
+
+        let filter = ActivityBulkFilter {
+            activity_types: Some(vec!["DIVIDEND".to_string()]),
+            ..Default::default()
+        };
+        let deleted = ActivityService::new()
+            .delete_activities_by_filter(&mut db.conn, filter)
+            .unwrap();
+
+        assert_eq!(deleted, 2);
+        assert_eq!(count_activities(&mut db.conn), 0);
+    }
+
+    #[test]
+    fn dry_run_writes_nothing_and_matches_the_import() {
+        let mut db = setup();
+        test_utils::insert_activity(
+            &mut db.conn,
+            test_utils::new_activity("account", "AAPL", "BUY", "2024-01-02", 1.0, 150.0),
+        );
+        let updated = test_utils::insert_activity(
+            &mut db.conn,
+            test_utils::new_activity("account", "AAPL", "BUY", "2024-01-05", 3.0, 100.0),
+        );
+        let file_path = test_utils::write_temp_file(
+            "csv",
+            &format!(
+                "id,date,symbol,activityType,quantity,unitPrice,currency,fee\n\
+                 ,2024-01-02,AAPL,BUY,1,150,USD,0\n\
+                 ,2024-01-03,AAPL,BUY,2,151,USD,1\n\
+                 ,2024-01-04,AAPL,SELL,1,152,USD,1\n\
+                 {},2024-01-05,AAPL,BUY,3,101,USD,0\n",
+                updated.id
+            ),
+        );
+        let unit_price = |conn: &mut SqliteConnection| -> f64 {
+            activities::table
+                .find(&updated.id)
+                .select(activities::unit_price)
+                .first(conn)
+                .unwrap()
+        };
+        let service = ActivityService::new();
+
+        let report = tauri::async_runtime::block_on(service.dry_run_activities_import(
+            &mut db.conn,
+            "account".to_string(),
+            file_path.clone(),
+            None,
+            None,
+        ))
+        .unwrap();
+
+        assert_eq!(
+            (
+                report.creates,
+                report.updates,
+                report.duplicates,
+                report.errors
+            ),
+            (2, 1, 1, 0)
+        );
+        assert_eq!(count_activities(&mut db.conn), 2);
+        assert_eq!(unit_price(&mut db.conn), 100.0);
+
+        let result = tauri::async_runtime::block_on(service.import_activities(
+            &mut db.conn,
+            "account".to_string(),
+            file_path,
+            None,
+            None,
+            ImportSelection::default(),
+            |_, _| {},
+        ))
+        .unwrap();
+
+        assert_eq!(
+            (result.imported, result.updated, result.skipped),
+            (report.creates, report.updates, report.duplicates)
+        );
+        assert_eq!(count_activities(&mut db.conn), 4);
+        assert_eq!(unit_price(&mut db.conn), 101.0);
+    }
+
+    #[test]
+    fn created_dividends_are_reinvested() {
+        let mut db = setup();
+        test_utils::insert_quote(&mut db.conn, "AAPL", "2024-03-01", 25.0);
+        let service = ActivityService::new();
+        service
+            .set_drip_enabled(
+                &mut db.conn,
+                DripSetting {
+                    account_id: "account".to_string(),
+                    asset_id: "AAPL".to_string(),
+                },
+                true,
+            )
+            .unwrap();
+        let dividend =
+            test_utils::new_activity("account", "AAPL", "DIVIDEND", "2024-03-01", 50.0, 1.0);
+        let dividend_id = dividend.id.clone();
+
+        service
+            .create_activities(&mut db.conn, vec![dividend], |_, _| {})
+            .unwrap();
+
+        let reinvestment = activities::table
+            .filter(activities::activity_type.eq("BUY"))
+            .first::<Activity>(&mut db.conn)
+            .unwrap();
+        assert_eq!(reinvestment.quantity, 2.0);
+        assert_eq!(reinvestment.unit_price, 25.0);
+        assert_eq!(reinvestment.source_activity_id, dividend_id);
+    }
+
+    #[test]
+    fn received_spin_off_shares_are_linked_to_their_parent() {
+        let mut db = setup();
+        test_utils::insert_asset(&mut db.conn, "MSFT", "USD");
+        test_utils::insert_asset(&mut db.conn, "NEWCO", "USD");
+        let parent = test_utils::insert_activity(
+            &mut db.conn,
+            test_utils::new_activity("account", "AAPL", "SPIN_OFF_OUT", "2024-06-03", 0.0, 0.2),
+        );
+        let service = ActivityService::new();
+        let received = |conn: &mut SqliteConnection| {
+            tauri::async_runtime::block_on(service.create_activity(
+                conn,
+                test_utils::new_activity("account", "NEWCO", "SPIN_OFF_IN", "2024-06-03", 5.0, 0.0),
+            ))
+        };
+
+        let child = received(&mut db.conn).unwrap();
+        assert_eq!(child.source_activity_id, Some(parent.id));
+
+        // Two spin-offs left unlinked on the same day need the parent to be given
+        for asset_id in ["AAPL", "MSFT"] {
+            test_utils::insert_activity(
+                &mut db.conn,
+                test_utils::new_activity(
+                    "account",
+                    asset_id,
+                    "SPIN_OFF_OUT",
+                    "2024-06-03",
+                    0.0,
+                    0.2,
+                ),
+            );
+        }
+        assert!(received(&mut db.conn).is_err());
+    }
+
+    #[test]
+    fn purge_removes_the_activities_deleted_before_the_undo_window() {
+        let mut db = setup();
+        test_utils::insert_settings(&mut db.conn, "USD");
+        let now = chrono::Utc::now().naive_utc();
+        for deleted_days_ago in [8, 6] {
+            let activity = test_utils::insert_activity(
+                &mut db.conn,
+                test_utils::new_activity("account", "AAPL", "BUY", "2024-01-02", 1.0, 150.0),
+            );
+            diesel::update(activities::table.find(&activity.id))
+                .set(activities::deleted_at.eq(now - chrono::Duration::days(deleted_days_ago)))
+                .execute(&mut db.conn)
+                .unwrap();
+        }
+
+        let purged = ActivityService::new()
+            .purge_deleted_activities(&mut db.conn)
+            .unwrap();
+
+        // Only the one deleted 8 days ago is past the default 7 days undo window
+        assert_eq!(purged, 1);
+        let remaining: i64 = activities::table.count().get_result(&mut db.conn).unwrap();
+        assert_eq!(remaining, 1);
+    }
+
+    #[test]
+    fn create_is_refused_in_read_only_mode() {
+        let mut db = setup();
+        read_only_mode::set_enabled(true);
+
+        let result = tauri::async_runtime::block_on(ActivityService::new().create_activity(
+            &mut db.conn,
+            test_utils::new_activity("account", "AAPL", "BUY", "2024-01-02", 1.0, 150.0),
+        ));
+
+        assert!(result.is_err());
+        assert_eq!(count_activities(&mut db.conn), 0);
+    }
+
+    #[test]
+    fn check_in_read_only_mode_saves_nothing() {
+        let mut db = setup();
+        read_only_mode::set_enabled(true);
+        let file_path = write_buys_csv(3);
+
+        let preview =
+            tauri::async_runtime::block_on(ActivityService::new().check_activities_import(
+                &mut db.conn,
+                "account".to_string(),
+                file_path,
+                None,
+                Some(ImportLocale::default()),
+            ))
+            .unwrap();
+
+        assert_eq!(preview.total, 3);
+        assert!(preview.activities.iter().all(|a| a.error.is_none()));
+        let service = ActivityService::new();
+        assert_eq!(
+            service.get_import_locale(&mut db.conn, "account").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn exported_activities_are_imported_back_as_exact_duplicates() {
+        let mut db = setup();
+        test_utils::insert_asset(&mut db.conn, "MSFT", "USD");
+        let mut buy = test_utils::new_activity("account", "AAPL", "BUY", "2024-01-02", 10.0, 150.0);
+        buy.fee = 1.0;
+        test_utils::insert_activity(&mut db.conn, buy);
+        let mut sell =
+            test_utils::new_activity("account", "AAPL", "SELL", "2024-02-01", 4.0, 175.5);
+        sell.comment = Some("Trim".to_string());
+        test_utils::insert_activity(&mut db.conn, sell);
+        test_utils::insert_activity(
+            &mut db.conn,
+            test_utils::new_activity("account", "MSFT", "BUY", "2024-03-15", 0.125, 410.25),
+        );
+        let service = ActivityService::new();
+        let file_path = test_utils::write_temp_file("csv", "");
+
+        let exported = service
+            .export_activities(
+                &mut db.conn,
+                &file_path,
+                ActivityExportFilter::default(),
+                None,
+            )
+            .unwrap();
+        let preview = tauri::async_runtime::block_on(service.check_activities_import(
+            &mut db.conn,
+            "account".to_string(),
+            file_path,
+            None,
+            None,
+        ))
+        .unwrap();
+
+        assert_eq!(exported, 3);
+        assert_eq!(preview.total, 3);
+        for activity_import in &preview.activities {
+            assert!(
+                activity_import.error.is_none(),
+                "{:?}",
+                activity_import.error
+            );
+            assert_eq!(activity_import.duplicate, Some(DuplicateStatus::Exact));
+        }
+        let sell = preview
+            .activities
+            .iter()
+            .find(|a| a.activity_type == "SELL")
+            .unwrap();
+        assert_eq!(sell.comment.as_deref(), Some("Trim"));
+    }
+
+    #[test]
+    fn exported_amounts_are_converted_to_the_requested_currency() {
+        let mut db = setup();
+        test_utils::insert_settings(&mut db.conn, "USD");
+        diesel::insert_into(assets::table)
+            .values(NewAsset {
+                id: "EURUSD=X".to_string(),
+                symbol: "EURUSD=X".to_string(),
+                asset_type: Some("Currency".to_string()),
+                currency: "USD".to_string(),
+                data_source: "Yahoo".to_string(),
+                ..Default::default()
+            })
+            .execute(&mut db.conn)
+            .unwrap();
+        test_utils::insert_quote(&mut db.conn, "EURUSD=X", "2024-01-02", 1.25);
+        let mut buy = test_utils::new_activity("account", "AAPL", "BUY", "2024-01-02", 10.0, 150.0);
+        buy.fee = 2.0;
+        test_utils::insert_activity(&mut db.conn, buy);
+        let file_path = test_utils::write_temp_file("csv", "");
+
+        ActivityService::new()
+            .export_activities(
+                &mut db.conn,
+                &file_path,
+                ActivityExportFilter::default(),
+                Some("EUR".to_string()),
+            )
+            .unwrap();
+
+        let content = std::fs::read_to_string(&file_path).unwrap();
+        let row: Vec<&str> = content.lines().nth(1).unwrap().split(',').collect();
+        assert_eq!(row[1], "AAPL");
+        assert_eq!(row[2], "10");
+        assert!((row[4].parse::<f64>().unwrap() - 120.0).abs() < 1e-9);
+        assert_eq!(row[5], "EUR");
+        assert!((row[6].parse::<f64>().unwrap() - 1.6).abs() < 1e-9);
+    }
+
+    // Symbol the first row of `file_path` is checked as for `account_id`
+    fn checked_symbol(conn: &mut SqliteConnection, account_id: &str, file_path: &str) -> String {
+        let preview =
+            tauri::async_runtime::block_on(ActivityService::new().check_activities_import(
+                conn,
+                account_id.to_string(),
+                file_path.to_string(),
+                None,
+                None,
+            ))
+            .unwrap();
+        let activity_import = &preview.activities[0];
+        assert!(
+            activity_import.error.is_none(),
+            "{:?}",
+            activity_import.error
+        );
+        activity_import.symbol.clone()
+    }
+
+    #[test]
+    fn symbol_overrides_skip_the_resolution_and_the_account_one_wins() {
+        let mut db = setup();
+        test_utils::insert_account(&mut db.conn, "other", "EUR");
+        test_utils::insert_asset(&mut db.conn, "VWCE.DE", "EUR");
+        test_utils::insert_asset(&mut db.conn, "VWCE.MI", "EUR");
+        let asset_service = AssetService::new();
+        for (account_id, provider_symbol) in [(None, "vwce.de"), (Some("account"), "VWCE.MI")] {
+            asset_service
+                .add_symbol_override(
+                    &mut db.conn,
+                    NewSymbolOverride {
+                        symbol: "ie00b3rbwm25".to_string(),
+                        account_id: account_id.map(str::to_string),
+                        provider_symbol: provider_symbol.to_string(),
+                    },
+                )
+                .unwrap();
+        }
+        // The ISIN is known to no asset, resolving it would need the market data provider
+        let file_path = test_utils::write_temp_file(
+            "csv",
+            "date,symbol,activityType,quantity,unitPrice,currency,fee\n\
+             2024-01-02,IE00B3RBWM25,BUY,10,110.5,EUR,0\n",
+        );
+
+        assert_eq!(
+            checked_symbol(&mut db.conn, "account", &file_path),
+            "VWCE.MI"
+        );
+        assert_eq!(checked_symbol(&mut db.conn, "other", &file_path), "VWCE.DE");
+    }
 }
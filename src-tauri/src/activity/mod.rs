@@ -1,5 +1,7 @@
 pub mod activity_commands;
+pub mod activity_import;
 pub mod activity_repository;
 pub mod activity_service;
+pub mod ofx_import;
 
 pub use activity_repository::ActivityRepository;
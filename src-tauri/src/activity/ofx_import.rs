@@ -0,0 +1,590 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use chrono::NaiveDate;
+
+use crate::models::ActivityImport;
+
+const OFX_EXTENSIONS: [&str; 2] = ["ofx", "qfx"];
+
+// Security descriptions of the SECLIST, one per kind of instrument
+const SECURITY_INFO_TAGS: [&str; 5] = ["STOCKINFO", "MFINFO", "DEBTINFO", "OPTINFO", "OTHERINFO"];
+
+const BUY_TAGS: [&str; 5] = ["BUYSTOCK", "BUYMF", "BUYDEBT", "BUYOPT", "BUYOTHER"];
+const SELL_TAGS: [&str; 5] = ["SELLSTOCK", "SELLMF", "SELLDEBT", "SELLOPT", "SELLOTHER"];
+
+pub fn is_ofx_file(file_path: &str) -> bool {
+    Path::new(file_path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            OFX_EXTENSIONS
+                .iter()
+                .any(|ofx_extension| extension.eq_ignore_ascii_case(ofx_extension))
+        })
+}
+
+/// An element of an OFX document. Aggregates have children, elements have a value.
+///
+/// OFX 1.x is SGML where elements are never closed, OFX 2.x is XML where they are.
+/// Both are read the same way: an element is a tag directly followed by text, an
+/// aggregate is a tag followed by other tags until its closing tag.
+#[derive(Debug, Default)]
+struct OfxNode {
+    name: String,
+    value: Option<String>,
+    children: Vec<OfxNode>,
+    line_number: usize,
+}
+
+impl OfxNode {
+    fn child(&self, name: &str) -> Option<&OfxNode> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    fn value_of(&self, name: &str) -> Option<&str> {
+        self.child(name)?.value.as_deref()
+    }
+
+    fn amount_of(&self, name: &str) -> Result<f64, String> {
+        self.optional_amount_of(name)?.ok_or_else(|| {
+            format!(
+                "Missing {} of {}. Line: {}",
+                name, self.name, self.line_number
+            )
+        })
+    }
+
+    // Amounts that may be left out, such as the fees of a trade
+    fn optional_amount_of(&self, name: &str) -> Result<Option<f64>, String> {
+        // An empty element is read as an aggregate without a value
+        let Some((element, value)) = self
+            .child(name)
+            .and_then(|element| Some((element, element.value.as_deref()?)))
+        else {
+            return Ok(None);
+        };
+        value
+            .replace(',', ".")
+            .parse::<f64>()
+            .map(Some)
+            .map_err(|_| format!("Invalid amount: {}. Line: {}", value, element.line_number))
+    }
+
+    // All the aggregates with one of the given names, in document order
+    fn find_all<'a>(&'a self, names: &[&str], found: &mut Vec<&'a OfxNode>) {
+        for child in &self.children {
+            if names.contains(&child.name.as_str()) {
+                found.push(child);
+            } else {
+                child.find_all(names, found);
+            }
+        }
+    }
+}
+
+fn decode_entities(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+fn parse_document(content: &str) -> Result<OfxNode, String> {
+    // Skip the SGML header lines or the XML declarations before the OFX root
+    let start = content
+        .find("<OFX>")
+        .or_else(|| content.find("<ofx>"))
+        .ok_or_else(|| "No OFX content found in the file".to_string())?;
+    let mut line_number = content[..start].matches('\n').count() + 1;
+
+    let mut stack = vec![OfxNode::default()];
+    let mut rest = &content[start..];
+
+    while let Some(tag_start) = rest.find('<') {
+        line_number += rest[..tag_start].matches('\n').count();
+        let tag_end = rest[tag_start..]
+            .find('>')
+            .map(|end| tag_start + end)
+            .ok_or_else(|| format!("Unterminated tag. Line: {}", line_number))?;
+        let tag = rest[tag_start + 1..tag_end].trim().to_uppercase();
+        rest = &rest[tag_end + 1..];
+
+        let text_end = rest.find('<').unwrap_or(rest.len());
+        let text = rest[..text_end].trim();
+
+        if let Some(name) = tag.strip_prefix('/') {
+            // Closing tags of elements are only present in OFX 2.x and are ignored. An
+            // aggregate closes any empty SGML element that was taken for an aggregate.
+            if let Some(position) = stack.iter().skip(1).position(|node| node.name == name) {
+                while stack.len() > position + 1 {
+                    let node = stack.pop().unwrap();
+                    stack.last_mut().unwrap().children.push(node);
+                }
+            }
+        } else if tag.starts_with('?') || tag.starts_with('!') {
+            continue;
+        } else if !text.is_empty() {
+            stack.last_mut().unwrap().children.push(OfxNode {
+                name: tag,
+                value: Some(decode_entities(text)),
+                children: Vec::new(),
+                line_number,
+            });
+        } else {
+            stack.push(OfxNode {
+                name: tag,
+                line_number,
+                ..Default::default()
+            });
+        }
+    }
+
+    // Close what a truncated file left open
+    while stack.len() > 1 {
+        let node = stack.pop().unwrap();
+        stack.last_mut().unwrap().children.push(node);
+    }
+
+    Ok(stack.pop().unwrap())
+}
+
+// OFX dates are YYYYMMDD, optionally followed by the time and the timezone
+fn parse_ofx_date(value: Option<&str>) -> Result<String, String> {
+    let value = value.unwrap_or("");
+    value
+        .get(..8)
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y%m%d").ok())
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .ok_or_else(|| format!("Invalid date: {}", value))
+}
+
+// Ticker of each security of the SECLIST, keyed by its unique id (CUSIP, ISIN...)
+fn parse_securities(document: &OfxNode) -> HashMap<String, String> {
+    let mut security_infos = Vec::new();
+    document.find_all(&SECURITY_INFO_TAGS, &mut security_infos);
+
+    security_infos
+        .into_iter()
+        .filter_map(|security_info| {
+            let secinfo = security_info.child("SECINFO")?;
+            let unique_id = secinfo.child("SECID")?.value_of("UNIQUEID")?;
+            let ticker = secinfo.value_of("TICKER").unwrap_or(unique_id);
+            Some((unique_id.to_string(), ticker.to_string()))
+        })
+        .collect()
+}
+
+struct TransactionContext<'a> {
+    securities: &'a HashMap<String, String>,
+    currency: &'a str,
+}
+
+impl TransactionContext<'_> {
+    fn symbol(&self, transaction: &OfxNode) -> Result<String, String> {
+        let unique_id = transaction
+            .child("SECID")
+            .and_then(|secid| secid.value_of("UNIQUEID"))
+            .ok_or_else(|| {
+                format!(
+                    "Missing security of {}. Line: {}",
+                    transaction.name, transaction.line_number
+                )
+            })?;
+
+        Ok(self
+            .securities
+            .get(unique_id)
+            .cloned()
+            .unwrap_or_else(|| unique_id.to_string()))
+    }
+
+    // Transactions in a currency other than the statement one carry it in CURRENCY
+    fn currency(&self, transaction: &OfxNode) -> String {
+        transaction
+            .child("CURRENCY")
+            .or_else(|| transaction.child("ORIGCURRENCY"))
+            .and_then(|currency| currency.value_of("CURSYM"))
+            .unwrap_or(self.currency)
+            .to_string()
+    }
+
+    fn activity(
+        &self,
+        transaction: &OfxNode,
+        activity_type: &str,
+        symbol: String,
+        quantity: f64,
+        unit_price: f64,
+        fee: f64,
+    ) -> Result<ActivityImport, String> {
+        // Investment transactions keep their date and memo in INVTRAN
+        let details = transaction.child("INVTRAN").unwrap_or(transaction);
+        let date = details
+            .value_of("DTTRADE")
+            .or_else(|| details.value_of("DTPOSTED"));
+        let comment = details
+            .value_of("MEMO")
+            .or_else(|| details.value_of("NAME"))
+            .map(str::to_string);
+
+        Ok(ActivityImport {
+            date: parse_ofx_date(date)
+                .map_err(|e| format!("{}. Line: {}", e, transaction.line_number))?,
+            symbol,
+            activity_type: activity_type.to_string(),
+            quantity,
+            unit_price,
+            currency: self.currency(transaction),
+            fee,
+            comment,
+            line_number: Some(transaction.line_number as i32),
+            ..Default::default()
+        })
+    }
+
+    fn fees(transaction: &OfxNode) -> Result<f64, String> {
+        let mut fees = 0.0;
+        for name in ["COMMISSION", "FEES", "TAXES", "LOAD"] {
+            fees += transaction.optional_amount_of(name)?.unwrap_or(0.0).abs();
+        }
+        Ok(fees)
+    }
+
+    fn cash_symbol(&self, transaction: &OfxNode) -> String {
+        format!("$CASH-{}", self.currency(transaction))
+    }
+
+    // BUYxxx and SELLxxx wrap the trade details in an INVBUY or INVSELL aggregate
+    fn parse_trade(
+        &self,
+        transaction: &OfxNode,
+        activity_type: &str,
+    ) -> Result<Vec<ActivityImport>, String> {
+        let trade = transaction
+            .child("INVBUY")
+            .or_else(|| transaction.child("INVSELL"))
+            .unwrap_or(transaction);
+
+        Ok(vec![self.activity(
+            trade,
+            activity_type,
+            self.symbol(trade)?,
+            trade.amount_of("UNITS")?.abs(),
+            trade.amount_of("UNITPRICE")?,
+            Self::fees(trade)?,
+        )?])
+    }
+
+    fn parse_income(&self, transaction: &OfxNode) -> Result<Vec<ActivityImport>, String> {
+        let total = transaction.amount_of("TOTAL")?.abs();
+
+        let activity = match transaction.value_of("INCOMETYPE") {
+            Some("INTEREST") => self.activity(
+                transaction,
+                "INTEREST",
+                self.cash_symbol(transaction),
+                total,
+                1.0,
+                0.0,
+            )?,
+            // DIV, CGLONG, CGSHORT and MISC are all paid by the security
+            _ => self.activity(
+                transaction,
+                "DIVIDEND",
+                self.symbol(transaction)?,
+                total,
+                1.0,
+                0.0,
+            )?,
+        };
+
+        Ok(vec![activity])
+    }
+
+    // A reinvested income is received as cash then used to buy more shares
+    fn parse_reinvest(&self, transaction: &OfxNode) -> Result<Vec<ActivityImport>, String> {
+        let symbol = self.symbol(transaction)?;
+
+        Ok(vec![
+            self.activity(
+                transaction,
+                "DIVIDEND",
+                symbol.clone(),
+                transaction.amount_of("TOTAL")?.abs(),
+                1.0,
+                0.0,
+            )?,
+            self.activity(
+                transaction,
+                "BUY",
+                symbol,
+                transaction.amount_of("UNITS")?.abs(),
+                transaction.amount_of("UNITPRICE")?,
+                Self::fees(transaction)?,
+            )?,
+        ])
+    }
+
+    // Bank transactions, also found as INVBANKTRAN in brokerage statements
+    fn parse_bank_transaction(&self, transaction: &OfxNode) -> Result<Vec<ActivityImport>, String> {
+        let amount = transaction.amount_of("TRNAMT")?;
+        let cash_symbol = self.cash_symbol(transaction);
+
+        let activity = match transaction.value_of("TRNTYPE") {
+            Some("INT") | Some("DIV") => {
+                self.activity(transaction, "INTEREST", cash_symbol, amount.abs(), 1.0, 0.0)?
+            }
+            Some("FEE") | Some("SRVCHG") => {
+                self.activity(transaction, "FEE", cash_symbol, 0.0, 1.0, amount.abs())?
+            }
+            _ => self.activity(
+                transaction,
+                if amount < 0.0 {
+                    "WITHDRAWAL"
+                } else {
+                    "DEPOSIT"
+                },
+                cash_symbol,
+                amount.abs(),
+                1.0,
+                0.0,
+            )?,
+        };
+
+        Ok(vec![activity])
+    }
+
+    fn parse_transaction(&self, transaction: &OfxNode) -> Result<Vec<ActivityImport>, String> {
+        let name = transaction.name.as_str();
+        if BUY_TAGS.contains(&name) {
+            self.parse_trade(transaction, "BUY")
+        } else if SELL_TAGS.contains(&name) {
+            self.parse_trade(transaction, "SELL")
+        } else if name == "INCOME" {
+            self.parse_income(transaction)
+        } else if name == "REINVEST" {
+            self.parse_reinvest(transaction)
+        } else {
+            self.parse_bank_transaction(transaction)
+        }
+    }
+}
+
+// Convert the investment and bank transactions of an OFX/QFX file into activities,
+// `currency` is used when a statement does not declare its own
+pub fn parse_ofx(content: &str, currency: &str) -> Result<Vec<ActivityImport>, String> {
+    let document = parse_document(content)?;
+    let securities = parse_securities(&document);
+
+    let mut statements = Vec::new();
+    document.find_all(&["STMTRS", "INVSTMTRS", "CCSTMTRS"], &mut statements);
+
+    let mut activities = Vec::new();
+    for statement in statements {
+        let context = TransactionContext {
+            securities: &securities,
+            currency: statement.value_of("CURDEF").unwrap_or(currency),
+        };
+
+        let mut transactions = Vec::new();
+        statement.find_all(
+            &[
+                &BUY_TAGS[..],
+                &SELL_TAGS[..],
+                &["INCOME", "REINVEST", "STMTTRN"],
+            ]
+            .concat(),
+            &mut transactions,
+        );
+
+        for transaction in transactions {
+            activities.extend(context.parse_transaction(transaction)?);
+        }
+    }
+
+    Ok(activities)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SGML_STATEMENT: &str = "OFXHEADER:100
+DATA:OFXSGML
+VERSION:102
+
+<OFX>
+<INVSTMTMSGSRSV1>
+<INVSTMTTRNRS>
+<INVSTMTRS>
+<CURDEF>USD
+<INVTRANLIST>
+<BUYSTOCK>
+<INVBUY>
+<INVTRAN>
+<FITID>1
+<DTTRADE>20240102120000[-5:EST]
+<MEMO>Buy Apple
+</INVTRAN>
+<SECID>
+<UNIQUEID>037833100
+<UNIQUEIDTYPE>CUSIP
+</SECID>
+<UNITS>10
+<UNITPRICE>185,5
+<COMMISSION>-1.5
+<TOTAL>-1856.5
+</INVBUY>
+<BUYTYPE>BUY
+</BUYSTOCK>
+</INVTRANLIST>
+</INVSTMTRS>
+</INVSTMTTRNRS>
+</INVSTMTMSGSRSV1>
+<SECLISTMSGSRSV1>
+<SECLIST>
+<STOCKINFO>
+<SECINFO>
+<SECID>
+<UNIQUEID>037833100
+<UNIQUEIDTYPE>CUSIP
+</SECID>
+<NAME>Apple Inc
+<TICKER>AAPL
+</SECINFO>
+</STOCKINFO>
+</SECLIST>
+</SECLISTMSGSRSV1>
+</OFX>
+";
+
+    const XML_STATEMENT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<?OFX OFXHEADER="200" VERSION="220"?>
+<OFX>
+  <INVSTMTMSGSRSV1>
+    <INVSTMTTRNRS>
+      <INVSTMTRS>
+        <CURDEF>USD</CURDEF>
+        <INVTRANLIST>
+          <BUYSTOCK>
+            <INVBUY>
+              <INVTRAN>
+                <FITID>1</FITID>
+                <DTTRADE>20240102</DTTRADE>
+                <MEMO>Buy Apple</MEMO>
+              </INVTRAN>
+              <SECID>
+                <UNIQUEID>037833100</UNIQUEID>
+                <UNIQUEIDTYPE>CUSIP</UNIQUEIDTYPE>
+              </SECID>
+              <UNITS>10</UNITS>
+              <UNITPRICE>185.5</UNITPRICE>
+              <COMMISSION>1.5</COMMISSION>
+              <FEES></FEES>
+              <TOTAL>-1856.5</TOTAL>
+            </INVBUY>
+            <BUYTYPE>BUY</BUYTYPE>
+          </BUYSTOCK>
+        </INVTRANLIST>
+      </INVSTMTRS>
+    </INVSTMTTRNRS>
+  </INVSTMTMSGSRSV1>
+  <SECLISTMSGSRSV1>
+    <SECLIST>
+      <STOCKINFO>
+        <SECINFO>
+          <SECID>
+            <UNIQUEID>037833100</UNIQUEID>
+            <UNIQUEIDTYPE>CUSIP</UNIQUEIDTYPE>
+          </SECID>
+          <NAME>Apple Inc</NAME>
+          <TICKER>AAPL</TICKER>
+        </SECINFO>
+      </STOCKINFO>
+    </SECLIST>
+  </SECLISTMSGSRSV1>
+</OFX>
+"#;
+
+    fn investment_statement(transactions: &str) -> String {
+        format!(
+            "<OFX><INVSTMTMSGSRSV1><INVSTMTTRNRS><INVSTMTRS><CURDEF>EUR\n<INVTRANLIST>\n{}\n\
+             </INVTRANLIST></INVSTMTRS></INVSTMTTRNRS></INVSTMTMSGSRSV1></OFX>",
+            transactions
+        )
+    }
+
+    #[test]
+    fn sgml_and_xml_statements_are_read_the_same_way() {
+        let sgml = parse_ofx(SGML_STATEMENT, "CAD").unwrap();
+        let xml = parse_ofx(XML_STATEMENT, "CAD").unwrap();
+
+        for activities in [&sgml, &xml] {
+            assert_eq!(activities.len(), 1);
+            let buy = &activities[0];
+            assert_eq!(buy.activity_type, "BUY");
+            assert_eq!(buy.symbol, "AAPL");
+            assert_eq!(buy.date, "2024-01-02");
+            assert_eq!(buy.quantity, 10.0);
+            assert_eq!(buy.unit_price, 185.5);
+            assert_eq!(buy.fee, 1.5);
+            assert_eq!(buy.currency, "USD");
+            assert_eq!(buy.comment.as_deref(), Some("Buy Apple"));
+        }
+    }
+
+    #[test]
+    fn a_reinvested_income_is_split_into_a_dividend_and_a_buy() {
+        let content = investment_statement(
+            "<REINVEST><INVTRAN><FITID>2\n<DTTRADE>20240315\n</INVTRAN>\
+             <SECID><UNIQUEID>IE00B4L5Y983\n<UNIQUEIDTYPE>ISIN\n</SECID>\
+             <INCOMETYPE>DIV\n<TOTAL>-40\n<UNITS>0.5\n<UNITPRICE>80\n</REINVEST>",
+        );
+
+        let activities = parse_ofx(&content, "USD").unwrap();
+
+        assert_eq!(activities.len(), 2);
+        let (dividend, buy) = (&activities[0], &activities[1]);
+        assert_eq!(dividend.activity_type, "DIVIDEND");
+        assert_eq!(dividend.symbol, "IE00B4L5Y983");
+        assert_eq!(dividend.quantity, 40.0);
+        assert_eq!(dividend.unit_price, 1.0);
+        assert_eq!(buy.activity_type, "BUY");
+        assert_eq!(buy.symbol, "IE00B4L5Y983");
+        assert_eq!(buy.quantity, 0.5);
+        assert_eq!(buy.unit_price, 80.0);
+        assert_eq!(buy.fee, 0.0);
+        assert_eq!(buy.date, "2024-03-15");
+        assert_eq!(buy.currency, "EUR");
+    }
+
+    #[test]
+    fn a_malformed_amount_is_reported_with_its_line() {
+        let content = investment_statement(
+            "<BUYSTOCK><INVBUY><INVTRAN><DTTRADE>20240102\n</INVTRAN>\n\
+             <SECID><UNIQUEID>AAPL\n</SECID>\n<UNITS>ten\n<UNITPRICE>185\n</INVBUY></BUYSTOCK>",
+        );
+
+        assert_eq!(
+            parse_ofx(&content, "USD").unwrap_err(),
+            "Invalid amount: ten. Line: 7"
+        );
+    }
+
+    #[test]
+    fn a_missing_amount_is_reported_with_the_transaction_line() {
+        let content = investment_statement(
+            "<BUYSTOCK><INVBUY><INVTRAN><DTTRADE>20240102\n</INVTRAN>\n\
+             <SECID><UNIQUEID>AAPL\n</SECID>\n<UNITPRICE>185\n</INVBUY></BUYSTOCK>",
+        );
+
+        assert_eq!(
+            parse_ofx(&content, "USD").unwrap_err(),
+            "Missing UNITS of INVBUY. Line: 3"
+        );
+    }
+}
@@ -1,14 +1,29 @@
+use crate::asset::quote_staleness;
+use crate::asset::security_identifier::SecurityIdentifier;
+use crate::asset::symbol_normalization;
 use crate::db;
-use crate::models::{Asset, AssetProfile, NewAsset, Quote, QuoteSummary};
+use crate::models::{
+    Asset, AssetClassificationReport, AssetLookup, AssetProfile, AssetTag, ManualExchangeRate,
+    NewAsset, NewManualExchangeRate, NewManualQuote, NewSymbolOverride, Quote,
+    QuoteStalenessPolicy, QuoteSummary, SymbolOverride,
+};
 use crate::providers::yahoo_provider::YahooProvider;
+use crate::settings::read_only_mode;
+use std::collections::HashSet;
 use std::time::SystemTime;
 
-use crate::schema::{activities, assets, quotes};
-use chrono::{NaiveDateTime, TimeZone, Utc};
+use crate::schema::{
+    activities, asset_tags, assets, manual_exchange_rates, quote_staleness_policies, quotes,
+    symbol_overrides,
+};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
 use diesel::prelude::*;
 use diesel::SqliteConnection;
 use std::collections::HashMap;
 
+// Data source of the quotes entered by hand, market data syncs leave them in place
+pub const MANUAL_DATA_SOURCE: &str = "MANUAL";
+
 pub struct AssetService {
     provider: YahooProvider,
 }
@@ -82,26 +97,22 @@ impl AssetService {
     pub fn load_currency_assets(
         &self,
         conn: &mut SqliteConnection,
-        base_currency: &str,
     ) -> Result<Vec<Asset>, diesel::result::Error> {
         use crate::schema::assets::dsl::*;
 
-        assets
-            .filter(asset_type.eq("Currency"))
-            .filter(symbol.like(format!("{}%", base_currency)))
-            .load::<Asset>(conn)
+        assets.filter(asset_type.eq("Currency")).load::<Asset>(conn)
     }
 
+    // Latest rate of every currency pair asset, keyed by its symbol
     pub fn load_exchange_rates(
         &self,
         conn: &mut SqliteConnection,
-        base_currency: &str,
     ) -> Result<HashMap<String, f64>, diesel::result::Error> {
         use crate::schema::quotes::dsl::{date, quotes, symbol};
 
         let mut exchange_rates = HashMap::new();
 
-        let currency_assets = self.load_currency_assets(conn, base_currency)?;
+        let currency_assets = self.load_currency_assets(conn)?;
 
         for asset in currency_assets {
             let latest_quote = quotes
@@ -118,6 +129,75 @@ impl AssetService {
         Ok(exchange_rates)
     }
 
+    // Daily rates of every currency pair asset keyed by its symbol, oldest first
+    pub fn load_exchange_rate_history(
+        &self,
+        conn: &mut SqliteConnection,
+    ) -> Result<HashMap<String, Vec<(NaiveDate, f64)>>, diesel::result::Error> {
+        let currency_symbols: Vec<String> = self
+            .load_currency_assets(conn)?
+            .into_iter()
+            .map(|asset| asset.symbol)
+            .collect();
+
+        let daily_rates = quotes::table
+            .filter(quotes::symbol.eq_any(&currency_symbols))
+            .order(quotes::date.asc())
+            .select((quotes::symbol, quotes::date, quotes::close))
+            .load::<(String, NaiveDateTime, f64)>(conn)?;
+
+        let mut rate_history: HashMap<String, Vec<(NaiveDate, f64)>> = HashMap::new();
+        for (symbol, date, close) in daily_rates {
+            rate_history
+                .entry(symbol)
+                .or_default()
+                .push((date.date(), close));
+        }
+
+        Ok(rate_history)
+    }
+
+    // Rates entered by the user, most recent first so it wins over older overlapping ones
+    pub fn load_manual_exchange_rates(
+        &self,
+        conn: &mut SqliteConnection,
+    ) -> Result<Vec<ManualExchangeRate>, diesel::result::Error> {
+        manual_exchange_rates::table
+            .order(manual_exchange_rates::created_at.desc())
+            .load::<ManualExchangeRate>(conn)
+    }
+
+    pub fn add_manual_exchange_rate(
+        &self,
+        conn: &mut SqliteConnection,
+        new_rate: NewManualExchangeRate,
+    ) -> Result<ManualExchangeRate, diesel::result::Error> {
+        read_only_mode::ensure_writable()?;
+        let manual_rate = ManualExchangeRate {
+            id: uuid::Uuid::new_v4().to_string(),
+            from_currency: new_rate.from_currency.to_uppercase(),
+            to_currency: new_rate.to_currency.to_uppercase(),
+            rate: new_rate.rate,
+            start_date: new_rate.start_date,
+            end_date: new_rate.end_date.unwrap_or(new_rate.start_date),
+            created_at: chrono::Utc::now().naive_utc(),
+        };
+
+        diesel::insert_into(manual_exchange_rates::table)
+            .values(&manual_rate)
+            .returning(ManualExchangeRate::as_returning())
+            .get_result(conn)
+    }
+
+    pub fn delete_manual_exchange_rate(
+        &self,
+        conn: &mut SqliteConnection,
+        rate_id: &str,
+    ) -> Result<usize, diesel::result::Error> {
+        read_only_mode::ensure_writable()?;
+        diesel::delete(manual_exchange_rates::table.find(rate_id)).execute(conn)
+    }
+
     // create CASH asset
     pub fn create_cash_asset(
         &self,
@@ -206,6 +286,21 @@ impl AssetService {
             .first::<Quote>(conn)
     }
 
+    // Last quote of the symbol on or before `day`
+    pub fn get_quote_on_date(
+        &self,
+        conn: &mut SqliteConnection,
+        symbol_query: &str,
+        day: NaiveDate,
+    ) -> QueryResult<Quote> {
+        let next_day_start = (day + chrono::Duration::days(1)).and_time(NaiveTime::MIN);
+        quotes::table
+            .filter(quotes::symbol.eq(symbol_query))
+            .filter(quotes::date.lt(next_day_start))
+            .order(quotes::date.desc())
+            .first::<Quote>(conn)
+    }
+
     pub fn get_history_quotes(
         &self,
         conn: &mut SqliteConnection,
@@ -213,6 +308,116 @@ impl AssetService {
         quotes::table.load::<Quote>(conn)
     }
 
+    // Daily closes of `symbol` between `start` and `end` from the market data provider,
+    // sorted by date. Used for symbols that are not held, so nothing is saved.
+    pub async fn fetch_history_closes(
+        &self,
+        symbol: &str,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<(NaiveDate, f64)>, String> {
+        let start_time: SystemTime = Utc
+            .from_utc_datetime(&start.and_time(NaiveTime::MIN))
+            .into();
+        let end_time: SystemTime = Utc
+            .from_utc_datetime(&(end + chrono::Duration::days(1)).and_time(NaiveTime::MIN))
+            .into();
+
+        let mut closes: Vec<(NaiveDate, f64)> = self
+            .provider
+            .fetch_stock_history(symbol, start_time, end_time)
+            .await
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .filter_map(|quote| {
+                chrono::NaiveDateTime::from_timestamp_opt(quote.timestamp as i64, 0)
+                    .map(|date| (date.date(), quote.close))
+            })
+            .collect();
+        closes.sort_by_key(|(date, _)| *date);
+        Ok(closes)
+    }
+
+    // Save a quote entered by hand. It replaces any other quote of the symbol on that day.
+    pub fn save_manual_quote(
+        &self,
+        conn: &mut SqliteConnection,
+        manual_quote: NewManualQuote,
+    ) -> Result<Quote, diesel::result::Error> {
+        read_only_mode::ensure_writable()?;
+        let day_start = manual_quote.date.and_time(NaiveTime::MIN);
+        let quote = Quote {
+            id: uuid::Uuid::new_v4().to_string(),
+            created_at: Utc::now().naive_utc(),
+            data_source: MANUAL_DATA_SOURCE.to_string(),
+            date: day_start,
+            symbol: manual_quote.symbol,
+            open: manual_quote.open.unwrap_or(manual_quote.close),
+            high: manual_quote.high.unwrap_or(manual_quote.close),
+            low: manual_quote.low.unwrap_or(manual_quote.close),
+            volume: manual_quote.volume.unwrap_or(0.0),
+            close: manual_quote.close,
+            adjclose: manual_quote.close,
+        };
+
+        conn.transaction(|conn| {
+            Self::delete_day_quotes(conn, &quote.symbol, manual_quote.date, None)?;
+            diesel::insert_into(quotes::table)
+                .values(&quote)
+                .execute(conn)?;
+            Ok(quote)
+        })
+    }
+
+    pub fn delete_manual_quote(
+        &self,
+        conn: &mut SqliteConnection,
+        quote_id: &str,
+    ) -> Result<usize, diesel::result::Error> {
+        read_only_mode::ensure_writable()?;
+        diesel::delete(
+            quotes::table
+                .filter(quotes::id.eq(quote_id))
+                .filter(quotes::data_source.eq(MANUAL_DATA_SOURCE)),
+        )
+        .execute(conn)
+    }
+
+    // Delete the quotes of `symbol` dated on `day`, only those of `data_source` when given
+    fn delete_day_quotes(
+        conn: &mut SqliteConnection,
+        symbol: &str,
+        day: NaiveDate,
+        data_source: Option<&str>,
+    ) -> Result<usize, diesel::result::Error> {
+        let day_start = day.and_time(NaiveTime::MIN);
+        let mut query = diesel::delete(quotes::table)
+            .filter(quotes::symbol.eq(symbol))
+            .filter(quotes::date.ge(day_start))
+            .filter(quotes::date.lt(day_start + chrono::Duration::days(1)))
+            .into_boxed();
+        if let Some(data_source) = data_source {
+            query = query.filter(quotes::data_source.eq(data_source));
+        }
+        query.execute(conn)
+    }
+
+    // Days with a manual quote of `symbol`
+    fn get_manual_quote_dates(
+        &self,
+        conn: &mut SqliteConnection,
+        symbol: &str,
+    ) -> Result<HashSet<NaiveDate>, diesel::result::Error> {
+        Ok(quotes::table
+            .filter(quotes::symbol.eq(symbol))
+            .filter(quotes::data_source.eq(MANUAL_DATA_SOURCE))
+            .select(quotes::date)
+            .load::<NaiveDateTime>(conn)?
+            .into_iter()
+            .map(|date| date.date())
+            .collect())
+    }
+
     pub async fn search_ticker(&self, query: &str) -> Result<Vec<QuoteSummary>, String> {
         self.provider
             .search_ticker(query)
@@ -220,6 +425,221 @@ impl AssetService {
             .map_err(|e| e.to_string())
     }
 
+    // Every tag in use, for filters and suggestions
+    pub fn get_tags(
+        &self,
+        conn: &mut SqliteConnection,
+    ) -> Result<Vec<String>, diesel::result::Error> {
+        asset_tags::table
+            .select(asset_tags::tag)
+            .distinct()
+            .order(asset_tags::tag.asc())
+            .load::<String>(conn)
+    }
+
+    // Tags of every tagged asset, keyed by asset id
+    pub fn get_assets_tags(
+        &self,
+        conn: &mut SqliteConnection,
+    ) -> Result<HashMap<String, Vec<String>>, diesel::result::Error> {
+        let tags = asset_tags::table
+            .order((asset_tags::asset_id.asc(), asset_tags::tag.asc()))
+            .load::<AssetTag>(conn)?;
+
+        let mut assets_tags: HashMap<String, Vec<String>> = HashMap::new();
+        for asset_tag in tags {
+            assets_tags
+                .entry(asset_tag.asset_id)
+                .or_default()
+                .push(asset_tag.tag);
+        }
+        Ok(assets_tags)
+    }
+
+    pub fn get_asset_tags(
+        &self,
+        conn: &mut SqliteConnection,
+        asset_id: &str,
+    ) -> Result<Vec<String>, diesel::result::Error> {
+        asset_tags::table
+            .filter(asset_tags::asset_id.eq(asset_id))
+            .select(asset_tags::tag)
+            .order(asset_tags::tag.asc())
+            .load::<String>(conn)
+    }
+
+    // Tagging an asset twice with the same tag keeps a single tag
+    pub fn add_asset_tag(
+        &self,
+        conn: &mut SqliteConnection,
+        asset_id: &str,
+        tag: &str,
+    ) -> Result<usize, diesel::result::Error> {
+        read_only_mode::ensure_writable()?;
+        diesel::insert_or_ignore_into(asset_tags::table)
+            .values(AssetTag {
+                asset_id: asset_id.to_string(),
+                tag: tag.trim().to_string(),
+            })
+            .execute(conn)
+    }
+
+    pub fn remove_asset_tag(
+        &self,
+        conn: &mut SqliteConnection,
+        asset_id: &str,
+        tag: &str,
+    ) -> Result<usize, diesel::result::Error> {
+        read_only_mode::ensure_writable()?;
+        diesel::delete(asset_tags::table.find((asset_id, tag.trim()))).execute(conn)
+    }
+
+    pub fn get_symbol_overrides(
+        &self,
+        conn: &mut SqliteConnection,
+    ) -> Result<Vec<SymbolOverride>, diesel::result::Error> {
+        symbol_overrides::table
+            .order((
+                symbol_overrides::symbol.asc(),
+                symbol_overrides::account_id.asc(),
+            ))
+            .load::<SymbolOverride>(conn)
+    }
+
+    // Pin a symbol to the given provider symbol, replacing the override already set for
+    // the same symbol and account
+    pub fn add_symbol_override(
+        &self,
+        conn: &mut SqliteConnection,
+        new_override: NewSymbolOverride,
+    ) -> Result<SymbolOverride, diesel::result::Error> {
+        read_only_mode::ensure_writable()?;
+        let symbol_override = SymbolOverride {
+            id: uuid::Uuid::new_v4().to_string(),
+            symbol: symbol_normalization::normalize_symbol(&new_override.symbol),
+            account_id: new_override.account_id,
+            provider_symbol: new_override.provider_symbol.trim().to_uppercase(),
+            created_at: chrono::Utc::now().naive_utc(),
+        };
+
+        conn.transaction(|conn| {
+            let existing = symbol_overrides::table
+                .filter(symbol_overrides::symbol.eq(&symbol_override.symbol))
+                .into_boxed();
+            let existing = match &symbol_override.account_id {
+                Some(account_id) => existing.filter(symbol_overrides::account_id.eq(account_id)),
+                None => existing.filter(symbol_overrides::account_id.is_null()),
+            };
+            let existing_ids: Vec<String> =
+                existing.select(symbol_overrides::id).load::<String>(conn)?;
+            diesel::delete(
+                symbol_overrides::table.filter(symbol_overrides::id.eq_any(existing_ids)),
+            )
+            .execute(conn)?;
+
+            diesel::insert_into(symbol_overrides::table)
+                .values(&symbol_override)
+                .returning(SymbolOverride::as_returning())
+                .get_result(conn)
+        })
+    }
+
+    // Remember the provider symbol the user picked for an import symbol of the account that
+    // failed to resolve, once the provider knows it
+    pub async fn learn_symbol_alias(
+        &self,
+        conn: &mut SqliteConnection,
+        account_id: &str,
+        symbol: &str,
+        provider_symbol: &str,
+    ) -> Result<SymbolOverride, String> {
+        let provider_symbol = provider_symbol.trim().to_uppercase();
+        self.get_asset_profile(conn, &provider_symbol)
+            .await
+            .map_err(|_| format!("Symbol {} not found", provider_symbol))?;
+
+        self.add_symbol_override(
+            conn,
+            NewSymbolOverride {
+                symbol: symbol.to_string(),
+                account_id: Some(account_id.to_string()),
+                provider_symbol,
+            },
+        )
+        .map_err(|e| e.to_string())
+    }
+
+    pub fn delete_symbol_override(
+        &self,
+        conn: &mut SqliteConnection,
+        override_id: &str,
+    ) -> Result<usize, diesel::result::Error> {
+        read_only_mode::ensure_writable()?;
+        diesel::delete(symbol_overrides::table.find(override_id)).execute(conn)
+    }
+
+    // Provider symbol pinned for each symbol of an account, an override of the account
+    // wins over one set for every account
+    pub fn get_account_symbol_overrides(
+        &self,
+        conn: &mut SqliteConnection,
+        account_id: &str,
+    ) -> Result<HashMap<String, String>, diesel::result::Error> {
+        let overrides = symbol_overrides::table
+            .filter(
+                symbol_overrides::account_id
+                    .eq(account_id)
+                    .or(symbol_overrides::account_id.is_null()),
+            )
+            // Account overrides come last so they replace the global ones
+            .order(symbol_overrides::account_id.is_not_null().asc())
+            .load::<SymbolOverride>(conn)?;
+
+        Ok(overrides
+            .into_iter()
+            .map(|o| (o.symbol, o.provider_symbol))
+            .collect())
+    }
+
+    // Find the ticker of a security known by its ISIN or CUSIP. Assets already stored with
+    // that ISIN are used first, otherwise the provider search is queried with the identifier
+    // and a listing on an exchange of the issuing country is preferred.
+    pub async fn resolve_security_identifier(
+        &self,
+        conn: &mut SqliteConnection,
+        identifier: &SecurityIdentifier,
+    ) -> Result<String, String> {
+        let known_asset = assets::table
+            .filter(assets::isin.eq(identifier.value()))
+            .select(assets::id)
+            .first::<String>(conn)
+            .optional()
+            .map_err(|e| e.to_string())?;
+        if let Some(asset_id) = known_asset {
+            return Ok(asset_id);
+        }
+
+        let listings = self.search_ticker(identifier.value()).await?;
+        identifier.select_listing(&listings)
+    }
+
+    // Remember the ISIN of an asset so later imports resolve it without the provider
+    pub fn set_asset_isin(
+        &self,
+        conn: &mut SqliteConnection,
+        asset_id: &str,
+        isin: &str,
+    ) -> Result<usize, diesel::result::Error> {
+        read_only_mode::ensure_writable()?;
+        diesel::update(
+            assets::table
+                .filter(assets::id.eq(asset_id))
+                .filter(assets::isin.is_null()),
+        )
+        .set(assets::isin.eq(isin))
+        .execute(conn)
+    }
+
     pub async fn initialize_crumb_data(&self) -> Result<(), String> {
         match self.provider.set_crumb().await {
             Ok(_) => {
@@ -240,36 +660,115 @@ impl AssetService {
         asset_id: &str,
     ) -> Result<Asset, diesel::result::Error> {
         use crate::schema::assets::dsl::*;
-        // Try to load the Asset from the database
+        match self.lookup_asset_profile(conn, asset_id).await? {
+            AssetLookup::Saved(existing_profile) => Ok(existing_profile),
+            // Save a fetched profile to the database
+            AssetLookup::Fetched(fetched_profile) => diesel::insert_into(assets)
+                .values(&fetched_profile)
+                .returning(Asset::as_returning())
+                .get_result(conn),
+        }
+    }
+
+    // Load the asset from the database, or fetch its profile from the provider without
+    // saving it
+    pub async fn lookup_asset_profile(
+        &self,
+        conn: &mut SqliteConnection,
+        asset_id: &str,
+    ) -> Result<AssetLookup, diesel::result::Error> {
+        use crate::schema::assets::dsl::*;
         match assets.find(asset_id).first::<Asset>(conn) {
-            Ok(existing_profile) => Ok(existing_profile),
-            Err(diesel::NotFound) => {
-                // If not found, fetch one and save it to the database
-                let fetched_profile = self
-                    .provider
-                    .fetch_quote_summary(asset_id)
-                    .await
-                    .map_err(|_e| diesel::result::Error::NotFound)?;
-
-                // Insert the new profile into the database
-                diesel::insert_into(assets)
-                    .values(&fetched_profile)
-                    .returning(Asset::as_returning())
-                    .get_result(conn)
-                    .map_err(|e| e.into())
-            }
+            Ok(existing_profile) => Ok(AssetLookup::Saved(existing_profile)),
+            Err(diesel::NotFound) => self
+                .provider
+                .fetch_quote_summary(asset_id)
+                .await
+                .map(AssetLookup::Fetched)
+                .map_err(|_e| diesel::result::Error::NotFound),
             Err(e) => Err(e),
         }
     }
 
+    // Fill the asset class and sectors of the assets that have neither, using the
+    // provider profile. Manual assets are left alone and profiles are fetched one at a
+    // time to stay within the provider rate limits. All updates are applied together.
+    pub async fn classify_assets(
+        &self,
+        conn: &mut SqliteConnection,
+    ) -> Result<AssetClassificationReport, diesel::result::Error> {
+        read_only_mode::ensure_writable()?;
+        let unclassified_assets: Vec<Asset> = self
+            .get_assets(conn)?
+            .into_iter()
+            .filter(Self::is_classification_candidate)
+            .collect();
+
+        let mut report = AssetClassificationReport::default();
+        let mut classified_profiles = Vec::new();
+
+        for asset in unclassified_assets {
+            match self.provider.fetch_quote_summary(&asset.symbol).await {
+                Ok(profile) if Self::has_classification(&profile) => {
+                    classified_profiles.push((asset.id, profile));
+                }
+                Ok(_) => report.skipped.push(asset.id),
+                Err(e) => {
+                    println!("Failed to fetch profile for {}: {}", asset.symbol, e);
+                    report.failed.push(asset.id);
+                }
+            }
+        }
+
+        Self::save_classifications(conn, classified_profiles, report)
+    }
+
+    // Manual assets have no provider profile, and assets with a class or sectors keep
+    // the ones they have
+    fn is_classification_candidate(asset: &Asset) -> bool {
+        asset.data_source != MANUAL_DATA_SOURCE
+            && asset.asset_class.is_none()
+            && asset.sectors.is_none()
+    }
+
+    fn has_classification(profile: &NewAsset) -> bool {
+        profile.asset_class.is_some() || profile.sectors.is_some()
+    }
+
+    // Assign the class, sectors and countries of each fetched profile to its asset
+    fn save_classifications(
+        conn: &mut SqliteConnection,
+        classified_profiles: Vec<(String, NewAsset)>,
+        mut report: AssetClassificationReport,
+    ) -> Result<AssetClassificationReport, diesel::result::Error> {
+        conn.transaction(|conn| {
+            for (asset_id, profile) in classified_profiles {
+                diesel::update(assets::table.find(&asset_id))
+                    .set((
+                        assets::asset_class.eq(profile.asset_class),
+                        assets::asset_sub_class.eq(profile.asset_sub_class),
+                        assets::sectors.eq(profile.sectors),
+                        assets::countries.eq(profile.countries),
+                        assets::updated_at.eq(Utc::now().naive_utc()),
+                    ))
+                    .execute(conn)?;
+                report.assigned.push(asset_id);
+            }
+
+            Ok(report)
+        })
+    }
+
     fn get_last_quote_sync_date(
         &self,
         conn: &mut SqliteConnection,
         ticker: &str,
     ) -> Result<Option<NaiveDateTime>, diesel::result::Error> {
-        // Try to get the latest quote date for the given ticker
+        // Try to get the latest quote date for the given ticker, manual quotes
+        // do not tell up to when the market data is synced
         let latest_quote_date = quotes::table
             .filter(quotes::symbol.eq(ticker))
+            .filter(quotes::data_source.ne(MANUAL_DATA_SOURCE))
             .select(diesel::dsl::max(quotes::date))
             .first::<Option<NaiveDateTime>>(conn)?;
 
@@ -281,6 +780,7 @@ impl AssetService {
         // The code reaches here only if latest_quote_date is None
         let earliest_activity_date = activities::table
             .filter(activities::asset_id.eq(ticker))
+            .filter(activities::deleted_at.is_null())
             .select(diesel::dsl::min(activities::activity_date))
             .first::<Option<NaiveDateTime>>(conn)?;
 
@@ -290,12 +790,24 @@ impl AssetService {
     pub async fn sync_history_quotes_for_all_assets(
         &self,
         conn: &mut SqliteConnection,
+        force: bool,
     ) -> Result<(), String> {
         println!("Syncing history quotes for all assets...");
 
         // 1. Query all assets
         let asset_list = Self::get_assets(self, conn).map_err(|e| e.to_string())?;
+        self.sync_history_quotes_for_assets(conn, asset_list, force)
+            .await
+    }
 
+    // Manual quotes are kept unless `force` is set, then the market data of their
+    // days replaces them
+    async fn sync_history_quotes_for_assets(
+        &self,
+        conn: &mut SqliteConnection,
+        asset_list: Vec<Asset>,
+        force: bool,
+    ) -> Result<(), String> {
         // 2. Determine your end date for fetching historical quotes (e.g., current time)
         let end_date = SystemTime::now();
 
@@ -313,6 +825,16 @@ impl AssetService {
                     chrono::Utc::now().naive_utc() - chrono::Duration::days(3 * 365)
                 }); // Default to today - 3 years
 
+            let manual_quote_dates = self
+                .get_manual_quote_dates(conn, symbol)
+                .map_err(|e| e.to_string())?;
+            let last_sync_date_naive = match manual_quote_dates.iter().min() {
+                Some(first_manual_date) if force => {
+                    last_sync_date_naive.min(first_manual_date.and_time(NaiveTime::MIN))
+                }
+                _ => last_sync_date_naive,
+            };
+
             // Convert NaiveDateTime to DateTime<Utc>
             let start_datetime_utc = Utc.from_utc_datetime(&last_sync_date_naive);
 
@@ -348,16 +870,140 @@ impl AssetService {
             }
         }
 
+        self.save_synced_quotes(conn, all_quotes_to_insert, force)
+            .map_err(|e| e.to_string())
+    }
+
+    // Store the quotes fetched by a sync. On a day with a manual quote the fetched one
+    // is dropped, unless `force` is set and it replaces the manual quote.
+    fn save_synced_quotes(
+        &self,
+        conn: &mut SqliteConnection,
+        synced_quotes: Vec<Quote>,
+        force: bool,
+    ) -> Result<(), diesel::result::Error> {
+        let mut manual_quote_dates: HashMap<String, HashSet<NaiveDate>> = HashMap::new();
+        let mut quotes_to_insert = Vec::new();
+        let mut replaced_manual_quotes = Vec::new();
+
+        for quote in synced_quotes {
+            if !manual_quote_dates.contains_key(&quote.symbol) {
+                let dates = self.get_manual_quote_dates(conn, &quote.symbol)?;
+                manual_quote_dates.insert(quote.symbol.clone(), dates);
+            }
+
+            let quote_day = quote.date.date();
+            if manual_quote_dates[&quote.symbol].contains(&quote_day) {
+                if !force {
+                    continue;
+                }
+                replaced_manual_quotes.push((quote.symbol.clone(), quote_day));
+            }
+            quotes_to_insert.push(quote);
+        }
+
+        for (symbol, day) in replaced_manual_quotes {
+            Self::delete_day_quotes(conn, &symbol, day, Some(MANUAL_DATA_SOURCE))?;
+        }
+
         // 4. Use Diesel's batch insert to insert all quotes in a single operation
         diesel::replace_into(quotes::table)
-            .values(&all_quotes_to_insert)
-            .execute(conn)
-            .map_err(|e| e.to_string())?;
+            .values(&quotes_to_insert)
+            .execute(conn)?;
 
         Ok(())
     }
 
-    pub async fn initialize_and_sync_quotes(&self) -> Result<(), String> {
+    pub fn get_quote_staleness_policies(
+        &self,
+        conn: &mut SqliteConnection,
+    ) -> Result<Vec<QuoteStalenessPolicy>, diesel::result::Error> {
+        quote_staleness_policies::table
+            .order(quote_staleness_policies::asset_type.asc())
+            .load::<QuoteStalenessPolicy>(conn)
+    }
+
+    // Age limits set by the user, by asset type
+    pub fn load_quote_staleness_policies(
+        &self,
+        conn: &mut SqliteConnection,
+    ) -> Result<HashMap<String, i32>, diesel::result::Error> {
+        Ok(self
+            .get_quote_staleness_policies(conn)?
+            .into_iter()
+            .map(|policy| (policy.asset_type, policy.max_age_days))
+            .collect())
+    }
+
+    pub fn set_quote_staleness_policy(
+        &self,
+        conn: &mut SqliteConnection,
+        policy: QuoteStalenessPolicy,
+    ) -> Result<QuoteStalenessPolicy, diesel::result::Error> {
+        read_only_mode::ensure_writable()?;
+        diesel::insert_into(quote_staleness_policies::table)
+            .values(&policy)
+            .on_conflict(quote_staleness_policies::asset_type)
+            .do_update()
+            .set(&policy)
+            .returning(QuoteStalenessPolicy::as_returning())
+            .get_result(conn)
+    }
+
+    // The asset type falls back to the default age limit
+    pub fn delete_quote_staleness_policy(
+        &self,
+        conn: &mut SqliteConnection,
+        asset_type: &str,
+    ) -> Result<usize, diesel::result::Error> {
+        read_only_mode::ensure_writable()?;
+        diesel::delete(quote_staleness_policies::table.find(asset_type)).execute(conn)
+    }
+
+    // Synced assets whose last quote up to `date` is stale
+    pub fn get_stale_assets(
+        &self,
+        conn: &mut SqliteConnection,
+        date: NaiveDate,
+    ) -> Result<Vec<Asset>, diesel::result::Error> {
+        let policies = self.load_quote_staleness_policies(conn)?;
+        let mut stale_assets = Vec::new();
+        for asset in self.get_assets(conn)? {
+            let quote_date = match self.get_quote_on_date(conn, &asset.symbol, date) {
+                Ok(quote) => Some(quote.date.date()),
+                Err(diesel::NotFound) => None,
+                Err(e) => return Err(e),
+            };
+            if quote_staleness::is_quote_stale(&asset, quote_date, date, &policies) {
+                stale_assets.push(asset);
+            }
+        }
+        Ok(stale_assets)
+    }
+
+    // Sync the quotes of the stale assets only, returns their symbols
+    pub async fn refresh_stale_quotes(
+        &self,
+        conn: &mut SqliteConnection,
+    ) -> Result<Vec<String>, String> {
+        let stale_assets = self
+            .get_stale_assets(conn, Utc::now().date_naive())
+            .map_err(|e| e.to_string())?;
+        if stale_assets.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        self.initialize_crumb_data().await?;
+        let symbols = stale_assets
+            .iter()
+            .map(|asset| asset.symbol.clone())
+            .collect();
+        self.sync_history_quotes_for_assets(conn, stale_assets, false)
+            .await?;
+        Ok(symbols)
+    }
+
+    pub async fn initialize_and_sync_quotes(&self, force: bool) -> Result<(), String> {
         // Initialize crumb data
         if let Err(e) = self.initialize_crumb_data().await {
             return Err(format!("Failed to initialize crumb data: {}", e));
@@ -366,7 +1012,10 @@ impl AssetService {
         let mut conn = db::establish_connection();
 
         // Synchronize history quotes
-        if let Err(e) = self.sync_history_quotes_for_all_assets(&mut conn).await {
+        if let Err(e) = self
+            .sync_history_quotes_for_all_assets(&mut conn, force)
+            .await
+        {
             return Err(format!("Failed to sync history quotes: {}", e));
         }
 
@@ -375,3 +1024,329 @@ impl AssetService {
 }
 
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fx::fx_service::FxService;
+    use crate::test_utils::{self, TestDatabase};
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn only_the_assets_with_an_old_quote_are_stale() {
+        let mut db = TestDatabase::new();
+        test_utils::insert_asset(&mut db.conn, "AAPL", "USD");
+        test_utils::insert_asset(&mut db.conn, "MSFT", "USD");
+        test_utils::insert_quote(&mut db.conn, "AAPL", "2024-05-24", 190.0);
+        test_utils::insert_quote(&mut db.conn, "MSFT", "2024-05-14", 420.0);
+        // A later quote doesn't refresh a past day
+        test_utils::insert_quote(&mut db.conn, "MSFT", "2024-05-30", 425.0);
+
+        let stale_assets = AssetService::new()
+            .get_stale_assets(&mut db.conn, date("2024-05-28"))
+            .unwrap();
+
+        let stale_symbols: Vec<&str> = stale_assets.iter().map(|a| a.symbol.as_str()).collect();
+        assert_eq!(stale_symbols, ["MSFT"]);
+    }
+
+    #[test]
+    fn a_manual_exchange_rate_applies_on_its_own_date_only() {
+        let mut db = TestDatabase::new();
+        diesel::insert_into(assets::table)
+            .values(NewAsset {
+                id: "EURUSD=X".to_string(),
+                symbol: "EURUSD=X".to_string(),
+                asset_type: Some("Currency".to_string()),
+                currency: "USD".to_string(),
+                data_source: "Yahoo".to_string(),
+                ..Default::default()
+            })
+            .execute(&mut db.conn)
+            .unwrap();
+        test_utils::insert_quote(&mut db.conn, "EURUSD=X", "2024-03-01", 1.08);
+        test_utils::insert_quote(&mut db.conn, "EURUSD=X", "2024-03-10", 1.1);
+        let service = AssetService::new();
+        service
+            .add_manual_exchange_rate(
+                &mut db.conn,
+                NewManualExchangeRate {
+                    from_currency: "eur".to_string(),
+                    to_currency: "usd".to_string(),
+                    rate: 1.25,
+                    start_date: date("2024-03-05"),
+                    end_date: None,
+                },
+            )
+            .unwrap();
+
+        let fx_service = FxService::new("USD", service.load_exchange_rates(&mut db.conn).unwrap())
+            .with_manual_rates(service.load_manual_exchange_rates(&mut db.conn).unwrap())
+            .with_rate_history(service.load_exchange_rate_history(&mut db.conn).unwrap());
+        let rate_on = |from: &str, to: &str, day: &str| {
+            fx_service
+                .get_exchange_rate_for_date(from, to, date(day))
+                .unwrap()
+        };
+
+        assert_eq!(rate_on("EUR", "USD", "2024-03-05"), 1.25);
+        assert_eq!(rate_on("USD", "EUR", "2024-03-05"), 0.8);
+        // The neighbouring days use the last quote up to them
+        assert_eq!(rate_on("EUR", "USD", "2024-03-04"), 1.08);
+        assert_eq!(rate_on("EUR", "USD", "2024-03-06"), 1.08);
+        assert_eq!(rate_on("EUR", "USD", "2024-03-11"), 1.1);
+        // Before the first quote the latest rate is used
+        assert_eq!(rate_on("EUR", "USD", "2024-02-01"), 1.1);
+    }
+
+    fn synced_quote(symbol: &str, day: &str, close: f64) -> Quote {
+        let date = date(day).and_time(NaiveTime::MIN);
+        Quote {
+            id: uuid::Uuid::new_v4().to_string(),
+            created_at: date,
+            data_source: "YAHOO".to_string(),
+            date,
+            symbol: symbol.to_string(),
+            open: close,
+            high: close,
+            low: close,
+            volume: 0.0,
+            close,
+            adjclose: close,
+        }
+    }
+
+    fn manual_quote(symbol: &str, day: &str, close: f64) -> NewManualQuote {
+        NewManualQuote {
+            symbol: symbol.to_string(),
+            date: date(day),
+            open: None,
+            high: None,
+            low: None,
+            close,
+            volume: None,
+        }
+    }
+
+    // Close and data source of the quotes of `symbol` on `day`
+    fn day_quotes(conn: &mut SqliteConnection, symbol: &str, day: &str) -> Vec<(f64, String)> {
+        let day_start = date(day).and_time(NaiveTime::MIN);
+        quotes::table
+            .filter(quotes::symbol.eq(symbol))
+            .filter(quotes::date.eq(day_start))
+            .select((quotes::close, quotes::data_source))
+            .load(conn)
+            .unwrap()
+    }
+
+    #[test]
+    fn a_manual_quote_is_only_replaced_by_a_forced_sync() {
+        let mut db = TestDatabase::new();
+        test_utils::insert_asset(&mut db.conn, "AAPL", "USD");
+        let service = AssetService::new();
+        service
+            .save_manual_quote(&mut db.conn, manual_quote("AAPL", "2024-03-05", 150.0))
+            .unwrap();
+        let synced_quotes = || {
+            vec![
+                synced_quote("AAPL", "2024-03-04", 180.0),
+                synced_quote("AAPL", "2024-03-05", 181.0),
+            ]
+        };
+
+        service
+            .save_synced_quotes(&mut db.conn, synced_quotes(), false)
+            .unwrap();
+        assert_eq!(
+            day_quotes(&mut db.conn, "AAPL", "2024-03-05"),
+            vec![(150.0, MANUAL_DATA_SOURCE.to_string())]
+        );
+        assert_eq!(
+            day_quotes(&mut db.conn, "AAPL", "2024-03-04"),
+            vec![(180.0, "YAHOO".to_string())]
+        );
+
+        service
+            .save_synced_quotes(&mut db.conn, synced_quotes(), true)
+            .unwrap();
+        assert_eq!(
+            day_quotes(&mut db.conn, "AAPL", "2024-03-05"),
+            vec![(181.0, "YAHOO".to_string())]
+        );
+    }
+
+    #[test]
+    fn the_last_sync_date_ignores_the_manual_quotes() {
+        let mut db = TestDatabase::new();
+        test_utils::insert_account(&mut db.conn, "account", "USD");
+        test_utils::insert_asset(&mut db.conn, "AAPL", "USD");
+        test_utils::insert_asset(&mut db.conn, "FUND", "USD");
+        test_utils::insert_quote(&mut db.conn, "AAPL", "2024-03-04", 180.0);
+        test_utils::insert_activity(
+            &mut db.conn,
+            test_utils::new_activity("account", "FUND", "BUY", "2024-01-02", 1.0, 10.0),
+        );
+        let service = AssetService::new();
+        for symbol in ["AAPL", "FUND"] {
+            service
+                .save_manual_quote(&mut db.conn, manual_quote(symbol, "2024-03-08", 150.0))
+                .unwrap();
+        }
+
+        let last_sync_date = |conn: &mut SqliteConnection, symbol: &str| {
+            service
+                .get_last_quote_sync_date(conn, symbol)
+                .unwrap()
+                .map(|date| date.date())
+        };
+        assert_eq!(
+            last_sync_date(&mut db.conn, "AAPL"),
+            Some(date("2024-03-04"))
+        );
+        // Without market data the sync starts from the first activity
+        assert_eq!(
+            last_sync_date(&mut db.conn, "FUND"),
+            Some(date("2024-01-02"))
+        );
+    }
+
+    #[test]
+    fn a_stored_isin_resolves_to_its_asset() {
+        let mut db = TestDatabase::new();
+        test_utils::insert_asset(&mut db.conn, "AAPL", "USD");
+        let service = AssetService::new();
+        service
+            .set_asset_isin(&mut db.conn, "AAPL", "US0378331005")
+            .unwrap();
+        // An ISIN is only stored on an asset without one
+        assert_eq!(
+            service
+                .set_asset_isin(&mut db.conn, "AAPL", "US5949181045")
+                .unwrap(),
+            0
+        );
+
+        let identifier = SecurityIdentifier::parse("US0378331005").unwrap();
+        let resolved = tauri::async_runtime::block_on(
+            service.resolve_security_identifier(&mut db.conn, &identifier),
+        );
+
+        assert_eq!(resolved.unwrap(), "AAPL");
+    }
+
+    #[test]
+    fn only_unclassified_provider_assets_are_classified() {
+        let mut db = TestDatabase::new();
+        for asset_id in ["AAPL", "HOUSE", "VTI"] {
+            test_utils::insert_asset(&mut db.conn, asset_id, "USD");
+        }
+        diesel::update(assets::table.find("HOUSE"))
+            .set(assets::data_source.eq(MANUAL_DATA_SOURCE))
+            .execute(&mut db.conn)
+            .unwrap();
+        diesel::update(assets::table.find("VTI"))
+            .set(assets::asset_class.eq("Equity"))
+            .execute(&mut db.conn)
+            .unwrap();
+
+        let candidates: Vec<String> = AssetService::new()
+            .get_assets(&mut db.conn)
+            .unwrap()
+            .into_iter()
+            .filter(AssetService::is_classification_candidate)
+            .map(|asset| asset.id)
+            .collect();
+
+        assert_eq!(candidates, ["AAPL"]);
+    }
+
+    #[test]
+    fn profiles_with_a_class_or_sectors_are_assigned_the_others_skipped() {
+        let mut db = TestDatabase::new();
+        test_utils::insert_asset(&mut db.conn, "AAPL", "USD");
+        let classified = NewAsset {
+            id: "AAPL".to_string(),
+            asset_class: Some("Equity".to_string()),
+            sectors: Some(r#"[{"name":"Technology","weight":1.0}]"#.to_string()),
+            ..Default::default()
+        };
+        let sectors_only = NewAsset {
+            sectors: Some("[]".to_string()),
+            ..Default::default()
+        };
+        assert!(AssetService::has_classification(&classified));
+        assert!(AssetService::has_classification(&sectors_only));
+        assert!(!AssetService::has_classification(&NewAsset::default()));
+
+        let report = AssetClassificationReport {
+            skipped: vec!["GOLD".to_string()],
+            ..Default::default()
+        };
+        let report = AssetService::save_classifications(
+            &mut db.conn,
+            vec![("AAPL".to_string(), classified)],
+            report,
+        )
+        .unwrap();
+
+        assert_eq!(report.assigned, ["AAPL"]);
+        assert_eq!(report.skipped, ["GOLD"]);
+        let asset = AssetService::new()
+            .get_asset_by_id(&mut db.conn, "AAPL")
+            .unwrap();
+        assert_eq!(asset.asset_class.as_deref(), Some("Equity"));
+        assert!(asset.sectors.unwrap().contains("Technology"));
+    }
+
+    #[test]
+    fn tags_are_added_once_listed_and_removed() {
+        let mut db = TestDatabase::new();
+        test_utils::insert_asset(&mut db.conn, "AAPL", "USD");
+        test_utils::insert_asset(&mut db.conn, "VTI", "USD");
+        let service = AssetService::new();
+
+        assert_eq!(
+            service.add_asset_tag(&mut db.conn, "AAPL", "Tech").unwrap(),
+            1
+        );
+        // Tagging twice, even with surrounding spaces, keeps a single tag
+        assert_eq!(
+            service
+                .add_asset_tag(&mut db.conn, "AAPL", " Tech ")
+                .unwrap(),
+            0
+        );
+        service.add_asset_tag(&mut db.conn, "AAPL", "Core").unwrap();
+        service.add_asset_tag(&mut db.conn, "VTI", "Core").unwrap();
+
+        assert_eq!(
+            service.get_asset_tags(&mut db.conn, "AAPL").unwrap(),
+            ["Core", "Tech"]
+        );
+        assert_eq!(service.get_tags(&mut db.conn).unwrap(), ["Core", "Tech"]);
+        let assets_tags = service.get_assets_tags(&mut db.conn).unwrap();
+        assert_eq!(assets_tags["AAPL"], ["Core", "Tech"]);
+        assert_eq!(assets_tags["VTI"], ["Core"]);
+
+        assert_eq!(
+            service
+                .remove_asset_tag(&mut db.conn, "AAPL", "Tech ")
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            service
+                .remove_asset_tag(&mut db.conn, "AAPL", "Tech")
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            service.get_asset_tags(&mut db.conn, "AAPL").unwrap(),
+            ["Core"]
+        );
+        assert_eq!(service.get_tags(&mut db.conn).unwrap(), ["Core"]);
+    }
+}
@@ -1,5 +1,9 @@
 use crate::asset::asset_service;
-use crate::models::{AssetProfile, QuoteSummary};
+use crate::db;
+use crate::models::{
+    AssetClassificationReport, AssetProfile, ManualExchangeRate, NewManualExchangeRate,
+    NewManualQuote, NewSymbolOverride, Quote, QuoteStalenessPolicy, QuoteSummary, SymbolOverride,
+};
 use crate::AppState;
 use tauri::State;
 
@@ -24,13 +28,271 @@ pub fn get_asset_data(asset_id: String, state: State<AppState>) -> Result<AssetP
 }
 
 #[tauri::command]
-pub async fn synch_quotes() -> Result<(), String> {
+pub async fn synch_quotes(force: Option<bool>) -> Result<(), String> {
     println!("Synch Quotes historical data...");
 
     let service = asset_service::AssetService::new();
 
     service
-        .initialize_and_sync_quotes()
+        .initialize_and_sync_quotes(force.unwrap_or(false))
         .await
         .map_err(|e| format!("Failed to Synch Quotes historical data: {}", e))
 }
+
+#[tauri::command]
+pub async fn classify_assets() -> Result<AssetClassificationReport, String> {
+    println!("Classifying unclassified assets...");
+
+    let mut conn = db::establish_connection();
+    let service = asset_service::AssetService::new();
+
+    service
+        .classify_assets(&mut conn)
+        .await
+        .map_err(|e| format!("Failed to classify assets: {}", e))
+}
+
+#[tauri::command]
+pub fn get_symbol_overrides(state: State<AppState>) -> Result<Vec<SymbolOverride>, String> {
+    println!("Fetching symbol overrides...");
+    let mut conn = state.conn.lock().unwrap();
+    let service = asset_service::AssetService::new();
+    service
+        .get_symbol_overrides(&mut conn)
+        .map_err(|e| format!("Failed to load symbol overrides: {}", e))
+}
+
+#[tauri::command]
+pub fn add_symbol_override(
+    symbol_override: NewSymbolOverride,
+    state: State<AppState>,
+) -> Result<SymbolOverride, String> {
+    println!("Adding symbol override: {:?}", symbol_override);
+    let mut conn = state.conn.lock().unwrap();
+    let service = asset_service::AssetService::new();
+    service
+        .add_symbol_override(&mut conn, symbol_override)
+        .map_err(|e| format!("Failed to add symbol override: {}", e))
+}
+
+#[tauri::command]
+pub fn learn_symbol_alias(
+    account_id: String,
+    symbol: String,
+    provider_symbol: String,
+    state: State<AppState>,
+) -> Result<SymbolOverride, String> {
+    println!(
+        "Learning symbol alias of account {}: {} -> {}",
+        account_id, symbol, provider_symbol
+    );
+    tauri::async_runtime::block_on(async {
+        let mut conn = state.conn.lock().unwrap();
+        let service = asset_service::AssetService::new();
+        service
+            .learn_symbol_alias(&mut conn, &account_id, &symbol, &provider_symbol)
+            .await
+    })
+    .map_err(|e| format!("Failed to learn symbol alias: {}", e))
+}
+
+#[tauri::command]
+pub fn delete_symbol_override(
+    override_id: String,
+    state: State<AppState>,
+) -> Result<usize, String> {
+    println!("Deleting symbol override: {}", override_id);
+    let mut conn = state.conn.lock().unwrap();
+    let service = asset_service::AssetService::new();
+    service
+        .delete_symbol_override(&mut conn, &override_id)
+        .map_err(|e| format!("Failed to delete symbol override: {}", e))
+}
+
+#[tauri::command]
+pub fn get_manual_exchange_rates(
+    state: State<AppState>,
+) -> Result<Vec<ManualExchangeRate>, String> {
+    println!("Fetching manual exchange rates...");
+    let mut conn = state.conn.lock().unwrap();
+    let service = asset_service::AssetService::new();
+    service
+        .load_manual_exchange_rates(&mut conn)
+        .map_err(|e| format!("Failed to load manual exchange rates: {}", e))
+}
+
+#[tauri::command]
+pub fn add_manual_exchange_rate(
+    exchange_rate: NewManualExchangeRate,
+    state: State<AppState>,
+) -> Result<ManualExchangeRate, String> {
+    println!("Adding manual exchange rate: {:?}", exchange_rate);
+    if exchange_rate.rate <= 0.0 {
+        return Err("Exchange rate must be a positive number".to_string());
+    }
+    if exchange_rate
+        .end_date
+        .is_some_and(|end_date| end_date < exchange_rate.start_date)
+    {
+        return Err("End date must be on or after the start date".to_string());
+    }
+
+    let mut conn = state.conn.lock().unwrap();
+    let service = asset_service::AssetService::new();
+    service
+        .add_manual_exchange_rate(&mut conn, exchange_rate)
+        .map_err(|e| format!("Failed to add manual exchange rate: {}", e))
+}
+
+#[tauri::command]
+pub fn delete_manual_exchange_rate(
+    rate_id: String,
+    state: State<AppState>,
+) -> Result<usize, String> {
+    println!("Deleting manual exchange rate: {}", rate_id);
+    let mut conn = state.conn.lock().unwrap();
+    let service = asset_service::AssetService::new();
+    service
+        .delete_manual_exchange_rate(&mut conn, &rate_id)
+        .map_err(|e| format!("Failed to delete manual exchange rate: {}", e))
+}
+
+#[tauri::command]
+pub fn get_tags(state: State<AppState>) -> Result<Vec<String>, String> {
+    println!("Fetching tags...");
+    let mut conn = state.conn.lock().unwrap();
+    let service = asset_service::AssetService::new();
+    service
+        .get_tags(&mut conn)
+        .map_err(|e| format!("Failed to load tags: {}", e))
+}
+
+#[tauri::command]
+pub fn get_asset_tags(asset_id: String, state: State<AppState>) -> Result<Vec<String>, String> {
+    println!("Fetching tags of asset: {}", asset_id);
+    let mut conn = state.conn.lock().unwrap();
+    let service = asset_service::AssetService::new();
+    service
+        .get_asset_tags(&mut conn, &asset_id)
+        .map_err(|e| format!("Failed to load asset tags: {}", e))
+}
+
+#[tauri::command]
+pub fn add_asset_tag(
+    asset_id: String,
+    tag: String,
+    state: State<AppState>,
+) -> Result<Vec<String>, String> {
+    println!("Tagging asset {} with: {}", asset_id, tag);
+    if tag.trim().is_empty() {
+        return Err("Tag cannot be empty".to_string());
+    }
+
+    let mut conn = state.conn.lock().unwrap();
+    let service = asset_service::AssetService::new();
+    service
+        .add_asset_tag(&mut conn, &asset_id, &tag)
+        .map_err(|e| format!("Failed to add asset tag: {}", e))?;
+    service
+        .get_asset_tags(&mut conn, &asset_id)
+        .map_err(|e| format!("Failed to load asset tags: {}", e))
+}
+
+#[tauri::command]
+pub fn remove_asset_tag(
+    asset_id: String,
+    tag: String,
+    state: State<AppState>,
+) -> Result<Vec<String>, String> {
+    println!("Removing tag {} from asset: {}", tag, asset_id);
+    let mut conn = state.conn.lock().unwrap();
+    let service = asset_service::AssetService::new();
+    service
+        .remove_asset_tag(&mut conn, &asset_id, &tag)
+        .map_err(|e| format!("Failed to remove asset tag: {}", e))?;
+    service
+        .get_asset_tags(&mut conn, &asset_id)
+        .map_err(|e| format!("Failed to load asset tags: {}", e))
+}
+
+#[tauri::command]
+pub fn save_manual_quote(
+    manual_quote: NewManualQuote,
+    state: State<AppState>,
+) -> Result<Quote, String> {
+    println!("Saving manual quote: {:?}", manual_quote);
+    if manual_quote.close <= 0.0 {
+        return Err("Close price must be a positive number".to_string());
+    }
+
+    let mut conn = state.conn.lock().unwrap();
+    let service = asset_service::AssetService::new();
+    service
+        .save_manual_quote(&mut conn, manual_quote)
+        .map_err(|e| format!("Failed to save manual quote: {}", e))
+}
+
+#[tauri::command]
+pub fn delete_manual_quote(quote_id: String, state: State<AppState>) -> Result<usize, String> {
+    println!("Deleting manual quote: {}", quote_id);
+    let mut conn = state.conn.lock().unwrap();
+    let service = asset_service::AssetService::new();
+    service
+        .delete_manual_quote(&mut conn, &quote_id)
+        .map_err(|e| format!("Failed to delete manual quote: {}", e))
+}
+
+#[tauri::command]
+pub fn get_quote_staleness_policies(
+    state: State<AppState>,
+) -> Result<Vec<QuoteStalenessPolicy>, String> {
+    println!("Fetching quote staleness policies...");
+    let mut conn = state.conn.lock().unwrap();
+    let service = asset_service::AssetService::new();
+    service
+        .get_quote_staleness_policies(&mut conn)
+        .map_err(|e| format!("Failed to load quote staleness policies: {}", e))
+}
+
+#[tauri::command]
+pub fn set_quote_staleness_policy(
+    policy: QuoteStalenessPolicy,
+    state: State<AppState>,
+) -> Result<QuoteStalenessPolicy, String> {
+    println!("Setting quote staleness policy: {:?}", policy);
+    if policy.max_age_days < 0 {
+        return Err("Maximum quote age must not be negative".to_string());
+    }
+
+    let mut conn = state.conn.lock().unwrap();
+    let service = asset_service::AssetService::new();
+    service
+        .set_quote_staleness_policy(&mut conn, policy)
+        .map_err(|e| format!("Failed to set quote staleness policy: {}", e))
+}
+
+#[tauri::command]
+pub fn delete_quote_staleness_policy(
+    asset_type: String,
+    state: State<AppState>,
+) -> Result<usize, String> {
+    println!("Deleting quote staleness policy: {}", asset_type);
+    let mut conn = state.conn.lock().unwrap();
+    let service = asset_service::AssetService::new();
+    service
+        .delete_quote_staleness_policy(&mut conn, &asset_type)
+        .map_err(|e| format!("Failed to delete quote staleness policy: {}", e))
+}
+
+#[tauri::command]
+pub async fn refresh_stale_quotes() -> Result<Vec<String>, String> {
+    println!("Refreshing stale quotes...");
+
+    let mut conn = db::establish_connection();
+    let service = asset_service::AssetService::new();
+
+    service
+        .refresh_stale_quotes(&mut conn)
+        .await
+        .map_err(|e| format!("Failed to refresh stale quotes: {}", e))
+}
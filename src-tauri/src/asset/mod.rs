@@ -1,2 +1,5 @@
 pub mod asset_service;
 pub mod assets_commands;
+pub mod quote_staleness;
+pub mod security_identifier;
+pub mod symbol_normalization;
@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+
+use crate::asset::asset_service::MANUAL_DATA_SOURCE;
+use crate::models::Asset;
+use crate::providers::models::AssetClass;
+
+// Days a quote stays fresh without a policy for its asset type, long enough for a
+// weekend followed by a holiday
+pub const DEFAULT_MAX_QUOTE_AGE_DAYS: i32 = 4;
+// Crypto trades every day
+const DEFAULT_MAX_CRYPTO_QUOTE_AGE_DAYS: i32 = 2;
+// Exchange rate assets are stored as manual but synced like the others
+const CURRENCY_ASSET_TYPE: &str = "Currency";
+
+// Whether quotes of the asset come from the market data sync, cash has no quotes and
+// manual assets are priced by hand
+pub fn is_synced(asset: &Asset) -> bool {
+    !asset.id.starts_with("$CASH-")
+        && (asset.data_source != MANUAL_DATA_SOURCE
+            || asset.asset_type.as_deref() == Some(CURRENCY_ASSET_TYPE))
+}
+
+// Assets store the asset class as their type, "Cryptocurrency", while search results
+// carry the raw quote type of the provider, "CRYPTOCURRENCY"
+fn is_crypto(asset_type: &str) -> bool {
+    asset_type.eq_ignore_ascii_case(&AssetClass::Cryptocurrency.to_string())
+}
+
+// Age limit of the quotes of an asset type, `policies` are the ones set by the user
+pub fn get_max_quote_age_days(policies: &HashMap<String, i32>, asset_type: Option<&str>) -> i32 {
+    let asset_type = asset_type.unwrap_or_default();
+    match policies.get(asset_type) {
+        Some(max_age_days) => *max_age_days,
+        None if is_crypto(asset_type) => DEFAULT_MAX_CRYPTO_QUOTE_AGE_DAYS,
+        None => DEFAULT_MAX_QUOTE_AGE_DAYS,
+    }
+}
+
+// A synced asset is stale on `date` when its last quote up to then is older than its
+// policy allows, or when it has no quote at all
+pub fn is_quote_stale(
+    asset: &Asset,
+    quote_date: Option<NaiveDate>,
+    date: NaiveDate,
+    policies: &HashMap<String, i32>,
+) -> bool {
+    if !is_synced(asset) {
+        return false;
+    }
+    let max_age_days = get_max_quote_age_days(policies, asset.asset_type.as_deref());
+    match quote_date {
+        Some(quote_date) => (date - quote_date).num_days() > i64::from(max_age_days),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    fn asset(id: &str, asset_type: Option<&str>, data_source: &str) -> Asset {
+        let created_at = date("2024-01-01").and_time(chrono::NaiveTime::MIN);
+        Asset {
+            id: id.to_string(),
+            isin: None,
+            name: None,
+            asset_type: asset_type.map(str::to_string),
+            symbol: id.to_string(),
+            symbol_mapping: None,
+            asset_class: None,
+            asset_sub_class: None,
+            comment: None,
+            countries: None,
+            categories: None,
+            classes: None,
+            attributes: None,
+            created_at,
+            updated_at: created_at,
+            currency: "USD".to_string(),
+            data_source: data_source.to_string(),
+            sectors: None,
+            url: None,
+        }
+    }
+
+    #[test]
+    fn user_policies_override_the_defaults() {
+        let policies = HashMap::from([("ETF".to_string(), 10)]);
+
+        assert_eq!(get_max_quote_age_days(&policies, Some("ETF")), 10);
+        assert_eq!(
+            get_max_quote_age_days(&policies, Some("Cryptocurrency")),
+            DEFAULT_MAX_CRYPTO_QUOTE_AGE_DAYS
+        );
+        assert_eq!(
+            get_max_quote_age_days(&policies, Some("CRYPTOCURRENCY")),
+            DEFAULT_MAX_CRYPTO_QUOTE_AGE_DAYS
+        );
+        assert_eq!(
+            get_max_quote_age_days(&policies, None),
+            DEFAULT_MAX_QUOTE_AGE_DAYS
+        );
+    }
+
+    #[test]
+    fn a_quote_is_stale_once_older_than_its_policy() {
+        // Typed with the asset class, as the provider stores them
+        let equity = AssetClass::Equity.to_string();
+        let cryptocurrency = AssetClass::Cryptocurrency.to_string();
+        let stock = asset("AAPL", Some(&equity), "Yahoo");
+        let crypto = asset("BTC-USD", Some(&cryptocurrency), "Yahoo");
+        let policies = HashMap::new();
+        // Friday quote, valued on the Tuesday after a holiday Monday
+        let quote_date = Some(date("2024-05-24"));
+
+        assert!(!is_quote_stale(
+            &stock,
+            quote_date,
+            date("2024-05-28"),
+            &policies
+        ));
+        assert!(is_quote_stale(
+            &stock,
+            quote_date,
+            date("2024-05-29"),
+            &policies
+        ));
+        assert!(is_quote_stale(
+            &crypto,
+            quote_date,
+            date("2024-05-27"),
+            &policies
+        ));
+        assert!(is_quote_stale(&stock, None, date("2024-05-28"), &policies));
+    }
+
+    #[test]
+    fn cash_and_manual_assets_are_never_stale() {
+        let policies = HashMap::new();
+        let today = date("2024-05-28");
+
+        assert!(!is_quote_stale(
+            &asset("$CASH-USD", None, "Yahoo"),
+            None,
+            today,
+            &policies
+        ));
+        assert!(!is_quote_stale(
+            &asset("HOUSE", Some("Property"), MANUAL_DATA_SOURCE),
+            None,
+            today,
+            &policies
+        ));
+        // Exchange rates are stored as manual assets but synced
+        assert!(is_quote_stale(
+            &asset("EURUSD=X", Some(CURRENCY_ASSET_TYPE), MANUAL_DATA_SOURCE),
+            None,
+            today,
+            &policies
+        ));
+    }
+}
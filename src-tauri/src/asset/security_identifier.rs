@@ -0,0 +1,222 @@
+use crate::models::QuoteSummary;
+
+/// Identifiers of a security other than its ticker, as found in broker exports
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecurityIdentifier {
+    Isin(String),
+    Cusip(String),
+}
+
+impl SecurityIdentifier {
+    // Only values with a valid check digit are identifiers, so tickers are never mistaken for one
+    pub fn parse(value: &str) -> Option<SecurityIdentifier> {
+        let value = value.trim().to_uppercase();
+        if is_valid_isin(&value) {
+            Some(SecurityIdentifier::Isin(value))
+        } else if is_valid_cusip(&value) {
+            Some(SecurityIdentifier::Cusip(value))
+        } else {
+            None
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        match self {
+            SecurityIdentifier::Isin(value) | SecurityIdentifier::Cusip(value) => value,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SecurityIdentifier::Isin(_) => "ISIN",
+            SecurityIdentifier::Cusip(_) => "CUSIP",
+        }
+    }
+
+    // CUSIPs are issued for North American securities
+    pub fn country_code(&self) -> &str {
+        match self {
+            SecurityIdentifier::Isin(value) => &value[..2],
+            SecurityIdentifier::Cusip(_) => "US",
+        }
+    }
+
+    // Yahoo exchange codes where securities of the issuing country are listed, main
+    // venue first. Funds domiciled in IE and LU are listed across Europe.
+    pub fn preferred_exchanges(&self) -> &'static [&'static str] {
+        match self.country_code() {
+            "US" => &["NMS", "NYQ", "NGM", "NCM", "ASE", "PCX", "BTS"],
+            "CA" => &["TOR", "VAN", "NEO"],
+            "GB" => &["LSE", "IOB"],
+            "DE" => &["GER", "FRA", "STU", "MUN", "DUS", "BER", "HAM"],
+            "FR" => &["PAR"],
+            "NL" => &["AMS"],
+            "BE" => &["BRU"],
+            "IT" => &["MIL"],
+            "ES" => &["MCE"],
+            "PT" => &["LIS"],
+            "AT" => &["VIE"],
+            "CH" => &["EBS"],
+            "SE" => &["STO"],
+            "DK" => &["CPH"],
+            "NO" => &["OSL"],
+            "FI" => &["HEL"],
+            "IE" | "LU" => &["LSE", "GER", "AMS", "PAR", "MIL", "EBS"],
+            "AU" => &["ASX"],
+            "JP" => &["JPX"],
+            "HK" => &["HKG"],
+            _ => &[],
+        }
+    }
+
+    // Symbol of the listing on the first preferred exchange, or of the first listing
+    pub fn select_listing(&self, listings: &[QuoteSummary]) -> Result<String, String> {
+        self.preferred_exchanges()
+            .iter()
+            .find_map(|exchange| listings.iter().find(|l| &l.exchange == exchange))
+            .or_else(|| listings.first())
+            .map(|listing| listing.symbol.clone())
+            .ok_or_else(|| format!("No listing found for {} {}", self.kind(), self.value()))
+    }
+}
+
+// Digit value of an identifier character, letters continue the digits from 10
+fn character_value(character: char) -> Option<u32> {
+    character.to_digit(36)
+}
+
+// Luhn check over the digits obtained by expanding each letter into its two digit value
+fn is_valid_isin(value: &str) -> bool {
+    if !value.is_ascii()
+        || value.len() != 12
+        || !value[..2].chars().all(|c| c.is_ascii_uppercase())
+        || !value.chars().all(|c| c.is_ascii_alphanumeric())
+        || !value[11..].chars().all(|c| c.is_ascii_digit())
+    {
+        return false;
+    }
+
+    let digits: Vec<u32> = value
+        .chars()
+        .filter_map(character_value)
+        .flat_map(|v| {
+            if v >= 10 {
+                vec![v / 10, v % 10]
+            } else {
+                vec![v]
+            }
+        })
+        .collect();
+
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(index, digit)| {
+            if index % 2 == 1 {
+                let doubled = digit * 2;
+                doubled / 10 + doubled % 10
+            } else {
+                *digit
+            }
+        })
+        .sum();
+
+    sum % 10 == 0
+}
+
+// Modulus 10 "double add double" check of the ninth character
+fn is_valid_cusip(value: &str) -> bool {
+    if !value.is_ascii()
+        || value.len() != 9
+        || !value[..8]
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "*@#".contains(c))
+        || !value[8..].chars().all(|c| c.is_ascii_digit())
+    {
+        return false;
+    }
+
+    let sum: u32 = value[..8]
+        .chars()
+        .enumerate()
+        .map(|(index, character)| {
+            let v = match character {
+                '*' => 36,
+                '@' => 37,
+                '#' => 38,
+                _ => character_value(character).unwrap_or(0),
+            };
+            let v = if index % 2 == 1 { v * 2 } else { v };
+            v / 10 + v % 10
+        })
+        .sum();
+
+    let check_digit = (10 - sum % 10) % 10;
+    value[8..].parse::<u32>().ok() == Some(check_digit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listing(symbol: &str, exchange: &str) -> QuoteSummary {
+        QuoteSummary {
+            exchange: exchange.to_string(),
+            short_name: symbol.to_string(),
+            quote_type: "EQUITY".to_string(),
+            symbol: symbol.to_string(),
+            index: "quotes".to_string(),
+            score: 1.0,
+            type_display: "Equity".to_string(),
+            long_name: symbol.to_string(),
+        }
+    }
+
+    #[test]
+    fn isins_with_a_valid_check_digit_are_parsed() {
+        assert_eq!(
+            SecurityIdentifier::parse(" us0378331005 "),
+            Some(SecurityIdentifier::Isin("US0378331005".to_string()))
+        );
+        assert_eq!(
+            SecurityIdentifier::parse("IE00B4L5Y983"),
+            Some(SecurityIdentifier::Isin("IE00B4L5Y983".to_string()))
+        );
+        assert_eq!(SecurityIdentifier::parse("US0378331006"), None);
+    }
+
+    #[test]
+    fn cusips_with_a_valid_check_digit_are_parsed() {
+        assert_eq!(
+            SecurityIdentifier::parse("037833100"),
+            Some(SecurityIdentifier::Cusip("037833100".to_string()))
+        );
+        assert_eq!(
+            SecurityIdentifier::parse("38259P508"),
+            Some(SecurityIdentifier::Cusip("38259P508".to_string()))
+        );
+        assert_eq!(SecurityIdentifier::parse("037833101"), None);
+    }
+
+    #[test]
+    fn tickers_are_not_identifiers() {
+        for ticker in ["AAPL", "BRK-B", "VWCE.DE", ""] {
+            assert_eq!(SecurityIdentifier::parse(ticker), None, "{}", ticker);
+        }
+    }
+
+    #[test]
+    fn the_listing_on_a_preferred_exchange_is_selected() {
+        let identifier = SecurityIdentifier::parse("DE0007164600").unwrap();
+        let listings = [listing("SAP", "NYQ"), listing("SAP.DE", "GER")];
+
+        assert_eq!(identifier.country_code(), "DE");
+        assert_eq!(identifier.select_listing(&listings).unwrap(), "SAP.DE");
+        assert_eq!(identifier.select_listing(&listings[..1]).unwrap(), "SAP");
+        assert_eq!(
+            identifier.select_listing(&[]).unwrap_err(),
+            "No listing found for ISIN DE0007164600"
+        );
+    }
+}
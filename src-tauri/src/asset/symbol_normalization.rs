@@ -0,0 +1,79 @@
+// Brokers write the class of a share after a dot, a slash or a space, e.g. "BRK.B", while
+// the provider separates it with a dash, "BRK-B"
+const CLASS_SEPARATORS: [char; 3] = ['.', '/', ' '];
+const PROVIDER_CLASS_SEPARATOR: char = '-';
+const MAX_CLASS_LENGTH: usize = 2;
+
+// Uppercase symbol without surrounding blanks, inner blanks collapsed to one space
+pub fn normalize_symbol(symbol: &str) -> String {
+    symbol
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_uppercase()
+}
+
+// Spellings of a symbol to look up with the provider, in order. The symbol as written
+// comes first, so exchange suffixes such as "RY.TO" are kept when they resolve.
+pub fn get_symbol_candidates(symbol: &str) -> Vec<String> {
+    let mut candidates = vec![symbol.to_string()];
+    let normalized = normalize_symbol(symbol);
+    if let Some(class_share) = get_class_share_symbol(&normalized) {
+        candidates.push(normalized.clone());
+        candidates.push(class_share);
+    } else {
+        candidates.push(normalized);
+    }
+    candidates.dedup();
+    candidates
+}
+
+// Provider spelling of a class share symbol, the dotted one for a symbol already using dashes
+fn get_class_share_symbol(symbol: &str) -> Option<String> {
+    let (index, separator) = symbol
+        .char_indices()
+        .rev()
+        .find(|(_, c)| CLASS_SEPARATORS.contains(c) || *c == PROVIDER_CLASS_SEPARATOR)?;
+    let (base, class) = (&symbol[..index], &symbol[index + 1..]);
+    let is_class = !class.is_empty()
+        && class.len() <= MAX_CLASS_LENGTH
+        && class.chars().all(|c| c.is_ascii_alphabetic());
+    if base.is_empty() || !is_class || !base.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+
+    let replacement = if separator == PROVIDER_CLASS_SEPARATOR {
+        '.'
+    } else {
+        PROVIDER_CLASS_SEPARATOR
+    };
+    Some(format!("{}{}{}", base, replacement, class))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symbols_are_trimmed_and_uppercased() {
+        assert_eq!(normalize_symbol("  aapl "), "AAPL");
+        assert_eq!(normalize_symbol("brk   b"), "BRK B");
+    }
+
+    #[test]
+    fn class_shares_are_looked_up_with_the_provider_separator() {
+        assert_eq!(get_symbol_candidates("brk.b"), ["brk.b", "BRK.B", "BRK-B"]);
+        assert_eq!(get_symbol_candidates("BRK/B"), ["BRK/B", "BRK-B"]);
+        assert_eq!(get_symbol_candidates("BRK B"), ["BRK B", "BRK-B"]);
+        assert_eq!(get_symbol_candidates("BF-B"), ["BF-B", "BF.B"]);
+    }
+
+    #[test]
+    fn the_symbol_as_written_is_tried_first() {
+        // An exchange suffix looks like a class, it resolves before its dashed spelling
+        assert_eq!(get_symbol_candidates("RY.TO"), ["RY.TO", "RY-TO"]);
+        assert_eq!(get_symbol_candidates("AAPL"), ["AAPL"]);
+        assert_eq!(get_symbol_candidates("ABC.DEF"), ["ABC.DEF"]);
+        assert_eq!(get_symbol_candidates("BRK.1"), ["BRK.1"]);
+    }
+}
@@ -1,33 +1,78 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
 use std::{env, fs};
 
-use diesel::sqlite::SqliteConnection;
+use diesel::migration::MigrationSource;
+use diesel::sql_types::{BigInt, Text};
+use diesel::sqlite::{Sqlite, SqliteConnection};
 use diesel::{prelude::*, sql_query};
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
 use dotenvy::dotenv;
 use tauri::api::path;
 
+use crate::models::{DatabaseStats, TableStats};
+
 const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
 
-pub fn init() {
+// Appended to the database path to name its copy taken before migrating
+const PRE_MIGRATION_BACKUP_SUFFIX: &str = ".pre-migration.bak";
+
+// The default profile keeps the database of single profile installs, the other
+// profiles each have their own database file in the profiles directory
+pub const DEFAULT_PROFILE: &str = "default";
+static ACTIVE_PROFILE: RwLock<Option<String>> = RwLock::new(None);
+
+// Directory holding the databases in place of the app data directory, for the tests
+#[cfg(test)]
+static TEST_APP_DATA_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+#[derive(QueryableByName)]
+struct TableName {
+    #[diesel(sql_type = Text)]
+    name: String,
+}
+
+#[derive(QueryableByName)]
+struct RowCount {
+    #[diesel(sql_type = BigInt)]
+    count: i64,
+}
+
+#[derive(QueryableByName)]
+struct TableSize {
+    #[diesel(sql_type = Text)]
+    name: String,
+    #[diesel(sql_type = BigInt)]
+    size: i64,
+}
+
+pub fn init() -> Result<(), String> {
     if !db_file_exists() {
         create_db_file();
     }
-    run_migrations();
+    migrate_database(&get_database_url(), MIGRATIONS)
 }
 
-pub fn establish_connection() -> SqliteConnection {
+fn get_database_url() -> String {
+    if get_active_profile() != DEFAULT_PROFILE {
+        return get_db_path();
+    }
+
     dotenv().ok(); // Load environment variables from .env file if available
 
     // Try to get the database URL from the environment variable
-    let database_url = match env::var("DATABASE_URL") {
+    match env::var("DATABASE_URL") {
         Ok(url) => url, // If DATABASE_URL is set, use it
         Err(_) => {
             // Fall back to your get_db_path() function when DATABASE_URL is not set
             let db_path = get_db_path().clone(); // Get the custom database path
             db_path // Return the custom path as the database URL
         }
-    };
+    }
+}
+
+pub fn establish_connection() -> SqliteConnection {
+    let database_url = get_database_url();
 
     // Establish the database connection
     let mut conn = SqliteConnection::establish(&database_url)
@@ -41,9 +86,188 @@ pub fn establish_connection() -> SqliteConnection {
     conn // Return the established database connection
 }
 
-fn run_migrations() {
-    let mut connection = establish_connection();
-    connection.run_pending_migrations(MIGRATIONS).unwrap();
+// Database in memory with every migration applied, for the tests
+#[cfg(test)]
+pub fn establish_test_connection() -> SqliteConnection {
+    let mut conn =
+        SqliteConnection::establish(":memory:").expect("Failed to open the test database");
+    sql_query("PRAGMA foreign_keys = ON")
+        .execute(&mut conn)
+        .expect("Failed to enable foreign key support");
+    conn.run_pending_migrations(MIGRATIONS)
+        .expect("Failed to migrate the test database");
+    conn
+}
+
+// Path of the database file. Sizes can't be read from a url like `file:app.db?mode=ro`
+// or `:memory:`, an error is returned rather than reporting them as empty.
+fn get_database_path() -> Result<String, String> {
+    let database_url = get_database_url();
+    if database_url == ":memory:"
+        || database_url.starts_with("file:")
+        || database_url.contains("://")
+        || database_url.contains('?')
+    {
+        return Err(format!(
+            "The database url {} is not a file path, its size can't be read",
+            database_url
+        ));
+    }
+    Ok(database_url)
+}
+
+fn get_file_size(path: &str) -> u64 {
+    fs::metadata(path).map_or(0, |metadata| metadata.len())
+}
+
+// Row count and size of each table, along with the size of the database and
+// its write-ahead log
+pub fn get_database_stats(conn: &mut SqliteConnection) -> Result<DatabaseStats, String> {
+    let database_path = get_database_path()?;
+    let tables = get_table_stats(conn).map_err(|e| e.to_string())?;
+
+    Ok(DatabaseStats {
+        tables,
+        database_size: get_file_size(&database_path),
+        wal_size: get_file_size(&format!("{}-wal", database_path)),
+    })
+}
+
+// Sizes per table need SQLite's dbstat virtual table, when it is not compiled in
+// only the row counts are returned
+fn get_table_stats(conn: &mut SqliteConnection) -> Result<Vec<TableStats>, diesel::result::Error> {
+    let table_sizes: Option<Vec<TableSize>> =
+        sql_query("SELECT name, SUM(pgsize) AS size FROM dbstat GROUP BY name")
+            .load::<TableSize>(conn)
+            .ok();
+
+    count_table_rows(conn, table_sizes.as_deref())
+}
+
+fn count_table_rows(
+    conn: &mut SqliteConnection,
+    table_sizes: Option<&[TableSize]>,
+) -> Result<Vec<TableStats>, diesel::result::Error> {
+    let table_names = sql_query(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' \
+         ORDER BY name",
+    )
+    .load::<TableName>(conn)?;
+
+    let mut tables = Vec::new();
+    for table in table_names {
+        let row_count = sql_query(format!("SELECT COUNT(*) AS count FROM \"{}\"", table.name))
+            .get_result::<RowCount>(conn)?
+            .count;
+        let size = table_sizes.map(|sizes| {
+            sizes
+                .iter()
+                .find(|table_size| table_size.name == table.name)
+                .map_or(0, |table_size| table_size.size)
+        });
+
+        tables.push(TableStats {
+            name: table.name,
+            row_count,
+            size,
+        });
+    }
+
+    Ok(tables)
+}
+
+// Size on disk of the database file and its -wal and -shm companions
+fn get_database_files_size() -> Result<u64, String> {
+    let database_path = get_database_path()?;
+
+    Ok(["", "-wal", "-shm"]
+        .iter()
+        .map(|suffix| get_file_size(&format!("{}{}", database_path, suffix)))
+        .sum())
+}
+
+// Move the write-ahead log back into the database file and truncate it, then
+// optionally rebuild the file with VACUUM. Returns the number of bytes reclaimed.
+// Callers must hold the app connection lock so no other write runs meanwhile.
+pub fn compact_database(conn: &mut SqliteConnection, vacuum: bool) -> Result<u64, String> {
+    let size_before = get_database_files_size()?;
+
+    sql_query("PRAGMA wal_checkpoint(TRUNCATE)")
+        .execute(conn)
+        .map_err(|e| e.to_string())?;
+    if vacuum {
+        sql_query("VACUUM")
+            .execute(conn)
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(size_before.saturating_sub(get_database_files_size()?))
+}
+
+// Apply the pending `migrations` to the database at `database_url` after copying it
+// next to it. When one of them fails the copy is put back, so the app never starts
+// on a half-migrated file.
+fn migrate_database<S: MigrationSource<Sqlite>>(
+    database_url: &str,
+    migrations: S,
+) -> Result<(), String> {
+    let mut connection = SqliteConnection::establish(database_url)
+        .map_err(|e| format!("Error connecting to {}: {}", database_url, e))?;
+    let pending_migrations = connection
+        .pending_migrations(migrations)
+        .map_err(|e| format!("Failed to check the pending database migrations: {}", e))?;
+    if pending_migrations.is_empty() {
+        return Ok(());
+    }
+
+    let backup_path = format!("{}{}", database_url, PRE_MIGRATION_BACKUP_SUFFIX);
+    backup_database(&mut connection, &backup_path)
+        .map_err(|e| format!("Failed to back up the database before migrating: {}", e))?;
+
+    let migration_result = pending_migrations
+        .iter()
+        .try_for_each(|migration| connection.run_migration(&**migration).map(|_| ()));
+    if let Err(migration_error) = migration_result {
+        drop(connection);
+        return Err(match restore_database(database_url, &backup_path) {
+            Ok(()) => format!(
+                "Failed to migrate the database, it was restored to its previous state: {}",
+                migration_error
+            ),
+            Err(restore_error) => format!(
+                "Failed to migrate the database: {}. Restoring the backup {} failed too: {}",
+                migration_error, backup_path, restore_error
+            ),
+        });
+    }
+    Ok(())
+}
+
+// Write a consistent copy of the database to `backup_path`, replacing any previous one
+fn backup_database(
+    conn: &mut SqliteConnection,
+    backup_path: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if Path::new(backup_path).exists() {
+        fs::remove_file(backup_path)?;
+    }
+    sql_query("VACUUM INTO ?")
+        .bind::<Text, _>(backup_path)
+        .execute(conn)?;
+    Ok(())
+}
+
+// Replace the database file with `backup_path`. No connection may be open, the
+// write-ahead log of the replaced file is removed with it.
+fn restore_database(database_url: &str, backup_path: &str) -> std::io::Result<()> {
+    for suffix in ["-wal", "-shm"] {
+        let path = format!("{}{}", database_url, suffix);
+        if Path::new(&path).exists() {
+            fs::remove_file(path)?;
+        }
+    }
+    fs::copy(backup_path, database_url)?;
+    Ok(())
 }
 
 fn create_db_file() {
@@ -62,12 +286,325 @@ fn db_file_exists() -> bool {
     Path::new(&db_path).exists()
 }
 
+pub fn get_active_profile() -> String {
+    ACTIVE_PROFILE
+        .read()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+}
+
+// The default profile followed by the profiles that have a database, by name
+pub fn get_profiles() -> Vec<String> {
+    let mut profiles: Vec<String> = fs::read_dir(get_profiles_dir())
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|extension| extension == "db"))
+                .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+                .filter(|profile| profile != DEFAULT_PROFILE)
+                .collect()
+        })
+        .unwrap_or_default();
+    profiles.sort();
+    profiles.insert(0, DEFAULT_PROFILE.to_string());
+    profiles
+}
+
+// Point new connections at the database of `profile`, created and migrated when
+// needed. Connections opened before keep their database.
+pub fn set_active_profile(profile: &str) -> Result<(), String> {
+    if profile.is_empty()
+        || !profile
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "Invalid profile name {}, use letters, digits, - and _",
+            profile
+        ));
+    }
+
+    let previous_profile = ACTIVE_PROFILE.read().unwrap().clone();
+    *ACTIVE_PROFILE.write().unwrap() =
+        Some(profile.to_string()).filter(|profile| profile != DEFAULT_PROFILE);
+    init().map_err(|e| {
+        *ACTIVE_PROFILE.write().unwrap() = previous_profile;
+        e
+    })
+}
+
+fn get_app_data_dir() -> PathBuf {
+    #[cfg(test)]
+    if let Some(dir) = TEST_APP_DATA_DIR.read().unwrap().clone() {
+        return dir;
+    }
+
+    path::data_dir()
+        .expect("failed to find AppData directory")
+        .join("com.teymz.wealthfolio")
+}
+
+fn get_profiles_dir() -> PathBuf {
+    get_app_data_dir().join("profiles")
+}
+
 fn get_db_path() -> String {
-    let app_data_path = path::data_dir().expect("failed to find AppData directory");
-    let database_path = app_data_path.join("com.teymz.wealthfolio/app.db");
+    let database_path = match ACTIVE_PROFILE.read().unwrap().as_deref() {
+        Some(profile) => get_profiles_dir().join(format!("{}.db", profile)),
+        None => get_app_data_dir().join("app.db"),
+    };
 
     let database_url = database_path
         .to_str()
         .expect("Failed to convert path to string");
     return database_url.to_string();
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+
+    use diesel::connection::{BoxableConnection, SimpleConnection};
+    use diesel::migration::{Migration, MigrationMetadata, MigrationName, MigrationVersion};
+
+    use super::*;
+    use crate::schema::accounts;
+    use crate::test_utils;
+
+    // Migration run after all the real ones, failing once they are applied
+    struct FailingMigration;
+
+    impl fmt::Display for FailingMigration {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "9999-12-31-000000_failing")
+        }
+    }
+
+    impl MigrationName for FailingMigration {
+        fn version(&self) -> MigrationVersion<'_> {
+            MigrationVersion::from("99991231000000")
+        }
+    }
+
+    impl MigrationMetadata for FailingMigration {}
+
+    impl Migration<Sqlite> for FailingMigration {
+        fn run(&self, _conn: &mut dyn BoxableConnection<Sqlite>) -> diesel::migration::Result<()> {
+            Err("the migration failed".into())
+        }
+
+        fn revert(
+            &self,
+            _conn: &mut dyn BoxableConnection<Sqlite>,
+        ) -> diesel::migration::Result<()> {
+            Ok(())
+        }
+
+        fn metadata(&self) -> &dyn MigrationMetadata {
+            self
+        }
+
+        fn name(&self) -> &dyn MigrationName {
+            self
+        }
+    }
+
+    struct MigrationsFailingLast;
+
+    impl MigrationSource<Sqlite> for MigrationsFailingLast {
+        fn migrations(&self) -> diesel::migration::Result<Vec<Box<dyn Migration<Sqlite>>>> {
+            let mut migrations = MigrationSource::<Sqlite>::migrations(&MIGRATIONS)?;
+            migrations.push(Box::new(FailingMigration));
+            Ok(migrations)
+        }
+    }
+
+    // Keep the databases in a new temporary directory, starting on the default profile
+    fn use_temp_app_data_dir() -> PathBuf {
+        let dir = env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        *TEST_APP_DATA_DIR.write().unwrap() = Some(dir.clone());
+        *ACTIVE_PROFILE.write().unwrap() = None;
+        dir
+    }
+
+    fn account_ids(profile: &str) -> Vec<String> {
+        set_active_profile(profile).unwrap();
+        accounts::table
+            .select(accounts::id)
+            .order(accounts::id)
+            .load::<String>(&mut establish_connection())
+            .unwrap()
+    }
+
+    #[test]
+    fn each_profile_sees_only_its_own_accounts() {
+        let _lock = test_utils::lock_global_state();
+        use_temp_app_data_dir();
+
+        set_active_profile("personal").unwrap();
+        test_utils::insert_account(&mut establish_connection(), "checking", "USD");
+        set_active_profile("business").unwrap();
+        test_utils::insert_account(&mut establish_connection(), "payroll", "USD");
+        test_utils::insert_account(&mut establish_connection(), "savings", "USD");
+
+        assert_eq!(account_ids("personal"), vec!["checking"]);
+        assert_eq!(account_ids("business"), vec!["payroll", "savings"]);
+        assert_eq!(get_active_profile(), "business");
+        assert_eq!(
+            get_profiles(),
+            vec![DEFAULT_PROFILE, "business", "personal"]
+        );
+
+        *ACTIVE_PROFILE.write().unwrap() = None;
+    }
+
+    #[test]
+    fn invalid_profile_names_are_rejected() {
+        let _lock = test_utils::lock_global_state();
+        let dir = use_temp_app_data_dir();
+
+        for profile in ["../x", "", "my profile", "a/b"] {
+            assert!(set_active_profile(profile).is_err(), "{:?}", profile);
+        }
+
+        assert_eq!(get_active_profile(), DEFAULT_PROFILE);
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn a_failing_migration_restores_the_database_from_its_backup() {
+        let dir = env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        fs::create_dir_all(&dir).unwrap();
+        let database_url = dir.join("app.db").to_string_lossy().to_string();
+        let mut conn = SqliteConnection::establish(&database_url).unwrap();
+        sql_query("CREATE TABLE notes (body TEXT NOT NULL)")
+            .execute(&mut conn)
+            .unwrap();
+        sql_query("INSERT INTO notes (body) VALUES ('kept')")
+            .execute(&mut conn)
+            .unwrap();
+        drop(conn);
+
+        let error = migrate_database(&database_url, MigrationsFailingLast).unwrap_err();
+        assert!(
+            error.contains("restored to its previous state"),
+            "{}",
+            error
+        );
+        assert!(Path::new(&format!("{}{}", database_url, PRE_MIGRATION_BACKUP_SUFFIX)).exists());
+
+        // The migrations applied before the failing one are rolled back with it
+        let mut conn = SqliteConnection::establish(&database_url).unwrap();
+        let tables = sql_query(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'",
+        )
+        .load::<TableName>(&mut conn)
+        .unwrap();
+        let table_names: Vec<String> = tables.into_iter().map(|table| table.name).collect();
+        assert!(table_names.contains(&"notes".to_string()));
+        assert!(!table_names.contains(&"accounts".to_string()));
+        let notes = sql_query("SELECT COUNT(*) AS count FROM notes WHERE body = 'kept'")
+            .get_result::<RowCount>(&mut conn)
+            .unwrap();
+        assert_eq!(notes.count, 1);
+    }
+
+    #[test]
+    fn migrating_an_up_to_date_database_takes_no_backup() {
+        let dir = env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        fs::create_dir_all(&dir).unwrap();
+        let database_url = dir.join("app.db").to_string_lossy().to_string();
+
+        migrate_database(&database_url, MIGRATIONS).unwrap();
+        fs::remove_file(format!("{}{}", database_url, PRE_MIGRATION_BACKUP_SUFFIX)).unwrap();
+        migrate_database(&database_url, MIGRATIONS).unwrap();
+
+        assert!(!Path::new(&format!("{}{}", database_url, PRE_MIGRATION_BACKUP_SUFFIX)).exists());
+    }
+
+    fn wal_size() -> u64 {
+        get_file_size(&format!("{}-wal", get_database_url()))
+    }
+
+    #[test]
+    fn compacting_truncates_the_write_ahead_log() {
+        let _lock = test_utils::lock_global_state();
+        use_temp_app_data_dir();
+        set_active_profile("compact").unwrap();
+        let mut conn = establish_connection();
+        conn.batch_execute("PRAGMA journal_mode = WAL").unwrap();
+        for index in 0..100 {
+            test_utils::insert_account(&mut conn, &format!("account-{}", index), "USD");
+        }
+        let wal_size_before = wal_size();
+        assert!(wal_size_before > 0);
+
+        let reclaimed = compact_database(&mut conn, false).unwrap();
+
+        assert_eq!(wal_size(), 0);
+        assert!(reclaimed > 0);
+        assert_eq!(get_database_stats(&mut conn).unwrap().wal_size, 0);
+
+        *ACTIVE_PROFILE.write().unwrap() = None;
+    }
+
+    #[test]
+    fn sizes_are_not_read_from_a_database_url_that_is_not_a_path() {
+        let mut db = test_utils::TestDatabase::new();
+        use_temp_app_data_dir();
+        let previous_url = env::var("DATABASE_URL").ok();
+        env::set_var("DATABASE_URL", "file:app.db?mode=ro");
+
+        let compact_result = compact_database(&mut db.conn, false);
+        let stats_result = get_database_stats(&mut db.conn);
+
+        match previous_url {
+            Some(url) => env::set_var("DATABASE_URL", url),
+            None => env::remove_var("DATABASE_URL"),
+        }
+        assert!(compact_result.unwrap_err().contains("not a file path"));
+        assert!(stats_result.unwrap_err().contains("not a file path"));
+    }
+
+    fn row_counts(tables: &[TableStats]) -> Vec<(&str, i64)> {
+        tables
+            .iter()
+            .filter(|table| ["accounts", "assets"].contains(&table.name.as_str()))
+            .map(|table| (table.name.as_str(), table.row_count))
+            .collect()
+    }
+
+    #[test]
+    fn table_stats_count_the_inserted_rows() {
+        let mut db = test_utils::TestDatabase::new();
+        test_utils::insert_account(&mut db.conn, "checking", "USD");
+        test_utils::insert_account(&mut db.conn, "savings", "USD");
+        for asset_id in ["AAPL", "MSFT", "BND"] {
+            test_utils::insert_asset(&mut db.conn, asset_id, "USD");
+        }
+
+        let tables = get_table_stats(&mut db.conn).unwrap();
+
+        assert_eq!(row_counts(&tables), vec![("accounts", 2), ("assets", 3)]);
+        let has_dbstat = sql_query("SELECT name, SUM(pgsize) AS size FROM dbstat GROUP BY name")
+            .load::<TableSize>(&mut db.conn)
+            .is_ok();
+        assert!(tables
+            .iter()
+            .all(|table| table.size.is_some() == has_dbstat));
+    }
+
+    #[test]
+    fn without_dbstat_only_the_row_counts_are_returned() {
+        let mut db = test_utils::TestDatabase::new();
+        test_utils::insert_account(&mut db.conn, "checking", "USD");
+        test_utils::insert_asset(&mut db.conn, "AAPL", "USD");
+
+        let tables = count_table_rows(&mut db.conn, None).unwrap();
+
+        assert_eq!(row_counts(&tables), vec![("accounts", 1), ("assets", 1)]);
+        assert!(tables.iter().all(|table| table.size.is_none()));
+    }
+}
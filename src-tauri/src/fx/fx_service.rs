@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use chrono::NaiveDate;
+use thiserror::Error;
+
+use crate::models::ManualExchangeRate;
+
+// Currency used to triangulate pairs that have no direct rate
+const INTERMEDIATE_CURRENCY: &str = "USD";
+
+// Rate of a pair, derived when it was triangulated instead of quoted
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExchangeRate {
+    pub rate: f64,
+    pub is_derived: bool,
+}
+
+#[derive(Debug, Error)]
+pub enum FxError {
+    #[error("No exchange rate found to convert {0} to {1}")]
+    RateNotFound(String, String),
+}
+
+/// Converts amounts between currencies using the exchange rate quotes
+/// stored as `{FROM}{TO}=X` currency assets.
+///
+/// Pairs without a direct (or inverse) quote are triangulated through the
+/// base currency or USD. Triangulated rates are cached separately from the
+/// quoted ones so they can be told apart.
+///
+/// Rates entered by the user for a date range take precedence over the quotes
+/// on the dates they cover. When the daily quotes are loaded, rates on past dates
+/// come from the last quote up to that date.
+pub struct FxService {
+    base_currency: String,
+    exchange_rates: HashMap<String, f64>,
+    derived_rates: RwLock<HashMap<String, f64>>,
+    manual_rates: Vec<ManualExchangeRate>,
+    rate_history: HashMap<String, Vec<(NaiveDate, f64)>>,
+}
+
+impl FxService {
+    pub fn new(base_currency: &str, exchange_rates: HashMap<String, f64>) -> Self {
+        FxService {
+            base_currency: base_currency.to_string(),
+            exchange_rates,
+            derived_rates: RwLock::new(HashMap::new()),
+            manual_rates: Vec::new(),
+            rate_history: HashMap::new(),
+        }
+    }
+
+    // Manual rates are matched in order, so the most recent entry should come first
+    pub fn with_manual_rates(mut self, manual_rates: Vec<ManualExchangeRate>) -> Self {
+        self.manual_rates = manual_rates;
+        self
+    }
+
+    // Daily quotes of the currency pairs keyed by symbol, each sorted by date
+    pub fn with_rate_history(
+        mut self,
+        rate_history: HashMap<String, Vec<(NaiveDate, f64)>>,
+    ) -> Self {
+        self.rate_history = rate_history;
+        self
+    }
+
+    // Last quoted rate of a pair on or before `date`, using the inverse pair if only
+    // that one exists
+    fn get_historical_rate(
+        &self,
+        from_currency: &str,
+        to_currency: &str,
+        date: NaiveDate,
+    ) -> Option<f64> {
+        let rate_on = |key: String| {
+            self.rate_history.get(&key).and_then(|rates| {
+                let end = rates.partition_point(|(rate_date, _)| *rate_date <= date);
+                end.checked_sub(1).map(|index| rates[index].1)
+            })
+        };
+
+        rate_on(format!("{}{}=X", from_currency, to_currency)).or_else(|| {
+            rate_on(format!("{}{}=X", to_currency, from_currency))
+                .filter(|rate| *rate != 0.0)
+                .map(|rate| 1.0 / rate)
+        })
+    }
+
+    fn get_manual_rate(
+        &self,
+        from_currency: &str,
+        to_currency: &str,
+        date: NaiveDate,
+    ) -> Option<f64> {
+        self.manual_rates
+            .iter()
+            .filter(|manual_rate| manual_rate.start_date <= date && date <= manual_rate.end_date)
+            .find_map(|manual_rate| {
+                if manual_rate.from_currency == from_currency
+                    && manual_rate.to_currency == to_currency
+                {
+                    Some(manual_rate.rate)
+                } else if manual_rate.from_currency == to_currency
+                    && manual_rate.to_currency == from_currency
+                    && manual_rate.rate != 0.0
+                {
+                    Some(1.0 / manual_rate.rate)
+                } else {
+                    None
+                }
+            })
+    }
+
+    // Quoted rate for a pair, using the inverse pair if only that one exists
+    fn get_direct_rate(&self, from_currency: &str, to_currency: &str) -> Option<f64> {
+        if from_currency == to_currency {
+            return Some(1.0);
+        }
+
+        let direct_key = format!("{}{}=X", from_currency, to_currency);
+        if let Some(rate) = self.exchange_rates.get(&direct_key) {
+            return Some(*rate);
+        }
+
+        let inverse_key = format!("{}{}=X", to_currency, from_currency);
+        self.exchange_rates
+            .get(&inverse_key)
+            .filter(|rate| **rate != 0.0)
+            .map(|rate| 1.0 / rate)
+    }
+
+    fn get_derived_rate(&self, from_currency: &str, to_currency: &str) -> Option<f64> {
+        let intermediates = [self.base_currency.as_str(), INTERMEDIATE_CURRENCY];
+
+        intermediates
+            .iter()
+            .filter(|via| **via != from_currency && **via != to_currency)
+            .find_map(|via| {
+                let first_leg = self.get_direct_rate(from_currency, via)?;
+                let second_leg = self.get_direct_rate(via, to_currency)?;
+                Some(first_leg * second_leg)
+            })
+    }
+
+    // Rate to convert one unit of `from_currency` into `to_currency`
+    pub fn get_exchange_rate(
+        &self,
+        from_currency: &str,
+        to_currency: &str,
+    ) -> Result<f64, FxError> {
+        self.get_exchange_rate_details(from_currency, to_currency)
+            .map(|exchange_rate| exchange_rate.rate)
+    }
+
+    // Same rate, telling whether it was triangulated
+    pub fn get_exchange_rate_details(
+        &self,
+        from_currency: &str,
+        to_currency: &str,
+    ) -> Result<ExchangeRate, FxError> {
+        if let Some(rate) = self.get_direct_rate(from_currency, to_currency) {
+            return Ok(ExchangeRate {
+                rate,
+                is_derived: false,
+            });
+        }
+
+        let pair_key = format!("{}{}", from_currency, to_currency);
+        if let Some(rate) = self.derived_rates.read().unwrap().get(&pair_key) {
+            return Ok(ExchangeRate {
+                rate: *rate,
+                is_derived: true,
+            });
+        }
+
+        let rate = self
+            .get_derived_rate(from_currency, to_currency)
+            .ok_or_else(|| {
+                FxError::RateNotFound(from_currency.to_string(), to_currency.to_string())
+            })?;
+
+        println!(
+            "Using derived exchange rate {} for {} to {}",
+            rate, from_currency, to_currency
+        );
+        self.derived_rates.write().unwrap().insert(pair_key, rate);
+
+        Ok(ExchangeRate {
+            rate,
+            is_derived: true,
+        })
+    }
+
+    // Rate on a given date, a manual rate covering the date wins over the quoted rates
+    pub fn get_exchange_rate_for_date(
+        &self,
+        from_currency: &str,
+        to_currency: &str,
+        date: NaiveDate,
+    ) -> Result<f64, FxError> {
+        self.get_exchange_rate_details_for_date(from_currency, to_currency, date)
+            .map(|exchange_rate| exchange_rate.rate)
+    }
+
+    pub fn get_exchange_rate_details_for_date(
+        &self,
+        from_currency: &str,
+        to_currency: &str,
+        date: NaiveDate,
+    ) -> Result<ExchangeRate, FxError> {
+        if from_currency == to_currency {
+            return Ok(ExchangeRate {
+                rate: 1.0,
+                is_derived: false,
+            });
+        }
+
+        match self
+            .get_manual_rate(from_currency, to_currency, date)
+            .or_else(|| self.get_historical_rate(from_currency, to_currency, date))
+        {
+            Some(rate) => Ok(ExchangeRate {
+                rate,
+                is_derived: false,
+            }),
+            None => self.get_exchange_rate_details(from_currency, to_currency),
+        }
+    }
+
+    pub fn convert_currency(
+        &self,
+        amount: f64,
+        from_currency: &str,
+        to_currency: &str,
+    ) -> Result<f64, FxError> {
+        Ok(amount * self.get_exchange_rate(from_currency, to_currency)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fx_service(base_currency: &str, rates: &[(&str, f64)]) -> FxService {
+        FxService::new(
+            base_currency,
+            rates
+                .iter()
+                .map(|(symbol, rate)| (symbol.to_string(), *rate))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn quoted_pairs_are_used_directly_or_inverted() {
+        let service = fx_service("USD", &[("EURUSD=X", 1.25)]);
+
+        assert_eq!(service.get_exchange_rate("EUR", "USD").unwrap(), 1.25);
+        assert_eq!(service.get_exchange_rate("USD", "EUR").unwrap(), 0.8);
+        assert_eq!(service.get_exchange_rate("EUR", "EUR").unwrap(), 1.0);
+    }
+
+    #[test]
+    fn missing_pairs_are_triangulated_through_the_base_currency() {
+        let service = fx_service("EUR", &[("GBPEUR=X", 1.2), ("EURCHF=X", 0.95)]);
+
+        let exchange_rate = service.get_exchange_rate_details("GBP", "CHF").unwrap();
+        assert!((exchange_rate.rate - 1.2 * 0.95).abs() < 1e-12);
+        assert!(exchange_rate.is_derived);
+        assert!(
+            !service
+                .get_exchange_rate_details("GBP", "EUR")
+                .unwrap()
+                .is_derived
+        );
+        let rate = service.get_exchange_rate("CHF", "GBP").unwrap();
+        assert!((rate - 1.0 / (1.2 * 0.95)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn missing_pairs_fall_back_to_usd() {
+        let service = fx_service("CAD", &[("USDJPY=X", 150.0), ("GBPUSD=X", 1.25)]);
+
+        let exchange_rate = service.get_exchange_rate_details("GBP", "JPY").unwrap();
+        assert!((exchange_rate.rate - 187.5).abs() < 1e-9);
+        // Cached once derived, still flagged as such
+        assert!(service.derived_rates.read().unwrap().contains_key("GBPJPY"));
+        assert!(
+            service
+                .get_exchange_rate_details("GBP", "JPY")
+                .unwrap()
+                .is_derived
+        );
+    }
+
+    #[test]
+    fn a_missing_leg_is_an_error() {
+        let service = fx_service("USD", &[("EURUSD=X", 1.1)]);
+
+        let error = service.get_exchange_rate("EUR", "BRL").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "No exchange rate found to convert EUR to BRL"
+        );
+        assert!(service.convert_currency(100.0, "BRL", "USD").is_err());
+    }
+}
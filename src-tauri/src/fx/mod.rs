@@ -0,0 +1,3 @@
+pub mod fx_service;
+
+pub use fx_service::FxService;
@@ -1,5 +1,5 @@
 use crate::goal::goal_service;
-use crate::models::{Goal, GoalsAllocation, NewGoal};
+use crate::models::{Goal, GoalProjection, GoalsAllocation, NewGoal};
 use crate::AppState;
 use tauri::State;
 
@@ -65,3 +65,24 @@ pub fn load_goals_allocations(state: State<AppState>) -> Result<Vec<GoalsAllocat
         .load_goals_allocations(&mut *conn)
         .map_err(|e| e.to_string())
 }
+
+#[tauri::command]
+pub fn project_goal_completion(
+    goal_id: String,
+    current_value: f64,
+    monthly_contribution: f64,
+    annual_return: f64,
+    state: State<AppState>,
+) -> Result<GoalProjection, String> {
+    println!("Projecting goal completion...");
+    let mut conn = state.conn.lock().unwrap();
+    let service = goal_service::GoalService::new();
+    let goal = service
+        .get_goals(&mut conn)
+        .map_err(|e| format!("Failed to load goals: {}", e))?
+        .into_iter()
+        .find(|goal| goal.id == goal_id)
+        .ok_or_else(|| format!("Goal not found: {}", goal_id))?;
+
+    service.project_goal_completion(&goal, current_value, monthly_contribution, annual_return)
+}
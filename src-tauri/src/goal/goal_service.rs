@@ -1,7 +1,12 @@
 use crate::goal::GoalRepository;
-use crate::models::{Goal, GoalsAllocation, NewGoal};
+use crate::models::{Goal, GoalProjection, GoalProjectionPoint, GoalsAllocation, NewGoal};
+use crate::settings::read_only_mode;
+use chrono::{Months, NaiveDate, Utc};
 use diesel::SqliteConnection;
 
+// Projections stop after 100 years, past that the goal is considered unreachable
+const MAX_PROJECTION_MONTHS: u32 = 1200;
+
 pub struct GoalService {
     goal_repo: GoalRepository,
 }
@@ -25,6 +30,7 @@ impl GoalService {
         conn: &mut SqliteConnection,
         new_goal: NewGoal,
     ) -> Result<Goal, diesel::result::Error> {
+        read_only_mode::ensure_writable()?;
         let goal = self.goal_repo.insert_new_goal(conn, new_goal)?;
         Ok(goal)
     }
@@ -34,6 +40,7 @@ impl GoalService {
         conn: &mut SqliteConnection,
         updated_goal_data: Goal,
     ) -> Result<Goal, diesel::result::Error> {
+        read_only_mode::ensure_writable()?;
         self.goal_repo.update_goal(conn, updated_goal_data)
     }
 
@@ -42,6 +49,7 @@ impl GoalService {
         conn: &mut SqliteConnection,
         goal_id_to_delete: String, // ID of the goal to delete
     ) -> Result<usize, diesel::result::Error> {
+        read_only_mode::ensure_writable()?;
         self.goal_repo.delete_goal(conn, goal_id_to_delete)
     }
 
@@ -50,6 +58,7 @@ impl GoalService {
         conn: &mut SqliteConnection,
         allocations: Vec<GoalsAllocation>,
     ) -> Result<usize, diesel::result::Error> {
+        read_only_mode::ensure_writable()?;
         self.goal_repo.upsert_goal_allocations(conn, allocations)
     }
 
@@ -59,4 +68,163 @@ impl GoalService {
     ) -> Result<Vec<GoalsAllocation>, diesel::result::Error> {
         self.goal_repo.load_allocations_for_non_achieved_goals(conn)
     }
+
+    // Project the month by month value of a goal from its current value, contributing
+    // `monthly_contribution` at the end of each month and compounding `annual_return`.
+    // A return of -100% or less would wipe out the value, it has no monthly equivalent.
+    pub fn project_goal_completion(
+        &self,
+        goal: &Goal,
+        current_value: f64,
+        monthly_contribution: f64,
+        annual_return: f64,
+    ) -> Result<GoalProjection, String> {
+        if !current_value.is_finite()
+            || !monthly_contribution.is_finite()
+            || !annual_return.is_finite()
+        {
+            return Err("Projection inputs must be finite numbers".to_string());
+        }
+        if annual_return <= -1.0 {
+            return Err("Annual return must be greater than -100%".to_string());
+        }
+
+        let start_date = Utc::now().naive_utc().date();
+        let monthly_return = (1.0 + annual_return).powf(1.0 / 12.0) - 1.0;
+
+        let mut value = current_value;
+        let mut values = vec![projection_point(start_date, 0, value)];
+        let mut months_to_completion = None;
+
+        if goal.is_achieved || value >= goal.target_amount {
+            months_to_completion = Some(0);
+        } else if monthly_contribution > 0.0 || (monthly_return > 0.0 && value > 0.0) {
+            for month in 1..=MAX_PROJECTION_MONTHS {
+                value = value * (1.0 + monthly_return) + monthly_contribution;
+                values.push(projection_point(start_date, month, value));
+
+                if value >= goal.target_amount {
+                    months_to_completion = Some(month);
+                    break;
+                }
+            }
+        }
+
+        Ok(GoalProjection {
+            goal_id: goal.id.clone(),
+            target_amount: goal.target_amount,
+            completion_date: months_to_completion
+                .map(|months| add_months(start_date, months).to_string()),
+            months_to_completion,
+            values,
+        })
+    }
+}
+
+fn add_months(date: NaiveDate, months: u32) -> NaiveDate {
+    date.checked_add_months(Months::new(months))
+        .unwrap_or(NaiveDate::MAX)
+}
+
+fn projection_point(start_date: NaiveDate, month: u32, value: f64) -> GoalProjectionPoint {
+    GoalProjectionPoint {
+        date: add_months(start_date, month).to_string(),
+        value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn goal(target_amount: f64, is_achieved: bool) -> Goal {
+        Goal {
+            id: "goal".to_string(),
+            title: "Goal".to_string(),
+            description: None,
+            target_amount,
+            is_achieved,
+        }
+    }
+
+    fn project(
+        goal: &Goal,
+        current_value: f64,
+        monthly_contribution: f64,
+        annual_return: f64,
+    ) -> Result<GoalProjection, String> {
+        GoalService::new().project_goal_completion(
+            goal,
+            current_value,
+            monthly_contribution,
+            annual_return,
+        )
+    }
+
+    #[test]
+    fn a_return_compounds_monthly() {
+        // 1% a month, 1000 * 1.01^10 = 1104.62
+        let annual_return = 1.01_f64.powi(12) - 1.0;
+
+        let projection = project(&goal(1_102.0, false), 1_000.0, 0.0, annual_return).unwrap();
+
+        assert_eq!(projection.months_to_completion, Some(10));
+        assert_eq!(projection.values.len(), 11);
+        assert!((projection.values[1].value - 1_010.0).abs() < 1e-9);
+        assert!((projection.values[10].value - 1_000.0 * 1.01_f64.powi(10)).abs() < 1e-9);
+        assert!(projection.completion_date.is_some());
+    }
+
+    #[test]
+    fn contributions_are_added_at_the_end_of_each_month() {
+        let projection = project(&goal(1_000.0, false), 0.0, 100.0, 0.0).unwrap();
+
+        assert_eq!(projection.months_to_completion, Some(10));
+        assert_eq!(projection.values[3].value, 300.0);
+    }
+
+    #[test]
+    fn a_goal_without_growth_is_never_reached() {
+        let goal = goal(1_000.0, false);
+
+        for monthly_contribution in [0.0, -10.0] {
+            let projection = project(&goal, 500.0, monthly_contribution, 0.0).unwrap();
+            assert_eq!(projection.months_to_completion, None);
+            assert_eq!(projection.completion_date, None);
+            assert_eq!(projection.values.len(), 1);
+        }
+    }
+
+    #[test]
+    fn a_reached_goal_is_complete_at_once() {
+        let projection = project(&goal(1_000.0, true), 10.0, 100.0, 0.05).unwrap();
+        assert_eq!(projection.months_to_completion, Some(0));
+
+        let projection = project(&goal(1_000.0, false), 1_000.0, 0.0, 0.0).unwrap();
+        assert_eq!(projection.months_to_completion, Some(0));
+        assert_eq!(projection.values.len(), 1);
+    }
+
+    #[test]
+    fn projections_stop_after_100_years() {
+        let projection = project(&goal(1e12, false), 0.0, 1.0, 0.0).unwrap();
+
+        assert_eq!(projection.months_to_completion, None);
+        assert_eq!(projection.values.len(), MAX_PROJECTION_MONTHS as usize + 1);
+        assert!(projection
+            .values
+            .iter()
+            .all(|point| point.value.is_finite()));
+    }
+
+    #[test]
+    fn invalid_inputs_are_rejected() {
+        let goal = goal(1_000.0, false);
+
+        assert!(project(&goal, 100.0, 10.0, -1.0).is_err());
+        assert!(project(&goal, 100.0, 10.0, -1.5).is_err());
+        assert!(project(&goal, 100.0, f64::NAN, 0.05).is_err());
+        assert!(project(&goal, f64::INFINITY, 10.0, 0.05).is_err());
+        assert!(project(&goal, 100.0, 10.0, -0.5).is_ok());
+    }
 }
@@ -5,44 +5,73 @@ mod account;
 mod activity;
 mod asset;
 mod db;
+mod fx;
 mod goal;
 mod models;
 mod portfolio;
 mod providers;
 mod schema;
+mod search;
 mod settings;
+#[cfg(test)]
+mod test_utils;
 use account::account_commands::{create_account, delete_account, get_accounts, update_account};
 use activity::activity_commands::{
-    check_activities_import, create_activities, create_activity, delete_activity,
-    search_activities, update_activity,
+    check_activities_import, create_activities, create_activity, delete_activities_by_filter,
+    delete_activity, dry_run_activities_import, export_activities, get_drip_settings,
+    get_import_locale, import_activities, restore_activity, search_activities, set_drip_enabled,
+    update_activities_by_filter, update_activity,
 };
 use asset::{
     asset_service,
-    assets_commands::{get_asset_data, search_ticker, synch_quotes},
+    assets_commands::{
+        add_asset_tag, add_manual_exchange_rate, add_symbol_override, classify_assets,
+        delete_manual_exchange_rate, delete_manual_quote, delete_quote_staleness_policy,
+        delete_symbol_override, get_asset_data, get_asset_tags, get_manual_exchange_rates,
+        get_quote_staleness_policies, get_symbol_overrides, get_tags, learn_symbol_alias,
+        refresh_stale_quotes, remove_asset_tag, save_manual_quote, search_ticker,
+        set_quote_staleness_policy, synch_quotes,
+    },
+};
+use portfolio::portfolio_commands::{
+    accrue_cash_interest, add_cash_interest_tier, compute_holdings, create_option_contract,
+    delete_cash_interest_tier, export_historical, get_allocation_history, get_cash_interest_tiers,
+    get_currency_returns, get_historical, get_holding_lots, get_option_contracts,
+    get_performance_history, get_performance_summary, get_portfolio_allocation, get_realized_gains,
+    get_rebalancing_suggestions, get_return_attribution, get_wash_sales,
+    reconcile_holdings_snapshot, settle_option, simulate_trades,
+};
+use search::search_commands::search;
+use settings::settings_commands::{
+    compact_database, get_active_profile, get_database_stats, get_profiles, get_settings,
+    switch_profile, update_currency, update_read_only, update_settings,
 };
-use portfolio::portfolio_commands::{compute_holdings, get_historical};
-use settings::settings_commands::{get_settings, update_currency, update_settings};
 use tauri::{api::dialog, CustomMenuItem, Manager, Menu, Submenu};
 
 use goal::goal_commands::{
-    create_goal, delete_goal, get_goals, load_goals_allocations, update_goal,
-    update_goal_allocations,
+    create_goal, delete_goal, get_goals, load_goals_allocations, project_goal_completion,
+    update_goal, update_goal_allocations,
 };
 
 use diesel::prelude::*;
 use std::sync::Mutex;
+use std::time::Duration;
 
 use tauri::async_runtime::spawn;
 
 // Learn more about Tauri commands at https://tauri.app/v1/guides/features/command
 
+// Time between two purges of the deleted activities past their undo window
+const ACTIVITY_PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
 pub struct AppState {
     conn: Mutex<SqliteConnection>,
 }
 
 fn main() {
     // Initialize database
-    db::init();
+    db::init().expect("Failed to initialize the database");
+    settings::read_only_mode::init(&mut db::establish_connection());
 
     // Initialize state and connection
     let state = AppState {
@@ -80,22 +109,77 @@ fn main() {
             create_activity,
             update_activity,
             delete_activity,
+            restore_activity,
+            delete_activities_by_filter,
+            get_drip_settings,
+            set_drip_enabled,
+            get_import_locale,
+            update_activities_by_filter,
             search_ticker,
+            classify_assets,
+            get_symbol_overrides,
+            add_symbol_override,
+            learn_symbol_alias,
+            delete_symbol_override,
+            get_manual_exchange_rates,
+            add_manual_exchange_rate,
+            delete_manual_exchange_rate,
+            get_tags,
+            get_asset_tags,
+            add_asset_tag,
+            remove_asset_tag,
+            save_manual_quote,
+            delete_manual_quote,
+            get_quote_staleness_policies,
+            set_quote_staleness_policy,
+            delete_quote_staleness_policy,
+            refresh_stale_quotes,
             check_activities_import,
+            dry_run_activities_import,
+            export_activities,
+            import_activities,
             create_activities,
             get_historical,
+            export_historical,
+            get_performance_summary,
+            get_performance_history,
+            get_return_attribution,
+            get_currency_returns,
+            get_portfolio_allocation,
+            simulate_trades,
+            get_rebalancing_suggestions,
+            get_holding_lots,
+            get_realized_gains,
+            get_wash_sales,
+            get_cash_interest_tiers,
+            add_cash_interest_tier,
+            delete_cash_interest_tier,
+            accrue_cash_interest,
+            get_option_contracts,
+            create_option_contract,
+            settle_option,
+            reconcile_holdings_snapshot,
             compute_holdings,
+            get_allocation_history,
             get_asset_data,
             synch_quotes,
             get_settings,
             update_settings,
             update_currency,
+            update_read_only,
+            compact_database,
+            get_database_stats,
+            get_profiles,
+            get_active_profile,
+            switch_profile,
             create_goal,
             update_goal,
             delete_goal,
             get_goals,
             update_goal_allocations,
             load_goals_allocations,
+            project_goal_completion,
+            search,
         ])
         .build(context)
         .expect("error while running wealthfolio application");
@@ -108,7 +192,7 @@ fn main() {
         app_handle
             .emit_all("QUOTES_SYNC_START", {})
             .expect("Failed to emit event");
-        match asset_service.initialize_and_sync_quotes().await {
+        match asset_service.initialize_and_sync_quotes(false).await {
             Ok(_) => {
                 app_handle
                     .emit_all("QUOTES_SYNC_COMPLETE", {})
@@ -123,6 +207,22 @@ fn main() {
         }
     });
 
+    // Purged on the connection of the active profile, nothing is purged in read-only mode
+    let purge_handle = app.app_handle();
+    std::thread::spawn(move || loop {
+        if !settings::read_only_mode::is_enabled() {
+            let state = purge_handle.state::<AppState>();
+            let mut conn = state.conn.lock().unwrap();
+            let service = activity::activity_service::ActivityService::new();
+            match service.purge_deleted_activities(&mut conn) {
+                Ok(0) => {}
+                Ok(purged) => println!("Purged {} deleted activities", purged),
+                Err(e) => eprintln!("Failed to purge deleted activities: {}", e),
+            }
+        }
+        std::thread::sleep(ACTIVITY_PURGE_INTERVAL);
+    });
+
     app.run(|_app_handle, _event| {
         // Handle various app events here if needed, otherwise do nothing
     });
@@ -99,6 +99,38 @@ pub struct Asset {
     pub sectors: Option<String>,
     pub url: Option<String>,
 }
+
+// Asset type of the option contracts, see `OptionContract`
+pub const OPTION_ASSET_TYPE: &str = "OPTION";
+
+// Units of the underlying in one option contract
+pub const OPTION_CONTRACT_MULTIPLIER: f64 = 100.0;
+
+impl Asset {
+    // Bonds are quoted as a percentage of their face value, and their
+    // quantity is the nominal amount held
+    pub fn is_percent_priced(&self) -> bool {
+        self.asset_sub_class.as_deref() == Some("Bond")
+    }
+
+    // Options are quoted per unit of the underlying, and their quantity is
+    // the number of contracts held
+    pub fn is_option(&self) -> bool {
+        self.asset_type.as_deref() == Some(OPTION_ASSET_TYPE)
+    }
+
+    // Value of one unit of quantity at a quoted price of 1
+    pub fn get_price_factor(&self) -> f64 {
+        if self.is_percent_priced() {
+            0.01
+        } else if self.is_option() {
+            OPTION_CONTRACT_MULTIPLIER
+        } else {
+            1.0
+        }
+    }
+}
+
 #[derive(Insertable, Serialize, Deserialize, Debug, Default, Clone)]
 #[diesel(table_name = crate::schema::assets)]
 #[serde(rename_all = "camelCase")]
@@ -122,6 +154,14 @@ pub struct NewAsset {
     pub url: Option<String>,
 }
 
+// Profile of an asset found without writing anything, saved already or fetched from
+// the provider
+#[derive(Debug, Clone)]
+pub enum AssetLookup {
+    Saved(Asset),
+    Fetched(NewAsset),
+}
+
 #[derive(
     Queryable,
     Selectable,
@@ -152,6 +192,31 @@ pub struct Activity {
     pub comment: Option<String>,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
+    pub deleted_at: Option<chrono::NaiveDateTime>, // Set while the deletion can be undone
+    pub source_activity_id: Option<String>, // Dividend of a reinvestment buy, parent of a spin-off
+}
+
+impl Activity {
+    // Shares of a SPIN_OFF_IN kept in the holding and the fraction paid in cash. The unit
+    // price of the received side is the cash in lieu per share, without it the fraction
+    // is kept too.
+    pub fn get_spin_off_shares(&self) -> (f64, f64) {
+        if self.unit_price > 0.0 {
+            let shares = (self.quantity + 1e-9).floor().max(0.0);
+            (shares, (self.quantity - shares).max(0.0))
+        } else {
+            (self.quantity, 0.0)
+        }
+    }
+
+    // Part of the basis received by a SPIN_OFF_IN that goes to the shares kept
+    pub fn get_spin_off_kept_fraction(&self) -> f64 {
+        if self.quantity > 0.0 {
+            self.get_spin_off_shares().0 / self.quantity
+        } else {
+            0.0
+        }
+    }
 }
 
 #[derive(PartialEq, Serialize, Deserialize, AsChangeset, Debug, Clone)]
@@ -186,6 +251,7 @@ pub struct NewActivity {
     pub fee: f64,
     pub is_draft: bool,
     pub comment: Option<String>,
+    pub source_activity_id: Option<String>,
 }
 
 #[derive(
@@ -263,7 +329,7 @@ pub struct ActivitySearchResponse {
     pub meta: ActivitySearchResponseMeta,
 }
 
-#[derive(Serialize, Debug, Deserialize)]
+#[derive(Serialize, Debug, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct ActivityImport {
     pub id: Option<String>,
@@ -274,6 +340,7 @@ pub struct ActivityImport {
     pub unit_price: f64,
     pub currency: String,
     pub fee: f64,
+    pub accrued_interest: Option<f64>,
     pub comment: Option<String>,
     pub account_id: Option<String>,
     pub account_name: Option<String>,
@@ -282,6 +349,198 @@ pub struct ActivityImport {
     pub is_draft: Option<String>,
     pub is_valid: Option<String>,
     pub line_number: Option<i32>,
+    pub duplicate: Option<DuplicateStatus>,
+    // Suspicious row that can still be imported, e.g. a date in the future
+    pub warning: Option<String>,
+    // Existing activity of the account the row replaces, named by the id column of the file
+    #[serde(skip_deserializing)]
+    pub updated_activity_id: Option<String>,
+    // Broker order of the row, to merge fee rows into their trade
+    #[serde(skip)]
+    pub order_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DuplicateStatus {
+    // Same trade with the same price and fee as an existing activity
+    Exact,
+    // Same trade whose amount only differs within the rounding tolerance
+    Possible,
+}
+
+// Filters of the activity search on top of the account, type, keyword and tag ones.
+// The amount is the quantity times the unit price, in the activity currency.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivitySearchFilter {
+    pub symbols: Option<Vec<String>>,
+    pub start_date: Option<String>, // Inclusive, YYYY-MM-DD
+    pub end_date: Option<String>,   // Inclusive, YYYY-MM-DD
+    pub min_amount: Option<f64>,
+    pub max_amount: Option<f64>,
+    pub comment_keyword: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityExportFilter {
+    pub account_ids: Option<Vec<String>>,
+    pub activity_types: Option<Vec<String>>,
+    pub start_date: Option<String>, // Inclusive, YYYY-MM-DD
+    pub end_date: Option<String>,   // Inclusive, YYYY-MM-DD
+}
+
+// Number and date formats of the generic csv layout. The date format uses the chrono
+// syntax, e.g. "%d/%m/%Y", and the thousands separator may be empty.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportLocale {
+    pub decimal_separator: String,
+    pub thousands_separator: String,
+    pub date_format: String,
+}
+
+impl Default for ImportLocale {
+    fn default() -> Self {
+        ImportLocale {
+            decimal_separator: ".".to_string(),
+            thousands_separator: ",".to_string(),
+            date_format: "%Y-%m-%d".to_string(),
+        }
+    }
+}
+
+// Import settings of an account, reused by its next imports
+#[derive(Queryable, Selectable, Insertable, AsChangeset, Debug, Clone)]
+#[diesel(table_name = crate::schema::import_mappings)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ImportMapping {
+    pub account_id: String,
+    pub decimal_separator: String,
+    pub thousands_separator: String,
+    pub date_format: String,
+}
+
+impl ImportMapping {
+    pub fn new(account_id: &str, locale: &ImportLocale) -> Self {
+        ImportMapping {
+            account_id: account_id.to_string(),
+            decimal_separator: locale.decimal_separator.clone(),
+            thousands_separator: locale.thousands_separator.clone(),
+            date_format: locale.date_format.clone(),
+        }
+    }
+
+    pub fn locale(&self) -> ImportLocale {
+        ImportLocale {
+            decimal_separator: self.decimal_separator.clone(),
+            thousands_separator: self.thousands_separator.clone(),
+            date_format: self.date_format.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportDryRunReport {
+    pub creates: usize,
+    pub updates: usize,
+    pub duplicates: usize,
+    pub errors: usize,
+    pub warnings: usize,      // Created activities with a warning
+    pub reinvestments: usize, // Dividend reinvestments bought along the created dividends
+    pub activities: Vec<ActivityImport>,
+}
+
+// First checked rows of an import file, along with the number of activities in the file
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityImportPreview {
+    pub activities: Vec<ActivityImport>,
+    pub total: usize,
+}
+
+// Rows of the preview the user chose to skip or import, by line number. The rows of the
+// other lines are skipped when they have a duplicate or a warning.
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSelection {
+    pub skipped_lines: Vec<i32>,
+    pub included_lines: Vec<i32>,
+}
+
+impl ImportSelection {
+    // Rows with an error are never imported
+    pub fn includes(&self, activity_import: &ActivityImport) -> bool {
+        if activity_import.error.is_some() {
+            return false;
+        }
+        let line_number = activity_import.line_number.unwrap_or_default();
+        if self.skipped_lines.contains(&line_number) {
+            false
+        } else if self.included_lines.contains(&line_number) {
+            true
+        } else {
+            activity_import.duplicate.is_none() && activity_import.warning.is_none()
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportActivitiesResult {
+    pub imported: usize,
+    pub updated: usize,
+    pub skipped: usize,
+}
+
+// Selects the activities of a bulk edit or delete, at least one field must be set
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityBulkFilter {
+    pub account_ids: Option<Vec<String>>,
+    pub activity_types: Option<Vec<String>>,
+    pub asset_ids: Option<Vec<String>>,
+    pub start_date: Option<String>, // Inclusive, YYYY-MM-DD
+    pub end_date: Option<String>,   // Inclusive, YYYY-MM-DD
+}
+
+impl ActivityBulkFilter {
+    // An empty list restricts nothing, so it counts as unset
+    pub fn is_empty(&self) -> bool {
+        let is_unset =
+            |values: &Option<Vec<String>>| values.as_deref().unwrap_or_default().is_empty();
+        is_unset(&self.account_ids)
+            && is_unset(&self.activity_types)
+            && is_unset(&self.asset_ids)
+            && self.start_date.is_none()
+            && self.end_date.is_none()
+    }
+}
+
+// Fields set on every activity matched by a bulk edit, unset fields are left unchanged
+#[derive(Deserialize, AsChangeset, Debug, Default)]
+#[diesel(table_name = crate::schema::activities)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityBulkUpdate {
+    pub account_id: Option<String>,
+    pub currency: Option<String>,
+}
+
+// Payload of the single event sent after a bulk edit or delete
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivitiesChanged {
+    pub updated: usize,
+    pub deleted: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportProgress {
+    pub processed: usize,
+    pub total: usize,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -323,6 +582,13 @@ pub struct Holding {
     pub asset_class: Option<String>,
     pub asset_sub_class: Option<String>,
     pub sectors: Option<Vec<Sector>>,
+    pub tags: Vec<String>,
+    pub quote_date: Option<chrono::NaiveDate>, // Day of the quote of the market price
+    pub is_quote_stale: bool,                  // Quote older than its staleness policy
+    // Missing exchange rate to the reporting currency, the converted values are left at 0
+    pub fx_error: Option<String>,
+    // Rate to the reporting currency triangulated through another currency
+    pub fx_derived: bool,
 }
 
 #[derive(QueryableByName, Debug)]
@@ -363,6 +629,293 @@ pub struct FinancialSnapshot {
     pub exchange_rate: Option<f64>,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PerformanceSummary {
+    pub account_id: String,
+    pub start_date: String,
+    pub end_date: String,
+    pub start_value: f64,
+    pub end_value: f64,
+    pub net_deposit: f64,
+    pub currency: String,
+    pub money_weighted_return: Option<f64>, // Annualized, None when it cannot be solved
+    // Risk metrics are None when the history is too short, returns are fractions
+    pub max_drawdown: Option<f64>,      // Largest fall from a peak
+    pub max_drawdown_days: Option<i64>, // Longest time below a previous peak
+    pub volatility: Option<f64>,        // Annualized
+    pub sharpe_ratio: Option<f64>,
+}
+
+// Time-weighted growth of an account and of benchmark symbols over the dates of its
+// history, each series indexed to 100 on the first date it has a value
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PerformanceHistory {
+    pub account_id: String,
+    pub currency: String,
+    pub dates: Vec<String>,
+    pub portfolio: Vec<f64>,
+    pub benchmarks: Vec<BenchmarkSeries>,
+}
+
+// Values aligned with the dates of the history, None before the first quote of the
+// symbol. Benchmarks stay in their quote currency.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchmarkSeries {
+    pub symbol: String,
+    pub values: Vec<Option<f64>>,
+}
+
+// Return of a foreign asset over a period, as fractions, split between the move of its
+// price in its own currency and the move of that currency against the reporting currency
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CurrencyReturn {
+    pub asset_id: String,
+    pub currency: String,
+    pub local_return: f64,
+    pub fx_contribution: f64, // Includes the cross term, so local + fx = total
+    pub total_return: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AttributionGroup {
+    AssetClass,
+    Sector,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ReturnAttribution {
+    pub category: String,
+    pub weight: f64,       // Percentage of the portfolio book value
+    pub contribution: f64, // Percentage points of the portfolio total return
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AllocationGroup {
+    AssetClass,
+    AssetSubClass,
+    Sector,
+    Currency,
+    HoldingType,
+    Account,
+    AccountType,
+    AccountGroup,
+    Tag,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Allocation {
+    pub category: String,
+    pub value: f64,  // Market value in the reporting currency
+    pub weight: f64, // Percentage of the portfolio market value
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AllocationInterval {
+    Weekly,
+    Monthly,
+}
+
+// Allocation weights over the dates of a range. Every category has a weight on every
+// date, 0 when it held nothing then, so the series stack.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AllocationHistory {
+    pub dates: Vec<String>,
+    pub series: Vec<AllocationSeries>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AllocationSeries {
+    pub category: String,
+    pub weights: Vec<f64>, // Percentages aligned with the dates
+}
+
+// A hypothetical BUY or SELL, priced in the currency of the asset
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatedTrade {
+    pub account_id: String,
+    pub asset_id: String,
+    pub activity_type: String,
+    pub quantity: f64,
+    pub unit_price: f64,
+    #[serde(default)]
+    pub fee: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AllocationShift {
+    pub category: String,
+    pub current_weight: f64,
+    pub projected_weight: f64,
+}
+
+// Cash balances are in the reporting currency
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TradeSimulation {
+    pub holdings: Vec<Holding>,
+    pub allocation: Vec<AllocationShift>,
+    pub current_cash: f64,
+    pub projected_cash: f64,
+}
+
+// Amounts are in the reporting currency
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RebalanceConstraints {
+    pub whole_shares: bool,
+    pub min_trade_value: f64,
+    pub cash_buffer: f64, // Cash kept out of the rebalance
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RebalanceTrade {
+    pub account_id: String,
+    pub asset_id: String,
+    pub category: String,
+    pub activity_type: String,
+    pub quantity: f64,
+    pub unit_price: f64, // Market price in the currency of the asset
+    pub value: f64,      // Value in the reporting currency
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RebalancePlan {
+    pub trades: Vec<RebalanceTrade>,
+    pub projected_cash: f64,
+    pub warnings: Vec<String>,
+}
+
+// Order in which the lots of a holding are sold
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum LotMatchingMethod {
+    #[default]
+    Fifo,
+    Lifo,
+    SpecificId, // Lots chosen per sell, the rest is matched first in first out
+    Average,    // Lots sold first in first out at the average cost of the holding
+}
+
+// A lot is identified by the activity that acquired it, amounts are in the
+// currency of the holding
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TaxLot {
+    pub id: String,
+    pub acquisition_date: chrono::NaiveDateTime,
+    pub quantity: f64,
+    pub cost_basis: f64,
+    pub market_value: f64,
+    pub unrealized_gain: f64,
+    pub unrealized_gain_percent: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RealizedLot {
+    pub sell_activity_id: String,
+    pub lot_id: String,
+    pub acquisition_date: chrono::NaiveDateTime,
+    pub sale_date: chrono::NaiveDateTime,
+    pub quantity: f64,
+    pub cost_basis: f64,
+    pub proceeds: f64,
+    pub realized_gain: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum HoldingPeriod {
+    ShortTerm,
+    LongTerm, // Held more than one year
+}
+
+// A sold part of a lot, converted amounts are in the base currency at the
+// exchange rate of the sale date
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RealizedGain {
+    pub account_id: String,
+    pub asset_id: String,
+    pub sell_activity_id: String,
+    pub lot_id: String,
+    pub acquisition_date: chrono::NaiveDateTime,
+    pub sale_date: chrono::NaiveDateTime,
+    pub holding_period: HoldingPeriod,
+    pub quantity: f64,
+    pub currency: String,
+    pub cost_basis: f64,
+    pub proceeds: f64,
+    pub realized_gain: f64,
+    pub exchange_rate: f64,
+    pub cost_basis_converted: f64,
+    pub proceeds_converted: f64,
+    pub realized_gain_converted: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RealizedGainsSummary {
+    pub cost_basis: f64,
+    pub proceeds: f64,
+    pub short_term_gain: f64,
+    pub long_term_gain: f64,
+    pub total_gain: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RealizedGainsReport {
+    pub base_currency: String,
+    pub method: LotMatchingMethod,
+    pub transactions: Vec<RealizedGain>,
+    pub summary: RealizedGainsSummary,
+}
+
+// A sale at a loss with a repurchase of the asset within 30 days of it. The
+// disallowed loss, in the currency of the asset, goes to the basis of the
+// replacement lot.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WashSale {
+    pub account_id: String,
+    pub asset_id: String,
+    pub sell_activity_id: String,
+    pub sale_date: chrono::NaiveDateTime,
+    pub currency: String,
+    pub quantity: f64, // Units sold covered by the replacement lot
+    pub disallowed_loss: f64,
+    pub replacement_lot_id: String,
+    pub replacement_account_id: String,
+    pub replacement_date: chrono::NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HoldingLots {
+    pub account_id: String,
+    pub asset_id: String,
+    pub currency: String,
+    pub method: LotMatchingMethod,
+    pub lots: Vec<TaxLot>,
+    pub realized: Vec<RealizedLot>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct FinancialHistory {
@@ -370,6 +923,37 @@ pub struct FinancialHistory {
     pub history: Vec<FinancialSnapshot>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TableStats {
+    pub name: String,
+    pub row_count: i64,
+    pub size: Option<i64>, // Bytes, None when the dbstat table is not available
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseStats {
+    pub tables: Vec<TableStats>,
+    pub database_size: u64,
+    pub wal_size: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetClassificationReport {
+    pub assigned: Vec<String>,
+    pub skipped: Vec<String>, // The provider returned no classification
+    pub failed: Vec<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct AssetProfile {
@@ -428,6 +1012,11 @@ pub struct Settings {
     pub theme: String,
     pub font: String,
     pub base_currency: String,
+    // Decimal places shown for quantities and prices, values are stored unrounded
+    pub quantity_precision: i32,
+    pub price_precision: i32,
+    pub read_only: bool,         // Changes to the user data are refused
+    pub activity_undo_days: i32, // Days a deleted activity can be restored
 }
 
 #[derive(Insertable, Serialize, AsChangeset, Deserialize, Debug)]
@@ -437,6 +1026,9 @@ pub struct NewSettings<'a> {
     pub theme: &'a str,
     pub font: &'a str,
     pub base_currency: &'a str,
+    pub quantity_precision: i32,
+    pub price_precision: i32,
+    pub activity_undo_days: i32,
 }
 
 #[derive(
@@ -496,3 +1088,238 @@ pub struct GoalsAllocation {
     pub account_id: String,
     pub percent_allocation: i32,
 }
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GoalProjectionPoint {
+    pub date: String,
+    pub value: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GoalProjection {
+    pub goal_id: String,
+    pub target_amount: f64,
+    pub completion_date: Option<String>, // None when the target is never reached
+    pub months_to_completion: Option<u32>,
+    pub values: Vec<GoalProjectionPoint>,
+}
+
+#[derive(Queryable, Selectable, Insertable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::symbol_overrides)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[serde(rename_all = "camelCase")]
+pub struct SymbolOverride {
+    pub id: String,
+    pub symbol: String,
+    pub account_id: Option<String>, // None applies the override to every account
+    pub provider_symbol: String,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NewSymbolOverride {
+    pub symbol: String,
+    pub account_id: Option<String>,
+    pub provider_symbol: String,
+}
+
+#[derive(Queryable, Selectable, Insertable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::manual_exchange_rates)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[serde(rename_all = "camelCase")]
+pub struct ManualExchangeRate {
+    pub id: String,
+    pub from_currency: String,
+    pub to_currency: String,
+    pub rate: f64,
+    pub start_date: chrono::NaiveDate,
+    pub end_date: chrono::NaiveDate, // Inclusive, equal to start_date for a single day
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NewManualExchangeRate {
+    pub from_currency: String,
+    pub to_currency: String,
+    pub rate: f64,
+    pub start_date: chrono::NaiveDate,
+    pub end_date: Option<chrono::NaiveDate>,
+}
+
+// Open, high and low default to the close when not known
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NewManualQuote {
+    pub symbol: String,
+    pub date: chrono::NaiveDate,
+    pub open: Option<f64>,
+    pub high: Option<f64>,
+    pub low: Option<f64>,
+    pub close: f64,
+    pub volume: Option<f64>,
+}
+
+#[derive(Queryable, Selectable, Insertable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::asset_tags)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[serde(rename_all = "camelCase")]
+pub struct AssetTag {
+    pub asset_id: String,
+    pub tag: String,
+}
+
+// Annual rate in percent paid on the part of the account cash above `min_balance` and
+// below the next tier, the tiers of an account together make a marginal schedule
+#[derive(Queryable, Selectable, Insertable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::cash_interest_tiers)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[serde(rename_all = "camelCase")]
+pub struct CashInterestTier {
+    pub id: String,
+    pub account_id: String,
+    pub min_balance: f64,
+    pub annual_rate: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NewCashInterestTier {
+    pub account_id: String,
+    #[serde(default)]
+    pub min_balance: f64,
+    pub annual_rate: f64,
+}
+
+// Interest estimated on the idle cash of an account over a month, posted as an
+// INTEREST activity on the last day of the month
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CashInterestAccrual {
+    pub account_id: String,
+    pub activity_id: String,
+    pub date: chrono::NaiveDate,
+    pub amount: f64,
+    pub currency: String,
+}
+
+// Terms of an option asset, the strike price is per unit of the underlying
+#[derive(Queryable, Selectable, Insertable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::option_contracts)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[serde(rename_all = "camelCase")]
+pub struct OptionContract {
+    pub asset_id: String,
+    pub underlying_asset_id: String,
+    pub option_type: String,
+    pub strike_price: f64,
+    pub expiration_date: chrono::NaiveDate,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OptionType {
+    Call,
+    Put,
+}
+
+impl OptionType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OptionType::Call => "CALL",
+            OptionType::Put => "PUT",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NewOptionContract {
+    pub underlying_asset_id: String,
+    pub option_type: OptionType,
+    pub strike_price: f64,
+    pub expiration_date: chrono::NaiveDate,
+}
+
+// How an option position is closed at expiry
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OptionSettlement {
+    Exercise, // A long position trades the underlying at the strike
+    Assign,   // A short position trades the underlying at the strike
+    Expire,   // Expired worthless, the premium is the realized result
+}
+
+// Days the last quote of an asset type may be old before valuations flag it stale
+#[derive(Queryable, Selectable, Insertable, AsChangeset, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::quote_staleness_policies)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[serde(rename_all = "camelCase")]
+pub struct QuoteStalenessPolicy {
+    pub asset_type: String,
+    pub max_age_days: i32,
+}
+
+// A position of a broker holdings snapshot
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HoldingSnapshotRow {
+    pub symbol: String,
+    pub quantity: f64,
+    pub unit_price: Option<f64>,
+}
+
+// A snapshot position that differs from the computed one, with the activity making them
+// match, or the reason there is none
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HoldingDiscrepancy {
+    pub symbol: String,
+    pub computed_quantity: f64,
+    pub snapshot_quantity: f64,
+    pub activity: Option<NewActivity>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HoldingsReconciliation {
+    pub account_id: String,
+    pub snapshot_date: chrono::NaiveDate,
+    pub discrepancies: Vec<HoldingDiscrepancy>,
+    pub created: usize, // Activities created, 0 unless applied
+}
+
+// Dividends of the asset in the account are reinvested in the asset
+#[derive(Queryable, Selectable, Insertable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::drip_settings)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[serde(rename_all = "camelCase")]
+pub struct DripSetting {
+    pub account_id: String,
+    pub asset_id: String,
+}
+
+// A record matching a search, tagged with its kind. Scores go from 0 to 1.
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SearchResult {
+    Account { score: f64, account: Account },
+    Asset { score: f64, asset: Asset },
+    Activity { score: f64, activity: Activity },
+    Goal { score: f64, goal: Goal },
+}
+
+impl SearchResult {
+    pub fn score(&self) -> f64 {
+        match self {
+            SearchResult::Account { score, .. }
+            | SearchResult::Asset { score, .. }
+            | SearchResult::Activity { score, .. }
+            | SearchResult::Goal { score, .. } => *score,
+        }
+    }
+}
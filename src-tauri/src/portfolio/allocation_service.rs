@@ -0,0 +1,475 @@
+use std::collections::HashMap;
+
+use chrono::{Duration, Months, NaiveDate};
+
+use crate::models::{
+    Allocation, AllocationGroup, AllocationHistory, AllocationInterval, AllocationSeries,
+    AllocationShift, Holding,
+};
+
+const OTHER_CATEGORY: &str = "Other";
+
+pub struct AllocationService;
+
+impl AllocationService {
+    pub fn new() -> Self {
+        AllocationService
+    }
+
+    // Split the market value of the holdings along `group_by`, holdings missing the
+    // grouping attribute are put in the "Other" bucket. Short holdings have a negative
+    // market value and reduce the exposure of their category.
+    pub fn calculate_allocation(
+        &self,
+        holdings: &[Holding],
+        group_by: AllocationGroup,
+    ) -> Vec<Allocation> {
+        let total_value: f64 = holdings.iter().map(|h| h.market_value_converted).sum();
+        if total_value == 0.0 {
+            return Vec::new();
+        }
+
+        let mut categories: HashMap<String, f64> = HashMap::new();
+        for holding in holdings {
+            for (category, share) in Self::get_holding_groups(holding, group_by) {
+                *categories.entry(category).or_insert(0.0) +=
+                    holding.market_value_converted * share;
+            }
+        }
+
+        let mut allocations: Vec<Allocation> = categories
+            .into_iter()
+            .map(|(category, value)| Allocation {
+                category,
+                value,
+                weight: value / total_value.abs() * 100.0,
+            })
+            .collect();
+        allocations.sort_by(|a, b| b.value.total_cmp(&a.value));
+
+        allocations
+    }
+
+    // Pair the weights of each category before and after a change of holdings, categories
+    // only present on one side weigh 0 on the other
+    pub fn calculate_allocation_shifts(
+        &self,
+        current_holdings: &[Holding],
+        projected_holdings: &[Holding],
+        group_by: AllocationGroup,
+    ) -> Vec<AllocationShift> {
+        let mut shifts: Vec<AllocationShift> = self
+            .calculate_allocation(projected_holdings, group_by)
+            .into_iter()
+            .map(|allocation| AllocationShift {
+                category: allocation.category,
+                current_weight: 0.0,
+                projected_weight: allocation.weight,
+            })
+            .collect();
+
+        for allocation in self.calculate_allocation(current_holdings, group_by) {
+            match shifts
+                .iter_mut()
+                .find(|s| s.category == allocation.category)
+            {
+                Some(shift) => shift.current_weight = allocation.weight,
+                None => shifts.push(AllocationShift {
+                    category: allocation.category,
+                    current_weight: allocation.weight,
+                    projected_weight: 0.0,
+                }),
+            }
+        }
+
+        shifts
+    }
+
+    // Weights of each category on each date of `holdings_by_date`, in date order.
+    // Categories are sorted by their average weight, the largest first.
+    pub fn calculate_allocation_history(
+        &self,
+        holdings_by_date: &[(NaiveDate, Vec<Holding>)],
+        group_by: AllocationGroup,
+    ) -> AllocationHistory {
+        let mut series: Vec<AllocationSeries> = Vec::new();
+        for (index, (_, holdings)) in holdings_by_date.iter().enumerate() {
+            for allocation in self.calculate_allocation(holdings, group_by) {
+                let position = series
+                    .iter()
+                    .position(|s| s.category == allocation.category)
+                    .unwrap_or_else(|| {
+                        series.push(AllocationSeries {
+                            category: allocation.category.clone(),
+                            weights: vec![0.0; holdings_by_date.len()],
+                        });
+                        series.len() - 1
+                    });
+                series[position].weights[index] = allocation.weight;
+            }
+        }
+        series.sort_by(|a, b| {
+            let total = |s: &AllocationSeries| s.weights.iter().sum::<f64>();
+            total(b).total_cmp(&total(a))
+        });
+
+        AllocationHistory {
+            dates: holdings_by_date
+                .iter()
+                .map(|(date, _)| date.format("%Y-%m-%d").to_string())
+                .collect(),
+            series,
+        }
+    }
+
+    // Dates from `start_date` spaced by `interval`, `end_date` closing the range
+    pub fn get_allocation_dates(
+        &self,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        interval: AllocationInterval,
+    ) -> Vec<NaiveDate> {
+        let mut dates = Vec::new();
+        let mut step = 0;
+        loop {
+            let date = match interval {
+                AllocationInterval::Weekly => Some(start_date + Duration::weeks(step)),
+                AllocationInterval::Monthly => {
+                    start_date.checked_add_months(Months::new(step as u32))
+                }
+            };
+            match date {
+                Some(date) if date < end_date => dates.push(date),
+                _ => break,
+            }
+            step += 1;
+        }
+        if start_date <= end_date {
+            dates.push(end_date);
+        }
+        dates
+    }
+
+    // The group with the largest share of a holding, for groupings splitting holdings
+    pub fn get_primary_group(&self, holding: &Holding, group_by: AllocationGroup) -> String {
+        Self::get_holding_groups(holding, group_by)
+            .into_iter()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(category, _)| category)
+            .unwrap_or_else(|| OTHER_CATEGORY.to_string())
+    }
+
+    // Groups a holding belongs to with the share of the holding in each of them
+    fn get_holding_groups(holding: &Holding, group_by: AllocationGroup) -> Vec<(String, f64)> {
+        let account = holding.account.as_ref();
+        let single_group = match group_by {
+            AllocationGroup::AssetClass => holding.asset_class.clone(),
+            AllocationGroup::AssetSubClass => holding.asset_sub_class.clone(),
+            AllocationGroup::Currency => Some(holding.currency.clone()),
+            AllocationGroup::HoldingType => Some(holding.holding_type.clone()),
+            AllocationGroup::Account => account.map(|a| a.name.clone()),
+            AllocationGroup::AccountType => account.map(|a| a.account_type.clone()),
+            AllocationGroup::AccountGroup => account.and_then(|a| a.group.clone()),
+            AllocationGroup::Sector => return Self::get_sector_groups(holding),
+            AllocationGroup::Tag => return Self::get_tag_groups(holding),
+        };
+
+        let group = single_group
+            .filter(|group| !group.is_empty())
+            .unwrap_or_else(|| OTHER_CATEGORY.to_string());
+        vec![(group, 1.0)]
+    }
+
+    // A holding with several tags is split evenly between them
+    fn get_tag_groups(holding: &Holding) -> Vec<(String, f64)> {
+        if holding.tags.is_empty() {
+            return vec![(OTHER_CATEGORY.to_string(), 1.0)];
+        }
+
+        let share = 1.0 / holding.tags.len() as f64;
+        holding
+            .tags
+            .iter()
+            .map(|tag| (tag.clone(), share))
+            .collect()
+    }
+
+    // A fund spreads over several sectors, any unassigned share goes to "Other"
+    fn get_sector_groups(holding: &Holding) -> Vec<(String, f64)> {
+        let mut groups: Vec<(String, f64)> = holding
+            .sectors
+            .iter()
+            .flatten()
+            .filter(|sector| sector.weight > 0.0)
+            .map(|sector| (sector.name.clone(), sector.weight.min(1.0)))
+            .collect();
+
+        let assigned_share: f64 = groups.iter().map(|(_, share)| share).sum();
+        if assigned_share > 1.0 {
+            for (_, share) in groups.iter_mut() {
+                *share /= assigned_share;
+            }
+        } else if assigned_share < 1.0 {
+            groups.push((OTHER_CATEGORY.to_string(), 1.0 - assigned_share));
+        }
+
+        groups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Performance, Sector};
+
+    fn holding(symbol: &str, currency: &str, market_value: f64) -> Holding {
+        Holding {
+            id: symbol.to_string(),
+            symbol: symbol.to_string(),
+            symbol_name: None,
+            holding_type: "Equity".to_string(),
+            quantity: 1.0,
+            currency: currency.to_string(),
+            base_currency: "USD".to_string(),
+            market_price: Some(market_value),
+            average_cost: Some(market_value),
+            market_value,
+            book_value: market_value,
+            market_value_converted: market_value,
+            book_value_converted: market_value,
+            performance: Performance {
+                total_gain_percent: 0.0,
+                total_gain_amount: 0.0,
+                total_gain_amount_converted: 0.0,
+                day_gain_percent: None,
+                day_gain_amount: None,
+                day_gain_amount_converted: None,
+            },
+            account: None,
+            asset_class: None,
+            asset_sub_class: None,
+            sectors: None,
+            tags: Vec::new(),
+            quote_date: None,
+            is_quote_stale: false,
+            fx_error: None,
+            fx_derived: false,
+        }
+    }
+
+    fn tagged(symbol: &str, market_value: f64, tags: &[&str]) -> Holding {
+        Holding {
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            ..holding(symbol, "USD", market_value)
+        }
+    }
+
+    fn with_sectors(symbol: &str, market_value: f64, sectors: &[(&str, f64)]) -> Holding {
+        Holding {
+            sectors: Some(
+                sectors
+                    .iter()
+                    .map(|(name, weight)| Sector {
+                        name: name.to_string(),
+                        weight: *weight,
+                    })
+                    .collect(),
+            ),
+            ..holding(symbol, "USD", market_value)
+        }
+    }
+
+    fn weight(allocations: &[Allocation], category: &str) -> f64 {
+        allocations
+            .iter()
+            .find(|a| a.category == category)
+            .map(|a| a.weight)
+            .unwrap()
+    }
+
+    #[test]
+    fn currency_allocation_is_sorted_by_value() {
+        let holdings = vec![
+            holding("SAP", "EUR", 400.0),
+            holding("AAPL", "USD", 500.0),
+            holding("MSFT", "USD", 100.0),
+        ];
+
+        let allocations =
+            AllocationService::new().calculate_allocation(&holdings, AllocationGroup::Currency);
+
+        let categories: Vec<&str> = allocations.iter().map(|a| a.category.as_str()).collect();
+        assert_eq!(categories, vec!["USD", "EUR"]);
+        assert!((weight(&allocations, "USD") - 60.0).abs() < 1e-9);
+        assert!((weight(&allocations, "EUR") - 40.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn tags_split_a_holding_evenly_and_untagged_holdings_go_to_other() {
+        let holdings = vec![
+            tagged("AAPL", 300.0, &["Growth", "Tech", "Core"]),
+            tagged("MSFT", 100.0, &["Tech"]),
+            tagged("BND", 100.0, &[]),
+        ];
+
+        let allocations =
+            AllocationService::new().calculate_allocation(&holdings, AllocationGroup::Tag);
+
+        assert_eq!(allocations.len(), 4);
+        assert!((weight(&allocations, "Growth") - 20.0).abs() < 1e-9);
+        assert!((weight(&allocations, "Core") - 20.0).abs() < 1e-9);
+        assert!((weight(&allocations, "Tech") - 40.0).abs() < 1e-9);
+        assert!((weight(&allocations, OTHER_CATEGORY) - 20.0).abs() < 1e-9);
+        let total: f64 = allocations.iter().map(|a| a.value).sum();
+        assert!((total - 500.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn unassigned_sector_shares_go_to_other() {
+        let holdings = vec![
+            with_sectors("VTI", 800.0, &[("Technology", 0.5), ("Healthcare", 0.25)]),
+            holding("AAPL", "USD", 200.0),
+        ];
+
+        let allocations =
+            AllocationService::new().calculate_allocation(&holdings, AllocationGroup::Sector);
+
+        assert_eq!(allocations.len(), 3);
+        assert!((weight(&allocations, "Technology") - 40.0).abs() < 1e-9);
+        assert!((weight(&allocations, "Healthcare") - 20.0).abs() < 1e-9);
+        // The remaining quarter of the fund and the holding without sectors
+        assert!((weight(&allocations, OTHER_CATEGORY) - 40.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn sector_shares_above_the_whole_are_scaled_down() {
+        let holdings = vec![with_sectors(
+            "QQQ",
+            1000.0,
+            &[("Technology", 0.9), ("Communication", 0.6)],
+        )];
+
+        let allocations =
+            AllocationService::new().calculate_allocation(&holdings, AllocationGroup::Sector);
+
+        assert_eq!(allocations.len(), 2);
+        assert!((weight(&allocations, "Technology") - 60.0).abs() < 1e-9);
+        assert!((weight(&allocations, "Communication") - 40.0).abs() < 1e-9);
+    }
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    fn dates(dates: &[&str]) -> Vec<NaiveDate> {
+        dates.iter().map(|d| date(d)).collect()
+    }
+
+    #[test]
+    fn allocation_history_follows_the_drift_of_the_weights() {
+        let holdings_by_date = vec![
+            (
+                date("2024-01-31"),
+                vec![holding("AAPL", "USD", 500.0), holding("SAP", "EUR", 500.0)],
+            ),
+            (
+                date("2024-02-29"),
+                vec![holding("AAPL", "USD", 600.0), holding("SAP", "EUR", 400.0)],
+            ),
+            (
+                date("2024-03-31"),
+                vec![holding("AAPL", "USD", 700.0), holding("SAP", "EUR", 300.0)],
+            ),
+            // A currency held on the last date only weighs 0 before
+            (
+                date("2024-04-30"),
+                vec![
+                    holding("AAPL", "USD", 700.0),
+                    holding("SAP", "EUR", 200.0),
+                    holding("7203.T", "JPY", 100.0),
+                ],
+            ),
+        ];
+
+        let history = AllocationService::new()
+            .calculate_allocation_history(&holdings_by_date, AllocationGroup::Currency);
+
+        assert_eq!(
+            history.dates,
+            ["2024-01-31", "2024-02-29", "2024-03-31", "2024-04-30"]
+        );
+        let categories: Vec<&str> = history.series.iter().map(|s| s.category.as_str()).collect();
+        assert_eq!(categories, ["USD", "EUR", "JPY"]);
+        let expected = [
+            vec![50.0, 60.0, 70.0, 70.0],
+            vec![50.0, 40.0, 30.0, 20.0],
+            vec![0.0, 0.0, 0.0, 10.0],
+        ];
+        for (series, expected) in history.series.iter().zip(expected) {
+            assert_eq!(series.weights.len(), expected.len());
+            for (weight, expected) in series.weights.iter().zip(expected) {
+                assert!((weight - expected).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn monthly_dates_stay_on_the_month_end() {
+        let allocation_dates = AllocationService::new().get_allocation_dates(
+            date("2024-01-31"),
+            date("2024-05-15"),
+            AllocationInterval::Monthly,
+        );
+
+        assert_eq!(
+            allocation_dates,
+            dates(&[
+                "2024-01-31",
+                "2024-02-29",
+                "2024-03-31",
+                "2024-04-30",
+                "2024-05-15"
+            ])
+        );
+    }
+
+    #[test]
+    fn weekly_dates_step_by_seven_days_and_end_on_the_last_date() {
+        let service = AllocationService::new();
+
+        let weekly = service.get_allocation_dates(
+            date("2024-01-01"),
+            date("2024-01-22"),
+            AllocationInterval::Weekly,
+        );
+        assert_eq!(
+            weekly,
+            dates(&["2024-01-01", "2024-01-08", "2024-01-15", "2024-01-22"])
+        );
+
+        let partial_week = service.get_allocation_dates(
+            date("2024-01-01"),
+            date("2024-01-10"),
+            AllocationInterval::Weekly,
+        );
+        assert_eq!(
+            partial_week,
+            dates(&["2024-01-01", "2024-01-08", "2024-01-10"])
+        );
+
+        let single_day = service.get_allocation_dates(
+            date("2024-01-01"),
+            date("2024-01-01"),
+            AllocationInterval::Weekly,
+        );
+        assert_eq!(single_day, dates(&["2024-01-01"]));
+
+        assert!(service
+            .get_allocation_dates(
+                date("2024-01-10"),
+                date("2024-01-01"),
+                AllocationInterval::Weekly
+            )
+            .is_empty());
+    }
+}
@@ -0,0 +1,329 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::{Datelike, Duration, NaiveDate, Utc};
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+
+use crate::activity::activity_service::ActivityService;
+use crate::asset::asset_service::AssetService;
+use crate::models::{
+    CashInterestAccrual, CashInterestTier, FinancialSnapshot, NewActivity, NewCashInterestTier,
+};
+use crate::portfolio::portfolio_service::PortfolioService;
+use crate::schema::cash_interest_tiers;
+use crate::settings::read_only_mode;
+
+// Comment of the posted accruals, they are estimates and not broker transactions
+pub const CASH_INTEREST_COMMENT: &str = "Estimated cash interest accrual";
+
+const DAYS_PER_YEAR: f64 = 365.0;
+
+pub struct CashInterestService {
+    activity_service: ActivityService,
+    asset_service: AssetService,
+}
+
+impl CashInterestService {
+    pub fn new() -> Self {
+        CashInterestService {
+            activity_service: ActivityService::new(),
+            asset_service: AssetService::new(),
+        }
+    }
+
+    pub fn get_tiers(
+        &self,
+        conn: &mut SqliteConnection,
+    ) -> Result<Vec<CashInterestTier>, diesel::result::Error> {
+        cash_interest_tiers::table
+            .order((
+                cash_interest_tiers::account_id.asc(),
+                cash_interest_tiers::min_balance.asc(),
+            ))
+            .load::<CashInterestTier>(conn)
+    }
+
+    pub fn add_tier(
+        &self,
+        conn: &mut SqliteConnection,
+        new_tier: NewCashInterestTier,
+    ) -> Result<CashInterestTier, diesel::result::Error> {
+        read_only_mode::ensure_writable()?;
+        let tier = CashInterestTier {
+            id: uuid::Uuid::new_v4().to_string(),
+            account_id: new_tier.account_id,
+            min_balance: new_tier.min_balance,
+            annual_rate: new_tier.annual_rate,
+        };
+
+        diesel::insert_into(cash_interest_tiers::table)
+            .values(&tier)
+            .returning(CashInterestTier::as_returning())
+            .get_result(conn)
+    }
+
+    pub fn delete_tier(
+        &self,
+        conn: &mut SqliteConnection,
+        tier_id: &str,
+    ) -> Result<usize, diesel::result::Error> {
+        read_only_mode::ensure_writable()?;
+        diesel::delete(cash_interest_tiers::table.find(tier_id)).execute(conn)
+    }
+
+    // Post the interest of every complete month not accrued yet for the accounts that
+    // have tiers. Balances come from the daily snapshots, in the account currency.
+    pub async fn accrue_cash_interest(
+        &self,
+        conn: &mut SqliteConnection,
+    ) -> Result<Vec<CashInterestAccrual>, Box<dyn std::error::Error>> {
+        read_only_mode::ensure_writable()?;
+        let mut account_tiers: HashMap<String, Vec<CashInterestTier>> = HashMap::new();
+        for tier in self.get_tiers(conn)? {
+            account_tiers
+                .entry(tier.account_id.clone())
+                .or_default()
+                .push(tier);
+        }
+        if account_tiers.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut portfolio_service = PortfolioService::new();
+        portfolio_service.initialize(conn, None).await?;
+        let histories = portfolio_service
+            .calculate_historical_portfolio_values(conn)
+            .await?;
+
+        let today = Utc::now().date_naive();
+        let mut accruals = Vec::new();
+        for financial_history in histories {
+            let account = financial_history.account;
+            let tiers = match account_tiers.get(&account.id) {
+                Some(tiers) => tiers,
+                None => continue,
+            };
+
+            let accrued_months: HashSet<(i32, u32)> = self
+                .activity_service
+                .get_account_activities(conn, &account.id)?
+                .into_iter()
+                .filter(|activity| {
+                    activity.activity_type == "INTEREST"
+                        && activity.comment.as_deref() == Some(CASH_INTEREST_COMMENT)
+                })
+                .map(|activity| {
+                    let date = activity.activity_date.date();
+                    (date.year(), date.month())
+                })
+                .collect();
+
+            let daily_balances = get_daily_balances(&financial_history.history);
+            for (month_end, amount) in calculate_monthly_interest(&daily_balances, tiers, today) {
+                let amount = (amount * 100.0).round() / 100.0;
+                if amount <= 0.0 || accrued_months.contains(&(month_end.year(), month_end.month()))
+                {
+                    continue;
+                }
+
+                let cash_asset_id = format!("$CASH-{}", account.currency);
+                if self
+                    .asset_service
+                    .get_asset_by_id(conn, &cash_asset_id)
+                    .is_err()
+                {
+                    self.asset_service
+                        .create_cash_asset(conn, &account.currency)?;
+                }
+
+                let activity = self
+                    .activity_service
+                    .create_activity(
+                        conn,
+                        NewActivity {
+                            id: None,
+                            account_id: account.id.clone(),
+                            asset_id: cash_asset_id,
+                            activity_type: "INTEREST".to_string(),
+                            activity_date: month_end.format("%Y-%m-%d 00:00:00").to_string(),
+                            quantity: amount,
+                            unit_price: 1.0,
+                            currency: account.currency.clone(),
+                            fee: 0.0,
+                            is_draft: false,
+                            comment: Some(CASH_INTEREST_COMMENT.to_string()),
+                            source_activity_id: None,
+                        },
+                    )
+                    .await?;
+
+                accruals.push(CashInterestAccrual {
+                    account_id: account.id.clone(),
+                    activity_id: activity.id,
+                    date: month_end,
+                    amount,
+                    currency: account.currency.clone(),
+                });
+            }
+        }
+
+        Ok(accruals)
+    }
+}
+
+// Cash balance of every day from the first snapshot to the last one, a day without a
+// snapshot keeps the balance of the previous one
+fn get_daily_balances(history: &[FinancialSnapshot]) -> Vec<(NaiveDate, f64)> {
+    let snapshots: Vec<(NaiveDate, f64)> = history
+        .iter()
+        .filter_map(|snapshot| {
+            NaiveDate::parse_from_str(&snapshot.date, "%Y-%m-%d")
+                .ok()
+                .map(|date| (date, snapshot.available_cash))
+        })
+        .collect();
+
+    let mut daily_balances = Vec::new();
+    for (index, (date, balance)) in snapshots.iter().enumerate() {
+        let next_date = snapshots
+            .get(index + 1)
+            .map_or(*date + Duration::days(1), |(next_date, _)| *next_date);
+        let mut day = *date;
+        while day < next_date {
+            daily_balances.push((day, *balance));
+            day += Duration::days(1);
+        }
+    }
+    daily_balances
+}
+
+// Yearly interest on `balance`, each tier rate applies to the part of the balance
+// between its minimum and the minimum of the next tier
+fn calculate_annual_interest(balance: f64, tiers: &[CashInterestTier]) -> f64 {
+    let mut sorted_tiers: Vec<&CashInterestTier> = tiers.iter().collect();
+    sorted_tiers.sort_by(|a, b| a.min_balance.total_cmp(&b.min_balance));
+
+    sorted_tiers
+        .iter()
+        .enumerate()
+        .map(|(index, tier)| {
+            let tier_max = sorted_tiers
+                .get(index + 1)
+                .map_or(f64::INFINITY, |next_tier| next_tier.min_balance);
+            let tier_balance = balance.min(tier_max) - tier.min_balance;
+            tier_balance.max(0.0) * tier.annual_rate / 100.0
+        })
+        .sum()
+}
+
+// Simple daily interest summed by month, for the months ended before `today`. Each
+// month is keyed by its last day.
+pub fn calculate_monthly_interest(
+    daily_balances: &[(NaiveDate, f64)],
+    tiers: &[CashInterestTier],
+    today: NaiveDate,
+) -> Vec<(NaiveDate, f64)> {
+    let mut monthly_interest: BTreeMap<NaiveDate, f64> = BTreeMap::new();
+    for (date, balance) in daily_balances {
+        let month_end = get_month_end(*date);
+        if month_end >= today {
+            continue;
+        }
+        *monthly_interest.entry(month_end).or_insert(0.0) +=
+            calculate_annual_interest(*balance, tiers) / DAYS_PER_YEAR;
+    }
+    monthly_interest.into_iter().collect()
+}
+
+fn get_month_end(date: NaiveDate) -> NaiveDate {
+    let (year, month) = if date.month() == 12 {
+        (date.year() + 1, 1)
+    } else {
+        (date.year(), date.month() + 1)
+    };
+    NaiveDate::from_ymd_opt(year, month, 1).unwrap() - Duration::days(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    fn tier(min_balance: f64, annual_rate: f64) -> CashInterestTier {
+        CashInterestTier {
+            id: format!("tier-{}", min_balance),
+            account_id: "account".to_string(),
+            min_balance,
+            annual_rate,
+        }
+    }
+
+    fn daily_balances(start: &str, days: i64, balance: f64) -> Vec<(NaiveDate, f64)> {
+        (0..days)
+            .map(|day| (date(start) + Duration::days(day), balance))
+            .collect()
+    }
+
+    #[test]
+    fn each_tier_rate_applies_to_its_band_of_the_balance() {
+        // Listed out of order, nothing below 10k, 2% up to 50k and 4% above
+        let tiers = [tier(50_000.0, 4.0), tier(0.0, 0.0), tier(10_000.0, 2.0)];
+
+        assert_eq!(calculate_annual_interest(5_000.0, &tiers), 0.0);
+        assert!((calculate_annual_interest(30_000.0, &tiers) - 400.0).abs() < 1e-9);
+        assert!((calculate_annual_interest(60_000.0, &tiers) - 1_200.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn interest_is_summed_by_month_up_to_the_current_one() {
+        let tiers = [tier(0.0, 3.65)];
+        let mut balances = daily_balances("2024-01-01", 31, 10_000.0);
+        balances.extend(daily_balances("2024-02-01", 10, 20_000.0));
+
+        let monthly_interest = calculate_monthly_interest(&balances, &tiers, date("2024-02-10"));
+
+        // 1 a day in January, February is not over yet
+        assert_eq!(monthly_interest.len(), 1);
+        let (month_end, amount) = monthly_interest[0];
+        assert_eq!(month_end, date("2024-01-31"));
+        assert!((amount - 31.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_day_without_snapshot_keeps_the_previous_balance() {
+        let snapshot = |date: &str, available_cash: f64| FinancialSnapshot {
+            date: date.to_string(),
+            total_value: available_cash,
+            market_value: 0.0,
+            book_cost: 0.0,
+            available_cash,
+            net_deposit: available_cash,
+            currency: "USD".to_string(),
+            base_currency: "USD".to_string(),
+            total_gain_value: 0.0,
+            total_gain_percentage: 0.0,
+            day_gain_percentage: 0.0,
+            day_gain_value: 0.0,
+            allocation_percentage: None,
+            exchange_rate: Some(1.0),
+        };
+
+        let balances =
+            get_daily_balances(&[snapshot("2024-01-30", 100.0), snapshot("2024-02-02", 200.0)]);
+
+        assert_eq!(
+            balances,
+            vec![
+                (date("2024-01-30"), 100.0),
+                (date("2024-01-31"), 100.0),
+                (date("2024-02-01"), 100.0),
+                (date("2024-02-02"), 200.0),
+            ]
+        );
+        assert_eq!(get_month_end(date("2024-02-02")), date("2024-02-29"));
+        assert_eq!(get_month_end(date("2024-12-15")), date("2024-12-31"));
+    }
+}
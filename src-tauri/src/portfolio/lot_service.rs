@@ -0,0 +1,817 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Months, NaiveDate, NaiveDateTime};
+use diesel::SqliteConnection;
+
+use crate::activity::activity_service::ActivityService;
+use crate::asset::asset_service::AssetService;
+use crate::fx::FxService;
+use crate::models::{
+    Activity, HoldingLots, HoldingPeriod, LotMatchingMethod, RealizedGain, RealizedGainsReport,
+    RealizedGainsSummary, RealizedLot, TaxLot, WashSale,
+};
+use crate::settings::SettingsService;
+
+// Quantities below this are rounding leftovers of fully sold lots
+const QUANTITY_EPSILON: f64 = 1e-9;
+
+// Days before or after a sale at a loss in which a repurchase makes it a wash sale
+const WASH_SALE_WINDOW_DAYS: i64 = 30;
+
+#[derive(Debug, Clone)]
+pub struct OpenLot {
+    pub id: String,
+    pub account_id: String,
+    pub asset_id: String,
+    pub acquisition_date: NaiveDateTime,
+    pub quantity: f64,
+    pub cost_basis: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct LotMatching {
+    pub open_lots: Vec<OpenLot>,
+    pub realized: Vec<(OpenLot, RealizedLot)>, // Sold part of the lot with the sale
+}
+
+pub struct LotService {
+    activity_service: ActivityService,
+    asset_service: AssetService,
+}
+
+impl LotService {
+    pub fn new() -> Self {
+        LotService {
+            activity_service: ActivityService::new(),
+            asset_service: AssetService::new(),
+        }
+    }
+
+    // Lots of the holding of `asset_id` in `account_id` valued at the latest quote, with the
+    // lots sold so far. `lot_selections` lists the lots to sell first by sell activity id.
+    pub fn get_holding_lots(
+        &self,
+        conn: &mut SqliteConnection,
+        account_id: &str,
+        asset_id: &str,
+        method: LotMatchingMethod,
+        lot_selections: &HashMap<String, Vec<String>>,
+    ) -> Result<HoldingLots, Box<dyn std::error::Error>> {
+        let asset = self.asset_service.get_asset_by_id(conn, asset_id)?;
+        let price_factor = asset.get_price_factor();
+        let price_factors = self.get_price_factors(conn)?;
+
+        // Spin-offs move basis between assets, so all trades of the account are replayed
+        let activities: Vec<Activity> = self
+            .activity_service
+            .get_trading_activities(conn)?
+            .into_iter()
+            .filter(|a| a.account_id == account_id)
+            .collect();
+        let matching = Self::match_lots(&activities, method, lot_selections, |id| {
+            price_factors.get(id).copied().unwrap_or(1.0)
+        });
+
+        let market_price = self
+            .asset_service
+            .get_latest_quote(conn, asset_id)
+            .map(|quote| quote.close)
+            .unwrap_or(0.0);
+
+        let lots = matching
+            .open_lots
+            .iter()
+            .filter(|lot| lot.asset_id == asset_id)
+            .map(|lot| {
+                let market_value = lot.quantity * market_price * price_factor;
+                let unrealized_gain = market_value - lot.cost_basis;
+                TaxLot {
+                    id: lot.id.clone(),
+                    acquisition_date: lot.acquisition_date,
+                    quantity: lot.quantity,
+                    cost_basis: lot.cost_basis,
+                    market_value,
+                    unrealized_gain,
+                    unrealized_gain_percent: if lot.cost_basis != 0.0 {
+                        unrealized_gain / lot.cost_basis.abs() * 100.0
+                    } else {
+                        0.0
+                    },
+                }
+            })
+            .collect();
+
+        let realized = matching
+            .realized
+            .into_iter()
+            .filter(|(lot, _)| lot.asset_id == asset_id)
+            .map(|(_, realized_lot)| realized_lot)
+            .collect();
+
+        Ok(HoldingLots {
+            account_id: account_id.to_string(),
+            asset_id: asset_id.to_string(),
+            currency: asset.currency,
+            method,
+            lots,
+            realized,
+        })
+    }
+
+    // Gains realized by the sales between `start_date` and `end_date` (inclusive) of all
+    // active accounts, with the cost basis of `method`
+    pub fn get_realized_gains(
+        &self,
+        conn: &mut SqliteConnection,
+        method: LotMatchingMethod,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> Result<RealizedGainsReport, Box<dyn std::error::Error>> {
+        let settings = SettingsService::new().get_settings(conn)?;
+        let fx_service = FxService::new(
+            &settings.base_currency,
+            self.asset_service.load_exchange_rates(conn)?,
+        )
+        .with_manual_rates(self.asset_service.load_manual_exchange_rates(conn)?)
+        .with_rate_history(self.asset_service.load_exchange_rate_history(conn)?);
+
+        let assets = self.asset_service.get_assets(conn)?;
+        let price_factors = self.get_price_factors(conn)?;
+        let activities = self.activity_service.get_trading_activities(conn)?;
+        let matching = Self::match_lots(&activities, method, &HashMap::new(), |id| {
+            price_factors.get(id).copied().unwrap_or(1.0)
+        });
+
+        let mut transactions = Vec::new();
+        let mut summary = RealizedGainsSummary::default();
+        for (lot, realized_lot) in matching.realized {
+            let sale_date = realized_lot.sale_date.date();
+            if start_date.is_some_and(|start| sale_date < start)
+                || end_date.is_some_and(|end| sale_date > end)
+            {
+                continue;
+            }
+
+            let currency = assets
+                .iter()
+                .find(|a| a.id == lot.asset_id)
+                .map(|a| a.currency.clone())
+                .unwrap_or_else(|| settings.base_currency.clone());
+            let exchange_rate = fx_service.get_exchange_rate_for_date(
+                &currency,
+                &settings.base_currency,
+                sale_date,
+            )?;
+            let holding_period =
+                Self::get_holding_period(realized_lot.acquisition_date.date(), sale_date);
+
+            let realized_gain_converted = realized_lot.realized_gain * exchange_rate;
+            summary.cost_basis += realized_lot.cost_basis * exchange_rate;
+            summary.proceeds += realized_lot.proceeds * exchange_rate;
+            summary.total_gain += realized_gain_converted;
+            match holding_period {
+                HoldingPeriod::ShortTerm => summary.short_term_gain += realized_gain_converted,
+                HoldingPeriod::LongTerm => summary.long_term_gain += realized_gain_converted,
+            }
+
+            transactions.push(RealizedGain {
+                account_id: lot.account_id,
+                asset_id: lot.asset_id,
+                sell_activity_id: realized_lot.sell_activity_id,
+                lot_id: realized_lot.lot_id,
+                acquisition_date: realized_lot.acquisition_date,
+                sale_date: realized_lot.sale_date,
+                holding_period,
+                quantity: realized_lot.quantity,
+                currency,
+                cost_basis: realized_lot.cost_basis,
+                proceeds: realized_lot.proceeds,
+                realized_gain: realized_lot.realized_gain,
+                exchange_rate,
+                cost_basis_converted: realized_lot.cost_basis * exchange_rate,
+                proceeds_converted: realized_lot.proceeds * exchange_rate,
+                realized_gain_converted,
+            });
+        }
+
+        Ok(RealizedGainsReport {
+            base_currency: settings.base_currency,
+            method,
+            transactions,
+            summary,
+        })
+    }
+
+    // Sales at a loss, matched first in first out, followed or preceded by a buy of the
+    // same asset in any active account within the wash sale window. Each bought unit
+    // replaces at most one sold unit. Informational only, the basis is not adjusted.
+    pub fn get_wash_sales(
+        &self,
+        conn: &mut SqliteConnection,
+    ) -> Result<Vec<WashSale>, Box<dyn std::error::Error>> {
+        let assets = self.asset_service.get_assets(conn)?;
+        let price_factors = self.get_price_factors(conn)?;
+        let activities = self.activity_service.get_trading_activities(conn)?;
+        let matching = Self::match_lots(
+            &activities,
+            LotMatchingMethod::Fifo,
+            &HashMap::new(),
+            |id| price_factors.get(id).copied().unwrap_or(1.0),
+        );
+
+        // Quantity, realized gain and closed lots of each sale
+        let mut sales: HashMap<&str, (f64, f64, HashSet<&str>)> = HashMap::new();
+        for (_, realized_lot) in &matching.realized {
+            let sale = sales
+                .entry(realized_lot.sell_activity_id.as_str())
+                .or_insert((0.0, 0.0, HashSet::new()));
+            sale.0 += realized_lot.quantity;
+            sale.1 += realized_lot.realized_gain;
+            sale.2.insert(realized_lot.lot_id.as_str());
+        }
+
+        let mut used_quantities: HashMap<&str, f64> = HashMap::new();
+        let mut wash_sales = Vec::new();
+        for sell in activities.iter().filter(|a| a.activity_type == "SELL") {
+            let (quantity, realized_gain, sold_lots) = match sales.get(sell.id.as_str()) {
+                Some((quantity, gain, sold_lots)) if *gain < 0.0 => (*quantity, *gain, sold_lots),
+                _ => continue,
+            };
+            let currency = assets
+                .iter()
+                .find(|a| a.id == sell.asset_id)
+                .map(|a| a.currency.clone())
+                .unwrap_or_else(|| sell.currency.clone());
+
+            let mut remaining = quantity;
+            for buy in activities.iter().filter(|a| {
+                a.activity_type == "BUY"
+                    && a.asset_id == sell.asset_id
+                    && !sold_lots.contains(a.id.as_str())
+                    && (a.activity_date.date() - sell.activity_date.date())
+                        .num_days()
+                        .abs()
+                        <= WASH_SALE_WINDOW_DAYS
+            }) {
+                if remaining <= QUANTITY_EPSILON {
+                    break;
+                }
+                let used_quantity = used_quantities.entry(buy.id.as_str()).or_insert(0.0);
+                let replaced_quantity = remaining.min(buy.quantity - *used_quantity);
+                if replaced_quantity <= QUANTITY_EPSILON {
+                    continue;
+                }
+                *used_quantity += replaced_quantity;
+                remaining -= replaced_quantity;
+
+                wash_sales.push(WashSale {
+                    account_id: sell.account_id.clone(),
+                    asset_id: sell.asset_id.clone(),
+                    sell_activity_id: sell.id.clone(),
+                    sale_date: sell.activity_date,
+                    currency: currency.clone(),
+                    quantity: replaced_quantity,
+                    disallowed_loss: -realized_gain * replaced_quantity / quantity,
+                    replacement_lot_id: buy.id.clone(),
+                    replacement_account_id: buy.account_id.clone(),
+                    replacement_date: buy.activity_date,
+                });
+            }
+        }
+
+        Ok(wash_sales)
+    }
+
+    // A lot sold on the anniversary of its acquisition is held exactly one year, which
+    // is still short term
+    fn get_holding_period(acquisition_date: NaiveDate, sale_date: NaiveDate) -> HoldingPeriod {
+        match acquisition_date.checked_add_months(Months::new(12)) {
+            Some(anniversary) if sale_date > anniversary => HoldingPeriod::LongTerm,
+            _ => HoldingPeriod::ShortTerm,
+        }
+    }
+
+    fn get_price_factors(
+        &self,
+        conn: &mut SqliteConnection,
+    ) -> Result<HashMap<String, f64>, diesel::result::Error> {
+        Ok(self
+            .asset_service
+            .get_assets(conn)?
+            .iter()
+            .map(|asset| (asset.id.clone(), asset.get_price_factor()))
+            .collect())
+    }
+
+    // Replay `activities` into lots. Sells close long lots and buys cover short lots in
+    // the order of `method`, splitting the last one when partially closed. What is left
+    // of the trade opens a new lot, short lots have a negative quantity and basis.
+    pub fn match_lots<F>(
+        activities: &[Activity],
+        method: LotMatchingMethod,
+        lot_selections: &HashMap<String, Vec<String>>,
+        get_price_factor: F,
+    ) -> LotMatching
+    where
+        F: Fn(&str) -> f64,
+    {
+        let mut activities = activities.to_vec();
+        // Same ordering as the holdings, the parent side of a spin-off comes first
+        activities.sort_by_key(|a| (a.activity_date.date(), a.activity_type == "SPIN_OFF_IN"));
+
+        let mut matching = LotMatching::default();
+        // Basis released by the parent side of each spin-off, by activity
+        let mut spin_off_basis: HashMap<String, f64> = HashMap::new();
+
+        for activity in &activities {
+            let price_factor = get_price_factor(&activity.asset_id);
+
+            match activity.activity_type.as_str() {
+                "BUY" | "SELL" => {
+                    let is_buy = activity.activity_type == "BUY";
+                    let selected = lot_selections
+                        .get(&activity.id)
+                        .filter(|_| method == LotMatchingMethod::SpecificId);
+                    let order =
+                        Self::get_closing_order(&matching.open_lots, activity, method, selected);
+                    let value = activity.quantity * activity.unit_price * price_factor;
+                    // Cost of a buy, proceeds of a sale
+                    let amount = if is_buy {
+                        value + activity.fee
+                    } else {
+                        value - activity.fee
+                    };
+
+                    let mut remaining = activity.quantity;
+                    for index in order {
+                        if remaining <= QUANTITY_EPSILON {
+                            break;
+                        }
+                        let lot = &mut matching.open_lots[index];
+                        let closed_quantity = remaining.min(lot.quantity.abs());
+                        let closed_cost = lot.cost_basis * closed_quantity / lot.quantity.abs();
+                        let closed_amount = amount * closed_quantity / activity.quantity;
+
+                        let closed_lot = OpenLot {
+                            quantity: closed_quantity * lot.quantity.signum(),
+                            cost_basis: closed_cost,
+                            ..lot.clone()
+                        };
+                        lot.quantity -= closed_lot.quantity;
+                        lot.cost_basis -= closed_cost;
+                        remaining -= closed_quantity;
+
+                        // A covered short realizes its proceeds less the cost of the buy
+                        let (cost_basis, proceeds) = if is_buy {
+                            (closed_amount, -closed_cost)
+                        } else {
+                            (closed_cost, closed_amount)
+                        };
+                        matching.realized.push((
+                            closed_lot.clone(),
+                            RealizedLot {
+                                sell_activity_id: activity.id.clone(),
+                                lot_id: closed_lot.id,
+                                acquisition_date: closed_lot.acquisition_date,
+                                sale_date: activity.activity_date,
+                                quantity: closed_quantity,
+                                cost_basis,
+                                proceeds,
+                                realized_gain: proceeds - cost_basis,
+                            },
+                        ));
+                    }
+                    matching
+                        .open_lots
+                        .retain(|lot| lot.quantity.abs() > QUANTITY_EPSILON);
+
+                    // The rest of a buy opens a long lot, the rest of a sale a short lot
+                    // with the negative of its proceeds as cost basis
+                    if remaining > QUANTITY_EPSILON {
+                        let opened_amount = amount * remaining / activity.quantity;
+                        let sign = if is_buy { 1.0 } else { -1.0 };
+                        matching.open_lots.push(OpenLot {
+                            id: activity.id.clone(),
+                            account_id: activity.account_id.clone(),
+                            asset_id: activity.asset_id.clone(),
+                            acquisition_date: activity.activity_date,
+                            quantity: sign * remaining,
+                            cost_basis: sign * opened_amount,
+                        });
+                        if method == LotMatchingMethod::Average {
+                            Self::average_cost_basis(&mut matching.open_lots, activity);
+                        }
+                    }
+                }
+                "SPLIT" => {
+                    if activity.quantity > 0.0 {
+                        for lot in matching.open_lots.iter_mut().filter(|lot| {
+                            lot.asset_id == activity.asset_id
+                                && lot.account_id == activity.account_id
+                        }) {
+                            lot.quantity *= activity.quantity;
+                        }
+                    }
+                }
+                "SPIN_OFF_OUT" => {
+                    // Every lot of the parent gives the same fraction of its basis
+                    let fraction = activity.unit_price.clamp(0.0, 1.0);
+                    let mut released_basis = 0.0;
+                    for lot in matching.open_lots.iter_mut().filter(|lot| {
+                        lot.asset_id == activity.asset_id && lot.account_id == activity.account_id
+                    }) {
+                        released_basis += lot.cost_basis * fraction;
+                        lot.cost_basis -= lot.cost_basis * fraction;
+                    }
+                    spin_off_basis.insert(activity.id.clone(), released_basis);
+                }
+                "SPIN_OFF_IN" => {
+                    let received_basis = activity
+                        .source_activity_id
+                        .as_ref()
+                        .and_then(|parent_id| spin_off_basis.remove(parent_id))
+                        .unwrap_or(0.0);
+                    let (shares, cash_fraction) = activity.get_spin_off_shares();
+                    let kept_basis = received_basis * activity.get_spin_off_kept_fraction();
+                    let received_lot = OpenLot {
+                        id: activity.id.clone(),
+                        account_id: activity.account_id.clone(),
+                        asset_id: activity.asset_id.clone(),
+                        acquisition_date: activity.activity_date,
+                        quantity: shares,
+                        cost_basis: kept_basis,
+                    };
+
+                    // The fractional share paid in cash is sold at once
+                    if cash_fraction > QUANTITY_EPSILON {
+                        let cost_basis = received_basis - kept_basis;
+                        let proceeds = cash_fraction * activity.unit_price * price_factor;
+                        matching.realized.push((
+                            OpenLot {
+                                quantity: cash_fraction,
+                                cost_basis,
+                                ..received_lot.clone()
+                            },
+                            RealizedLot {
+                                sell_activity_id: activity.id.clone(),
+                                lot_id: activity.id.clone(),
+                                acquisition_date: activity.activity_date,
+                                sale_date: activity.activity_date,
+                                quantity: cash_fraction,
+                                cost_basis,
+                                proceeds,
+                                realized_gain: proceeds - cost_basis,
+                            },
+                        ));
+                    }
+                    if shares > QUANTITY_EPSILON {
+                        matching.open_lots.push(received_lot);
+                        if method == LotMatchingMethod::Average {
+                            Self::average_cost_basis(&mut matching.open_lots, activity);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        matching
+    }
+
+    // Spread the cost basis of the holding acquired by `activity` evenly over its units
+    fn average_cost_basis(open_lots: &mut [OpenLot], activity: &Activity) {
+        let is_holding_lot = |lot: &OpenLot| {
+            lot.asset_id == activity.asset_id && lot.account_id == activity.account_id
+        };
+        let (quantity, cost_basis) = open_lots
+            .iter()
+            .filter(|lot| is_holding_lot(lot))
+            .fold((0.0, 0.0), |(quantity, cost_basis), lot| {
+                (quantity + lot.quantity, cost_basis + lot.cost_basis)
+            });
+        if quantity.abs() <= QUANTITY_EPSILON {
+            return;
+        }
+
+        for lot in open_lots.iter_mut().filter(|lot| is_holding_lot(lot)) {
+            lot.cost_basis = cost_basis * lot.quantity / quantity;
+        }
+    }
+
+    // Indexes of the open lots closed by `trade` in the order they are closed, the long
+    // lots for a sell and the short lots for a buy. Lots are kept in acquisition order.
+    fn get_closing_order(
+        open_lots: &[OpenLot],
+        trade: &Activity,
+        method: LotMatchingMethod,
+        selected: Option<&Vec<String>>,
+    ) -> Vec<usize> {
+        let mut order: Vec<usize> = open_lots
+            .iter()
+            .enumerate()
+            .filter(|(_, lot)| {
+                lot.asset_id == trade.asset_id
+                    && lot.account_id == trade.account_id
+                    && (lot.quantity < 0.0) == (trade.activity_type == "BUY")
+            })
+            .map(|(index, _)| index)
+            .collect();
+
+        match method {
+            LotMatchingMethod::Fifo | LotMatchingMethod::Average => {}
+            LotMatchingMethod::Lifo => order.reverse(),
+            LotMatchingMethod::SpecificId => {
+                if let Some(selected) = selected {
+                    let position = |index: &usize| {
+                        selected
+                            .iter()
+                            .position(|id| *id == open_lots[*index].id)
+                            .unwrap_or(selected.len())
+                    };
+                    // Stable, so unselected lots stay first in first out
+                    order.sort_by_key(position);
+                }
+            }
+        }
+
+        order
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, activity, TestDatabase};
+
+    fn open_lot<'a>(matching: &'a LotMatching, asset_id: &str) -> &'a OpenLot {
+        matching
+            .open_lots
+            .iter()
+            .find(|lot| lot.asset_id == asset_id)
+            .unwrap()
+    }
+
+    fn spin_off(parent_id: &str, fraction: f64, child_id: &str, shares: f64) -> [Activity; 2] {
+        let parent = activity(
+            "account",
+            parent_id,
+            "SPIN_OFF_OUT",
+            "2024-06-03",
+            0.0,
+            fraction,
+        );
+        let mut child = activity(
+            "account",
+            child_id,
+            "SPIN_OFF_IN",
+            "2024-06-03",
+            shares,
+            0.0,
+        );
+        child.source_activity_id = Some(parent.id.clone());
+        [parent, child]
+    }
+
+    fn match_fifo(activities: &[Activity]) -> LotMatching {
+        LotService::match_lots(activities, LotMatchingMethod::Fifo, &HashMap::new(), |_| {
+            1.0
+        })
+    }
+
+    #[test]
+    fn spin_off_moves_a_fraction_of_the_basis() {
+        let mut activities = vec![activity(
+            "account",
+            "AAPL",
+            "BUY",
+            "2024-01-02",
+            10.0,
+            100.0,
+        )];
+        activities.extend(spin_off("AAPL", 0.2, "NEWCO", 5.0));
+
+        let matching = match_fifo(&activities);
+
+        assert!((open_lot(&matching, "AAPL").cost_basis - 800.0).abs() < 1e-9);
+        assert_eq!(open_lot(&matching, "AAPL").quantity, 10.0);
+        assert!((open_lot(&matching, "NEWCO").cost_basis - 200.0).abs() < 1e-9);
+        assert_eq!(open_lot(&matching, "NEWCO").quantity, 5.0);
+        assert!(matching.realized.is_empty());
+    }
+
+    #[test]
+    fn spin_offs_of_the_same_day_keep_their_own_basis() {
+        let mut activities = vec![
+            activity("account", "AAPL", "BUY", "2024-01-02", 10.0, 100.0),
+            activity("account", "MSFT", "BUY", "2024-01-02", 10.0, 300.0),
+        ];
+        activities.extend(spin_off("AAPL", 0.2, "NEWCO", 5.0));
+        activities.extend(spin_off("MSFT", 0.5, "OTHERCO", 5.0));
+
+        let matching = match_fifo(&activities);
+
+        assert!((open_lot(&matching, "NEWCO").cost_basis - 200.0).abs() < 1e-9);
+        assert!((open_lot(&matching, "OTHERCO").cost_basis - 1500.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn cash_in_lieu_realizes_the_fractional_share() {
+        let mut activities = vec![activity(
+            "account",
+            "AAPL",
+            "BUY",
+            "2024-01-02",
+            10.0,
+            100.0,
+        )];
+        let [parent, mut child] = spin_off("AAPL", 0.2, "NEWCO", 5.5);
+        child.unit_price = 40.0;
+        activities.extend([parent, child]);
+
+        let matching = match_fifo(&activities);
+
+        let basis_per_share = 200.0 / 5.5;
+        assert_eq!(open_lot(&matching, "NEWCO").quantity, 5.0);
+        assert!((open_lot(&matching, "NEWCO").cost_basis - 5.0 * basis_per_share).abs() < 1e-9);
+        let (_, realized) = &matching.realized[0];
+        assert_eq!(realized.quantity, 0.5);
+        assert!((realized.proceeds - 20.0).abs() < 1e-9);
+        assert!((realized.realized_gain - (20.0 - 0.5 * basis_per_share)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_partial_sale_closes_the_first_lot_and_splits_the_next() {
+        let activities = vec![
+            activity("account", "AAPL", "BUY", "2024-01-02", 10.0, 100.0),
+            activity("account", "AAPL", "BUY", "2024-02-01", 10.0, 120.0),
+            activity("account", "AAPL", "SELL", "2024-03-01", 15.0, 130.0),
+        ];
+
+        let matching = match_fifo(&activities);
+
+        assert_eq!(matching.open_lots.len(), 1);
+        let lot = &matching.open_lots[0];
+        assert_eq!(lot.id, "BUY-AAPL-2024-02-01");
+        assert_eq!(lot.quantity, 5.0);
+        assert!((lot.cost_basis - 600.0).abs() < 1e-9);
+        let realized: Vec<&RealizedLot> = matching.realized.iter().map(|(_, r)| r).collect();
+        assert_eq!(realized.len(), 2);
+        assert_eq!(realized[0].lot_id, "BUY-AAPL-2024-01-02");
+        assert!((realized[0].realized_gain - 300.0).abs() < 1e-9);
+        assert_eq!(realized[1].quantity, 5.0);
+        assert!((realized[1].realized_gain - 50.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_sale_beyond_the_holding_opens_a_short_lot_covered_by_the_next_buy() {
+        let activities = vec![
+            activity("account", "AAPL", "BUY", "2024-01-02", 5.0, 100.0),
+            activity("account", "AAPL", "SELL", "2024-02-01", 8.0, 110.0),
+        ];
+
+        let matching = match_fifo(&activities);
+
+        let short_lot = open_lot(&matching, "AAPL");
+        assert_eq!(short_lot.quantity, -3.0);
+        assert!((short_lot.cost_basis + 330.0).abs() < 1e-9);
+
+        let mut activities = activities;
+        activities.push(activity("account", "AAPL", "BUY", "2024-03-01", 3.0, 100.0));
+        let matching = match_fifo(&activities);
+
+        assert!(matching.open_lots.is_empty());
+        let (_, covered) = matching.realized.last().unwrap();
+        assert_eq!(covered.sell_activity_id, "BUY-AAPL-2024-03-01");
+        assert!((covered.realized_gain - 30.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_split_multiplies_the_lot_quantities_and_keeps_their_basis() {
+        let activities = vec![
+            activity("account", "AAPL", "BUY", "2024-01-02", 10.0, 100.0),
+            activity("account", "AAPL", "SPLIT", "2024-02-01", 2.0, 0.0),
+            activity("other", "AAPL", "BUY", "2024-01-02", 10.0, 100.0),
+        ];
+
+        let matching = match_fifo(&activities);
+
+        let lot = |account_id: &str| {
+            matching
+                .open_lots
+                .iter()
+                .find(|lot| lot.account_id == account_id)
+                .unwrap()
+        };
+        assert_eq!(lot("account").quantity, 20.0);
+        assert!((lot("account").cost_basis - 1000.0).abs() < 1e-9);
+        assert_eq!(lot("other").quantity, 10.0);
+    }
+
+    fn realized_gain(
+        method: LotMatchingMethod,
+        lot_selections: &HashMap<String, Vec<String>>,
+    ) -> f64 {
+        let activities = vec![
+            activity("account", "AAPL", "BUY", "2024-01-02", 10.0, 100.0),
+            activity("account", "AAPL", "BUY", "2024-02-01", 10.0, 150.0),
+            activity("account", "AAPL", "SELL", "2024-03-01", 10.0, 120.0),
+        ];
+        LotService::match_lots(&activities, method, lot_selections, |_| 1.0)
+            .realized
+            .iter()
+            .map(|(_, realized_lot)| realized_lot.realized_gain)
+            .sum()
+    }
+
+    #[test]
+    fn the_cost_basis_depends_on_the_matching_method() {
+        let no_selection = HashMap::new();
+        let selection = HashMap::from([(
+            "SELL-AAPL-2024-03-01".to_string(),
+            vec!["BUY-AAPL-2024-02-01".to_string()],
+        )]);
+
+        assert!((realized_gain(LotMatchingMethod::Fifo, &no_selection) - 200.0).abs() < 1e-9);
+        assert!((realized_gain(LotMatchingMethod::Lifo, &no_selection) + 300.0).abs() < 1e-9);
+        assert!((realized_gain(LotMatchingMethod::Average, &no_selection) + 50.0).abs() < 1e-9);
+        assert!((realized_gain(LotMatchingMethod::SpecificId, &selection) + 300.0).abs() < 1e-9);
+        // Without a selection the lots are sold first in first out
+        assert!((realized_gain(LotMatchingMethod::SpecificId, &no_selection) - 200.0).abs() < 1e-9);
+        // Selections only apply to the specific id method
+        assert!((realized_gain(LotMatchingMethod::Fifo, &selection) - 200.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_lot_sold_on_its_anniversary_is_short_term() {
+        let date = |date: &str| NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+
+        assert_eq!(
+            LotService::get_holding_period(date("2023-01-02"), date("2024-01-02")),
+            HoldingPeriod::ShortTerm
+        );
+        assert_eq!(
+            LotService::get_holding_period(date("2023-01-02"), date("2024-01-03")),
+            HoldingPeriod::LongTerm
+        );
+    }
+
+    #[test]
+    fn a_repurchase_within_the_window_makes_a_loss_a_wash_sale() {
+        let mut db = TestDatabase::new();
+        test_utils::insert_account(&mut db.conn, "account", "USD");
+        test_utils::insert_account(&mut db.conn, "ira", "USD");
+        test_utils::insert_asset(&mut db.conn, "AAPL", "USD");
+        // The sold lot itself is bought within the window but doesn't replace the sale
+        test_utils::insert_activity(
+            &mut db.conn,
+            test_utils::new_activity("account", "AAPL", "BUY", "2024-02-15", 10.0, 100.0),
+        );
+        let sell = test_utils::insert_activity(
+            &mut db.conn,
+            test_utils::new_activity("account", "AAPL", "SELL", "2024-03-01", 10.0, 80.0),
+        );
+        let replacement = test_utils::insert_activity(
+            &mut db.conn,
+            test_utils::new_activity("ira", "AAPL", "BUY", "2024-03-20", 4.0, 85.0),
+        );
+        // 45 days after the sale
+        test_utils::insert_activity(
+            &mut db.conn,
+            test_utils::new_activity("account", "AAPL", "BUY", "2024-04-15", 10.0, 90.0),
+        );
+
+        let wash_sales = LotService::new().get_wash_sales(&mut db.conn).unwrap();
+
+        assert_eq!(wash_sales.len(), 1);
+        let wash_sale = &wash_sales[0];
+        assert_eq!(wash_sale.sell_activity_id, sell.id);
+        assert_eq!(wash_sale.replacement_lot_id, replacement.id);
+        assert_eq!(wash_sale.replacement_account_id, "ira");
+        assert_eq!(wash_sale.quantity, 4.0);
+        assert!((wash_sale.disallowed_loss - 80.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_sale_at_a_gain_is_never_a_wash_sale() {
+        let mut db = TestDatabase::new();
+        test_utils::insert_account(&mut db.conn, "account", "USD");
+        test_utils::insert_asset(&mut db.conn, "AAPL", "USD");
+        test_utils::insert_activity(
+            &mut db.conn,
+            test_utils::new_activity("account", "AAPL", "BUY", "2024-01-02", 10.0, 100.0),
+        );
+        test_utils::insert_activity(
+            &mut db.conn,
+            test_utils::new_activity("account", "AAPL", "SELL", "2024-03-01", 10.0, 120.0),
+        );
+        test_utils::insert_activity(
+            &mut db.conn,
+            test_utils::new_activity("account", "AAPL", "BUY", "2024-03-05", 10.0, 110.0),
+        );
+
+        assert!(LotService::new()
+            .get_wash_sales(&mut db.conn)
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -1,2 +1,9 @@
+pub mod allocation_service;
+pub mod cash_interest_service;
+pub mod lot_service;
+pub mod option_service;
+pub mod performance_service;
 pub mod portfolio_commands;
 pub mod portfolio_service;
+pub mod rebalancing_service;
+pub mod reconciliation_service;
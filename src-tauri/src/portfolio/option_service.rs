@@ -0,0 +1,316 @@
+use chrono::{NaiveDate, Utc};
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+
+use crate::activity::activity_service::ActivityService;
+use crate::asset::asset_service::AssetService;
+use crate::models::{
+    NewActivity, NewAsset, NewOptionContract, OptionContract, OptionSettlement, OptionType,
+    OPTION_ASSET_TYPE, OPTION_CONTRACT_MULTIPLIER,
+};
+use crate::schema::{assets, option_contracts};
+use crate::settings::read_only_mode;
+
+pub struct OptionService {
+    activity_service: ActivityService,
+    asset_service: AssetService,
+}
+
+impl OptionService {
+    pub fn new() -> Self {
+        OptionService {
+            activity_service: ActivityService::new(),
+            asset_service: AssetService::new(),
+        }
+    }
+
+    pub fn get_option_contracts(
+        &self,
+        conn: &mut SqliteConnection,
+    ) -> Result<Vec<OptionContract>, diesel::result::Error> {
+        option_contracts::table
+            .order(option_contracts::expiration_date.asc())
+            .load::<OptionContract>(conn)
+    }
+
+    // Create the asset of an option on an existing asset. Its id is the OCC symbol, which
+    // is also the symbol of its market quotes, and it is quoted in the underlying currency.
+    pub fn create_option_contract(
+        &self,
+        conn: &mut SqliteConnection,
+        new_contract: NewOptionContract,
+    ) -> Result<OptionContract, String> {
+        read_only_mode::ensure_writable().map_err(|e| e.to_string())?;
+        if new_contract.strike_price <= 0.0 {
+            return Err("Strike price must be a positive number".to_string());
+        }
+        let underlying = self
+            .asset_service
+            .get_asset_by_id(conn, &new_contract.underlying_asset_id)
+            .map_err(|_| format!("Asset {} not found", new_contract.underlying_asset_id))?;
+
+        let symbol = get_option_symbol(
+            &underlying.symbol,
+            new_contract.option_type,
+            new_contract.strike_price,
+            new_contract.expiration_date,
+        );
+        let contract = OptionContract {
+            asset_id: symbol.clone(),
+            underlying_asset_id: underlying.id.clone(),
+            option_type: new_contract.option_type.as_str().to_string(),
+            strike_price: new_contract.strike_price,
+            expiration_date: new_contract.expiration_date,
+        };
+        let new_asset = NewAsset {
+            id: symbol.clone(),
+            name: Some(format!(
+                "{} {} {} {}",
+                underlying.symbol,
+                new_contract.expiration_date,
+                new_contract.strike_price,
+                new_contract.option_type.as_str()
+            )),
+            asset_type: Some(OPTION_ASSET_TYPE.to_string()),
+            symbol: symbol.clone(),
+            symbol_mapping: Some(symbol),
+            asset_class: Some("Derivative".to_string()),
+            asset_sub_class: Some("Option".to_string()),
+            currency: underlying.currency,
+            data_source: "Yahoo".to_string(),
+            ..Default::default()
+        };
+
+        conn.transaction(|conn| {
+            diesel::insert_into(assets::table)
+                .values(&new_asset)
+                .execute(conn)?;
+            diesel::insert_into(option_contracts::table)
+                .values(&contract)
+                .returning(OptionContract::as_returning())
+                .get_result(conn)
+        })
+        .map_err(|e| format!("Failed to create option contract: {}", e))
+    }
+
+    // Close the position of `account_id` in the option `asset_id`. Exercise and assignment
+    // also trade the underlying at the strike, which moves the cash of the account. The
+    // activities are dated on the expiration date, or today for an early exercise.
+    pub fn settle_option(
+        &self,
+        conn: &mut SqliteConnection,
+        account_id: &str,
+        asset_id: &str,
+        settlement: OptionSettlement,
+    ) -> Result<usize, String> {
+        let contract = option_contracts::table
+            .find(asset_id)
+            .first::<OptionContract>(conn)
+            .map_err(|_| format!("Option contract {} not found", asset_id))?;
+        let option = self
+            .asset_service
+            .get_asset_by_id(conn, asset_id)
+            .map_err(|e| e.to_string())?;
+
+        let contracts: f64 = self
+            .activity_service
+            .get_account_activities(conn, account_id)
+            .map_err(|e| e.to_string())?
+            .iter()
+            .filter(|activity| activity.asset_id == asset_id)
+            .map(|activity| match activity.activity_type.as_str() {
+                "BUY" => activity.quantity,
+                "SELL" => -activity.quantity,
+                _ => 0.0,
+            })
+            .sum();
+        if contracts == 0.0 {
+            return Err(format!("No open position in {}", asset_id));
+        }
+
+        let today = Utc::now().date_naive();
+        match settlement {
+            OptionSettlement::Exercise if contracts < 0.0 => {
+                return Err("Only a long option can be exercised".to_string());
+            }
+            OptionSettlement::Assign if contracts < 0.0 => {}
+            OptionSettlement::Assign => {
+                return Err("Only a short option can be assigned".to_string());
+            }
+            OptionSettlement::Expire if today < contract.expiration_date => {
+                return Err(format!(
+                    "Option {} expires on {}",
+                    asset_id, contract.expiration_date
+                ));
+            }
+            _ => {}
+        }
+
+        let settlement_date = today.min(contract.expiration_date);
+        let comment = match settlement {
+            OptionSettlement::Exercise => "Option exercised",
+            OptionSettlement::Assign => "Option assigned",
+            OptionSettlement::Expire => "Option expired worthless",
+        };
+        let trade = |asset_id: &str, is_buy: bool, quantity: f64, unit_price: f64| NewActivity {
+            id: Some(uuid::Uuid::new_v4().to_string()),
+            account_id: account_id.to_string(),
+            asset_id: asset_id.to_string(),
+            activity_type: if is_buy { "BUY" } else { "SELL" }.to_string(),
+            activity_date: settlement_date.format("%Y-%m-%d 00:00:00").to_string(),
+            quantity,
+            unit_price,
+            currency: option.currency.clone(),
+            fee: 0.0,
+            is_draft: false,
+            comment: Some(comment.to_string()),
+            source_activity_id: None,
+        };
+
+        // The option leaves the account at no value, its premium stays as the result
+        let mut activities = vec![trade(asset_id, contracts < 0.0, contracts.abs(), 0.0)];
+        if settlement != OptionSettlement::Expire {
+            // A long call and a short put buy the underlying, the other two sell it
+            let is_call = contract.option_type == OptionType::Call.as_str();
+            activities.push(trade(
+                &contract.underlying_asset_id,
+                is_call == (contracts > 0.0),
+                contracts.abs() * OPTION_CONTRACT_MULTIPLIER,
+                contract.strike_price,
+            ));
+        }
+
+        self.activity_service
+            .create_activities(conn, activities, |_, _| {})
+            .map_err(|e| format!("Failed to settle option {}: {}", asset_id, e))
+    }
+}
+
+// OCC symbol of an option, the underlying symbol followed by the expiration date, the
+// option type and the strike price in thousandths on eight digits
+fn get_option_symbol(
+    underlying_symbol: &str,
+    option_type: OptionType,
+    strike_price: f64,
+    expiration_date: NaiveDate,
+) -> String {
+    format!(
+        "{}{}{}{:08}",
+        underlying_symbol,
+        expiration_date.format("%y%m%d"),
+        match option_type {
+            OptionType::Call => "C",
+            OptionType::Put => "P",
+        },
+        (strike_price * 1000.0).round() as u64
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Activity;
+    use crate::schema::activities;
+    use crate::test_utils::{self, TestDatabase};
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn option_symbols_follow_the_occ_format() {
+        assert_eq!(
+            get_option_symbol("AAPL", OptionType::Call, 150.0, date("2024-01-19")),
+            "AAPL240119C00150000"
+        );
+        assert_eq!(
+            get_option_symbol("F", OptionType::Put, 2.5, date("2025-12-19")),
+            "F251219P00002500"
+        );
+        assert_eq!(
+            get_option_symbol("SPY", OptionType::Call, 412.345, date("2024-03-15")),
+            "SPY240315C00412345"
+        );
+    }
+
+    #[test]
+    fn an_exercised_call_buys_the_underlying_at_the_strike() {
+        let mut db = TestDatabase::new();
+        test_utils::insert_settings(&mut db.conn, "USD");
+        test_utils::insert_account(&mut db.conn, "account", "USD");
+        test_utils::insert_asset(&mut db.conn, "AAPL", "USD");
+        let service = OptionService::new();
+        let contract = service
+            .create_option_contract(
+                &mut db.conn,
+                NewOptionContract {
+                    underlying_asset_id: "AAPL".to_string(),
+                    option_type: OptionType::Call,
+                    strike_price: 150.0,
+                    expiration_date: date("2024-01-19"),
+                },
+            )
+            .unwrap();
+        assert_eq!(contract.asset_id, "AAPL240119C00150000");
+        test_utils::insert_activity(
+            &mut db.conn,
+            test_utils::new_activity("account", &contract.asset_id, "BUY", "2024-01-02", 2.0, 3.5),
+        );
+
+        service
+            .settle_option(
+                &mut db.conn,
+                "account",
+                &contract.asset_id,
+                OptionSettlement::Exercise,
+            )
+            .unwrap();
+
+        let settled: Vec<Activity> = activities::table
+            .filter(activities::comment.eq("Option exercised"))
+            .load(&mut db.conn)
+            .unwrap();
+        let trade = |asset_id: &str| settled.iter().find(|a| a.asset_id == asset_id).unwrap();
+        assert_eq!(settled.len(), 2);
+        assert_eq!(trade(&contract.asset_id).activity_type, "SELL");
+        assert_eq!(trade(&contract.asset_id).quantity, 2.0);
+        assert_eq!(trade(&contract.asset_id).unit_price, 0.0);
+        assert_eq!(trade("AAPL").activity_type, "BUY");
+        assert_eq!(trade("AAPL").quantity, 2.0 * OPTION_CONTRACT_MULTIPLIER);
+        assert_eq!(trade("AAPL").unit_price, 150.0);
+        assert_eq!(trade("AAPL").activity_date.date(), date("2024-01-19"));
+    }
+
+    #[test]
+    fn only_a_short_option_can_be_assigned() {
+        let mut db = TestDatabase::new();
+        test_utils::insert_account(&mut db.conn, "account", "USD");
+        test_utils::insert_asset(&mut db.conn, "AAPL", "USD");
+        let service = OptionService::new();
+        let contract = service
+            .create_option_contract(
+                &mut db.conn,
+                NewOptionContract {
+                    underlying_asset_id: "AAPL".to_string(),
+                    option_type: OptionType::Put,
+                    strike_price: 140.0,
+                    expiration_date: date("2024-01-19"),
+                },
+            )
+            .unwrap();
+        test_utils::insert_activity(
+            &mut db.conn,
+            test_utils::new_activity("account", &contract.asset_id, "BUY", "2024-01-02", 1.0, 2.0),
+        );
+
+        assert_eq!(
+            service.settle_option(
+                &mut db.conn,
+                "account",
+                &contract.asset_id,
+                OptionSettlement::Assign
+            ),
+            Err("Only a short option can be assigned".to_string())
+        );
+    }
+}
@@ -0,0 +1,749 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+
+use crate::models::{
+    AttributionGroup, BenchmarkSeries, CurrencyReturn, FinancialHistory, FinancialSnapshot,
+    Holding, PerformanceHistory, PerformanceSummary, ReturnAttribution,
+};
+
+const UNCLASSIFIED_CATEGORY: &str = "Unclassified";
+
+// Value of every indexed series on its first date
+const PERFORMANCE_INDEX_BASE: f64 = 100.0;
+
+// Histories have a snapshot for every calendar day
+const PERIODS_PER_YEAR: f64 = 365.0;
+// Returns needed for a sample standard deviation
+const MIN_RISK_PERIODS: usize = 2;
+
+const XIRR_MAX_ITERATIONS: usize = 100;
+const XIRR_TOLERANCE: f64 = 1e-7;
+// Bounds of the annual rate searched by the bisection fallback
+const XIRR_MIN_RATE: f64 = -0.999_999;
+const XIRR_MAX_RATE: f64 = 1_000.0;
+
+pub struct PerformanceService;
+
+impl PerformanceService {
+    pub fn new() -> Self {
+        PerformanceService
+    }
+
+    // Summarize each account history (including the total portfolio) with its
+    // money-weighted return, treating net deposit changes as external cash flows.
+    // Risk metrics use the daily returns net of those flows, `risk_free_rate` is the
+    // annual rate in percent of the Sharpe ratio.
+    pub fn calculate_performance_summary(
+        &self,
+        histories: &[FinancialHistory],
+        risk_free_rate: f64,
+    ) -> Vec<PerformanceSummary> {
+        histories
+            .iter()
+            .filter_map(|financial_history| {
+                let history = &financial_history.history;
+                let first = history.first()?;
+                let last = history.last()?;
+
+                let returns = get_period_returns(history);
+                let dates: Vec<Option<NaiveDate>> = history
+                    .iter()
+                    .map(|snapshot| NaiveDate::parse_from_str(&snapshot.date, "%Y-%m-%d").ok())
+                    .collect();
+                let drawdown = calculate_max_drawdown(&dates, &get_index(&returns));
+                let volatility = calculate_volatility(&returns);
+
+                Some(PerformanceSummary {
+                    account_id: financial_history.account.id.clone(),
+                    start_date: first.date.clone(),
+                    end_date: last.date.clone(),
+                    start_value: first.total_value,
+                    end_value: last.total_value,
+                    net_deposit: last.net_deposit,
+                    currency: last.currency.clone(),
+                    money_weighted_return: self.calculate_money_weighted_return(history),
+                    max_drawdown: drawdown.map(|(max_drawdown, _)| max_drawdown),
+                    max_drawdown_days: drawdown.map(|(_, days)| days),
+                    volatility,
+                    sharpe_ratio: volatility.filter(|volatility| *volatility > 0.0).map(
+                        |volatility| {
+                            let mean_return = returns.iter().sum::<f64>() / returns.len() as f64;
+                            (mean_return * PERIODS_PER_YEAR - risk_free_rate / 100.0) / volatility
+                        },
+                    ),
+                })
+            })
+            .collect()
+    }
+
+    // Index the account history and the closes of each benchmark symbol, sorted by date,
+    // to the same base. The account index chains the daily returns net of the deposits
+    // and withdrawals. A benchmark without a close on a date keeps its last one.
+    pub fn calculate_performance_history(
+        &self,
+        financial_history: &FinancialHistory,
+        benchmark_closes: &[(String, Vec<(NaiveDate, f64)>)],
+    ) -> PerformanceHistory {
+        let history = &financial_history.history;
+        let portfolio = if history.is_empty() {
+            Vec::new()
+        } else {
+            get_index(&get_period_returns(history))
+        };
+
+        let dates: Vec<Option<NaiveDate>> = history
+            .iter()
+            .map(|snapshot| NaiveDate::parse_from_str(&snapshot.date, "%Y-%m-%d").ok())
+            .collect();
+        let benchmarks = benchmark_closes
+            .iter()
+            .map(|(symbol, closes)| BenchmarkSeries {
+                symbol: symbol.clone(),
+                values: index_benchmark(&dates, closes),
+            })
+            .collect();
+
+        PerformanceHistory {
+            account_id: financial_history.account.id.clone(),
+            currency: financial_history.account.currency.clone(),
+            dates: history
+                .iter()
+                .map(|snapshot| snapshot.date.clone())
+                .collect(),
+            portfolio,
+            benchmarks,
+        }
+    }
+
+    // Annualized IRR of the deposits and withdrawals recorded in the history,
+    // valuing the holdings at the last snapshot as the final inflow
+    pub fn calculate_money_weighted_return(&self, history: &[FinancialSnapshot]) -> Option<f64> {
+        let mut cash_flows: Vec<(NaiveDate, f64)> = Vec::new();
+        let mut previous_net_deposit = 0.0;
+
+        for snapshot in history {
+            let flow = snapshot.net_deposit - previous_net_deposit;
+            previous_net_deposit = snapshot.net_deposit;
+            if flow != 0.0 {
+                let date = NaiveDate::parse_from_str(&snapshot.date, "%Y-%m-%d").ok()?;
+                // Money put into the portfolio is an outflow for the investor
+                cash_flows.push((date, -flow));
+            }
+        }
+
+        let last = history.last()?;
+        let end_date = NaiveDate::parse_from_str(&last.date, "%Y-%m-%d").ok()?;
+        cash_flows.push((end_date, last.total_value));
+
+        xirr(&cash_flows)
+    }
+
+    // Split the total return of the holdings by asset class or sector. Weights are
+    // taken on book value so the contributions add up to the portfolio total return.
+    pub fn calculate_return_attribution(
+        &self,
+        holdings: &[Holding],
+        group_by: AttributionGroup,
+    ) -> Vec<ReturnAttribution> {
+        let total_book_value: f64 = holdings.iter().map(|h| h.book_value_converted).sum();
+        if total_book_value == 0.0 {
+            return Vec::new();
+        }
+
+        let mut categories: HashMap<String, (f64, f64)> = HashMap::new();
+        for holding in holdings {
+            for (category, share) in Self::get_holding_categories(holding, group_by) {
+                let entry = categories.entry(category).or_insert((0.0, 0.0));
+                entry.0 += holding.book_value_converted * share;
+                entry.1 += holding.performance.total_gain_amount_converted * share;
+            }
+        }
+
+        let mut attributions: Vec<ReturnAttribution> = categories
+            .into_iter()
+            .map(|(category, (book_value, gain))| ReturnAttribution {
+                category,
+                weight: book_value / total_book_value * 100.0,
+                contribution: gain / total_book_value * 100.0,
+            })
+            .collect();
+        attributions.sort_by(|a, b| b.contribution.total_cmp(&a.contribution));
+
+        attributions
+    }
+
+    // Split the return between the local price move and the currency move from the
+    // prices and the rates to the reporting currency at both ends of the period. The
+    // FX part takes the cross term so the two parts add up to the total return.
+    pub fn calculate_currency_return(
+        &self,
+        asset_id: &str,
+        currency: &str,
+        (start_price, end_price): (f64, f64),
+        (start_rate, end_rate): (f64, f64),
+    ) -> Option<CurrencyReturn> {
+        if start_price <= 0.0 || start_rate <= 0.0 {
+            return None;
+        }
+
+        let local_return = end_price / start_price - 1.0;
+        let total_return = (end_price * end_rate) / (start_price * start_rate) - 1.0;
+
+        Some(CurrencyReturn {
+            asset_id: asset_id.to_string(),
+            currency: currency.to_string(),
+            local_return,
+            fx_contribution: total_return - local_return,
+            total_return,
+        })
+    }
+
+    // Categories of a holding with the share of the holding in each of them,
+    // any share left unclassified goes to the "Unclassified" category
+    fn get_holding_categories(holding: &Holding, group_by: AttributionGroup) -> Vec<(String, f64)> {
+        let mut categories: Vec<(String, f64)> = match group_by {
+            AttributionGroup::AssetClass => holding
+                .asset_class
+                .iter()
+                .filter(|asset_class| !asset_class.is_empty())
+                .map(|asset_class| (asset_class.clone(), 1.0))
+                .collect(),
+            AttributionGroup::Sector => holding
+                .sectors
+                .iter()
+                .flatten()
+                .filter(|sector| sector.weight > 0.0)
+                .map(|sector| (sector.name.clone(), sector.weight.min(1.0)))
+                .collect(),
+        };
+
+        let classified_share: f64 = categories.iter().map(|(_, share)| share).sum();
+        if classified_share > 1.0 {
+            for (_, share) in categories.iter_mut() {
+                *share /= classified_share;
+            }
+        } else if classified_share < 1.0 {
+            categories.push((UNCLASSIFIED_CATEGORY.to_string(), 1.0 - classified_share));
+        }
+
+        categories
+    }
+}
+
+// Return between consecutive snapshots net of the deposits and withdrawals made between
+// them, 0 after a snapshot without value
+fn get_period_returns(history: &[FinancialSnapshot]) -> Vec<f64> {
+    history
+        .windows(2)
+        .map(|pair| {
+            let (previous, snapshot) = (&pair[0], &pair[1]);
+            let flow = snapshot.net_deposit - previous.net_deposit;
+            if previous.total_value != 0.0 {
+                (snapshot.total_value - flow) / previous.total_value - 1.0
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
+
+// Chain `returns` from the index base, the first value is the base itself
+fn get_index(returns: &[f64]) -> Vec<f64> {
+    let mut index = PERFORMANCE_INDEX_BASE;
+    std::iter::once(index)
+        .chain(returns.iter().map(|period_return| {
+            index *= 1.0 + period_return;
+            index
+        }))
+        .collect()
+}
+
+// Largest fall from a peak of `index` as a fraction of the peak, with the longest time
+// in days spent below a previous peak. None without at least two dated values.
+fn calculate_max_drawdown(dates: &[Option<NaiveDate>], index: &[f64]) -> Option<(f64, i64)> {
+    let points: Vec<(NaiveDate, f64)> = dates
+        .iter()
+        .zip(index)
+        .filter_map(|(date, value)| date.map(|date| (date, *value)))
+        .collect();
+    if points.len() < 2 {
+        return None;
+    }
+
+    let (mut peak_date, mut peak_value) = points[0];
+    let mut max_drawdown: f64 = 0.0;
+    let mut max_drawdown_days = 0;
+    for (date, value) in points {
+        if value >= peak_value {
+            peak_date = date;
+            peak_value = value;
+        } else if peak_value > 0.0 {
+            max_drawdown = max_drawdown.max(1.0 - value / peak_value);
+            max_drawdown_days = max_drawdown_days.max((date - peak_date).num_days());
+        }
+    }
+    Some((max_drawdown, max_drawdown_days))
+}
+
+// Annualized sample standard deviation of daily `returns`
+fn calculate_volatility(returns: &[f64]) -> Option<f64> {
+    if returns.len() < MIN_RISK_PERIODS {
+        return None;
+    }
+    let count = returns.len() as f64;
+    let mean = returns.iter().sum::<f64>() / count;
+    let variance = returns
+        .iter()
+        .map(|period_return| (period_return - mean).powi(2))
+        .sum::<f64>()
+        / (count - 1.0);
+    Some(variance.sqrt() * PERIODS_PER_YEAR.sqrt())
+}
+
+// Last close on or before each date divided by the first of them, times the index base
+fn index_benchmark(dates: &[Option<NaiveDate>], closes: &[(NaiveDate, f64)]) -> Vec<Option<f64>> {
+    let mut next_close = 0;
+    let mut last_close: Option<f64> = None;
+    let mut base: Option<f64> = None;
+
+    dates
+        .iter()
+        .map(|date| {
+            let date = (*date)?;
+            while next_close < closes.len() && closes[next_close].0 <= date {
+                last_close = Some(closes[next_close].1)
+                    .filter(|close| *close > 0.0)
+                    .or(last_close);
+                next_close += 1;
+            }
+            let close = last_close?;
+            let base = *base.get_or_insert(close);
+            Some(close / base * PERFORMANCE_INDEX_BASE)
+        })
+        .collect()
+}
+
+// Net present value of dated cash flows at an annual `rate` and its derivative
+fn xnpv(cash_flows: &[(NaiveDate, f64)], rate: f64) -> (f64, f64) {
+    let first_date = cash_flows[0].0;
+
+    cash_flows
+        .iter()
+        .fold((0.0, 0.0), |(value, derivative), (date, amount)| {
+            let years = (*date - first_date).num_days() as f64 / 365.0;
+            let discount = (1.0 + rate).powf(years);
+            (
+                value + amount / discount,
+                derivative - years * amount / (discount * (1.0 + rate)),
+            )
+        })
+}
+
+// Solve XNPV = 0 with Newton's method, falling back to bisection when Newton
+// diverges. Returns None when the flows have no sign change or nothing converges.
+pub fn xirr(cash_flows: &[(NaiveDate, f64)]) -> Option<f64> {
+    let has_inflow = cash_flows.iter().any(|(_, amount)| *amount > 0.0);
+    let has_outflow = cash_flows.iter().any(|(_, amount)| *amount < 0.0);
+    if !has_inflow || !has_outflow {
+        println!("XIRR undefined: cash flows need both an inflow and an outflow");
+        return None;
+    }
+
+    let mut rate = 0.1;
+    for _ in 0..XIRR_MAX_ITERATIONS {
+        let (value, derivative) = xnpv(cash_flows, rate);
+        if value.abs() < XIRR_TOLERANCE {
+            return Some(rate);
+        }
+        if derivative == 0.0 || !derivative.is_finite() {
+            break;
+        }
+
+        let next_rate = rate - value / derivative;
+        if !next_rate.is_finite() || next_rate <= XIRR_MIN_RATE {
+            break;
+        }
+        if (next_rate - rate).abs() < XIRR_TOLERANCE {
+            return Some(next_rate);
+        }
+        rate = next_rate;
+    }
+
+    let (mut low, mut high) = (XIRR_MIN_RATE, XIRR_MAX_RATE);
+    let mut low_value = xnpv(cash_flows, low).0;
+    if low_value.signum() == xnpv(cash_flows, high).0.signum() {
+        println!(
+            "XIRR did not converge: no root between {} and {}",
+            low, high
+        );
+        return None;
+    }
+
+    for _ in 0..XIRR_MAX_ITERATIONS * 10 {
+        let middle = (low + high) / 2.0;
+        let middle_value = xnpv(cash_flows, middle).0;
+        if middle_value.abs() < XIRR_TOLERANCE || (high - low) / 2.0 < XIRR_TOLERANCE {
+            return Some(middle);
+        }
+        if middle_value.signum() == low_value.signum() {
+            low = middle;
+            low_value = middle_value;
+        } else {
+            high = middle;
+        }
+    }
+
+    println!("XIRR did not converge after bisection");
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Account, Performance, Sector};
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    fn snapshot(date: &str, total_value: f64, net_deposit: f64) -> FinancialSnapshot {
+        FinancialSnapshot {
+            date: date.to_string(),
+            total_value,
+            market_value: total_value,
+            book_cost: net_deposit,
+            available_cash: 0.0,
+            net_deposit,
+            currency: "USD".to_string(),
+            base_currency: "USD".to_string(),
+            total_gain_value: total_value - net_deposit,
+            total_gain_percentage: 0.0,
+            day_gain_percentage: 0.0,
+            day_gain_value: 0.0,
+            allocation_percentage: None,
+            exchange_rate: Some(1.0),
+        }
+    }
+
+    #[test]
+    fn xirr_of_a_one_year_gain() {
+        let rate = xirr(&[(date("2023-01-01"), -1000.0), (date("2024-01-01"), 1100.0)]).unwrap();
+        assert!((rate - 0.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn xirr_is_undefined_without_a_sign_change() {
+        assert_eq!(
+            xirr(&[(date("2023-01-01"), -1000.0), (date("2024-01-01"), -100.0)]),
+            None
+        );
+    }
+
+    #[test]
+    fn money_weighted_return_treats_deposits_as_outflows() {
+        // 1000 deposited for a year and another 1000 for half of it, ending at 2150
+        let history = [
+            snapshot("2023-01-01", 1000.0, 1000.0),
+            snapshot("2023-07-02", 2050.0, 2000.0),
+            snapshot("2024-01-01", 2150.0, 2000.0),
+        ];
+
+        let rate = PerformanceService::new()
+            .calculate_money_weighted_return(&history)
+            .unwrap();
+
+        let cash_flows = [
+            (date("2023-01-01"), -1000.0),
+            (date("2023-07-02"), -1000.0),
+            (date("2024-01-01"), 2150.0),
+        ];
+        assert!(xnpv(&cash_flows, rate).0.abs() < 1e-6);
+        assert!(rate > 0.09 && rate < 0.11, "{}", rate);
+    }
+
+    #[test]
+    fn currency_return_splits_into_local_and_fx_parts() {
+        // A 10% price rise while the currency gains 10% against the reporting one
+        let currency_return = PerformanceService::new()
+            .calculate_currency_return("SAP", "EUR", (100.0, 110.0), (1.0, 1.1))
+            .unwrap();
+
+        assert!((currency_return.local_return - 0.1).abs() < 1e-9);
+        assert!((currency_return.total_return - 0.21).abs() < 1e-9);
+        assert!((currency_return.fx_contribution - 0.11).abs() < 1e-9);
+    }
+
+    #[test]
+    fn currency_return_needs_a_starting_price_and_rate() {
+        let service = PerformanceService::new();
+
+        assert!(service
+            .calculate_currency_return("SAP", "EUR", (0.0, 110.0), (1.0, 1.1))
+            .is_none());
+        assert!(service
+            .calculate_currency_return("SAP", "EUR", (100.0, 110.0), (0.0, 1.1))
+            .is_none());
+    }
+
+    #[test]
+    fn withdrawals_are_not_counted_as_a_drawdown() {
+        let account_date = date("2024-01-01").and_time(chrono::NaiveTime::MIN);
+        let financial_history = FinancialHistory {
+            account: crate::models::Account {
+                id: "account".to_string(),
+                name: "account".to_string(),
+                account_type: "SECURITIES".to_string(),
+                group: None,
+                currency: "USD".to_string(),
+                is_default: false,
+                is_active: true,
+                created_at: account_date,
+                updated_at: account_date,
+                platform_id: None,
+            },
+            history: vec![
+                snapshot("2024-01-01", 1000.0, 1000.0),
+                snapshot("2024-01-02", 1200.0, 1000.0),
+                snapshot("2024-01-03", 900.0, 1000.0),
+                snapshot("2024-01-04", 1080.0, 1000.0),
+                // 500 withdrawn, the value falls without any loss
+                snapshot("2024-01-05", 580.0, 500.0),
+                snapshot("2024-01-06", 700.0, 500.0),
+            ],
+        };
+
+        let summaries =
+            PerformanceService::new().calculate_performance_summary(&[financial_history], 0.0);
+
+        assert_eq!(summaries.len(), 1);
+        let summary = &summaries[0];
+        assert!((summary.max_drawdown.unwrap() - 0.25).abs() < 1e-9);
+        // Below the peak of the 2nd from the 3rd to the 5th
+        assert_eq!(summary.max_drawdown_days, Some(3));
+        assert!(summary.volatility.is_some());
+        assert!(summary.sharpe_ratio.is_some());
+    }
+
+    #[test]
+    fn volatility_is_the_annualized_sample_deviation() {
+        let volatility = calculate_volatility(&[0.01, -0.01]).unwrap();
+
+        assert!((volatility - 0.0002_f64.sqrt() * 365.0_f64.sqrt()).abs() < 1e-12);
+        assert_eq!(calculate_volatility(&[0.01]), None);
+    }
+
+    #[test]
+    fn drawdown_needs_two_dated_values() {
+        assert_eq!(
+            calculate_max_drawdown(&[Some(date("2024-01-01"))], &[100.0]),
+            None
+        );
+        assert_eq!(
+            calculate_max_drawdown(
+                &[Some(date("2024-01-01")), Some(date("2024-01-02"))],
+                &[100.0, 110.0]
+            ),
+            Some((0.0, 0))
+        );
+    }
+
+    fn holding(
+        symbol: &str,
+        asset_class: Option<&str>,
+        book_value: f64,
+        market_value: f64,
+    ) -> Holding {
+        let gain = market_value - book_value;
+        Holding {
+            id: symbol.to_string(),
+            symbol: symbol.to_string(),
+            symbol_name: None,
+            holding_type: "Equity".to_string(),
+            quantity: 1.0,
+            currency: "USD".to_string(),
+            base_currency: "USD".to_string(),
+            market_price: Some(market_value),
+            average_cost: Some(book_value),
+            market_value,
+            book_value,
+            market_value_converted: market_value,
+            book_value_converted: book_value,
+            performance: Performance {
+                total_gain_percent: gain / book_value * 100.0,
+                total_gain_amount: gain,
+                total_gain_amount_converted: gain,
+                day_gain_percent: None,
+                day_gain_amount: None,
+                day_gain_amount_converted: None,
+            },
+            account: None,
+            asset_class: asset_class.map(str::to_string),
+            asset_sub_class: None,
+            sectors: None,
+            tags: Vec::new(),
+            quote_date: None,
+            is_quote_stale: false,
+            fx_error: None,
+            fx_derived: false,
+        }
+    }
+
+    fn sectors(weights: &[(&str, f64)]) -> Option<Vec<Sector>> {
+        Some(
+            weights
+                .iter()
+                .map(|(name, weight)| Sector {
+                    name: name.to_string(),
+                    weight: *weight,
+                })
+                .collect(),
+        )
+    }
+
+    // Total return of the holdings in percent of their book value
+    fn total_return(holdings: &[Holding]) -> f64 {
+        let gain: f64 = holdings
+            .iter()
+            .map(|h| h.performance.total_gain_amount_converted)
+            .sum();
+        let book_value: f64 = holdings.iter().map(|h| h.book_value_converted).sum();
+        gain / book_value * 100.0
+    }
+
+    #[test]
+    fn contributions_by_asset_class_add_up_to_the_total_return() {
+        let holdings = [
+            holding("VTI", Some("Equity"), 6000.0, 6600.0),
+            holding("BND", Some("Fixed Income"), 3000.0, 2850.0),
+            holding("ART", None, 1000.0, 1100.0),
+        ];
+
+        let attributions = PerformanceService::new()
+            .calculate_return_attribution(&holdings, AttributionGroup::AssetClass);
+
+        let expected = [
+            ("Equity", 60.0, 6.0),
+            (UNCLASSIFIED_CATEGORY, 10.0, 1.0),
+            ("Fixed Income", 30.0, -1.5),
+        ];
+        assert_eq!(attributions.len(), expected.len());
+        for (attribution, (category, weight, contribution)) in attributions.iter().zip(expected) {
+            assert_eq!(attribution.category, category);
+            assert!((attribution.weight - weight).abs() < 1e-9);
+            assert!((attribution.contribution - contribution).abs() < 1e-9);
+        }
+        let contributions: f64 = attributions.iter().map(|a| a.contribution).sum();
+        assert!((contributions - total_return(&holdings)).abs() < 1e-9);
+        assert!((contributions - 5.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn contributions_by_sector_add_up_to_the_total_return() {
+        let mut partly_classified = holding("VTI", Some("Equity"), 5000.0, 6000.0);
+        partly_classified.sectors = sectors(&[("Technology", 0.5), ("Healthcare", 0.3)]);
+        // Weights above 100% are scaled down
+        let mut overweighted = holding("QQQ", Some("Equity"), 5000.0, 4500.0);
+        overweighted.sectors = sectors(&[("Technology", 0.8), ("Healthcare", 0.4)]);
+        let holdings = [partly_classified, overweighted];
+
+        let attributions = PerformanceService::new()
+            .calculate_return_attribution(&holdings, AttributionGroup::Sector);
+
+        let contributions: f64 = attributions.iter().map(|a| a.contribution).sum();
+        assert!((contributions - total_return(&holdings)).abs() < 1e-9);
+        let weights: f64 = attributions.iter().map(|a| a.weight).sum();
+        assert!((weights - 100.0).abs() < 1e-9);
+        let unclassified = attributions
+            .iter()
+            .find(|a| a.category == UNCLASSIFIED_CATEGORY)
+            .unwrap();
+        // 20% of the first holding, which gained 1000
+        assert!((unclassified.weight - 10.0).abs() < 1e-9);
+        assert!((unclassified.contribution - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn holdings_without_book_value_have_no_attribution() {
+        let holdings = [holding("VTI", Some("Equity"), 0.0, 100.0)];
+
+        assert!(PerformanceService::new()
+            .calculate_return_attribution(&holdings, AttributionGroup::AssetClass)
+            .is_empty());
+    }
+
+    fn financial_history(history: Vec<FinancialSnapshot>) -> FinancialHistory {
+        let created_at = date("2024-01-01").and_time(chrono::NaiveTime::MIN);
+        FinancialHistory {
+            account: Account {
+                id: "TOTAL".to_string(),
+                name: "Total".to_string(),
+                account_type: "TOTAL".to_string(),
+                group: None,
+                currency: "USD".to_string(),
+                is_default: true,
+                is_active: true,
+                created_at,
+                updated_at: created_at,
+                platform_id: None,
+            },
+            history,
+        }
+    }
+
+    #[test]
+    fn benchmarks_are_indexed_to_100_on_the_portfolio_dates() {
+        // A deposit of 500 on the 3rd is not counted as a gain
+        let history = financial_history(vec![
+            snapshot("2024-01-01", 1000.0, 1000.0),
+            snapshot("2024-01-02", 1100.0, 1000.0),
+            snapshot("2024-01-03", 1650.0, 1500.0),
+            snapshot("2024-01-04", 1700.0, 1500.0),
+        ]);
+        let benchmark_closes = vec![
+            (
+                "SPY".to_string(),
+                // No close on the 3rd, a close before the history is before its start
+                vec![
+                    (date("2023-12-29"), 190.0),
+                    (date("2024-01-01"), 200.0),
+                    (date("2024-01-02"), 210.0),
+                    (date("2024-01-04"), 220.0),
+                ],
+            ),
+            (
+                "QQQ".to_string(),
+                vec![(date("2024-01-02"), 400.0), (date("2024-01-03"), 380.0)],
+            ),
+        ];
+
+        let performance =
+            PerformanceService::new().calculate_performance_history(&history, &benchmark_closes);
+
+        assert_eq!(
+            performance.dates,
+            ["2024-01-01", "2024-01-02", "2024-01-03", "2024-01-04"]
+        );
+        let expected_portfolio = [100.0, 110.0, 115.0, 115.0 * 1700.0 / 1650.0];
+        for (value, expected) in performance.portfolio.iter().zip(expected_portfolio) {
+            assert!((value - expected).abs() < 1e-9, "{} != {}", value, expected);
+        }
+        let values = |symbol: &str| {
+            performance
+                .benchmarks
+                .iter()
+                .find(|benchmark| benchmark.symbol == symbol)
+                .unwrap()
+                .values
+                .iter()
+                .map(|value| value.map(|value| (value * 1e9).round() / 1e9))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            values("SPY"),
+            [Some(100.0), Some(105.0), Some(105.0), Some(110.0)]
+        );
+        // Indexed from its first close within the history
+        assert_eq!(values("QQQ"), [None, Some(100.0), Some(95.0), Some(95.0)]);
+    }
+}
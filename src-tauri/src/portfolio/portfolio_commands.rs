@@ -1,16 +1,37 @@
+use std::collections::HashMap;
+
+use crate::asset::asset_service::AssetService;
 use crate::db;
-use crate::models::{FinancialHistory, Holding};
+use crate::models::{
+    Allocation, AllocationGroup, AllocationHistory, AllocationInterval, AttributionGroup,
+    CashInterestAccrual, CashInterestTier, CurrencyReturn, ExportFormat, FinancialHistory, Holding,
+    HoldingLots, HoldingsReconciliation, LotMatchingMethod, NewCashInterestTier, NewOptionContract,
+    OptionContract, OptionSettlement, PerformanceHistory, PerformanceSummary, RealizedGainsReport,
+    RebalanceConstraints, RebalancePlan, ReturnAttribution, SimulatedTrade, TradeSimulation,
+    WashSale,
+};
+use crate::portfolio::allocation_service::AllocationService;
+use crate::portfolio::cash_interest_service::CashInterestService;
+use crate::portfolio::lot_service::LotService;
+use crate::portfolio::option_service::OptionService;
+use crate::portfolio::performance_service::PerformanceService;
 use crate::portfolio::portfolio_service;
+use crate::portfolio::rebalancing_service::RebalancingService;
+use crate::portfolio::reconciliation_service::ReconciliationService;
+use crate::AppState;
+use tauri::State;
 
 #[tauri::command]
-pub async fn get_historical() -> Result<Vec<FinancialHistory>, String> {
+pub async fn get_historical(
+    reporting_currency: Option<String>,
+) -> Result<Vec<FinancialHistory>, String> {
     println!("Fetching portfolio historical...");
 
     let mut conn = db::establish_connection();
 
     let mut service = portfolio_service::PortfolioService::new();
     service
-        .initialize(&mut conn)
+        .initialize(&mut conn, reporting_currency)
         .await
         .map_err(|e| format!("Failed to initialize portfolio: {}", e))?;
 
@@ -21,19 +42,451 @@ pub async fn get_historical() -> Result<Vec<FinancialHistory>, String> {
 }
 
 #[tauri::command]
-pub async fn compute_holdings() -> Result<Vec<Holding>, String> {
+pub async fn compute_holdings(
+    reporting_currency: Option<String>,
+    tag: Option<String>,
+    as_of: Option<chrono::NaiveDate>,
+) -> Result<Vec<Holding>, String> {
     println!("Compute holdings...");
 
     let mut conn = db::establish_connection();
 
     let mut service = portfolio_service::PortfolioService::new();
     service
-        .initialize(&mut conn)
+        .initialize(&mut conn, reporting_currency)
+        .await
+        .map_err(|e| format!("Failed to initialize portfolio: {}", e))?;
+
+    let holdings = match as_of {
+        Some(as_of) => service.compute_holdings_as_of(&mut conn, as_of).await,
+        None => service.compute_holdings(&mut conn).await,
+    }
+    .map_err(|e| format!("Failed to fetch activities: {}", e))?;
+
+    Ok(match tag {
+        Some(tag) => holdings
+            .into_iter()
+            .filter(|holding| holding.tags.contains(&tag))
+            .collect(),
+        None => holdings,
+    })
+}
+
+#[tauri::command]
+pub async fn export_historical(
+    format: ExportFormat,
+    file_path: String,
+    start_date: Option<String>,
+    end_date: Option<String>,
+    reporting_currency: Option<String>,
+) -> Result<usize, String> {
+    println!("Exporting portfolio historical... {}", file_path);
+
+    let mut conn = db::establish_connection();
+
+    let mut service = portfolio_service::PortfolioService::new();
+    service
+        .initialize(&mut conn, reporting_currency)
+        .await
+        .map_err(|e| format!("Failed to initialize portfolio: {}", e))?;
+
+    service
+        .export_historical_portfolio_values(&mut conn, format, &file_path, start_date, end_date)
+        .await
+        .map_err(|e| format!("Failed to export portfolio historical: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_performance_summary(
+    risk_free_rate: Option<f64>,
+    reporting_currency: Option<String>,
+) -> Result<Vec<PerformanceSummary>, String> {
+    println!("Calculating performance summary...");
+
+    let mut conn = db::establish_connection();
+
+    let mut service = portfolio_service::PortfolioService::new();
+    service
+        .initialize(&mut conn, reporting_currency)
+        .await
+        .map_err(|e| format!("Failed to initialize portfolio: {}", e))?;
+
+    let histories = service
+        .calculate_historical_portfolio_values(&mut conn)
+        .await
+        .map_err(|e| format!("Failed to fetch activities: {}", e))?;
+
+    Ok(PerformanceService::new()
+        .calculate_performance_summary(&histories, risk_free_rate.unwrap_or(0.0)))
+}
+
+#[tauri::command]
+pub async fn get_performance_history(
+    account_id: Option<String>,
+    benchmarks: Option<Vec<String>>,
+    reporting_currency: Option<String>,
+) -> Result<PerformanceHistory, String> {
+    println!("Calculating performance history...");
+
+    let mut conn = db::establish_connection();
+
+    let mut service = portfolio_service::PortfolioService::new();
+    service
+        .initialize(&mut conn, reporting_currency)
+        .await
+        .map_err(|e| format!("Failed to initialize portfolio: {}", e))?;
+
+    let histories = service
+        .calculate_historical_portfolio_values(&mut conn)
+        .await
+        .map_err(|e| format!("Failed to fetch activities: {}", e))?;
+    let account_id = account_id.unwrap_or_else(|| "TOTAL".to_string());
+    let financial_history = histories
+        .iter()
+        .find(|history| history.account.id == account_id)
+        .ok_or_else(|| format!("No history for account {}", account_id))?;
+
+    let mut benchmark_closes = Vec::new();
+    if let (Some(first), Some(last)) = (
+        financial_history.history.first(),
+        financial_history.history.last(),
+    ) {
+        let parse_date = |date: &str| {
+            chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map_err(|e| format!("Invalid history date {}: {}", date, e))
+        };
+        let (start_date, end_date) = (parse_date(&first.date)?, parse_date(&last.date)?);
+        let asset_service = AssetService::new();
+        for symbol in benchmarks.unwrap_or_default() {
+            let closes = asset_service
+                .fetch_history_closes(&symbol, start_date, end_date)
+                .await
+                .map_err(|e| format!("Failed to fetch quotes of benchmark {}: {}", symbol, e))?;
+            benchmark_closes.push((symbol, closes));
+        }
+    }
+
+    Ok(PerformanceService::new()
+        .calculate_performance_history(financial_history, &benchmark_closes))
+}
+
+#[tauri::command]
+pub async fn get_return_attribution(
+    group_by: AttributionGroup,
+    reporting_currency: Option<String>,
+) -> Result<Vec<ReturnAttribution>, String> {
+    println!("Calculating return attribution...");
+
+    let mut conn = db::establish_connection();
+
+    let mut service = portfolio_service::PortfolioService::new();
+    service
+        .initialize(&mut conn, reporting_currency)
         .await
         .map_err(|e| format!("Failed to initialize portfolio: {}", e))?;
 
+    let holdings = service
+        .compute_holdings(&mut conn)
+        .await
+        .map_err(|e| format!("Failed to fetch activities: {}", e))?;
+
+    Ok(PerformanceService::new().calculate_return_attribution(&holdings, group_by))
+}
+
+#[tauri::command]
+pub async fn get_currency_returns(
+    start_date: chrono::NaiveDate,
+    end_date: Option<chrono::NaiveDate>,
+    reporting_currency: Option<String>,
+) -> Result<Vec<CurrencyReturn>, String> {
+    println!("Calculating currency returns since {}...", start_date);
+
+    let mut conn = db::establish_connection();
+
+    let mut service = portfolio_service::PortfolioService::new();
     service
+        .initialize(&mut conn, reporting_currency)
+        .await
+        .map_err(|e| format!("Failed to initialize portfolio: {}", e))?;
+
+    let end_date = end_date.unwrap_or_else(|| chrono::Utc::now().date_naive());
+    if end_date < start_date {
+        return Err("End date must be on or after the start date".to_string());
+    }
+
+    service
+        .get_currency_returns(&mut conn, start_date, end_date)
+        .await
+        .map_err(|e| format!("Failed to calculate currency returns: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_portfolio_allocation(
+    group_by: AllocationGroup,
+    reporting_currency: Option<String>,
+) -> Result<Vec<Allocation>, String> {
+    println!("Calculating portfolio allocation by {:?}...", group_by);
+
+    let mut conn = db::establish_connection();
+
+    let mut service = portfolio_service::PortfolioService::new();
+    service
+        .initialize(&mut conn, reporting_currency)
+        .await
+        .map_err(|e| format!("Failed to initialize portfolio: {}", e))?;
+
+    let holdings = service
         .compute_holdings(&mut conn)
         .await
-        .map_err(|e| format!("Failed to fetch activities: {}", e))
+        .map_err(|e| format!("Failed to fetch activities: {}", e))?;
+
+    Ok(AllocationService::new().calculate_allocation(&holdings, group_by))
+}
+
+#[tauri::command]
+pub async fn get_allocation_history(
+    group_by: AllocationGroup,
+    start_date: chrono::NaiveDate,
+    end_date: Option<chrono::NaiveDate>,
+    interval: Option<AllocationInterval>,
+    reporting_currency: Option<String>,
+) -> Result<AllocationHistory, String> {
+    println!("Calculating allocation history by {:?}...", group_by);
+
+    let mut conn = db::establish_connection();
+
+    let mut service = portfolio_service::PortfolioService::new();
+    service
+        .initialize(&mut conn, reporting_currency)
+        .await
+        .map_err(|e| format!("Failed to initialize portfolio: {}", e))?;
+
+    let allocation_service = AllocationService::new();
+    let end_date = end_date.unwrap_or_else(|| chrono::Utc::now().date_naive());
+    let dates = allocation_service.get_allocation_dates(
+        start_date,
+        end_date,
+        interval.unwrap_or(AllocationInterval::Monthly),
+    );
+    let mut holdings_by_date = Vec::new();
+    for date in dates {
+        let holdings = service
+            .compute_holdings_as_of(&mut conn, date)
+            .await
+            .map_err(|e| format!("Failed to compute holdings on {}: {}", date, e))?;
+        holdings_by_date.push((date, holdings));
+    }
+
+    Ok(allocation_service.calculate_allocation_history(&holdings_by_date, group_by))
+}
+
+#[tauri::command]
+pub async fn simulate_trades(
+    trades: Vec<SimulatedTrade>,
+    group_by: AllocationGroup,
+    reporting_currency: Option<String>,
+) -> Result<TradeSimulation, String> {
+    println!("Simulating {} trades...", trades.len());
+
+    let mut conn = db::establish_connection();
+
+    let mut service = portfolio_service::PortfolioService::new();
+    service
+        .initialize(&mut conn, reporting_currency)
+        .await
+        .map_err(|e| format!("Failed to initialize portfolio: {}", e))?;
+
+    service
+        .simulate_trades(&mut conn, &trades, group_by)
+        .await
+        .map_err(|e| format!("Failed to simulate trades: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_rebalancing_suggestions(
+    targets: HashMap<String, f64>,
+    group_by: AllocationGroup,
+    constraints: Option<RebalanceConstraints>,
+    reporting_currency: Option<String>,
+) -> Result<RebalancePlan, String> {
+    println!("Calculating rebalancing suggestions by {:?}...", group_by);
+
+    let mut conn = db::establish_connection();
+
+    let mut service = portfolio_service::PortfolioService::new();
+    service
+        .initialize(&mut conn, reporting_currency)
+        .await
+        .map_err(|e| format!("Failed to initialize portfolio: {}", e))?;
+
+    let holdings = service
+        .compute_holdings(&mut conn)
+        .await
+        .map_err(|e| format!("Failed to fetch activities: {}", e))?;
+    let available_cash = service
+        .get_available_cash(&mut conn)
+        .await
+        .map_err(|e| format!("Failed to calculate available cash: {}", e))?;
+
+    RebalancingService::new().calculate_rebalance(
+        &holdings,
+        available_cash,
+        &targets,
+        group_by,
+        &constraints.unwrap_or_default(),
+    )
+}
+
+#[tauri::command]
+pub async fn get_holding_lots(
+    account_id: String,
+    asset_id: String,
+    method: Option<LotMatchingMethod>,
+    lot_selections: Option<HashMap<String, Vec<String>>>,
+) -> Result<HoldingLots, String> {
+    println!("Fetching lots of {} in account {}...", asset_id, account_id);
+
+    let mut conn = db::establish_connection();
+
+    LotService::new()
+        .get_holding_lots(
+            &mut conn,
+            &account_id,
+            &asset_id,
+            method.unwrap_or_default(),
+            &lot_selections.unwrap_or_default(),
+        )
+        .map_err(|e| format!("Failed to fetch holding lots: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_realized_gains(
+    method: Option<LotMatchingMethod>,
+    start_date: Option<chrono::NaiveDate>,
+    end_date: Option<chrono::NaiveDate>,
+) -> Result<RealizedGainsReport, String> {
+    println!("Calculating realized gains...");
+
+    let mut conn = db::establish_connection();
+
+    LotService::new()
+        .get_realized_gains(&mut conn, method.unwrap_or_default(), start_date, end_date)
+        .map_err(|e| format!("Failed to calculate realized gains: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_wash_sales() -> Result<Vec<WashSale>, String> {
+    println!("Detecting wash sales...");
+
+    let mut conn = db::establish_connection();
+
+    LotService::new()
+        .get_wash_sales(&mut conn)
+        .map_err(|e| format!("Failed to detect wash sales: {}", e))
+}
+
+#[tauri::command]
+pub fn get_cash_interest_tiers(state: State<AppState>) -> Result<Vec<CashInterestTier>, String> {
+    println!("Fetching cash interest tiers...");
+    let mut conn = state.conn.lock().unwrap();
+    CashInterestService::new()
+        .get_tiers(&mut conn)
+        .map_err(|e| format!("Failed to load cash interest tiers: {}", e))
+}
+
+#[tauri::command]
+pub fn add_cash_interest_tier(
+    tier: NewCashInterestTier,
+    state: State<AppState>,
+) -> Result<CashInterestTier, String> {
+    println!("Adding cash interest tier: {:?}", tier);
+    if tier.annual_rate < 0.0 || tier.min_balance < 0.0 {
+        return Err("Rate and minimum balance cannot be negative".to_string());
+    }
+
+    let mut conn = state.conn.lock().unwrap();
+    CashInterestService::new()
+        .add_tier(&mut conn, tier)
+        .map_err(|e| format!("Failed to add cash interest tier: {}", e))
+}
+
+#[tauri::command]
+pub fn delete_cash_interest_tier(tier_id: String, state: State<AppState>) -> Result<usize, String> {
+    println!("Deleting cash interest tier: {}", tier_id);
+    let mut conn = state.conn.lock().unwrap();
+    CashInterestService::new()
+        .delete_tier(&mut conn, &tier_id)
+        .map_err(|e| format!("Failed to delete cash interest tier: {}", e))
+}
+
+#[tauri::command]
+pub async fn accrue_cash_interest() -> Result<Vec<CashInterestAccrual>, String> {
+    println!("Accruing cash interest...");
+
+    let mut conn = db::establish_connection();
+
+    CashInterestService::new()
+        .accrue_cash_interest(&mut conn)
+        .await
+        .map_err(|e| format!("Failed to accrue cash interest: {}", e))
+}
+
+#[tauri::command]
+pub fn get_option_contracts(state: State<AppState>) -> Result<Vec<OptionContract>, String> {
+    println!("Fetching option contracts...");
+    let mut conn = state.conn.lock().unwrap();
+    OptionService::new()
+        .get_option_contracts(&mut conn)
+        .map_err(|e| format!("Failed to load option contracts: {}", e))
+}
+
+#[tauri::command]
+pub fn create_option_contract(
+    contract: NewOptionContract,
+    state: State<AppState>,
+) -> Result<OptionContract, String> {
+    println!("Creating option contract: {:?}", contract);
+    let mut conn = state.conn.lock().unwrap();
+    OptionService::new().create_option_contract(&mut conn, contract)
+}
+
+#[tauri::command]
+pub fn settle_option(
+    account_id: String,
+    asset_id: String,
+    settlement: OptionSettlement,
+    state: State<AppState>,
+) -> Result<usize, String> {
+    println!(
+        "Settling option {} in account {}: {:?}",
+        asset_id, account_id, settlement
+    );
+    let mut conn = state.conn.lock().unwrap();
+    OptionService::new().settle_option(&mut conn, &account_id, &asset_id, settlement)
+}
+
+#[tauri::command]
+pub fn reconcile_holdings_snapshot(
+    account_id: String,
+    file_path: String,
+    snapshot_date: chrono::NaiveDate,
+    apply: Option<bool>,
+    state: State<AppState>,
+) -> Result<HoldingsReconciliation, String> {
+    println!(
+        "Reconciling holdings snapshot of account {} on {}: {}",
+        account_id, snapshot_date, file_path
+    );
+    tauri::async_runtime::block_on(async {
+        let mut conn = state.conn.lock().unwrap();
+        ReconciliationService::new()
+            .reconcile_holdings_snapshot(
+                &mut conn,
+                &account_id,
+                &file_path,
+                snapshot_date,
+                apply.unwrap_or(false),
+            )
+            .await
+    })
 }
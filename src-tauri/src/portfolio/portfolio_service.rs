@@ -1,12 +1,18 @@
 use rayon::prelude::*;
 use std::collections::{HashMap, HashSet};
+use std::fs::File;
 
 use crate::account::account_service::AccountService;
 use crate::activity::activity_service::ActivityService;
 use crate::asset::asset_service::AssetService;
+use crate::asset::quote_staleness;
+use crate::fx::fx_service::{ExchangeRate, FxError, FxService};
 use crate::models::{
-    Account, Activity, FinancialHistory, FinancialSnapshot, Holding, Performance, Quote,
+    Account, Activity, AllocationGroup, CurrencyReturn, ExportFormat, FinancialHistory,
+    FinancialSnapshot, Holding, Performance, Quote, SimulatedTrade, TradeSimulation,
 };
+use crate::portfolio::allocation_service::AllocationService;
+use crate::portfolio::performance_service::PerformanceService;
 use crate::settings::SettingsService;
 
 use chrono::{Duration, NaiveDate, Utc};
@@ -16,8 +22,8 @@ pub struct PortfolioService {
     account_service: AccountService,
     activity_service: ActivityService,
     asset_service: AssetService,
-    base_currency: String,
-    exchange_rates: HashMap<String, f64>,
+    reporting_currency: String,
+    fx_service: FxService,
 }
 
 /// This module contains the implementation of the `PortfolioService` struct.
@@ -32,53 +38,101 @@ impl PortfolioService {
             account_service: AccountService::new(),
             activity_service: ActivityService::new(),
             asset_service: AssetService::new(),
-            base_currency: String::new(),
-            exchange_rates: HashMap::new(),
+            reporting_currency: String::new(),
+            fx_service: FxService::new("", HashMap::new()),
         }
     }
 
+    // Load the base currency and exchange rates. Valuations are reported in
+    // `reporting_currency` when given, otherwise in the base currency.
     pub async fn initialize(
         &mut self,
         conn: &mut SqliteConnection,
+        reporting_currency: Option<String>,
     ) -> Result<(), Box<dyn std::error::Error>> {
         let settings_service = SettingsService::new();
         let settings = settings_service.get_settings(conn)?;
-        self.base_currency = settings.base_currency.clone();
-        self.exchange_rates = self
-            .asset_service
-            .load_exchange_rates(conn, &settings.base_currency)?;
+        self.reporting_currency =
+            reporting_currency.unwrap_or_else(|| settings.base_currency.clone());
+        let exchange_rates = self.asset_service.load_exchange_rates(conn)?;
+        let manual_rates = self.asset_service.load_manual_exchange_rates(conn)?;
+        self.fx_service =
+            FxService::new(&settings.base_currency, exchange_rates).with_manual_rates(manual_rates);
         Ok(())
     }
 
-    fn convert_to_base_currency(&self, amount: f64, currency: &str) -> f64 {
-        if currency == self.base_currency {
-            amount
-        } else {
-            let rate = self.get_exchange_rate(currency);
-            amount * rate
-        }
+    fn convert_to_reporting_currency(&self, amount: f64, currency: &str) -> Result<f64, FxError> {
+        self.fx_service
+            .convert_currency(amount, currency, &self.reporting_currency)
     }
 
-    fn get_exchange_rate(&self, currency: &str) -> f64 {
-        if currency == self.base_currency {
-            1.0
-        } else {
-            let currency_key = format!("{}{}=X", self.base_currency, currency);
-            1.0 / *self
-                .exchange_rates
-                .get(&currency_key.to_string())
-                .unwrap_or(&1.0)
-        }
+    // Exchange rates along with their daily quotes, to value past days
+    fn load_dated_fx_service(
+        &self,
+        conn: &mut SqliteConnection,
+    ) -> Result<FxService, Box<dyn std::error::Error>> {
+        let settings = SettingsService::new().get_settings(conn)?;
+        Ok(FxService::new(
+            &settings.base_currency,
+            self.asset_service.load_exchange_rates(conn)?,
+        )
+        .with_manual_rates(self.asset_service.load_manual_exchange_rates(conn)?)
+        .with_rate_history(self.asset_service.load_exchange_rate_history(conn)?))
+    }
+
+    fn get_exchange_rate(&self, currency: &str, date: NaiveDate) -> Result<f64, FxError> {
+        self.fx_service
+            .get_exchange_rate_for_date(currency, &self.reporting_currency, date)
     }
 
     pub async fn compute_holdings(
         &self,
         conn: &mut SqliteConnection,
+    ) -> Result<Vec<Holding>, Box<dyn std::error::Error>> {
+        self.compute_holdings_with_overlay(conn, Vec::new(), None)
+            .await
+    }
+
+    // Holdings at the end of `as_of`, replayed from the activities up to that day and
+    // valued at the quotes and exchange rates of that day. Empty before the first activity.
+    pub async fn compute_holdings_as_of(
+        &self,
+        conn: &mut SqliteConnection,
+        as_of: NaiveDate,
+    ) -> Result<Vec<Holding>, Box<dyn std::error::Error>> {
+        self.compute_holdings_with_overlay(conn, Vec::new(), Some(as_of))
+            .await
+    }
+
+    // Holdings after applying the `overlay` activities on top of the stored ones,
+    // the overlay is only held in memory
+    async fn compute_holdings_with_overlay(
+        &self,
+        conn: &mut SqliteConnection,
+        overlay: Vec<Activity>,
+        as_of: Option<NaiveDate>,
     ) -> Result<Vec<Holding>, Box<dyn std::error::Error>> {
         let mut holdings: HashMap<String, Holding> = HashMap::new();
         let accounts = self.account_service.get_accounts(conn)?;
-        let activities = self.activity_service.get_trading_activities(conn)?;
+        let mut activities = self.activity_service.get_trading_activities(conn)?;
+        activities.extend(overlay);
+        if let Some(as_of) = as_of {
+            activities.retain(|activity| activity.activity_date.date() <= as_of);
+        }
         let assets = self.asset_service.get_assets(conn)?;
+        let assets_tags = self.asset_service.get_assets_tags(conn)?;
+        let staleness_policies = self.asset_service.load_quote_staleness_policies(conn)?;
+        // A past day is converted at the rates of that day
+        let dated_fx_service = match as_of {
+            Some(as_of) => Some((self.load_dated_fx_service(conn)?, as_of)),
+            None => None,
+        };
+
+        // A spin-off receives the basis released by its parent on the same day,
+        // so the parent side has to be applied first
+        activities.sort_by_key(|a| (a.activity_date.date(), a.activity_type == "SPIN_OFF_IN"));
+        // Basis released by the parent side of each spin-off, by activity
+        let mut spin_off_basis: HashMap<String, f64> = HashMap::new();
 
         for activity in activities {
             //find asset by id
@@ -104,7 +158,7 @@ impl PortfolioService {
                 holding_type: asset.asset_type.clone().unwrap_or_default(),
                 quantity: 0.0,
                 currency: activity.currency.clone(),
-                base_currency: "CAD".to_string(),
+                base_currency: self.reporting_currency.clone(),
                 market_price: None,          // You need to provide market price
                 average_cost: None,          // Will be calculated
                 market_value: 0.0,           // Will be calculated
@@ -126,20 +180,45 @@ impl PortfolioService {
                     .sectors
                     .clone()
                     .map(|s| serde_json::from_str(&s).unwrap_or_default()),
+                tags: assets_tags
+                    .get(&activity.asset_id)
+                    .cloned()
+                    .unwrap_or_default(),
+                quote_date: None,
+                is_quote_stale: false,
+                fx_error: None,
+                fx_derived: false,
             });
 
+            let price_factor = asset.get_price_factor();
+
             match activity.activity_type.as_str() {
-                "BUY" => {
-                    holding.quantity += activity.quantity;
-                    holding.book_value += activity.quantity * activity.unit_price + activity.fee;
+                "BUY" => Self::apply_trade(holding, &activity, activity.quantity, price_factor),
+                "SELL" => Self::apply_trade(holding, &activity, -activity.quantity, price_factor),
+                "SPLIT" => {
+                    // The quantity of a split is its multiple (4 for 4:1, 0.25 for 1:4),
+                    // the book value is unchanged so the average cost is divided by it
+                    if activity.quantity > 0.0 {
+                        holding.quantity *= activity.quantity;
+                    }
                 }
-                "SELL" => {
-                    holding.quantity -= activity.quantity;
-                    holding.book_value -= activity.quantity * activity.unit_price + activity.fee;
+                "SPIN_OFF_OUT" => {
+                    // The unit price of the parent side is the fraction of its basis
+                    // allocated to the spun-off company, the parent shares are unchanged
+                    let released_basis = holding.book_value * activity.unit_price.clamp(0.0, 1.0);
+                    holding.book_value -= released_basis;
+                    spin_off_basis.insert(activity.id.clone(), released_basis);
                 }
-                "SPLIT" => {
-                    // Handle the split logic here
-                    // You might need additional information to handle a split correctly.
+                "SPIN_OFF_IN" => {
+                    // The received side points to its parent, the fraction paid in cash
+                    // leaves with its part of the basis
+                    let received_basis = activity
+                        .source_activity_id
+                        .as_ref()
+                        .and_then(|parent_id| spin_off_basis.remove(parent_id))
+                        .unwrap_or(0.0);
+                    holding.quantity += activity.get_spin_off_shares().0;
+                    holding.book_value += received_basis * activity.get_spin_off_kept_fraction();
                 }
                 _ => {}
             }
@@ -156,7 +235,11 @@ impl PortfolioService {
         // Fetch quotes for each symbol asynchronously
         let mut quotes = HashMap::new();
         for symbol in symbols {
-            match self.asset_service.get_latest_quote(conn, &symbol) {
+            let quote = match as_of {
+                Some(as_of) => self.asset_service.get_quote_on_date(conn, &symbol, as_of),
+                None => self.asset_service.get_latest_quote(conn, &symbol),
+            };
+            match quote {
                 Ok(quote) => {
                     quotes.insert(symbol, quote);
                 }
@@ -172,30 +255,243 @@ impl PortfolioService {
             if let Some(quote) = quotes.get(&holding.symbol) {
                 //prinln!("Quote: {:?}", quote);
                 holding.market_price = Some(quote.close); // Assuming you want to use the 'close' value as market price
+                holding.quote_date = Some(quote.date.date());
+            }
+            let asset = assets.iter().find(|a| a.id == holding.symbol);
+            // Quote age at the valuation day
+            if let Some(asset) = asset {
+                holding.is_quote_stale = quote_staleness::is_quote_stale(
+                    asset,
+                    holding.quote_date,
+                    as_of.unwrap_or_else(|| Utc::now().date_naive()),
+                    &staleness_policies,
+                );
             }
-            holding.average_cost = Some(holding.book_value / holding.quantity);
-            holding.market_value = holding.quantity * holding.market_price.unwrap_or(0.0);
-            holding.market_value_converted =
-                self.convert_to_base_currency(holding.market_value, &holding.currency);
-            holding.book_value_converted =
-                self.convert_to_base_currency(holding.book_value, &holding.currency);
+            let price_factor = asset.map_or(1.0, |a| a.get_price_factor());
+            holding.average_cost = Some(holding.book_value / (holding.quantity * price_factor));
+            holding.market_value =
+                holding.quantity * holding.market_price.unwrap_or(0.0) * price_factor;
 
             // Calculate performance metrics
             holding.performance.total_gain_amount = holding.market_value - holding.book_value;
             holding.performance.total_gain_percent = if holding.book_value != 0.0 {
-                holding.performance.total_gain_amount / holding.book_value * 100.0
+                holding.performance.total_gain_amount / holding.book_value.abs() * 100.0
             } else {
                 0.0
             };
-            holding.performance.total_gain_amount_converted = self
-                .convert_to_base_currency(holding.performance.total_gain_amount, &holding.currency);
+
+            // A holding without a rate to the reporting currency keeps its converted
+            // values at 0 along with the error, the other holdings are still valued
+            let exchange_rate = match &dated_fx_service {
+                Some((fx_service, as_of)) => fx_service.get_exchange_rate_details_for_date(
+                    &holding.currency,
+                    &self.reporting_currency,
+                    *as_of,
+                ),
+                None => self
+                    .fx_service
+                    .get_exchange_rate_details(&holding.currency, &self.reporting_currency),
+            };
+            match exchange_rate {
+                Ok(ExchangeRate { rate, is_derived }) => {
+                    holding.fx_derived = is_derived;
+                    holding.market_value_converted = holding.market_value * rate;
+                    holding.book_value_converted = holding.book_value * rate;
+                    holding.performance.total_gain_amount_converted =
+                        holding.performance.total_gain_amount * rate;
+                }
+                Err(e) => holding.fx_error = Some(e.to_string()),
+            }
         }
 
-        holdings
+        Ok(holdings
             .into_values()
-            .filter(|holding| holding.quantity > 0.0)
-            .map(Ok)
-            .collect::<Result<Vec<_>, _>>()
+            .filter(|holding| holding.quantity != 0.0)
+            .collect())
+    }
+
+    // Apply a BUY (positive `quantity`) or a SELL (negative `quantity`) to `holding`.
+    // A SELL beyond the long quantity opens a short position, its book value is the
+    // negative of the proceeds so the gain is positive when the price falls. A BUY
+    // covers the short before opening a long position.
+    fn apply_trade(holding: &mut Holding, activity: &Activity, quantity: f64, price_factor: f64) {
+        let value = quantity.abs() * activity.unit_price * price_factor;
+        let closed_quantity = if holding.quantity * quantity < 0.0 {
+            quantity.abs().min(holding.quantity.abs())
+        } else {
+            0.0
+        };
+        let opened_fraction = 1.0 - closed_quantity / quantity.abs();
+
+        if closed_quantity > 0.0 {
+            let closed_fraction = 1.0 - opened_fraction;
+            holding.book_value += quantity.signum() * (value + activity.fee) * closed_fraction;
+        }
+        if opened_fraction > 0.0 {
+            let opened_value = value * opened_fraction;
+            let opened_fee = activity.fee * opened_fraction;
+            if closed_quantity > 0.0 {
+                // The position changed side, its previous book value is realized
+                holding.book_value = 0.0;
+            }
+            holding.book_value += if quantity > 0.0 {
+                opened_value + opened_fee
+            } else {
+                -(opened_value - opened_fee)
+            };
+        }
+        holding.quantity += quantity;
+    }
+
+    // Cash balance of all active accounts today, in the reporting currency
+    pub async fn get_available_cash(
+        &self,
+        conn: &mut SqliteConnection,
+    ) -> Result<f64, Box<dyn std::error::Error>> {
+        Ok(self
+            .calculate_historical_portfolio_values(conn)
+            .await?
+            .into_iter()
+            .find(|fh| fh.account.id == "TOTAL")
+            .and_then(|fh| fh.history.last().map(|s| s.available_cash))
+            .unwrap_or(0.0))
+    }
+
+    // Project the holdings, allocation and cash balance resulting from `trades` as if they
+    // were placed now. Nothing is persisted.
+    pub async fn simulate_trades(
+        &self,
+        conn: &mut SqliteConnection,
+        trades: &[SimulatedTrade],
+        group_by: AllocationGroup,
+    ) -> Result<TradeSimulation, Box<dyn std::error::Error>> {
+        let accounts = self.account_service.get_accounts(conn)?;
+        let assets = self.asset_service.get_assets(conn)?;
+        let now = Utc::now().naive_utc();
+
+        let mut overlay = Vec::new();
+        let mut cash_impact = 0.0;
+        for (index, trade) in trades.iter().enumerate() {
+            if !accounts
+                .iter()
+                .any(|a| a.id == trade.account_id && a.is_active)
+            {
+                return Err(format!("Active account not found: {}", trade.account_id).into());
+            }
+            let asset = assets
+                .iter()
+                .find(|a| a.id == trade.asset_id)
+                .ok_or_else(|| format!("Asset not found: {}", trade.asset_id))?;
+            if trade.quantity <= 0.0 || trade.unit_price < 0.0 || trade.fee < 0.0 {
+                return Err(format!("Invalid trade of {}", trade.asset_id).into());
+            }
+
+            let trade_value = trade.quantity * trade.unit_price * asset.get_price_factor();
+            let cash_change = match trade.activity_type.as_str() {
+                "BUY" => -(trade_value + trade.fee),
+                "SELL" => trade_value - trade.fee,
+                other => return Err(format!("Unsupported trade type: {}", other).into()),
+            };
+            cash_impact += self.convert_to_reporting_currency(cash_change, &asset.currency)?;
+
+            overlay.push(Activity {
+                id: format!("SIMULATED-{}", index),
+                account_id: trade.account_id.clone(),
+                asset_id: trade.asset_id.clone(),
+                activity_type: trade.activity_type.clone(),
+                activity_date: now,
+                quantity: trade.quantity,
+                unit_price: trade.unit_price,
+                currency: asset.currency.clone(),
+                fee: trade.fee,
+                is_draft: true,
+                comment: None,
+                created_at: now,
+                updated_at: now,
+                deleted_at: None,
+                source_activity_id: None,
+            });
+        }
+
+        let current_holdings = self.compute_holdings(conn).await?;
+        let holdings = self
+            .compute_holdings_with_overlay(conn, overlay, None)
+            .await?;
+        let allocation = AllocationService::new().calculate_allocation_shifts(
+            &current_holdings,
+            &holdings,
+            group_by,
+        );
+
+        let current_cash = self.get_available_cash(conn).await?;
+
+        Ok(TradeSimulation {
+            holdings,
+            allocation,
+            current_cash,
+            projected_cash: current_cash + cash_impact,
+        })
+    }
+
+    // Local and FX parts of the return between `start_date` and `end_date` of every
+    // asset held in a currency other than the reporting one. Prices and rates are
+    // the last known on or before each date.
+    pub async fn get_currency_returns(
+        &self,
+        conn: &mut SqliteConnection,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> Result<Vec<CurrencyReturn>, Box<dyn std::error::Error>> {
+        let fx_service = self.load_dated_fx_service(conn)?;
+
+        let mut foreign_assets: Vec<(String, String)> = self
+            .compute_holdings(conn)
+            .await?
+            .into_iter()
+            .filter(|holding| {
+                holding.quantity > 0.0
+                    && holding.holding_type != "CASH"
+                    && holding.currency != self.reporting_currency
+            })
+            .map(|holding| (holding.symbol, holding.currency))
+            .collect();
+        foreign_assets.sort();
+        foreign_assets.dedup();
+
+        let performance_service = PerformanceService::new();
+        let mut currency_returns = Vec::new();
+        for (asset_id, currency) in foreign_assets {
+            let prices = match (
+                self.asset_service
+                    .get_quote_on_date(conn, &asset_id, start_date),
+                self.asset_service
+                    .get_quote_on_date(conn, &asset_id, end_date),
+            ) {
+                (Ok(start_quote), Ok(end_quote)) => (start_quote.close, end_quote.close),
+                _ => {
+                    println!("No price of {} for the whole period, skipped", asset_id);
+                    continue;
+                }
+            };
+            let rates = (
+                fx_service.get_exchange_rate_for_date(
+                    &currency,
+                    &self.reporting_currency,
+                    start_date,
+                )?,
+                fx_service.get_exchange_rate_for_date(
+                    &currency,
+                    &self.reporting_currency,
+                    end_date,
+                )?,
+            );
+
+            currency_returns.extend(
+                performance_service.calculate_currency_return(&asset_id, &currency, prices, rates),
+            );
+        }
+
+        Ok(currency_returns)
     }
 
     fn get_dates_between(start: NaiveDate, end: NaiveDate) -> Vec<NaiveDate> {
@@ -230,6 +526,13 @@ impl PortfolioService {
 
         let (accounts, activities, market_data) = self.fetch_data(conn)?;
 
+        let price_factors: HashMap<String, f64> = self
+            .asset_service
+            .get_assets(conn)?
+            .iter()
+            .map(|asset| (asset.id.clone(), asset.get_price_factor()))
+            .collect();
+
         // Use Rayon's par_iter to process each account in parallel
         let results: Vec<FinancialHistory> = accounts
             .par_iter()
@@ -243,8 +546,11 @@ impl PortfolioService {
                 if account_activities.is_empty() {
                     None
                 } else {
-                    let history =
-                        self.calculate_historical_value(&account_activities, &market_data);
+                    let history = self.calculate_historical_value(
+                        &account_activities,
+                        &market_data,
+                        &price_factors,
+                    );
                     Some(FinancialHistory {
                         account: account.clone(),
                         history,
@@ -295,6 +601,85 @@ impl PortfolioService {
         Ok(results_with_percentage)
     }
 
+    // Write the historical values of the total portfolio and of each account between
+    // the optional `start_date` and `end_date` (inclusive) to `file_path`.
+    // Returns the number of snapshots written.
+    pub async fn export_historical_portfolio_values(
+        &self,
+        conn: &mut SqliteConnection,
+        format: ExportFormat,
+        file_path: &str,
+        start_date: Option<String>,
+        end_date: Option<String>,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let in_range = |snapshot: &FinancialSnapshot| {
+            start_date.iter().all(|start| snapshot.date >= *start)
+                && end_date.iter().all(|end| snapshot.date <= *end)
+        };
+
+        let histories: Vec<FinancialHistory> = self
+            .calculate_historical_portfolio_values(conn)
+            .await?
+            .into_iter()
+            .map(|mut financial_history| {
+                financial_history.history.retain(&in_range);
+                financial_history
+            })
+            .collect();
+        let snapshot_count = histories.iter().map(|fh| fh.history.len()).sum();
+
+        let file = File::create(file_path)?;
+        match format {
+            ExportFormat::Json => serde_json::to_writer_pretty(file, &histories)?,
+            ExportFormat::Csv => {
+                let mut writer = csv::Writer::from_writer(file);
+                writer.write_record([
+                    "accountId",
+                    "accountName",
+                    "date",
+                    "currency",
+                    "baseCurrency",
+                    "exchangeRate",
+                    "totalValue",
+                    "marketValue",
+                    "bookCost",
+                    "availableCash",
+                    "netDeposit",
+                    "totalGainValue",
+                    "totalGainPercentage",
+                    "dayGainValue",
+                    "dayGainPercentage",
+                ])?;
+                for financial_history in &histories {
+                    for snapshot in &financial_history.history {
+                        writer.write_record([
+                            financial_history.account.id.clone(),
+                            financial_history.account.name.clone(),
+                            snapshot.date.clone(),
+                            snapshot.currency.clone(),
+                            snapshot.base_currency.clone(),
+                            snapshot
+                                .exchange_rate
+                                .map_or(String::new(), |r| r.to_string()),
+                            snapshot.total_value.to_string(),
+                            snapshot.market_value.to_string(),
+                            snapshot.book_cost.to_string(),
+                            snapshot.available_cash.to_string(),
+                            snapshot.net_deposit.to_string(),
+                            snapshot.total_gain_value.to_string(),
+                            snapshot.total_gain_percentage.to_string(),
+                            snapshot.day_gain_value.to_string(),
+                            snapshot.day_gain_percentage.to_string(),
+                        ])?;
+                    }
+                }
+                writer.flush()?;
+            }
+        }
+
+        Ok(snapshot_count)
+    }
+
     fn aggregate_account_history(
         &self,
         aggregated_history: &mut HashMap<String, FinancialSnapshot>,
@@ -311,7 +696,7 @@ impl PortfolioService {
                     available_cash: 0.0,
                     net_deposit: 0.0,
                     currency: snapshot.currency.to_string(),
-                    base_currency: self.base_currency.to_string(),
+                    base_currency: self.reporting_currency.to_string(),
                     total_gain_value: 0.0,
                     total_gain_percentage: 0.0,
                     day_gain_percentage: 0.0,
@@ -320,7 +705,10 @@ impl PortfolioService {
                     exchange_rate: Some(1.0), // Default exchange rate for base currency
                 });
 
-            let exchange_rate = snapshot.exchange_rate.unwrap_or(1.0);
+            let exchange_rate = match snapshot.exchange_rate {
+                Some(exchange_rate) => exchange_rate,
+                None => continue,
+            };
 
             // Convert values to base currency before aggregating
             entry.total_value += snapshot.total_value * exchange_rate;
@@ -354,7 +742,7 @@ impl PortfolioService {
             created_at: Utc::now().naive_utc(),
             updated_at: Utc::now().naive_utc(),
             platform_id: None,
-            currency: self.base_currency.to_string(),
+            currency: self.reporting_currency.to_string(),
         }
     }
 
@@ -362,6 +750,7 @@ impl PortfolioService {
         &self,
         activities: &[Activity],
         quotes: &[Quote],
+        price_factors: &HashMap<String, f64>,
     ) -> Vec<FinancialSnapshot> {
         let first_activity = activities[0].clone();
 
@@ -370,7 +759,7 @@ impl PortfolioService {
         let end_date = Utc::now().naive_utc().date();
         let all_dates = Self::get_dates_between(start_date, end_date);
 
-        let mut currency = self.base_currency.as_str();
+        let mut currency = self.reporting_currency.as_str();
         let mut cumulative_cash = 0.0;
         let mut holdings: HashMap<String, f64> = HashMap::new();
 
@@ -378,6 +767,7 @@ impl PortfolioService {
         let mut _initial_investment = 0.0;
         let mut net_deposit = 0.0;
         let mut book_cost = 0.0;
+        let mut is_rate_missing = false;
 
         // HashMap to keep the last available quote for each symbol
         let mut last_available_quotes: HashMap<String, &Quote> = HashMap::new();
@@ -387,23 +777,29 @@ impl PortfolioService {
                 currency = activity.currency.as_str();
                 let activity_amount = activity.quantity;
                 let activity_fee = activity.fee;
+                let price_factor = price_factors
+                    .get(&activity.asset_id)
+                    .copied()
+                    .unwrap_or(1.0);
 
                 match activity.activity_type.as_str() {
                     "BUY" => {
                         let entry = holdings.entry(activity.asset_id.clone()).or_insert(0.0);
                         *entry += activity_amount;
-                        let buy_cost = activity_amount * activity.unit_price + activity_fee;
+                        let buy_value = activity_amount * activity.unit_price * price_factor;
+                        let buy_cost = buy_value + activity_fee;
                         cumulative_cash -= buy_cost;
-                        _initial_investment += activity_amount * activity.unit_price;
+                        _initial_investment += buy_value;
                         book_cost += buy_cost;
                     }
                     "SELL" => {
                         let entry = holdings.entry(activity.asset_id.clone()).or_insert(0.0);
                         *entry -= activity_amount;
-                        let sell_profit = activity_amount * activity.unit_price - activity_fee;
+                        let sell_value = activity_amount * activity.unit_price * price_factor;
+                        let sell_profit = sell_value - activity_fee;
                         cumulative_cash += sell_profit;
-                        _initial_investment -= activity_amount * activity.unit_price;
-                        book_cost -= activity_amount * activity.unit_price + activity_fee;
+                        _initial_investment -= sell_value;
+                        book_cost -= sell_value + activity_fee;
                     }
                     "DEPOSIT" | "TRANSFER_IN" | "CONVERSION_IN" => {
                         cumulative_cash += activity_amount * activity.unit_price - activity_fee;
@@ -419,6 +815,19 @@ impl PortfolioService {
                     "FEE" | "TAX" => {
                         cumulative_cash -= activity_fee;
                     }
+                    "SPIN_OFF_IN" => {
+                        let (shares, cash_fraction) = activity.get_spin_off_shares();
+                        let entry = holdings.entry(activity.asset_id.clone()).or_insert(0.0);
+                        *entry += shares;
+                        cumulative_cash += cash_fraction * activity.unit_price * price_factor;
+                    }
+                    "SPLIT" => {
+                        if activity_amount > 0.0 {
+                            if let Some(entry) = holdings.get_mut(&activity.asset_id) {
+                                *entry *= activity_amount;
+                            }
+                        }
+                    }
                     _ => {}
                 }
             }
@@ -436,7 +845,8 @@ impl PortfolioService {
                    ; // Copy the reference to the quote
 
                 if let Some(quote) = quote {
-                    let holding_value_for_symbol = holding_amount * quote.close;
+                    let price_factor = price_factors.get(symbol).copied().unwrap_or(1.0);
+                    let holding_value_for_symbol = holding_amount * quote.close * price_factor;
                     let daily_change_percent = ((quote.close - quote.open) / quote.open) * 100.0;
                     let day_gain_for_symbol =
                         (daily_change_percent / 100.0) * holding_value_for_symbol;
@@ -449,8 +859,9 @@ impl PortfolioService {
                 }
             }
 
+            // Short positions have a negative value, percentages are relative to its size
             let day_gain_percentage = if holdings_value != 0.0 {
-                (day_gain_value / holdings_value) * 100.0
+                (day_gain_value / holdings_value.abs()) * 100.0
             } else {
                 0.0
             };
@@ -458,12 +869,22 @@ impl PortfolioService {
             let total_value = cumulative_cash + holdings_value;
             let total_gain_value = holdings_value - book_cost;
             let total_gain_percentage = if book_cost != 0.0 {
-                (total_gain_value / book_cost) * 100.0
+                (total_gain_value / book_cost.abs()) * 100.0
             } else {
                 0.0
             };
 
-            let exchange_rate = self.get_exchange_rate(currency);
+            // Snapshots without a rate are left out of the total portfolio
+            let exchange_rate = match self.get_exchange_rate(currency, date) {
+                Ok(rate) => Some(rate),
+                Err(e) => {
+                    if !is_rate_missing {
+                        eprintln!("{}, account left out of the total from {}", e, date);
+                        is_rate_missing = true;
+                    }
+                    None
+                }
+            };
 
             results.push(FinancialSnapshot {
                 date: date.format("%Y-%m-%d").to_string(),
@@ -473,16 +894,512 @@ impl PortfolioService {
                 available_cash: cumulative_cash,
                 net_deposit,
                 currency: currency.to_string(),
-                base_currency: self.base_currency.to_string(),
+                base_currency: self.reporting_currency.to_string(),
                 total_gain_value: holdings_value - book_cost,
                 total_gain_percentage,
                 day_gain_percentage,
                 day_gain_value,
                 allocation_percentage: None, // to Calculate later
-                exchange_rate: Some(exchange_rate),
+                exchange_rate,
             });
         }
 
         results
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{NewAsset, NewSettings, SimulatedTrade};
+    use crate::schema::{activities, assets};
+    use crate::test_utils::{self, TestDatabase};
+    use diesel::prelude::*;
+
+    // Currency asset of the pair `symbol`, e.g. "EURUSD=X", quoted at `rate` on `date`
+    fn insert_rate(conn: &mut SqliteConnection, symbol: &str, date: &str, rate: f64) {
+        diesel::insert_into(assets::table)
+            .values(NewAsset {
+                id: symbol.to_string(),
+                symbol: symbol.to_string(),
+                asset_type: Some("Currency".to_string()),
+                currency: symbol[3..6].to_string(),
+                data_source: "Yahoo".to_string(),
+                ..Default::default()
+            })
+            .on_conflict_do_nothing()
+            .execute(conn)
+            .unwrap();
+        test_utils::insert_quote(conn, symbol, date, rate);
+    }
+
+    #[test]
+    fn holdings_of_a_past_day_use_the_exchange_rate_of_that_day() {
+        let mut db = TestDatabase::new();
+        test_utils::insert_settings(&mut db.conn, "USD");
+        test_utils::insert_account(&mut db.conn, "account", "EUR");
+        test_utils::insert_asset(&mut db.conn, "SAP", "EUR");
+        insert_rate(&mut db.conn, "EURUSD=X", "2024-01-02", 1.1);
+        insert_rate(&mut db.conn, "EURUSD=X", "2024-06-03", 1.2);
+        test_utils::insert_quote(&mut db.conn, "SAP", "2024-01-02", 100.0);
+        let mut buy = test_utils::new_activity("account", "SAP", "BUY", "2024-01-02", 10.0, 100.0);
+        buy.currency = "EUR".to_string();
+        test_utils::insert_activity(&mut db.conn, buy);
+
+        let mut service = PortfolioService::new();
+        tauri::async_runtime::block_on(service.initialize(&mut db.conn, None)).unwrap();
+        let as_of = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        let holdings =
+            tauri::async_runtime::block_on(service.compute_holdings_as_of(&mut db.conn, as_of))
+                .unwrap();
+
+        let holding = holdings.iter().find(|h| h.symbol == "SAP").unwrap();
+        assert!((holding.market_value_converted - 1100.0).abs() < 1e-9);
+        assert!((holding.book_value_converted - 1100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn holdings_in_the_reporting_currency_need_no_exchange_rate() {
+        let mut db = TestDatabase::new();
+        test_utils::insert_settings(&mut db.conn, "EUR");
+        test_utils::insert_account(&mut db.conn, "account", "EUR");
+        test_utils::insert_asset(&mut db.conn, "SAP", "EUR");
+        // No currency pair is quoted at all
+        test_utils::insert_quote(&mut db.conn, "SAP", "2024-01-02", 120.0);
+        let mut buy = test_utils::new_activity("account", "SAP", "BUY", "2024-01-02", 10.0, 100.0);
+        buy.currency = "EUR".to_string();
+        test_utils::insert_activity(&mut db.conn, buy);
+
+        let mut service = PortfolioService::new();
+        tauri::async_runtime::block_on(service.initialize(&mut db.conn, None)).unwrap();
+        let holdings =
+            tauri::async_runtime::block_on(service.compute_holdings(&mut db.conn)).unwrap();
+
+        let sap = holdings.iter().find(|h| h.symbol == "SAP").unwrap();
+        assert!(sap.fx_error.is_none());
+        assert!(!sap.fx_derived);
+        assert_eq!(sap.base_currency, "EUR");
+        assert_eq!(sap.market_value_converted, sap.market_value);
+        assert_eq!(sap.book_value_converted, sap.book_value);
+        assert!((sap.market_value_converted - 1200.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_missing_exchange_rate_is_reported_on_the_holding() {
+        let mut db = TestDatabase::new();
+        test_utils::insert_settings(&mut db.conn, "USD");
+        test_utils::insert_account(&mut db.conn, "account", "USD");
+        test_utils::insert_asset(&mut db.conn, "AAPL", "USD");
+        test_utils::insert_asset(&mut db.conn, "VOD", "GBP");
+        test_utils::insert_quote(&mut db.conn, "AAPL", "2024-01-02", 150.0);
+        test_utils::insert_quote(&mut db.conn, "VOD", "2024-01-02", 0.7);
+        test_utils::insert_activity(
+            &mut db.conn,
+            test_utils::new_activity("account", "AAPL", "BUY", "2024-01-02", 2.0, 150.0),
+        );
+        let mut buy = test_utils::new_activity("account", "VOD", "BUY", "2024-01-02", 100.0, 0.7);
+        buy.currency = "GBP".to_string();
+        test_utils::insert_activity(&mut db.conn, buy);
+
+        let mut service = PortfolioService::new();
+        tauri::async_runtime::block_on(service.initialize(&mut db.conn, None)).unwrap();
+        let holdings =
+            tauri::async_runtime::block_on(service.compute_holdings(&mut db.conn)).unwrap();
+
+        let vod = holdings.iter().find(|h| h.symbol == "VOD").unwrap();
+        assert!(vod.fx_error.is_some());
+        assert_eq!(vod.market_value_converted, 0.0);
+        let aapl = holdings.iter().find(|h| h.symbol == "AAPL").unwrap();
+        assert!(aapl.fx_error.is_none());
+        assert!((aapl.market_value_converted - 300.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_triangulated_exchange_rate_is_flagged_on_the_holding() {
+        let mut db = TestDatabase::new();
+        test_utils::insert_settings(&mut db.conn, "EUR");
+        test_utils::insert_account(&mut db.conn, "account", "GBP");
+        test_utils::insert_asset(&mut db.conn, "VOD", "GBP");
+        test_utils::insert_asset(&mut db.conn, "SAP", "EUR");
+        // No GBP to EUR quote, only both against USD
+        insert_rate(&mut db.conn, "GBPUSD=X", "2024-01-02", 1.25);
+        insert_rate(&mut db.conn, "EURUSD=X", "2024-01-02", 1.1);
+        test_utils::insert_quote(&mut db.conn, "VOD", "2024-01-02", 0.7);
+        test_utils::insert_quote(&mut db.conn, "SAP", "2024-01-02", 100.0);
+        let mut buy = test_utils::new_activity("account", "VOD", "BUY", "2024-01-02", 1100.0, 0.7);
+        buy.currency = "GBP".to_string();
+        test_utils::insert_activity(&mut db.conn, buy);
+        let mut buy = test_utils::new_activity("account", "SAP", "BUY", "2024-01-02", 1.0, 100.0);
+        buy.currency = "EUR".to_string();
+        test_utils::insert_activity(&mut db.conn, buy);
+
+        let mut service = PortfolioService::new();
+        tauri::async_runtime::block_on(service.initialize(&mut db.conn, None)).unwrap();
+        let holdings =
+            tauri::async_runtime::block_on(service.compute_holdings(&mut db.conn)).unwrap();
+
+        let vod = holdings.iter().find(|h| h.symbol == "VOD").unwrap();
+        assert!(vod.fx_derived);
+        // 770 GBP at 1.25 / 1.1
+        assert!((vod.market_value_converted - 875.0).abs() < 1e-9);
+        let sap = holdings.iter().find(|h| h.symbol == "SAP").unwrap();
+        assert!(!sap.fx_derived);
+    }
+
+    #[test]
+    fn simulated_trades_shift_the_allocation_without_writing() {
+        let mut db = TestDatabase::new();
+        test_utils::insert_settings(&mut db.conn, "USD");
+        test_utils::insert_account(&mut db.conn, "account", "USD");
+        test_utils::insert_asset(&mut db.conn, "$CASH-USD", "USD");
+        for (asset_id, asset_class) in [("AAPL", "Equity"), ("BND", "Fixed Income")] {
+            test_utils::insert_asset(&mut db.conn, asset_id, "USD");
+            diesel::update(assets::table.find(asset_id))
+                .set(assets::asset_class.eq(asset_class))
+                .execute(&mut db.conn)
+                .unwrap();
+            test_utils::insert_quote(&mut db.conn, asset_id, "2024-01-02", 100.0);
+            test_utils::insert_activity(
+                &mut db.conn,
+                test_utils::new_activity("account", asset_id, "BUY", "2024-01-02", 5.0, 100.0),
+            );
+        }
+        test_utils::insert_activity(
+            &mut db.conn,
+            test_utils::new_activity("account", "$CASH-USD", "DEPOSIT", "2024-01-02", 2000.0, 1.0),
+        );
+        let count_activities = |conn: &mut SqliteConnection| -> i64 {
+            activities::table.count().get_result(conn).unwrap()
+        };
+        let activity_count = count_activities(&mut db.conn);
+
+        let mut service = PortfolioService::new();
+        tauri::async_runtime::block_on(service.initialize(&mut db.conn, None)).unwrap();
+        let trades = [SimulatedTrade {
+            account_id: "account".to_string(),
+            asset_id: "AAPL".to_string(),
+            activity_type: "BUY".to_string(),
+            quantity: 5.0,
+            unit_price: 100.0,
+            fee: 1.0,
+        }];
+        let simulation = tauri::async_runtime::block_on(service.simulate_trades(
+            &mut db.conn,
+            &trades,
+            AllocationGroup::AssetClass,
+        ))
+        .unwrap();
+
+        let shift = |category: &str| {
+            simulation
+                .allocation
+                .iter()
+                .find(|shift| shift.category == category)
+                .unwrap()
+        };
+        assert!(shift("Equity").projected_weight > shift("Equity").current_weight);
+        assert!(shift("Fixed Income").projected_weight < shift("Fixed Income").current_weight);
+        assert!((simulation.current_cash - 1000.0).abs() < 1e-9);
+        assert!((simulation.projected_cash - 499.0).abs() < 1e-9);
+        let aapl = simulation
+            .holdings
+            .iter()
+            .find(|h| h.symbol == "AAPL")
+            .unwrap();
+        assert_eq!(aapl.quantity, 10.0);
+
+        // Nothing was written
+        assert_eq!(count_activities(&mut db.conn), activity_count);
+        let holdings =
+            tauri::async_runtime::block_on(service.compute_holdings(&mut db.conn)).unwrap();
+        let aapl = holdings.iter().find(|h| h.symbol == "AAPL").unwrap();
+        assert_eq!(aapl.quantity, 5.0);
+    }
+
+    // `shares` of AAPL bought at `price` on 2024-01-02, split by `multiple` the next day
+    // when it is quoted at `price_after`
+    fn insert_split(
+        db: &mut TestDatabase,
+        shares: f64,
+        price: f64,
+        multiple: f64,
+        price_after: f64,
+    ) {
+        test_utils::insert_settings(&mut db.conn, "USD");
+        test_utils::insert_account(&mut db.conn, "account", "USD");
+        test_utils::insert_asset(&mut db.conn, "AAPL", "USD");
+        test_utils::insert_quote(&mut db.conn, "AAPL", "2024-01-02", price);
+        test_utils::insert_quote(&mut db.conn, "AAPL", "2024-01-03", price_after);
+        test_utils::insert_activity(
+            &mut db.conn,
+            test_utils::new_activity("account", "AAPL", "BUY", "2024-01-02", shares, price),
+        );
+        test_utils::insert_activity(
+            &mut db.conn,
+            test_utils::new_activity("account", "AAPL", "SPLIT", "2024-01-03", multiple, 0.0),
+        );
+    }
+
+    fn aapl_holding(db: &mut TestDatabase) -> Holding {
+        let mut service = PortfolioService::new();
+        tauri::async_runtime::block_on(service.initialize(&mut db.conn, None)).unwrap();
+        let holdings =
+            tauri::async_runtime::block_on(service.compute_holdings(&mut db.conn)).unwrap();
+        holdings.into_iter().find(|h| h.symbol == "AAPL").unwrap()
+    }
+
+    #[test]
+    fn a_split_multiplies_the_shares_and_keeps_the_value() {
+        let mut db = TestDatabase::new();
+        insert_split(&mut db, 10.0, 400.0, 4.0, 100.0);
+
+        let aapl = aapl_holding(&mut db);
+
+        assert_eq!(aapl.quantity, 40.0);
+        assert!((aapl.average_cost.unwrap() - 100.0).abs() < 1e-9);
+        assert!((aapl.book_value - 4000.0).abs() < 1e-9);
+        assert!((aapl.market_value - 4000.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_reverse_split_divides_the_shares_and_keeps_the_value() {
+        let mut db = TestDatabase::new();
+        insert_split(&mut db, 40.0, 100.0, 0.25, 400.0);
+
+        let aapl = aapl_holding(&mut db);
+
+        assert_eq!(aapl.quantity, 10.0);
+        assert!((aapl.average_cost.unwrap() - 400.0).abs() < 1e-9);
+        assert!((aapl.market_value - 4000.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn the_history_values_the_split_shares_at_the_new_price() {
+        let mut db = TestDatabase::new();
+        insert_split(&mut db, 10.0, 400.0, 4.0, 100.0);
+
+        let mut service = PortfolioService::new();
+        tauri::async_runtime::block_on(service.initialize(&mut db.conn, None)).unwrap();
+        let histories = tauri::async_runtime::block_on(
+            service.calculate_historical_portfolio_values(&mut db.conn),
+        )
+        .unwrap();
+
+        let history = &histories
+            .iter()
+            .find(|fh| fh.account.id == "account")
+            .unwrap()
+            .history;
+        let market_value = |date: &str| {
+            history
+                .iter()
+                .find(|snapshot| snapshot.date == date)
+                .unwrap()
+                .market_value
+        };
+        assert!((market_value("2024-01-02") - 4000.0).abs() < 1e-9);
+        assert!((market_value("2024-01-03") - 4000.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_bond_is_valued_at_its_price_as_a_percentage_of_the_nominal() {
+        let mut db = TestDatabase::new();
+        test_utils::insert_settings(&mut db.conn, "EUR");
+        test_utils::insert_account(&mut db.conn, "account", "EUR");
+        test_utils::insert_asset(&mut db.conn, "BTP", "EUR");
+        diesel::update(assets::table.find("BTP"))
+            .set(assets::asset_sub_class.eq("Bond"))
+            .execute(&mut db.conn)
+            .unwrap();
+        test_utils::insert_quote(&mut db.conn, "BTP", "2024-02-01", 99.0);
+        let mut buy = test_utils::new_activity("account", "BTP", "BUY", "2024-02-01", 3000.0, 98.5);
+        buy.currency = "EUR".to_string();
+        test_utils::insert_activity(&mut db.conn, buy);
+
+        let mut service = PortfolioService::new();
+        tauri::async_runtime::block_on(service.initialize(&mut db.conn, None)).unwrap();
+        let holdings =
+            tauri::async_runtime::block_on(service.compute_holdings(&mut db.conn)).unwrap();
+
+        let btp = holdings.iter().find(|h| h.symbol == "BTP").unwrap();
+        assert_eq!(btp.quantity, 3000.0);
+        assert!((btp.book_value - 2955.0).abs() < 1e-9);
+        assert!((btp.market_value - 2970.0).abs() < 1e-9);
+        assert!((btp.average_cost.unwrap() - 98.5).abs() < 1e-9);
+    }
+
+    // Histories of 10 AAPL bought at 100 on 2024-01-02 and quoted 110 the next day,
+    // exported for those two days
+    fn export_history(format: ExportFormat) -> (usize, String) {
+        let mut db = TestDatabase::new();
+        test_utils::insert_settings(&mut db.conn, "USD");
+        test_utils::insert_account(&mut db.conn, "account", "USD");
+        test_utils::insert_asset(&mut db.conn, "AAPL", "USD");
+        test_utils::insert_quote(&mut db.conn, "AAPL", "2024-01-02", 100.0);
+        test_utils::insert_quote(&mut db.conn, "AAPL", "2024-01-03", 110.0);
+        test_utils::insert_activity(
+            &mut db.conn,
+            test_utils::new_activity("account", "AAPL", "BUY", "2024-01-02", 10.0, 100.0),
+        );
+        let file_path = test_utils::write_temp_file("export", "");
+
+        let mut service = PortfolioService::new();
+        tauri::async_runtime::block_on(service.initialize(&mut db.conn, None)).unwrap();
+        let exported = tauri::async_runtime::block_on(service.export_historical_portfolio_values(
+            &mut db.conn,
+            format,
+            &file_path,
+            Some("2024-01-02".to_string()),
+            Some("2024-01-03".to_string()),
+        ))
+        .unwrap();
+        (exported, std::fs::read_to_string(file_path).unwrap())
+    }
+
+    #[test]
+    fn the_history_is_exported_to_csv_with_a_row_per_account_and_day() {
+        let (exported, content) = export_history(ExportFormat::Csv);
+
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(
+            lines[0],
+            "accountId,accountName,date,currency,baseCurrency,exchangeRate,totalValue,\
+             marketValue,bookCost,availableCash,netDeposit,totalGainValue,\
+             totalGainPercentage,dayGainValue,dayGainPercentage"
+        );
+        assert_eq!(exported, 4);
+        assert_eq!(lines.len(), 5);
+        let rows: Vec<Vec<&str>> = lines[1..]
+            .iter()
+            .map(|line| line.split(',').collect())
+            .collect();
+        let summary: Vec<(&str, &str, &str)> =
+            rows.iter().map(|row| (row[0], row[2], row[7])).collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("account", "2024-01-02", "1000"),
+                ("account", "2024-01-03", "1100"),
+                ("TOTAL", "2024-01-02", "1000"),
+                ("TOTAL", "2024-01-03", "1100"),
+            ]
+        );
+        assert_eq!(
+            rows[1][..6],
+            ["account", "account", "2024-01-03", "USD", "USD", "1"]
+        );
+        // Book cost and total gain
+        assert_eq!((rows[1][8], rows[1][11]), ("1000", "100"));
+    }
+
+    #[test]
+    fn the_history_exported_to_json_reads_back() {
+        let (exported, content) = export_history(ExportFormat::Json);
+
+        let histories: Vec<FinancialHistory> = serde_json::from_str(&content).unwrap();
+        assert_eq!(exported, 4);
+        let account_ids: Vec<&str> = histories.iter().map(|fh| fh.account.id.as_str()).collect();
+        assert_eq!(account_ids, ["account", "TOTAL"]);
+        let account_history = &histories[0].history;
+        let dates: Vec<&str> = account_history.iter().map(|s| s.date.as_str()).collect();
+        assert_eq!(dates, ["2024-01-02", "2024-01-03"]);
+        assert_eq!(account_history[1].market_value, 1100.0);
+        assert_eq!(account_history[1].book_cost, 1000.0);
+        assert_eq!(histories[1].history[1].market_value, 1100.0);
+    }
+
+    #[test]
+    fn a_fraction_of_a_satoshi_survives_storage_holdings_and_history() {
+        let mut db = TestDatabase::new();
+        let quantity = 0.000000012;
+        test_utils::insert_settings(&mut db.conn, "USD");
+        SettingsService::new()
+            .update_settings(
+                &mut db.conn,
+                &NewSettings {
+                    theme: "light",
+                    font: "font-mono",
+                    base_currency: "USD",
+                    quantity_precision: 9,
+                    price_precision: 2,
+                    activity_undo_days: 7,
+                },
+            )
+            .unwrap();
+        test_utils::insert_account(&mut db.conn, "account", "USD");
+        test_utils::insert_asset(&mut db.conn, "BTC-USD", "USD");
+        test_utils::insert_quote(&mut db.conn, "BTC-USD", "2024-01-02", 50000.0);
+        test_utils::insert_activity(
+            &mut db.conn,
+            test_utils::new_activity("account", "BTC-USD", "BUY", "2024-01-02", quantity, 45000.0),
+        );
+
+        let mut service = PortfolioService::new();
+        tauri::async_runtime::block_on(service.initialize(&mut db.conn, None)).unwrap();
+        let stored = service
+            .activity_service
+            .get_activities(&mut db.conn)
+            .unwrap();
+        let holdings =
+            tauri::async_runtime::block_on(service.compute_holdings(&mut db.conn)).unwrap();
+        let histories = tauri::async_runtime::block_on(
+            service.calculate_historical_portfolio_values(&mut db.conn),
+        )
+        .unwrap();
+
+        assert_eq!(stored[0].quantity, quantity);
+        let btc = holdings.iter().find(|h| h.symbol == "BTC-USD").unwrap();
+        assert_eq!(btc.quantity, quantity);
+        assert_eq!(btc.market_value, quantity * 50000.0);
+        assert_eq!(btc.book_value, quantity * 45000.0);
+        let snapshot = histories
+            .iter()
+            .find(|fh| fh.account.id == "account")
+            .unwrap()
+            .history
+            .iter()
+            .find(|snapshot| snapshot.date == "2024-01-02")
+            .unwrap();
+        assert_eq!(snapshot.market_value, quantity * 50000.0);
+        // Shown unrounded at the precision of the settings
+        let precision = SettingsService::new()
+            .get_settings(&mut db.conn)
+            .unwrap()
+            .quantity_precision as usize;
+        assert_eq!(format!("{:.*}", precision, btc.quantity), "0.000000012");
+    }
+
+    #[test]
+    fn holdings_carry_the_tags_of_their_asset() {
+        let mut db = TestDatabase::new();
+        test_utils::insert_settings(&mut db.conn, "USD");
+        test_utils::insert_account(&mut db.conn, "account", "USD");
+        test_utils::insert_asset(&mut db.conn, "AAPL", "USD");
+        test_utils::insert_asset(&mut db.conn, "MSFT", "USD");
+        test_utils::insert_quote(&mut db.conn, "AAPL", "2024-01-02", 150.0);
+        test_utils::insert_quote(&mut db.conn, "MSFT", "2024-01-02", 400.0);
+        for (symbol, price) in [("AAPL", 150.0), ("MSFT", 400.0)] {
+            test_utils::insert_activity(
+                &mut db.conn,
+                test_utils::new_activity("account", symbol, "BUY", "2024-01-02", 1.0, price),
+            );
+        }
+        let asset_service = AssetService::new();
+        asset_service
+            .add_asset_tag(&mut db.conn, "AAPL", "Tech")
+            .unwrap();
+        asset_service
+            .add_asset_tag(&mut db.conn, "AAPL", "Core")
+            .unwrap();
+
+        let mut service = PortfolioService::new();
+        tauri::async_runtime::block_on(service.initialize(&mut db.conn, None)).unwrap();
+        let holdings =
+            tauri::async_runtime::block_on(service.compute_holdings(&mut db.conn)).unwrap();
+
+        let aapl = holdings.iter().find(|h| h.symbol == "AAPL").unwrap();
+        assert_eq!(aapl.tags, ["Core", "Tech"]);
+        let msft = holdings.iter().find(|h| h.symbol == "MSFT").unwrap();
+        assert!(msft.tags.is_empty());
+    }
+}
@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+
+use crate::models::{
+    AllocationGroup, Holding, RebalanceConstraints, RebalancePlan, RebalanceTrade,
+};
+use crate::portfolio::allocation_service::AllocationService;
+
+// Target weights are accepted as adding up to 100% within this many percentage points
+const WEIGHT_TOLERANCE: f64 = 0.01;
+
+pub struct RebalancingService;
+
+impl RebalancingService {
+    pub fn new() -> Self {
+        RebalancingService
+    }
+
+    // Suggest trades moving the holdings toward `targets`, the weights in percent of the
+    // categories of `group_by`. Only the difference to each target is traded to keep the
+    // turnover down, spread over the holdings of the category by market value.
+    pub fn calculate_rebalance(
+        &self,
+        holdings: &[Holding],
+        available_cash: f64,
+        targets: &HashMap<String, f64>,
+        group_by: AllocationGroup,
+        constraints: &RebalanceConstraints,
+    ) -> Result<RebalancePlan, String> {
+        let mut warnings = Vec::new();
+
+        if targets.values().any(|weight| *weight < 0.0) {
+            return Err("Target weights cannot be negative".to_string());
+        }
+        let target_total: f64 = targets.values().sum();
+        if target_total <= 0.0 {
+            return Err("Target weights must add up to more than 0%".to_string());
+        }
+        if (target_total - 100.0).abs() > WEIGHT_TOLERANCE {
+            warnings.push(format!(
+                "Target weights add up to {:.2}%, they were scaled to 100%",
+                target_total
+            ));
+        }
+
+        let allocation_service = AllocationService::new();
+        let mut category_values: HashMap<String, f64> = HashMap::new();
+        let mut holding_categories = Vec::new();
+        for holding in holdings {
+            let category = allocation_service.get_primary_group(holding, group_by);
+            *category_values.entry(category.clone()).or_insert(0.0) +=
+                holding.market_value_converted;
+            holding_categories.push((holding, category));
+        }
+
+        for category in category_values.keys() {
+            if !targets.contains_key(category) {
+                warnings.push(format!("{} has no target weight and is sold off", category));
+            }
+        }
+        for (category, weight) in targets {
+            if *weight > 0.0 && !category_values.contains_key(category) {
+                warnings.push(format!("No holding to buy for {}", category));
+            }
+        }
+
+        let holdings_value: f64 = category_values.values().sum();
+        let investable_value = (holdings_value + available_cash - constraints.cash_buffer).max(0.0);
+
+        let mut trades = Vec::new();
+        let mut projected_cash = available_cash;
+        for (holding, category) in holding_categories {
+            let market_price = match holding.market_price {
+                Some(price) if price > 0.0 && holding.market_value_converted > 0.0 => price,
+                _ => {
+                    warnings.push(format!(
+                        "{} has no market price and is left out",
+                        holding.symbol
+                    ));
+                    continue;
+                }
+            };
+
+            let category_value = category_values[&category];
+            let target_weight = targets.get(&category).copied().unwrap_or(0.0) / target_total;
+            let trade_value = (investable_value * target_weight - category_value)
+                * (holding.market_value_converted / category_value);
+
+            let mut quantity = trade_value / holding.market_value_converted * holding.quantity;
+            if constraints.whole_shares {
+                // Rounding toward zero never spends more than the computed amount
+                quantity = quantity.trunc();
+            }
+            let quantity = quantity.max(-holding.quantity);
+            let value = quantity / holding.quantity * holding.market_value_converted;
+            if quantity == 0.0 || value.abs() < constraints.min_trade_value {
+                continue;
+            }
+
+            projected_cash -= value;
+            trades.push(RebalanceTrade {
+                account_id: holding
+                    .account
+                    .as_ref()
+                    .map(|a| a.id.clone())
+                    .unwrap_or_default(),
+                asset_id: holding.symbol.clone(),
+                category,
+                activity_type: if quantity > 0.0 { "BUY" } else { "SELL" }.to_string(),
+                quantity: quantity.abs(),
+                unit_price: market_price,
+                value: value.abs(),
+            });
+        }
+
+        // Sells first, they fund the buys
+        trades.sort_by(|a, b| {
+            (a.activity_type == "BUY")
+                .cmp(&(b.activity_type == "BUY"))
+                .then(b.value.total_cmp(&a.value))
+        });
+
+        Ok(RebalancePlan {
+            trades,
+            projected_cash,
+            warnings,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Performance;
+
+    fn holding(symbol: &str, asset_class: &str, quantity: f64, market_price: f64) -> Holding {
+        let market_value = quantity * market_price;
+        Holding {
+            id: symbol.to_string(),
+            symbol: symbol.to_string(),
+            symbol_name: None,
+            holding_type: "Equity".to_string(),
+            quantity,
+            currency: "USD".to_string(),
+            base_currency: "USD".to_string(),
+            market_price: Some(market_price),
+            average_cost: Some(market_price),
+            market_value,
+            book_value: market_value,
+            market_value_converted: market_value,
+            book_value_converted: market_value,
+            performance: Performance {
+                total_gain_percent: 0.0,
+                total_gain_amount: 0.0,
+                total_gain_amount_converted: 0.0,
+                day_gain_percent: None,
+                day_gain_amount: None,
+                day_gain_amount_converted: None,
+            },
+            account: None,
+            asset_class: Some(asset_class.to_string()),
+            asset_sub_class: None,
+            sectors: None,
+            tags: Vec::new(),
+            quote_date: None,
+            is_quote_stale: false,
+            fx_error: None,
+            fx_derived: false,
+        }
+    }
+
+    fn targets(weights: &[(&str, f64)]) -> HashMap<String, f64> {
+        weights
+            .iter()
+            .map(|(category, weight)| (category.to_string(), *weight))
+            .collect()
+    }
+
+    fn trade<'a>(plan: &'a RebalancePlan, asset_id: &str) -> &'a RebalanceTrade {
+        plan.trades.iter().find(|t| t.asset_id == asset_id).unwrap()
+    }
+
+    #[test]
+    fn target_weights_are_scaled_to_100_percent() {
+        let holdings = [
+            holding("VTI", "Equity", 60.0, 10.0),
+            holding("BND", "Bond", 40.0, 10.0),
+        ];
+
+        // 30/50 is read as 37.5% and 62.5%
+        let plan = RebalancingService::new()
+            .calculate_rebalance(
+                &holdings,
+                0.0,
+                &targets(&[("Equity", 30.0), ("Bond", 50.0)]),
+                AllocationGroup::AssetClass,
+                &RebalanceConstraints::default(),
+            )
+            .unwrap();
+
+        assert_eq!(plan.trades.len(), 2);
+        assert_eq!(plan.trades[0].activity_type, "SELL");
+        assert!((trade(&plan, "VTI").quantity - 22.5).abs() < 1e-9);
+        assert_eq!(trade(&plan, "BND").activity_type, "BUY");
+        assert!((trade(&plan, "BND").value - 225.0).abs() < 1e-9);
+        assert!(plan.projected_cash.abs() < 1e-9);
+        assert!(plan.warnings[0].contains("80.00%"), "{:?}", plan.warnings);
+    }
+
+    #[test]
+    fn invalid_target_weights_are_rejected() {
+        let service = RebalancingService::new();
+        let holdings = [holding("VTI", "Equity", 60.0, 10.0)];
+        let rebalance = |weights: &[(&str, f64)]| {
+            service.calculate_rebalance(
+                &holdings,
+                0.0,
+                &targets(weights),
+                AllocationGroup::AssetClass,
+                &RebalanceConstraints::default(),
+            )
+        };
+
+        assert!(rebalance(&[("Equity", 110.0), ("Bond", -10.0)]).is_err());
+        assert!(rebalance(&[("Equity", 0.0)]).is_err());
+    }
+
+    #[test]
+    fn constraints_round_and_skip_small_trades() {
+        let holdings = [
+            holding("VTI", "Equity", 10.0, 30.0),
+            holding("BND", "Bond", 10.0, 30.0),
+            holding("GLD", "Commodity", 1.0, 5.0),
+        ];
+
+        let plan = RebalancingService::new()
+            .calculate_rebalance(
+                &holdings,
+                100.0,
+                &targets(&[("Equity", 50.0), ("Bond", 50.0)]),
+                AllocationGroup::AssetClass,
+                &RebalanceConstraints {
+                    whole_shares: true,
+                    min_trade_value: 10.0,
+                    cash_buffer: 5.0,
+                },
+            )
+            .unwrap();
+
+        // 350 to invest by category, 50 more than held or 1.67 shares of each
+        assert_eq!(trade(&plan, "VTI").quantity, 1.0);
+        assert_eq!(trade(&plan, "BND").quantity, 1.0);
+        // Selling the 5 of gold falls under the minimum trade value
+        assert!(plan.trades.iter().all(|t| t.asset_id != "GLD"));
+        assert!(plan
+            .warnings
+            .iter()
+            .any(|warning| warning == "Commodity has no target weight and is sold off"));
+        assert!((plan.projected_cash - 40.0).abs() < 1e-9);
+    }
+}
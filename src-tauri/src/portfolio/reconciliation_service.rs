@@ -0,0 +1,299 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::NaiveDate;
+use csv::ReaderBuilder;
+use diesel::SqliteConnection;
+
+use crate::activity::activity_service::ActivityService;
+use crate::asset::asset_service::AssetService;
+use crate::asset::symbol_normalization;
+use crate::models::{
+    Activity, AssetLookup, HoldingDiscrepancy, HoldingSnapshotRow, HoldingsReconciliation,
+    NewActivity,
+};
+use crate::settings::read_only_mode;
+
+// Comment of the generated activities, they adjust the history and are not broker trades
+pub const RECONCILIATION_COMMENT: &str = "Holdings snapshot reconciliation";
+
+// Quantity difference below which a position matches the snapshot
+const QUANTITY_TOLERANCE: f64 = 1e-6;
+
+pub struct ReconciliationService {
+    activity_service: ActivityService,
+    asset_service: AssetService,
+}
+
+impl ReconciliationService {
+    pub fn new() -> Self {
+        ReconciliationService {
+            activity_service: ActivityService::new(),
+            asset_service: AssetService::new(),
+        }
+    }
+
+    // Compare a broker holdings csv, with symbol, quantity and an optional unitPrice
+    // column, to the positions of the account on `snapshot_date`. A computed position
+    // missing from the snapshot is closed. Each discrepancy gets the BUY or SELL making
+    // the positions match, priced at the snapshot price or the close of that day, and
+    // the activities are created when `apply` is set.
+    pub async fn reconcile_holdings_snapshot(
+        &self,
+        conn: &mut SqliteConnection,
+        account_id: &str,
+        file_path: &str,
+        snapshot_date: NaiveDate,
+        apply: bool,
+    ) -> Result<HoldingsReconciliation, String> {
+        if apply {
+            read_only_mode::ensure_writable().map_err(|e| e.to_string())?;
+        }
+        let rows = parse_holdings_snapshot(file_path)?;
+        let symbol_overrides = self
+            .asset_service
+            .get_account_symbol_overrides(conn, account_id)
+            .map_err(|e| e.to_string())?;
+        let activities = self
+            .activity_service
+            .get_account_activities(conn, account_id)
+            .map_err(|e| e.to_string())?;
+        let computed_quantities = get_quantities_on_date(&activities, snapshot_date);
+
+        // Snapshot rows by resolved symbol, a symbol listed twice is summed
+        let mut snapshot: BTreeMap<String, (f64, Option<f64>)> = BTreeMap::new();
+        let mut currencies: HashMap<String, String> = HashMap::new();
+        let mut discrepancies = Vec::new();
+        for row in rows {
+            let symbol = symbol_overrides
+                .get(&symbol_normalization::normalize_symbol(&row.symbol))
+                .unwrap_or(&row.symbol);
+            match self.resolve_symbol(conn, symbol, apply).await {
+                Some((symbol, currency)) => {
+                    currencies.insert(symbol.clone(), currency);
+                    let position = snapshot.entry(symbol).or_insert((0.0, None));
+                    position.0 += row.quantity;
+                    position.1 = row.unit_price.or(position.1);
+                }
+                None => discrepancies.push(HoldingDiscrepancy {
+                    symbol: row.symbol.clone(),
+                    computed_quantity: 0.0,
+                    snapshot_quantity: row.quantity,
+                    activity: None,
+                    error: Some(format!("Symbol {} not found", row.symbol)),
+                }),
+            }
+        }
+        for symbol in computed_quantities.keys() {
+            snapshot.entry(symbol.clone()).or_insert((0.0, None));
+        }
+
+        for (symbol, (snapshot_quantity, unit_price)) in snapshot {
+            let computed_quantity = computed_quantities.get(&symbol).copied().unwrap_or(0.0);
+            let difference = snapshot_quantity - computed_quantity;
+            if difference.abs() < QUANTITY_TOLERANCE {
+                continue;
+            }
+
+            let (activity, error) = match self.get_adjustment(
+                conn,
+                account_id,
+                &symbol,
+                currencies.get(&symbol).cloned(),
+                difference,
+                unit_price,
+                snapshot_date,
+            ) {
+                Ok(activity) => (Some(activity), None),
+                Err(e) => (None, Some(e)),
+            };
+            discrepancies.push(HoldingDiscrepancy {
+                symbol,
+                computed_quantity,
+                snapshot_quantity,
+                activity,
+                error,
+            });
+        }
+
+        let created = if apply {
+            let adjustments: Vec<NewActivity> = discrepancies
+                .iter()
+                .filter_map(|discrepancy| discrepancy.activity.clone())
+                .collect();
+            self.activity_service
+                .create_activities(conn, adjustments, |_, _| {})
+                .map_err(|e| format!("Failed to create reconciling activities: {}", e))?
+        } else {
+            0
+        };
+
+        Ok(HoldingsReconciliation {
+            account_id: account_id.to_string(),
+            snapshot_date,
+            discrepancies,
+            created,
+        })
+    }
+
+    // Provider symbol of a snapshot symbol and its currency, trying the broker spellings in
+    // turn. The profile of a new symbol is only saved when the reconciliation is applied.
+    async fn resolve_symbol(
+        &self,
+        conn: &mut SqliteConnection,
+        symbol: &str,
+        apply: bool,
+    ) -> Option<(String, String)> {
+        for candidate in symbol_normalization::get_symbol_candidates(symbol) {
+            let currency = if apply {
+                self.asset_service
+                    .get_asset_profile(conn, &candidate)
+                    .await
+                    .map(|asset| asset.currency)
+            } else {
+                self.asset_service
+                    .lookup_asset_profile(conn, &candidate)
+                    .await
+                    .map(|profile| match profile {
+                        AssetLookup::Saved(asset) => asset.currency,
+                        AssetLookup::Fetched(new_asset) => new_asset.currency,
+                    })
+            };
+            if let Ok(currency) = currency {
+                return Some((candidate, currency));
+            }
+        }
+        None
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn get_adjustment(
+        &self,
+        conn: &mut SqliteConnection,
+        account_id: &str,
+        symbol: &str,
+        currency: Option<String>,
+        difference: f64,
+        unit_price: Option<f64>,
+        snapshot_date: NaiveDate,
+    ) -> Result<NewActivity, String> {
+        // Symbols only held in the account are stored already
+        let currency = match currency {
+            Some(currency) => currency,
+            None => {
+                self.asset_service
+                    .get_asset_by_id(conn, symbol)
+                    .map_err(|_| format!("Asset {} not found", symbol))?
+                    .currency
+            }
+        };
+        let unit_price = match unit_price {
+            Some(unit_price) => unit_price,
+            None => self
+                .asset_service
+                .get_quote_on_date(conn, symbol, snapshot_date)
+                .map(|quote| quote.close)
+                .map_err(|_| format!("No price for {} on {}", symbol, snapshot_date))?,
+        };
+
+        Ok(NewActivity {
+            id: Some(uuid::Uuid::new_v4().to_string()),
+            account_id: account_id.to_string(),
+            asset_id: symbol.to_string(),
+            activity_type: if difference > 0.0 { "BUY" } else { "SELL" }.to_string(),
+            activity_date: snapshot_date.format("%Y-%m-%d 00:00:00").to_string(),
+            quantity: difference.abs(),
+            unit_price,
+            currency,
+            fee: 0.0,
+            is_draft: false,
+            comment: Some(RECONCILIATION_COMMENT.to_string()),
+            source_activity_id: None,
+        })
+    }
+}
+
+fn parse_holdings_snapshot(file_path: &str) -> Result<Vec<HoldingSnapshotRow>, String> {
+    let mut rdr = ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_path(file_path)
+        .map_err(|e| e.to_string())?;
+    rdr.deserialize::<HoldingSnapshotRow>()
+        .enumerate()
+        .map(|(index, row)| row.map_err(|e| format!("{}. Line: {}", e, index + 2)))
+        .collect()
+}
+
+// Security quantities of the activities dated on or before `date`, cash excluded
+fn get_quantities_on_date(activities: &[Activity], date: NaiveDate) -> HashMap<String, f64> {
+    let mut activities: Vec<&Activity> = activities
+        .iter()
+        .filter(|activity| {
+            activity.activity_date.date() <= date && !activity.asset_id.starts_with("$CASH-")
+        })
+        .collect();
+    activities.sort_by_key(|activity| activity.activity_date);
+
+    let mut quantities: HashMap<String, f64> = HashMap::new();
+    for activity in activities {
+        let quantity = quantities.entry(activity.asset_id.clone()).or_insert(0.0);
+        match activity.activity_type.as_str() {
+            "BUY" => *quantity += activity.quantity,
+            "SPIN_OFF_IN" => *quantity += activity.get_spin_off_shares().0,
+            "SELL" => *quantity -= activity.quantity,
+            "SPLIT" if activity.quantity > 0.0 => *quantity *= activity.quantity,
+            _ => {}
+        }
+    }
+    quantities.retain(|_, quantity| quantity.abs() >= QUANTITY_TOLERANCE);
+    quantities
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::activities;
+    use crate::test_utils::{self, TestDatabase};
+    use diesel::prelude::*;
+
+    fn count_activities(conn: &mut SqliteConnection) -> i64 {
+        activities::table.count().get_result(conn).unwrap()
+    }
+
+    #[test]
+    fn a_preview_writes_nothing_and_applying_creates_the_adjustments() {
+        let mut db = TestDatabase::new();
+        test_utils::insert_account(&mut db.conn, "account", "USD");
+        test_utils::insert_asset(&mut db.conn, "AAPL", "USD");
+        test_utils::insert_activity(
+            &mut db.conn,
+            test_utils::new_activity("account", "AAPL", "BUY", "2024-01-02", 10.0, 100.0),
+        );
+        let file_path =
+            test_utils::write_temp_file("csv", "symbol,quantity,unitPrice\nAAPL,12,150\n");
+        let snapshot_date = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+        let service = ReconciliationService::new();
+        let reconcile = |conn: &mut SqliteConnection, apply| {
+            tauri::async_runtime::block_on(service.reconcile_holdings_snapshot(
+                conn,
+                "account",
+                &file_path,
+                snapshot_date,
+                apply,
+            ))
+            .unwrap()
+        };
+
+        let preview = reconcile(&mut db.conn, false);
+        let adjustment = preview.discrepancies[0].activity.clone().unwrap();
+        assert_eq!(
+            (adjustment.activity_type.as_str(), adjustment.quantity),
+            ("BUY", 2.0)
+        );
+        assert_eq!(preview.created, 0);
+        assert_eq!(count_activities(&mut db.conn), 1);
+
+        let applied = reconcile(&mut db.conn, true);
+        assert_eq!(applied.created, 1);
+        assert_eq!(count_activities(&mut db.conn), 2);
+    }
+}
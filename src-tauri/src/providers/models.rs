@@ -314,7 +314,7 @@ pub struct TopHoldingsSectorWeighting {
 #[derive(Debug)]
 pub enum AssetClass {
     Equity,
-    // FixedIncome,
+    FixedIncome,
     // Cash,
     // RealEstate,
     Commodity,
@@ -328,7 +328,7 @@ impl fmt::Display for AssetClass {
             AssetClass::Cryptocurrency => "Cryptocurrency",
             AssetClass::Equity => "Equity",
             AssetClass::Commodity => "Commodity",
-            // AssetClass::FixedIncome => "Fixed Income",
+            AssetClass::FixedIncome => "Fixed Income",
             // AssetClass::Cash => "Cash",
             // AssetClass::RealEstate => "Real Estate",
         };
@@ -345,6 +345,7 @@ pub enum AssetSubClass {
     Commodity,
     PreciousMetal,
     MutualFund,
+    Bond,
 }
 impl fmt::Display for AssetSubClass {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -356,6 +357,7 @@ impl fmt::Display for AssetSubClass {
             AssetSubClass::Commodity => "Commodity",
             AssetSubClass::PreciousMetal => "Precious Metal",
             AssetSubClass::MutualFund => "Mutual Fund",
+            AssetSubClass::Bond => "Bond",
         };
         write!(f, "{}", display_string)
     }
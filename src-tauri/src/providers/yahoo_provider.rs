@@ -365,6 +365,7 @@ impl YahooProvider {
                 (AssetClass::Commodity, asset_sub_class)
             }
             "mutualfund" => (AssetClass::Equity, AssetSubClass::MutualFund),
+            "bond" => (AssetClass::FixedIncome, AssetSubClass::Bond),
             _ => (AssetClass::Alternative, AssetSubClass::Alternative),
         }
     }
@@ -30,6 +30,8 @@ diesel::table! {
         comment -> Nullable<Text>,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        deleted_at -> Nullable<Timestamp>,
+        source_activity_id -> Nullable<Text>,
     }
 }
 
@@ -87,6 +89,10 @@ diesel::table! {
         theme -> Text,
         font -> Text,
         base_currency -> Text,
+        quantity_precision -> Integer,
+        price_precision -> Integer,
+        read_only -> Bool,
+        activity_undo_days -> Integer,
     }
 }
 
@@ -109,14 +115,104 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    symbol_overrides (id) {
+        id -> Text,
+        symbol -> Text,
+        account_id -> Nullable<Text>,
+        provider_symbol -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    asset_tags (asset_id, tag) {
+        asset_id -> Text,
+        tag -> Text,
+    }
+}
+
+diesel::table! {
+    cash_interest_tiers (id) {
+        id -> Text,
+        account_id -> Text,
+        min_balance -> Double,
+        annual_rate -> Double,
+    }
+}
+
+diesel::table! {
+    drip_settings (account_id, asset_id) {
+        account_id -> Text,
+        asset_id -> Text,
+    }
+}
+
+diesel::table! {
+    manual_exchange_rates (id) {
+        id -> Text,
+        from_currency -> Text,
+        to_currency -> Text,
+        rate -> Double,
+        start_date -> Date,
+        end_date -> Date,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    option_contracts (asset_id) {
+        asset_id -> Text,
+        underlying_asset_id -> Text,
+        option_type -> Text,
+        strike_price -> Double,
+        expiration_date -> Date,
+    }
+}
+
+diesel::table! {
+    import_mappings (account_id) {
+        account_id -> Text,
+        decimal_separator -> Text,
+        thousands_separator -> Text,
+        date_format -> Text,
+    }
+}
+
+diesel::table! {
+    quote_staleness_policies (asset_type) {
+        asset_type -> Text,
+        max_age_days -> Integer,
+    }
+}
+
 diesel::joinable!(accounts -> platforms (platform_id));
 diesel::joinable!(activities -> accounts (account_id));
 diesel::joinable!(activities -> assets (asset_id));
 diesel::joinable!(quotes -> assets (symbol));
 diesel::joinable!(goals_allocation -> goals (goal_id));
+diesel::joinable!(symbol_overrides -> accounts (account_id));
+diesel::joinable!(asset_tags -> assets (asset_id));
+diesel::joinable!(cash_interest_tiers -> accounts (account_id));
+diesel::joinable!(drip_settings -> accounts (account_id));
+diesel::joinable!(drip_settings -> assets (asset_id));
+diesel::joinable!(option_contracts -> assets (asset_id));
+diesel::joinable!(import_mappings -> accounts (account_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
-    accounts, activities, assets, platforms, quotes, settings,
+    accounts,
+    activities,
+    asset_tags,
+    assets,
+    cash_interest_tiers,
+    drip_settings,
+    import_mappings,
+    option_contracts,
+    platforms,
+    quote_staleness_policies,
+    quotes,
+    settings,
+    symbol_overrides,
 );
 
 diesel::allow_tables_to_appear_in_same_query!(goals, goals_allocation);
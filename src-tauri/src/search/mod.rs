@@ -0,0 +1,2 @@
+pub mod search_commands;
+pub mod search_service;
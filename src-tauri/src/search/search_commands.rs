@@ -0,0 +1,20 @@
+use crate::models::SearchResult;
+use crate::search::search_service::SearchService;
+use crate::AppState;
+use tauri::State;
+
+// Results kept for each kind when no limit is given
+const DEFAULT_SEARCH_LIMIT: usize = 5;
+
+#[tauri::command]
+pub fn search(
+    query: String,
+    limit: Option<usize>,
+    state: State<AppState>,
+) -> Result<Vec<SearchResult>, String> {
+    println!("Searching for: {}", query);
+    let mut conn = state.conn.lock().unwrap();
+    SearchService::new()
+        .search(&mut conn, &query, limit.unwrap_or(DEFAULT_SEARCH_LIMIT))
+        .map_err(|e| format!("Failed to search: {}", e))
+}
@@ -0,0 +1,225 @@
+use diesel::SqliteConnection;
+
+use crate::account::account_service::AccountService;
+use crate::activity::activity_service::ActivityService;
+use crate::asset::asset_service::AssetService;
+use crate::goal::goal_service::GoalService;
+use crate::models::SearchResult;
+
+// Scores of a text matching the query as a whole, at its start, at the start of one of
+// its words and anywhere in it
+const EXACT_MATCH_SCORE: f64 = 1.0;
+const PREFIX_MATCH_SCORE: f64 = 0.75;
+const WORD_MATCH_SCORE: f64 = 0.5;
+const PARTIAL_MATCH_SCORE: f64 = 0.25;
+
+pub struct SearchService {
+    account_service: AccountService,
+    activity_service: ActivityService,
+    asset_service: AssetService,
+    goal_service: GoalService,
+}
+
+impl SearchService {
+    pub fn new() -> Self {
+        SearchService {
+            account_service: AccountService::new(),
+            activity_service: ActivityService::new(),
+            asset_service: AssetService::new(),
+            goal_service: GoalService::new(),
+        }
+    }
+
+    // Accounts by name, assets by symbol or name, activities by comment or symbol and goals
+    // by title matching `query`, ignoring case. Keeps the `limit` best results of each
+    // kind and sorts them all by score.
+    pub fn search(
+        &self,
+        conn: &mut SqliteConnection,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, diesel::result::Error> {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let accounts = self
+            .account_service
+            .get_accounts(conn)?
+            .into_iter()
+            .filter_map(|account| {
+                let score = score_text(&account.name, &query);
+                (score > 0.0).then_some(SearchResult::Account { score, account })
+            });
+        let mut results = take_best(accounts, limit);
+
+        let assets = self
+            .asset_service
+            .get_assets(conn)?
+            .into_iter()
+            .filter_map(|asset| {
+                let score = score_text(&asset.symbol, &query).max(score_text(
+                    asset.name.as_deref().unwrap_or_default(),
+                    &query,
+                ));
+                (score > 0.0).then_some(SearchResult::Asset { score, asset })
+            });
+        results.extend(take_best(assets, limit));
+
+        let activities = self
+            .activity_service
+            .get_activities(conn)?
+            .into_iter()
+            .filter_map(|activity| {
+                let score = score_text(&activity.asset_id, &query).max(score_text(
+                    activity.comment.as_deref().unwrap_or_default(),
+                    &query,
+                ));
+                (score > 0.0).then_some(SearchResult::Activity { score, activity })
+            });
+        results.extend(take_best(activities, limit));
+
+        let goals = self
+            .goal_service
+            .get_goals(conn)?
+            .into_iter()
+            .filter_map(|goal| {
+                let score = score_text(&goal.title, &query);
+                (score > 0.0).then_some(SearchResult::Goal { score, goal })
+            });
+        results.extend(take_best(goals, limit));
+
+        results.sort_by(|a, b| b.score().total_cmp(&a.score()));
+        Ok(results)
+    }
+}
+
+// Relevance of `text` for the lowercase `query`, 0 when it does not match
+fn score_text(text: &str, query: &str) -> f64 {
+    let text = text.to_lowercase();
+    if text == query {
+        EXACT_MATCH_SCORE
+    } else if text.starts_with(query) {
+        PREFIX_MATCH_SCORE
+    } else if text
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| word.starts_with(query))
+    {
+        WORD_MATCH_SCORE
+    } else if text.contains(query) {
+        PARTIAL_MATCH_SCORE
+    } else {
+        0.0
+    }
+}
+
+// The `limit` results with the highest score, stable for equal scores
+fn take_best(results: impl Iterator<Item = SearchResult>, limit: usize) -> Vec<SearchResult> {
+    let mut results: Vec<SearchResult> = results.collect();
+    results.sort_by(|a, b| b.score().total_cmp(&a.score()));
+    results.truncate(limit);
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::assets;
+    use crate::test_utils::{self, TestDatabase};
+    use diesel::prelude::*;
+
+    #[test]
+    fn exact_matches_score_above_prefix_word_and_partial_ones() {
+        let scores: Vec<f64> = ["Apple", "Apple Inc.", "Big Apple Fund", "Pineapple", "Pear"]
+            .iter()
+            .map(|text| score_text(text, "apple"))
+            .collect();
+
+        assert_eq!(
+            scores,
+            [
+                EXACT_MATCH_SCORE,
+                PREFIX_MATCH_SCORE,
+                WORD_MATCH_SCORE,
+                PARTIAL_MATCH_SCORE,
+                0.0
+            ]
+        );
+    }
+
+    fn setup() -> TestDatabase {
+        let mut db = TestDatabase::new();
+        test_utils::insert_account(&mut db.conn, "Apple", "USD");
+        test_utils::insert_account(&mut db.conn, "Big Apple Fund", "USD");
+        test_utils::insert_account(&mut db.conn, "Savings", "USD");
+        test_utils::insert_asset(&mut db.conn, "AAPL", "USD");
+        diesel::update(assets::table.find("AAPL"))
+            .set(assets::name.eq("Apple Inc."))
+            .execute(&mut db.conn)
+            .unwrap();
+        test_utils::insert_asset(&mut db.conn, "PINEAPPLE", "USD");
+        test_utils::insert_asset(&mut db.conn, "MSFT", "USD");
+        db
+    }
+
+    // Kind, name and score of each result
+    fn summary(results: &[SearchResult]) -> Vec<(&str, &str, f64)> {
+        results
+            .iter()
+            .map(|result| match result {
+                SearchResult::Account { score, account } => {
+                    ("account", account.name.as_str(), *score)
+                }
+                SearchResult::Asset { score, asset } => ("asset", asset.symbol.as_str(), *score),
+                SearchResult::Activity { score, activity } => {
+                    ("activity", activity.id.as_str(), *score)
+                }
+                SearchResult::Goal { score, goal } => ("goal", goal.title.as_str(), *score),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn accounts_and_assets_are_returned_by_relevance() {
+        let mut db = setup();
+
+        let results = SearchService::new()
+            .search(&mut db.conn, "  APPLE ", 10)
+            .unwrap();
+
+        assert_eq!(
+            summary(&results),
+            [
+                ("account", "Apple", EXACT_MATCH_SCORE),
+                ("asset", "AAPL", PREFIX_MATCH_SCORE),
+                ("account", "Big Apple Fund", WORD_MATCH_SCORE),
+                ("asset", "PINEAPPLE", PARTIAL_MATCH_SCORE),
+            ]
+        );
+        let tagged = serde_json::to_value(&results[1]).unwrap();
+        assert_eq!(tagged["type"], "ASSET");
+        assert_eq!(tagged["asset"]["symbol"], "AAPL");
+    }
+
+    #[test]
+    fn each_kind_is_capped_to_its_best_results() {
+        let mut db = setup();
+
+        let results = SearchService::new()
+            .search(&mut db.conn, "apple", 1)
+            .unwrap();
+
+        assert_eq!(
+            summary(&results),
+            [
+                ("account", "Apple", EXACT_MATCH_SCORE),
+                ("asset", "AAPL", PREFIX_MATCH_SCORE),
+            ]
+        );
+        assert!(SearchService::new()
+            .search(&mut db.conn, " ", 10)
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -1,3 +1,4 @@
+pub mod read_only_mode;
 pub mod settings_commands;
 pub mod settings_service;
 
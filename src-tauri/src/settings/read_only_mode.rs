@@ -0,0 +1,101 @@
+use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use diesel::result::{DatabaseErrorKind, Error};
+use diesel::SqliteConnection;
+
+use crate::settings::SettingsService;
+
+// While read-only mode is on, services refuse every change to the user data with the
+// same error. Reads, valuations and market data syncs are unaffected.
+// The mode saved in the settings can be turned on and off at runtime, the one forced by
+// WEALTHFOLIO_READ_ONLY at startup always wins over it.
+static FORCED: AtomicBool = AtomicBool::new(false);
+static FROM_SETTINGS: AtomicBool = AtomicBool::new(false);
+
+pub const READ_ONLY_MESSAGE: &str = "Wealthfolio is in read-only mode";
+pub const FORCED_MESSAGE: &str = "Read-only mode is forced by WEALTHFOLIO_READ_ONLY";
+
+// Read-only mode starts on when saved in the settings or when WEALTHFOLIO_READ_ONLY is set
+pub fn init(conn: &mut SqliteConnection) {
+    let from_env = env::var("WEALTHFOLIO_READ_ONLY")
+        .map(|value| matches!(value.to_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false);
+    FORCED.store(from_env, Ordering::SeqCst);
+
+    let from_settings = SettingsService::new()
+        .get_settings(conn)
+        .map(|settings| settings.read_only)
+        .unwrap_or(false);
+    set_enabled(from_settings);
+}
+
+pub fn is_enabled() -> bool {
+    is_forced() || FROM_SETTINGS.load(Ordering::SeqCst)
+}
+
+pub fn is_forced() -> bool {
+    FORCED.load(Ordering::SeqCst)
+}
+
+// Mode of the settings, it can't turn off a forced mode
+pub fn set_enabled(enabled: bool) {
+    FROM_SETTINGS.store(enabled, Ordering::SeqCst);
+}
+
+// Called first by every mutating service method, before the database is touched
+pub fn ensure_writable() -> Result<(), Error> {
+    if is_enabled() {
+        Err(read_only_error(READ_ONLY_MESSAGE))
+    } else {
+        Ok(())
+    }
+}
+
+pub fn read_only_error(message: &str) -> Error {
+    Error::DatabaseError(
+        DatabaseErrorKind::ReadOnlyTransaction,
+        Box::new(message.to_string()),
+    )
+}
+
+// Both modes off, the tests share them
+#[cfg(test)]
+pub fn reset() {
+    FORCED.store(false, Ordering::SeqCst);
+    set_enabled(false);
+}
+
+#[cfg(test)]
+pub fn force() {
+    FORCED.store(true, Ordering::SeqCst);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+
+    #[test]
+    fn the_forced_mode_wins_over_the_settings() {
+        let _lock = test_utils::lock_global_state();
+        force();
+
+        set_enabled(false);
+        assert!(is_enabled());
+        assert!(ensure_writable().is_err());
+
+        reset();
+        assert!(ensure_writable().is_ok());
+    }
+
+    #[test]
+    fn the_settings_mode_turns_on_and_off() {
+        let _lock = test_utils::lock_global_state();
+        set_enabled(true);
+        assert!(ensure_writable().is_err());
+
+        set_enabled(false);
+        assert!(ensure_writable().is_ok());
+    }
+}
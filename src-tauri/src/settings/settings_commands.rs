@@ -1,7 +1,8 @@
-use crate::models::{NewSettings, Settings};
-use crate::settings::settings_service;
+use crate::db;
+use crate::models::{DatabaseStats, NewSettings, Settings};
+use crate::settings::{read_only_mode, settings_service};
 use crate::AppState;
-use tauri::State;
+use tauri::{AppHandle, Manager, State};
 
 #[tauri::command]
 pub fn get_settings(state: State<AppState>) -> Result<Settings, String> {
@@ -38,3 +39,65 @@ pub fn update_currency(currency: String, state: State<AppState>) -> Result<Setti
         .get_settings(&mut conn)
         .map_err(|e| format!("Failed to load settings: {}", e))
 }
+
+#[tauri::command]
+pub fn update_read_only(read_only: bool, state: State<AppState>) -> Result<Settings, String> {
+    println!("Setting read-only mode: {}", read_only);
+    let mut conn = state.conn.lock().unwrap();
+    let service = settings_service::SettingsService::new();
+    service
+        .update_read_only(&mut conn, read_only)
+        .map_err(|e| format!("Failed to update settings: {}", e))?;
+    service
+        .get_settings(&mut conn)
+        .map_err(|e| format!("Failed to load settings: {}", e))
+}
+
+#[tauri::command]
+pub fn compact_database(vacuum: bool, state: State<AppState>) -> Result<u64, String> {
+    println!("Compacting database..."); // Log message
+    let mut conn = state.conn.lock().unwrap();
+    db::compact_database(&mut conn, vacuum)
+        .map_err(|e| format!("Failed to compact database: {}", e))
+}
+
+#[tauri::command]
+pub fn get_database_stats(state: State<AppState>) -> Result<DatabaseStats, String> {
+    println!("Fetching database stats..."); // Log message
+    let mut conn = state.conn.lock().unwrap();
+    db::get_database_stats(&mut conn).map_err(|e| format!("Failed to load database stats: {}", e))
+}
+
+#[tauri::command]
+pub fn get_profiles() -> Vec<String> {
+    println!("Fetching profiles...");
+    db::get_profiles()
+}
+
+#[tauri::command]
+pub fn get_active_profile() -> String {
+    db::get_active_profile()
+}
+
+// Reconnect the app to the database of `profile` and tell the windows to reload their
+// data. Holding the connection lock keeps other commands off the previous database.
+#[tauri::command]
+pub fn switch_profile(
+    profile: String,
+    state: State<AppState>,
+    app_handle: AppHandle,
+) -> Result<Settings, String> {
+    println!("Switching to profile: {}", profile);
+    let mut conn = state.conn.lock().unwrap();
+    db::set_active_profile(&profile)?;
+    *conn = db::establish_connection();
+    read_only_mode::init(&mut conn);
+
+    let settings = settings_service::SettingsService::new()
+        .get_settings(&mut conn)
+        .map_err(|e| format!("Failed to load settings: {}", e))?;
+    app_handle
+        .emit_all("PROFILE_CHANGED", &profile)
+        .unwrap_or_else(|e| println!("Failed to emit profile changed: {}", e));
+    Ok(settings)
+}
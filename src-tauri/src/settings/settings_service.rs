@@ -2,6 +2,7 @@
 
 use crate::models::{NewSettings, Settings};
 use crate::schema::settings::dsl::*;
+use crate::settings::read_only_mode;
 use diesel::prelude::*;
 use diesel::sqlite::SqliteConnection;
 
@@ -18,7 +19,14 @@ impl SettingsService {
         &self,
         conn: &mut SqliteConnection,
     ) -> Result<Settings, diesel::result::Error> {
-        settings.find(self.settings_id).first::<Settings>(conn)
+        // A mode forced by the environment shows as on
+        settings
+            .find(self.settings_id)
+            .first::<Settings>(conn)
+            .map(|mut active_settings| {
+                active_settings.read_only |= read_only_mode::is_forced();
+                active_settings
+            })
     }
 
     pub fn update_settings(
@@ -26,6 +34,7 @@ impl SettingsService {
         conn: &mut SqliteConnection,
         new_setting: &NewSettings,
     ) -> Result<(), diesel::result::Error> {
+        read_only_mode::ensure_writable()?;
         // First, try to update
         let rows_affected = diesel::update(settings.find(self.settings_id))
             .set(new_setting)
@@ -42,14 +51,51 @@ impl SettingsService {
         Ok(())
     }
 
+    // Saved so the mode survives restarts, allowed while read-only to turn it back off
+    // unless the mode is forced by the environment
+    pub fn update_read_only(
+        &self,
+        conn: &mut SqliteConnection,
+        enabled: bool,
+    ) -> Result<(), diesel::result::Error> {
+        if !enabled && read_only_mode::is_forced() {
+            return Err(read_only_mode::read_only_error(
+                read_only_mode::FORCED_MESSAGE,
+            ));
+        }
+        diesel::update(settings.find(self.settings_id))
+            .set(read_only.eq(enabled))
+            .execute(conn)?;
+        read_only_mode::set_enabled(enabled);
+        Ok(())
+    }
+
     pub fn update_base_currency(
         &self,
         conn: &mut SqliteConnection,
         new_base_currency: &str,
     ) -> Result<(), diesel::result::Error> {
+        read_only_mode::ensure_writable()?;
         diesel::update(settings.find(self.settings_id))
             .set(base_currency.eq(new_base_currency))
             .execute(conn)?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestDatabase;
+
+    #[test]
+    fn a_forced_read_only_mode_cant_be_turned_off() {
+        let mut db = TestDatabase::new();
+        read_only_mode::force();
+
+        let result = SettingsService::new().update_read_only(&mut db.conn, false);
+
+        assert!(result.is_err());
+        assert!(read_only_mode::is_enabled());
+    }
+}
@@ -0,0 +1,175 @@
+use std::sync::{Mutex, MutexGuard};
+
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+
+use crate::db;
+use crate::models::{Activity, NewAccount, NewActivity, NewAsset, NewSettings};
+use crate::schema::{accounts, activities, assets, quotes, settings};
+use crate::settings::read_only_mode;
+
+// Read-only mode is global to the process, so the tests using a database run one at a
+// time and a test switching it on can't make the others fail
+static GLOBAL_STATE_LOCK: Mutex<()> = Mutex::new(());
+
+// Held by the tests depending on the read-only mode, which starts off
+pub fn lock_global_state() -> MutexGuard<'static, ()> {
+    let lock = GLOBAL_STATE_LOCK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    read_only_mode::reset();
+    lock
+}
+
+pub struct TestDatabase {
+    pub conn: SqliteConnection,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl TestDatabase {
+    pub fn new() -> Self {
+        TestDatabase {
+            conn: db::establish_test_connection(),
+            _lock: lock_global_state(),
+        }
+    }
+}
+
+// Settings with the default precisions, valuations need them
+pub fn insert_settings(conn: &mut SqliteConnection, base_currency: &str) {
+    diesel::insert_into(settings::table)
+        .values(NewSettings {
+            theme: "light",
+            font: "font-mono",
+            base_currency,
+            quantity_precision: 2,
+            price_precision: 2,
+            activity_undo_days: 7,
+        })
+        .execute(conn)
+        .expect("Failed to insert the test settings");
+}
+
+pub fn insert_account(conn: &mut SqliteConnection, account_id: &str, currency: &str) {
+    diesel::insert_into(accounts::table)
+        .values(NewAccount {
+            id: Some(account_id.to_string()),
+            name: account_id.to_string(),
+            account_type: "SECURITIES".to_string(),
+            group: None,
+            currency: currency.to_string(),
+            is_default: false,
+            is_active: true,
+            platform_id: None,
+        })
+        .execute(conn)
+        .expect("Failed to insert the test account");
+}
+
+pub fn insert_asset(conn: &mut SqliteConnection, asset_id: &str, currency: &str) {
+    diesel::insert_into(assets::table)
+        .values(NewAsset {
+            id: asset_id.to_string(),
+            symbol: asset_id.to_string(),
+            currency: currency.to_string(),
+            data_source: "Yahoo".to_string(),
+            ..Default::default()
+        })
+        .execute(conn)
+        .expect("Failed to insert the test asset");
+}
+
+// Activity in USD dated `activity_date` (YYYY-MM-DD), not inserted yet
+pub fn new_activity(
+    account_id: &str,
+    asset_id: &str,
+    activity_type: &str,
+    activity_date: &str,
+    quantity: f64,
+    unit_price: f64,
+) -> NewActivity {
+    NewActivity {
+        id: Some(uuid::Uuid::new_v4().to_string()),
+        account_id: account_id.to_string(),
+        asset_id: asset_id.to_string(),
+        activity_type: activity_type.to_string(),
+        activity_date: format!("{} 00:00:00", activity_date),
+        quantity,
+        unit_price,
+        currency: "USD".to_string(),
+        fee: 0.0,
+        is_draft: false,
+        comment: None,
+        source_activity_id: None,
+    }
+}
+
+// Activity in USD dated `activity_date` (YYYY-MM-DD) as loaded from the database, its id
+// is made of its type, asset and date
+pub fn activity(
+    account_id: &str,
+    asset_id: &str,
+    activity_type: &str,
+    activity_date: &str,
+    quantity: f64,
+    unit_price: f64,
+) -> Activity {
+    let activity_date = chrono::NaiveDate::parse_from_str(activity_date, "%Y-%m-%d")
+        .expect("Invalid test activity date")
+        .and_time(chrono::NaiveTime::MIN);
+    Activity {
+        id: format!("{}-{}-{}", activity_type, asset_id, activity_date.date()),
+        account_id: account_id.to_string(),
+        asset_id: asset_id.to_string(),
+        activity_type: activity_type.to_string(),
+        activity_date,
+        quantity,
+        unit_price,
+        currency: "USD".to_string(),
+        fee: 0.0,
+        is_draft: false,
+        comment: None,
+        created_at: activity_date,
+        updated_at: activity_date,
+        deleted_at: None,
+        source_activity_id: None,
+    }
+}
+
+pub fn insert_activity(conn: &mut SqliteConnection, new_activity: NewActivity) -> Activity {
+    diesel::insert_into(activities::table)
+        .values(&new_activity)
+        .returning(Activity::as_returning())
+        .get_result(conn)
+        .expect("Failed to insert the test activity")
+}
+
+// Daily quote of `symbol` on `date` (YYYY-MM-DD) closing at `close`
+pub fn insert_quote(conn: &mut SqliteConnection, symbol: &str, date: &str, close: f64) {
+    let date = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .expect("Invalid test quote date")
+        .and_time(chrono::NaiveTime::MIN);
+    diesel::insert_into(quotes::table)
+        .values((
+            quotes::id.eq(uuid::Uuid::new_v4().to_string()),
+            quotes::created_at.eq(date),
+            quotes::data_source.eq("Yahoo"),
+            quotes::date.eq(date),
+            quotes::symbol.eq(symbol),
+            quotes::open.eq(close),
+            quotes::high.eq(close),
+            quotes::low.eq(close),
+            quotes::volume.eq(0.0),
+            quotes::close.eq(close),
+            quotes::adjclose.eq(close),
+        ))
+        .execute(conn)
+        .expect("Failed to insert the test quote");
+}
+
+// Write `content` to a new file of the temporary directory, returns its path
+pub fn write_temp_file(extension: &str, content: &str) -> String {
+    let path = std::env::temp_dir().join(format!("{}.{}", uuid::Uuid::new_v4(), extension));
+    std::fs::write(&path, content).expect("Failed to write the test file");
+    path.to_string_lossy().to_string()
+}
@@ -1,6 +1,21 @@
 import { invoke } from '@tauri-apps/api';
 import * as z from 'zod';
-import { Activity, ActivityDetails, ActivityImport, ActivitySearchResponse } from '@/lib/types';
+import {
+  Activity,
+  ActivityBulkFilter,
+  ActivityBulkUpdate,
+  ActivityDetails,
+  ActivityExportFilter,
+  ActivityImportPreview,
+  ActivitySearchFilter,
+  ActivitySearchResponse,
+  DripSetting,
+  ImportActivitiesResult,
+  ImportDryRunReport,
+  ImportLocale,
+  ImportPreset,
+  ImportSelection,
+} from '@/lib/types';
 import { newActivitySchema } from '@/lib/schemas';
 
 export type NewActivity = z.infer<typeof newActivitySchema>;
@@ -9,6 +24,7 @@ interface Filters {
   accountId?: string;
   activityType?: string;
   symbol?: string;
+  tag?: string[];
 }
 
 interface Sort {
@@ -16,6 +32,13 @@ interface Sort {
   desc: boolean;
 }
 
+export interface ImportFileRequest {
+  account_id: string;
+  file_path: string;
+  preset?: ImportPreset;
+  locale?: ImportLocale;
+}
+
 export const getActivities = async (): Promise<ActivityDetails[]> => {
   try {
     const activities = await invoke('get_activities');
@@ -32,6 +55,7 @@ export const searchActivities = async (
   filters: Filters,
   searchKeyword: string,
   sort: Sort,
+  filter?: ActivitySearchFilter,
 ): Promise<ActivitySearchResponse> => {
   try {
     const result = await invoke('search_activities', {
@@ -40,6 +64,8 @@ export const searchActivities = async (
       accountIdFilter: filters?.accountId,
       activityTypeFilter: filters?.activityType,
       assetIdKeyword: searchKeyword,
+      tagFilter: filters?.tag,
+      filter,
       sort,
     });
     return result as ActivitySearchResponse;
@@ -81,18 +107,91 @@ export const deleteActivity = async (activityId: string): Promise<void> => {
   }
 };
 
+// deleteActivitiesByFilter
+export const deleteActivitiesByFilter = async (filter: ActivityBulkFilter): Promise<number> => {
+  try {
+    const deleted: number = await invoke('delete_activities_by_filter', { filter });
+    return deleted;
+  } catch (error) {
+    console.error('Error deleting activities:', error);
+    throw error;
+  }
+};
+
+// updateActivitiesByFilter
+export const updateActivitiesByFilter = async (
+  filter: ActivityBulkFilter,
+  changes: ActivityBulkUpdate,
+): Promise<number> => {
+  try {
+    const updated: number = await invoke('update_activities_by_filter', { filter, changes });
+    return updated;
+  } catch (error) {
+    console.error('Error updating activities:', error);
+    throw error;
+  }
+};
+
+// restoreActivity
+export const restoreActivity = async (activityId: string): Promise<Activity> => {
+  try {
+    const activity = await invoke('restore_activity', { activityId });
+    return activity as Activity;
+  } catch (error) {
+    console.error('Error restoring activity:', error);
+    throw error;
+  }
+};
+
+// getDripSettings
+export const getDripSettings = async (): Promise<DripSetting[]> => {
+  try {
+    const dripSettings = await invoke('get_drip_settings');
+    return dripSettings as DripSetting[];
+  } catch (error) {
+    console.error('Error fetching dividend reinvestment settings:', error);
+    throw error;
+  }
+};
+
+// setDripEnabled
+export const setDripEnabled = async (
+  dripSetting: DripSetting,
+  enabled: boolean,
+): Promise<DripSetting[]> => {
+  try {
+    const dripSettings = await invoke('set_drip_enabled', { dripSetting, enabled });
+    return dripSettings as DripSetting[];
+  } catch (error) {
+    console.error('Error updating dividend reinvestment:', error);
+    throw error;
+  }
+};
+
+// getImportLocale
+export const getImportLocale = async (accountId: string): Promise<ImportLocale | null> => {
+  try {
+    const locale: ImportLocale | null = await invoke('get_import_locale', { accountId });
+    return locale;
+  } catch (error) {
+    console.error('Error fetching import locale:', error);
+    throw error;
+  }
+};
+
 //checkActivitiesImport
 export const checkActivitiesImport = async ({
   account_id,
   file_path,
-}: {
-  account_id: string;
-  file_path: string;
-}): Promise<ActivityImport[]> => {
+  preset,
+  locale,
+}: ImportFileRequest): Promise<ActivityImportPreview> => {
   try {
-    const result: ActivityImport[] = await invoke('check_activities_import', {
+    const result: ActivityImportPreview = await invoke('check_activities_import', {
       accountId: account_id,
       filePath: file_path,
+      preset,
+      locale,
     });
     return result;
   } catch (error) {
@@ -101,7 +200,51 @@ export const checkActivitiesImport = async ({
   }
 };
 
+//dryRunActivitiesImport
+export const dryRunActivitiesImport = async ({
+  account_id,
+  file_path,
+  preset,
+  locale,
+}: ImportFileRequest): Promise<ImportDryRunReport> => {
+  try {
+    const result: ImportDryRunReport = await invoke('dry_run_activities_import', {
+      accountId: account_id,
+      filePath: file_path,
+      preset,
+      locale,
+    });
+    return result;
+  } catch (error) {
+    console.error('Error running activities import dry run:', error);
+    throw error;
+  }
+};
+
 // importActivities
+export const importActivities = async ({
+  account_id,
+  file_path,
+  preset,
+  locale,
+  selection,
+}: ImportFileRequest & { selection?: ImportSelection }): Promise<ImportActivitiesResult> => {
+  try {
+    const result: ImportActivitiesResult = await invoke('import_activities', {
+      accountId: account_id,
+      filePath: file_path,
+      preset,
+      locale,
+      selection,
+    });
+    return result;
+  } catch (error) {
+    console.error('Error importing activities:', error);
+    throw error;
+  }
+};
+
+// createActivities
 export const createActivities = async (activities: NewActivity[]): Promise<Number> => {
   try {
     const importResult: Number = await invoke('create_activities', { activities });
@@ -111,3 +254,18 @@ export const createActivities = async (activities: NewActivity[]): Promise<Numbe
     throw error;
   }
 };
+
+// exportActivities
+export const exportActivities = async (
+  filePath: string,
+  filter?: ActivityExportFilter,
+  currency?: string,
+): Promise<number> => {
+  try {
+    const exported: number = await invoke('export_activities', { filePath, filter, currency });
+    return exported;
+  } catch (error) {
+    console.error('Error exporting activities:', error);
+    throw error;
+  }
+};
@@ -0,0 +1,33 @@
+import { invoke } from '@tauri-apps/api';
+import { ManualExchangeRate, NewManualExchangeRate } from '@/lib/types';
+
+export const getManualExchangeRates = async (): Promise<ManualExchangeRate[]> => {
+  try {
+    const result = await invoke('get_manual_exchange_rates');
+    return result as ManualExchangeRate[];
+  } catch (error) {
+    console.error('Error loading manual exchange rates:', error);
+    throw error;
+  }
+};
+
+export const addManualExchangeRate = async (
+  exchangeRate: NewManualExchangeRate,
+): Promise<ManualExchangeRate> => {
+  try {
+    const result = await invoke('add_manual_exchange_rate', { exchangeRate });
+    return result as ManualExchangeRate;
+  } catch (error) {
+    console.error('Error adding manual exchange rate:', error);
+    throw error;
+  }
+};
+
+export const deleteManualExchangeRate = async (rateId: string): Promise<void> => {
+  try {
+    await invoke('delete_manual_exchange_rate', { rateId });
+  } catch (error) {
+    console.error('Error deleting manual exchange rate:', error);
+    throw error;
+  }
+};
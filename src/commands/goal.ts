@@ -1,6 +1,6 @@
 import { invoke } from '@tauri-apps/api';
 import * as z from 'zod';
-import { Goal, GoalAllocation } from '@/lib/types';
+import { Goal, GoalAllocation, GoalProjection } from '@/lib/types';
 import { newGoalSchema } from '@/lib/schemas';
 
 type NewGoal = z.infer<typeof newGoalSchema>;
@@ -68,3 +68,23 @@ export const getGoalsAllocation = async (): Promise<GoalAllocation[]> => {
     throw error;
   }
 };
+
+export const projectGoalCompletion = async (
+  goalId: string,
+  currentValue: number,
+  monthlyContribution: number,
+  annualReturn: number,
+): Promise<GoalProjection> => {
+  try {
+    const projection = await invoke('project_goal_completion', {
+      goalId,
+      currentValue,
+      monthlyContribution,
+      annualReturn,
+    });
+    return projection as GoalProjection;
+  } catch (error) {
+    console.error('Error projecting goal completion:', error);
+    throw error;
+  }
+};
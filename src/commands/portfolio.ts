@@ -1,9 +1,37 @@
 import { invoke } from '@tauri-apps/api';
-import { FinancialHistory, Holding } from '@/lib/types';
+import {
+  Allocation,
+  AllocationGroup,
+  AllocationHistory,
+  AllocationInterval,
+  AttributionGroup,
+  CashInterestAccrual,
+  CashInterestTier,
+  CurrencyReturn,
+  ExportFormat,
+  FinancialHistory,
+  Holding,
+  HoldingLots,
+  HoldingsReconciliation,
+  LotMatchingMethod,
+  NewCashInterestTier,
+  NewOptionContract,
+  OptionContract,
+  OptionSettlement,
+  PerformanceHistory,
+  PerformanceSummary,
+  RealizedGainsReport,
+  RebalanceConstraints,
+  RebalancePlan,
+  ReturnAttribution,
+  SimulatedTrade,
+  TradeSimulation,
+  WashSale,
+} from '@/lib/types';
 
-export const getHistorical = async (): Promise<FinancialHistory[]> => {
+export const getHistorical = async (reportingCurrency?: string): Promise<FinancialHistory[]> => {
   try {
-    const result = await invoke('get_historical');
+    const result = await invoke('get_historical', { reportingCurrency });
     return result as FinancialHistory[];
   } catch (error) {
     console.error('Error fetching accounts:', error);
@@ -11,12 +39,316 @@ export const getHistorical = async (): Promise<FinancialHistory[]> => {
   }
 };
 
-export const computeHoldings = async (): Promise<Holding[]> => {
+// Current holdings, or the ones at the end of the `asOf` day (YYYY-MM-DD)
+export const computeHoldings = async (
+  reportingCurrency?: string,
+  tag?: string,
+  asOf?: string,
+): Promise<Holding[]> => {
   try {
-    const result = await invoke('compute_holdings');
+    const result = await invoke('compute_holdings', { reportingCurrency, tag, asOf });
     return result as Holding[];
   } catch (error) {
     console.error('Error computing holdings:', error);
     throw error;
   }
 };
+
+export const exportHistorical = async ({
+  format,
+  filePath,
+  startDate,
+  endDate,
+  reportingCurrency,
+}: {
+  format: ExportFormat;
+  filePath: string;
+  startDate?: string;
+  endDate?: string;
+  reportingCurrency?: string;
+}): Promise<number> => {
+  try {
+    const result = await invoke('export_historical', {
+      format,
+      filePath,
+      startDate,
+      endDate,
+      reportingCurrency,
+    });
+    return result as number;
+  } catch (error) {
+    console.error('Error exporting portfolio history:', error);
+    throw error;
+  }
+};
+
+// riskFreeRate is the annual rate in percent used for the Sharpe ratio, 0 by default
+export const getPerformanceSummary = async (
+  reportingCurrency?: string,
+  riskFreeRate?: number,
+): Promise<PerformanceSummary[]> => {
+  try {
+    const result = await invoke('get_performance_summary', { riskFreeRate, reportingCurrency });
+    return result as PerformanceSummary[];
+  } catch (error) {
+    console.error('Error calculating performance summary:', error);
+    throw error;
+  }
+};
+
+// History of the account, the total portfolio by default, compared to benchmark symbols
+export const getPerformanceHistory = async (
+  accountId?: string,
+  benchmarks?: string[],
+  reportingCurrency?: string,
+): Promise<PerformanceHistory> => {
+  try {
+    const result = await invoke('get_performance_history', {
+      accountId,
+      benchmarks,
+      reportingCurrency,
+    });
+    return result as PerformanceHistory;
+  } catch (error) {
+    console.error('Error calculating performance history:', error);
+    throw error;
+  }
+};
+
+export const getReturnAttribution = async (
+  groupBy: AttributionGroup,
+  reportingCurrency?: string,
+): Promise<ReturnAttribution[]> => {
+  try {
+    const result = await invoke('get_return_attribution', { groupBy, reportingCurrency });
+    return result as ReturnAttribution[];
+  } catch (error) {
+    console.error('Error calculating return attribution:', error);
+    throw error;
+  }
+};
+
+// Dates are YYYY-MM-DD, the period ends today without an end date
+export const getCurrencyReturns = async (
+  startDate: string,
+  endDate?: string,
+  reportingCurrency?: string,
+): Promise<CurrencyReturn[]> => {
+  try {
+    const result = await invoke('get_currency_returns', { startDate, endDate, reportingCurrency });
+    return result as CurrencyReturn[];
+  } catch (error) {
+    console.error('Error calculating currency returns:', error);
+    throw error;
+  }
+};
+
+export const getPortfolioAllocation = async (
+  groupBy: AllocationGroup,
+  reportingCurrency?: string,
+): Promise<Allocation[]> => {
+  try {
+    const result = await invoke('get_portfolio_allocation', { groupBy, reportingCurrency });
+    return result as Allocation[];
+  } catch (error) {
+    console.error('Error calculating portfolio allocation:', error);
+    throw error;
+  }
+};
+
+// Weights by category from startDate to endDate (today by default), dates are YYYY-MM-DD
+export const getAllocationHistory = async (
+  groupBy: AllocationGroup,
+  startDate: string,
+  endDate?: string,
+  interval?: AllocationInterval,
+  reportingCurrency?: string,
+): Promise<AllocationHistory> => {
+  try {
+    const result = await invoke('get_allocation_history', {
+      groupBy,
+      startDate,
+      endDate,
+      interval,
+      reportingCurrency,
+    });
+    return result as AllocationHistory;
+  } catch (error) {
+    console.error('Error calculating allocation history:', error);
+    throw error;
+  }
+};
+
+export const simulateTrades = async (
+  trades: SimulatedTrade[],
+  groupBy: AllocationGroup,
+  reportingCurrency?: string,
+): Promise<TradeSimulation> => {
+  try {
+    const result = await invoke('simulate_trades', { trades, groupBy, reportingCurrency });
+    return result as TradeSimulation;
+  } catch (error) {
+    console.error('Error simulating trades:', error);
+    throw error;
+  }
+};
+
+export const getRebalancingSuggestions = async (
+  targets: Record<string, number>,
+  groupBy: AllocationGroup,
+  constraints?: RebalanceConstraints,
+  reportingCurrency?: string,
+): Promise<RebalancePlan> => {
+  try {
+    const result = await invoke('get_rebalancing_suggestions', {
+      targets,
+      groupBy,
+      constraints,
+      reportingCurrency,
+    });
+    return result as RebalancePlan;
+  } catch (error) {
+    console.error('Error calculating rebalancing suggestions:', error);
+    throw error;
+  }
+};
+
+// lotSelections lists, by sell activity id, the lots sold first with SPECIFIC_ID
+export const getHoldingLots = async (
+  accountId: string,
+  assetId: string,
+  method?: LotMatchingMethod,
+  lotSelections?: Record<string, string[]>,
+): Promise<HoldingLots> => {
+  try {
+    const result = await invoke('get_holding_lots', { accountId, assetId, method, lotSelections });
+    return result as HoldingLots;
+  } catch (error) {
+    console.error('Error fetching holding lots:', error);
+    throw error;
+  }
+};
+
+// Dates are YYYY-MM-DD and inclusive
+export const getRealizedGains = async (
+  method?: LotMatchingMethod,
+  startDate?: string,
+  endDate?: string,
+): Promise<RealizedGainsReport> => {
+  try {
+    const result = await invoke('get_realized_gains', { method, startDate, endDate });
+    return result as RealizedGainsReport;
+  } catch (error) {
+    console.error('Error calculating realized gains:', error);
+    throw error;
+  }
+};
+
+export const getWashSales = async (): Promise<WashSale[]> => {
+  try {
+    const result = await invoke('get_wash_sales');
+    return result as WashSale[];
+  } catch (error) {
+    console.error('Error detecting wash sales:', error);
+    throw error;
+  }
+};
+
+export const getCashInterestTiers = async (): Promise<CashInterestTier[]> => {
+  try {
+    const result = await invoke('get_cash_interest_tiers');
+    return result as CashInterestTier[];
+  } catch (error) {
+    console.error('Error fetching cash interest tiers:', error);
+    throw error;
+  }
+};
+
+export const addCashInterestTier = async (tier: NewCashInterestTier): Promise<CashInterestTier> => {
+  try {
+    const result = await invoke('add_cash_interest_tier', { tier });
+    return result as CashInterestTier;
+  } catch (error) {
+    console.error('Error adding cash interest tier:', error);
+    throw error;
+  }
+};
+
+export const deleteCashInterestTier = async (tierId: string): Promise<void> => {
+  try {
+    await invoke('delete_cash_interest_tier', { tierId });
+  } catch (error) {
+    console.error('Error deleting cash interest tier:', error);
+    throw error;
+  }
+};
+
+// Posts the interest of the complete months not accrued yet
+export const accrueCashInterest = async (): Promise<CashInterestAccrual[]> => {
+  try {
+    const result = await invoke('accrue_cash_interest');
+    return result as CashInterestAccrual[];
+  } catch (error) {
+    console.error('Error accruing cash interest:', error);
+    throw error;
+  }
+};
+
+export const getOptionContracts = async (): Promise<OptionContract[]> => {
+  try {
+    const result = await invoke('get_option_contracts');
+    return result as OptionContract[];
+  } catch (error) {
+    console.error('Error fetching option contracts:', error);
+    throw error;
+  }
+};
+
+export const createOptionContract = async (
+  contract: NewOptionContract,
+): Promise<OptionContract> => {
+  try {
+    const result = await invoke('create_option_contract', { contract });
+    return result as OptionContract;
+  } catch (error) {
+    console.error('Error creating option contract:', error);
+    throw error;
+  }
+};
+
+// Closes the option position of the account, returns the number of activities created
+export const settleOption = async (
+  accountId: string,
+  assetId: string,
+  settlement: OptionSettlement,
+): Promise<number> => {
+  try {
+    const result = await invoke('settle_option', { accountId, assetId, settlement });
+    return result as number;
+  } catch (error) {
+    console.error('Error settling option:', error);
+    throw error;
+  }
+};
+
+// Compares a holdings csv (symbol, quantity, unitPrice) to the positions of the account on the
+// snapshot date, the reconciling activities are only created when `apply` is set
+export const reconcileHoldingsSnapshot = async (
+  accountId: string,
+  filePath: string,
+  snapshotDate: string,
+  apply?: boolean,
+): Promise<HoldingsReconciliation> => {
+  try {
+    const result = await invoke('reconcile_holdings_snapshot', {
+      accountId,
+      filePath,
+      snapshotDate,
+      apply,
+    });
+    return result as HoldingsReconciliation;
+  } catch (error) {
+    console.error('Error reconciling holdings snapshot:', error);
+    throw error;
+  }
+};
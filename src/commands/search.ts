@@ -0,0 +1,13 @@
+import { invoke } from '@tauri-apps/api';
+import { SearchResult } from '@/lib/types';
+
+// Searches accounts, assets, activities and goals, keeping the limit best results of each
+export const search = async (query: string, limit?: number): Promise<SearchResult[]> => {
+  try {
+    const results = await invoke('search', { query, limit });
+    return results as SearchResult[];
+  } catch (error) {
+    console.error('Error searching:', error);
+    throw error;
+  }
+};
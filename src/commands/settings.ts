@@ -0,0 +1,63 @@
+import { invoke } from '@tauri-apps/api';
+import { DatabaseStats, Settings } from '@/lib/types';
+
+export const compactDatabase = async (vacuum: boolean): Promise<number> => {
+  try {
+    const bytesReclaimed = await invoke('compact_database', { vacuum });
+    return bytesReclaimed as number;
+  } catch (error) {
+    console.error('Error compacting database:', error);
+    throw error;
+  }
+};
+
+export const getDatabaseStats = async (): Promise<DatabaseStats> => {
+  try {
+    const stats = await invoke('get_database_stats');
+    return stats as DatabaseStats;
+  } catch (error) {
+    console.error('Error fetching database stats:', error);
+    throw error;
+  }
+};
+
+export const updateReadOnly = async (readOnly: boolean): Promise<Settings> => {
+  try {
+    const settings = await invoke('update_read_only', { readOnly });
+    return settings as Settings;
+  } catch (error) {
+    console.error('Error updating read-only mode:', error);
+    throw error;
+  }
+};
+
+export const getProfiles = async (): Promise<string[]> => {
+  try {
+    const profiles = await invoke('get_profiles');
+    return profiles as string[];
+  } catch (error) {
+    console.error('Error fetching profiles:', error);
+    throw error;
+  }
+};
+
+export const getActiveProfile = async (): Promise<string> => {
+  try {
+    const profile = await invoke('get_active_profile');
+    return profile as string;
+  } catch (error) {
+    console.error('Error fetching active profile:', error);
+    throw error;
+  }
+};
+
+// Switches to the database of the profile, creating it when new, and returns its settings
+export const switchProfile = async (profile: string): Promise<Settings> => {
+  try {
+    const settings = await invoke('switch_profile', { profile });
+    return settings as Settings;
+  } catch (error) {
+    console.error('Error switching profile:', error);
+    throw error;
+  }
+};
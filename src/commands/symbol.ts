@@ -1,5 +1,14 @@
 import { invoke } from '@tauri-apps/api';
-import { AssetData, QuoteSummary } from '@/lib/types';
+import {
+  AssetClassificationReport,
+  AssetData,
+  NewManualQuote,
+  NewSymbolOverride,
+  Quote,
+  QuoteStalenessPolicy,
+  QuoteSummary,
+  SymbolOverride,
+} from '@/lib/types';
 
 export const searchTicker = async (query: string): Promise<QuoteSummary[]> => {
   try {
@@ -11,9 +20,9 @@ export const searchTicker = async (query: string): Promise<QuoteSummary[]> => {
   }
 };
 
-export const syncHistoryQuotes = async (): Promise<any> => {
+export const syncHistoryQuotes = async (force?: boolean): Promise<any> => {
   try {
-    const result = await invoke('synch_quotes');
+    const result = await invoke('synch_quotes', { force });
     return result;
   } catch (error) {
     console.error('Error syncing history quotes:', error);
@@ -30,3 +39,159 @@ export const getAssetData = async (assetId: string): Promise<AssetData> => {
     throw error;
   }
 };
+
+export const classifyAssets = async (): Promise<AssetClassificationReport> => {
+  try {
+    const result = await invoke('classify_assets');
+    return result as AssetClassificationReport;
+  } catch (error) {
+    console.error('Error classifying assets:', error);
+    throw error;
+  }
+};
+
+export const getSymbolOverrides = async (): Promise<SymbolOverride[]> => {
+  try {
+    const result = await invoke('get_symbol_overrides');
+    return result as SymbolOverride[];
+  } catch (error) {
+    console.error('Error loading symbol overrides:', error);
+    throw error;
+  }
+};
+
+export const addSymbolOverride = async (
+  symbolOverride: NewSymbolOverride,
+): Promise<SymbolOverride> => {
+  try {
+    const result = await invoke('add_symbol_override', { symbolOverride });
+    return result as SymbolOverride;
+  } catch (error) {
+    console.error('Error adding symbol override:', error);
+    throw error;
+  }
+};
+
+// Pin the symbol the user picked for an import symbol of the account that did not resolve
+export const learnSymbolAlias = async (
+  accountId: string,
+  symbol: string,
+  providerSymbol: string,
+): Promise<SymbolOverride> => {
+  try {
+    const result = await invoke('learn_symbol_alias', { accountId, symbol, providerSymbol });
+    return result as SymbolOverride;
+  } catch (error) {
+    console.error('Error learning symbol alias:', error);
+    throw error;
+  }
+};
+
+export const deleteSymbolOverride = async (overrideId: string): Promise<void> => {
+  try {
+    await invoke('delete_symbol_override', { overrideId });
+  } catch (error) {
+    console.error('Error deleting symbol override:', error);
+    throw error;
+  }
+};
+
+export const getTags = async (): Promise<string[]> => {
+  try {
+    const result = await invoke('get_tags');
+    return result as string[];
+  } catch (error) {
+    console.error('Error loading tags:', error);
+    throw error;
+  }
+};
+
+export const getAssetTags = async (assetId: string): Promise<string[]> => {
+  try {
+    const result = await invoke('get_asset_tags', { assetId });
+    return result as string[];
+  } catch (error) {
+    console.error('Error loading asset tags:', error);
+    throw error;
+  }
+};
+
+export const addAssetTag = async (assetId: string, tag: string): Promise<string[]> => {
+  try {
+    const result = await invoke('add_asset_tag', { assetId, tag });
+    return result as string[];
+  } catch (error) {
+    console.error('Error adding asset tag:', error);
+    throw error;
+  }
+};
+
+export const removeAssetTag = async (assetId: string, tag: string): Promise<string[]> => {
+  try {
+    const result = await invoke('remove_asset_tag', { assetId, tag });
+    return result as string[];
+  } catch (error) {
+    console.error('Error removing asset tag:', error);
+    throw error;
+  }
+};
+
+export const saveManualQuote = async (manualQuote: NewManualQuote): Promise<Quote> => {
+  try {
+    const result = await invoke('save_manual_quote', { manualQuote });
+    return result as Quote;
+  } catch (error) {
+    console.error('Error saving manual quote:', error);
+    throw error;
+  }
+};
+
+export const deleteManualQuote = async (quoteId: string): Promise<void> => {
+  try {
+    await invoke('delete_manual_quote', { quoteId });
+  } catch (error) {
+    console.error('Error deleting manual quote:', error);
+    throw error;
+  }
+};
+
+export const getQuoteStalenessPolicies = async (): Promise<QuoteStalenessPolicy[]> => {
+  try {
+    const result = await invoke('get_quote_staleness_policies');
+    return result as QuoteStalenessPolicy[];
+  } catch (error) {
+    console.error('Error fetching quote staleness policies:', error);
+    throw error;
+  }
+};
+
+export const setQuoteStalenessPolicy = async (
+  policy: QuoteStalenessPolicy,
+): Promise<QuoteStalenessPolicy> => {
+  try {
+    const result = await invoke('set_quote_staleness_policy', { policy });
+    return result as QuoteStalenessPolicy;
+  } catch (error) {
+    console.error('Error setting quote staleness policy:', error);
+    throw error;
+  }
+};
+
+export const deleteQuoteStalenessPolicy = async (assetType: string): Promise<void> => {
+  try {
+    await invoke('delete_quote_staleness_policy', { assetType });
+  } catch (error) {
+    console.error('Error deleting quote staleness policy:', error);
+    throw error;
+  }
+};
+
+export const refreshStaleQuotes = async (): Promise<string[]> => {
+  try {
+    const result = await invoke('refresh_stale_quotes');
+    return result as string[];
+  } catch (error) {
+    console.error('Error refreshing stale quotes:', error);
+    throw error;
+  }
+};
@@ -66,6 +66,8 @@ export const newActivitySchema = z.object({
     'FEE',
     'TAX',
     'SPLIT',
+    'SPIN_OFF_OUT',
+    'SPIN_OFF_IN',
   ]),
   unitPrice: z.coerce
     .number({
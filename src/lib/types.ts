@@ -159,6 +159,317 @@ export interface FinancialSnapshot {
   exchangeRate?: number;
 }
 
+export interface PerformanceSummary {
+  accountId: string;
+  startDate: string;
+  endDate: string;
+  startValue: number;
+  endValue: number;
+  netDeposit: number;
+  currency: string;
+  moneyWeightedReturn?: number;
+  maxDrawdown?: number;
+  maxDrawdownDays?: number;
+  volatility?: number;
+  sharpeRatio?: number;
+}
+
+// Series indexed to 100 and aligned with dates, benchmark values are null before their
+// first quote
+export interface PerformanceHistory {
+  accountId: string;
+  currency: string;
+  dates: string[];
+  portfolio: number[];
+  benchmarks: BenchmarkSeries[];
+}
+
+export interface BenchmarkSeries {
+  symbol: string;
+  values: (number | null)[];
+}
+
+export type AttributionGroup = 'ASSET_CLASS' | 'SECTOR';
+
+export interface ReturnAttribution {
+  category: string;
+  weight: number;
+  contribution: number;
+}
+
+// Annual rate in percent on the cash between minBalance and the next tier of the account
+export interface CashInterestTier {
+  id: string;
+  accountId: string;
+  minBalance: number;
+  annualRate: number;
+}
+
+export interface NewCashInterestTier {
+  accountId: string;
+  minBalance?: number;
+  annualRate: number;
+}
+
+// Estimated interest posted as an INTEREST activity on the last day of the month
+export interface CashInterestAccrual {
+  accountId: string;
+  activityId: string;
+  date: string;
+  amount: number;
+  currency: string;
+}
+
+export type OptionType = 'CALL' | 'PUT';
+
+// Quantities of options are in contracts of 100 units of the underlying
+export interface OptionContract {
+  assetId: string;
+  underlyingAssetId: string;
+  optionType: OptionType;
+  strikePrice: number;
+  expirationDate: string;
+}
+
+export interface NewOptionContract {
+  underlyingAssetId: string;
+  optionType: OptionType;
+  strikePrice: number;
+  expirationDate: string;
+}
+
+export type OptionSettlement = 'EXERCISE' | 'ASSIGN' | 'EXPIRE';
+
+// A snapshot position that differs from the computed one, with the activity making them match
+export interface HoldingDiscrepancy {
+  symbol: string;
+  computedQuantity: number;
+  snapshotQuantity: number;
+  activity?: Omit<NewActivity, 'activityDate'> & { activityDate: string };
+  error?: string;
+}
+
+export interface HoldingsReconciliation {
+  accountId: string;
+  snapshotDate: string;
+  discrepancies: HoldingDiscrepancy[];
+  created: number;
+}
+
+// Fractions of the period return, localReturn + fxContribution = totalReturn
+export interface CurrencyReturn {
+  assetId: string;
+  currency: string;
+  localReturn: number;
+  fxContribution: number;
+  totalReturn: number;
+}
+
+export type AllocationGroup =
+  | 'ASSET_CLASS'
+  | 'ASSET_SUB_CLASS'
+  | 'SECTOR'
+  | 'CURRENCY'
+  | 'HOLDING_TYPE'
+  | 'ACCOUNT'
+  | 'ACCOUNT_TYPE'
+  | 'ACCOUNT_GROUP'
+  | 'TAG';
+
+export interface Allocation {
+  category: string;
+  value: number;
+  weight: number;
+}
+
+export type AllocationInterval = 'WEEKLY' | 'MONTHLY';
+
+// Every category has a weight (percentage) on every date, 0 when it held nothing then
+export interface AllocationHistory {
+  dates: string[];
+  series: { category: string; weights: number[] }[];
+}
+
+export interface SimulatedTrade {
+  accountId: string;
+  assetId: string;
+  activityType: 'BUY' | 'SELL';
+  quantity: number;
+  unitPrice: number;
+  fee?: number;
+}
+
+export interface AllocationShift {
+  category: string;
+  currentWeight: number;
+  projectedWeight: number;
+}
+
+export interface TradeSimulation {
+  holdings: Holding[];
+  allocation: AllocationShift[];
+  currentCash: number;
+  projectedCash: number;
+}
+
+export interface RebalanceConstraints {
+  wholeShares?: boolean;
+  minTradeValue?: number;
+  cashBuffer?: number;
+}
+
+export interface RebalanceTrade {
+  accountId: string;
+  assetId: string;
+  category: string;
+  activityType: 'BUY' | 'SELL';
+  quantity: number;
+  unitPrice: number;
+  value: number;
+}
+
+export interface RebalancePlan {
+  trades: RebalanceTrade[];
+  projectedCash: number;
+  warnings: string[];
+}
+
+export type LotMatchingMethod = 'FIFO' | 'LIFO' | 'SPECIFIC_ID' | 'AVERAGE';
+
+export interface TaxLot {
+  id: string;
+  acquisitionDate: string;
+  quantity: number;
+  costBasis: number;
+  marketValue: number;
+  unrealizedGain: number;
+  unrealizedGainPercent: number;
+}
+
+export interface RealizedLot {
+  sellActivityId: string;
+  lotId: string;
+  acquisitionDate: string;
+  saleDate: string;
+  quantity: number;
+  costBasis: number;
+  proceeds: number;
+  realizedGain: number;
+}
+
+export type HoldingPeriod = 'SHORT_TERM' | 'LONG_TERM';
+
+export interface RealizedGain {
+  accountId: string;
+  assetId: string;
+  sellActivityId: string;
+  lotId: string;
+  acquisitionDate: string;
+  saleDate: string;
+  holdingPeriod: HoldingPeriod;
+  quantity: number;
+  currency: string;
+  costBasis: number;
+  proceeds: number;
+  realizedGain: number;
+  exchangeRate: number;
+  costBasisConverted: number;
+  proceedsConverted: number;
+  realizedGainConverted: number;
+}
+
+export interface RealizedGainsSummary {
+  costBasis: number;
+  proceeds: number;
+  shortTermGain: number;
+  longTermGain: number;
+  totalGain: number;
+}
+
+export interface RealizedGainsReport {
+  baseCurrency: string;
+  method: LotMatchingMethod;
+  transactions: RealizedGain[];
+  summary: RealizedGainsSummary;
+}
+
+export interface WashSale {
+  accountId: string;
+  assetId: string;
+  sellActivityId: string;
+  saleDate: string;
+  currency: string;
+  quantity: number;
+  disallowedLoss: number;
+  replacementLotId: string;
+  replacementAccountId: string;
+  replacementDate: string;
+}
+
+export interface HoldingLots {
+  accountId: string;
+  assetId: string;
+  currency: string;
+  method: LotMatchingMethod;
+  lots: TaxLot[];
+  realized: RealizedLot[];
+}
+
+export interface ManualExchangeRate {
+  id: string;
+  fromCurrency: string;
+  toCurrency: string;
+  rate: number;
+  startDate: string;
+  endDate: string;
+  createdAt: Date;
+}
+
+export interface NewManualExchangeRate {
+  fromCurrency: string;
+  toCurrency: string;
+  rate: number;
+  startDate: string;
+  endDate?: string;
+}
+
+export interface SymbolOverride {
+  id: string;
+  symbol: string;
+  accountId?: string;
+  providerSymbol: string;
+  createdAt: Date;
+}
+
+export interface NewSymbolOverride {
+  symbol: string;
+  accountId?: string;
+  providerSymbol: string;
+}
+
+export interface QuoteStalenessPolicy {
+  assetType: string;
+  maxAgeDays: number;
+}
+
+export interface AssetClassificationReport {
+  assigned: string[];
+  skipped: string[];
+  failed: string[];
+}
+
+export interface TableStats {
+  name: string;
+  rowCount: number;
+  size?: number;
+}
+
+export interface DatabaseStats {
+  tables: TableStats[];
+  databaseSize: number;
+  walSize: number;
+}
+
 export interface FinancialHistory {
   account: Account; // This can be an account or the entire portfolio.
   history: FinancialSnapshot[];
@@ -175,6 +486,7 @@ export interface ActivityImport {
   unitPrice: number;
   currency: string;
   fee: number;
+  accruedInterest?: number;
   comment?: string;
   accountId?: string;
   accountName?: string;
@@ -183,6 +495,93 @@ export interface ActivityImport {
   isDraft?: string;
   isValid?: string;
   lineNumber?: number;
+  duplicate?: 'EXACT' | 'POSSIBLE';
+  warning?: string;
+  // Existing activity replaced by the row, named by the id column of the file
+  updatedActivityId?: string;
+  skip?: boolean;
+}
+
+export type ImportPreset = 'WEALTHFOLIO' | 'SCHWAB' | 'DEGIRO';
+
+// The date format uses the chrono syntax, e.g. '%d/%m/%Y'
+export interface ImportLocale {
+  decimalSeparator: string;
+  thousandsSeparator: string;
+  dateFormat: string;
+}
+
+// First checked rows of an import file, the total counts every activity of the file
+export interface ActivityImportPreview {
+  activities: ActivityImport[];
+  total: number;
+}
+
+// Line numbers of the preview rows to skip or import, the other rows with a duplicate or a
+// warning are skipped
+export interface ImportSelection {
+  skippedLines: number[];
+  includedLines: number[];
+}
+
+export interface ImportActivitiesResult {
+  imported: number;
+  updated: number;
+  skipped: number;
+}
+
+export interface ImportDryRunReport {
+  creates: number;
+  updates: number;
+  duplicates: number;
+  errors: number;
+  warnings: number;
+  reinvestments: number;
+  activities: ActivityImport[];
+}
+
+// Dates are YYYY-MM-DD and inclusive, the amount is the quantity times the unit price
+export interface ActivitySearchFilter {
+  symbols?: string[];
+  startDate?: string;
+  endDate?: string;
+  minAmount?: number;
+  maxAmount?: number;
+  commentKeyword?: string;
+}
+
+// Dividends of the asset in the account are reinvested in the asset
+export interface DripSetting {
+  accountId: string;
+  assetId: string;
+}
+
+// At least one field must be set
+export interface ActivityBulkFilter {
+  accountIds?: string[];
+  activityTypes?: string[];
+  assetIds?: string[];
+  startDate?: string;
+  endDate?: string;
+}
+
+export interface ActivityBulkUpdate {
+  accountId?: string;
+  currency?: string;
+}
+
+export interface ActivityExportFilter {
+  accountIds?: string[];
+  activityTypes?: string[];
+  startDate?: string;
+  endDate?: string;
+}
+
+export type ExportFormat = 'CSV' | 'JSON';
+
+export interface ImportProgress {
+  processed: number;
+  total: number;
 }
 
 export interface Holding {
@@ -223,6 +622,11 @@ export interface Holding {
       weight: number;
     },
   ];
+  tags: string[];
+  quoteDate?: string;
+  isQuoteStale: boolean;
+  fxError?: string;
+  fxDerived: boolean;
 }
 
 export interface Asset {
@@ -261,6 +665,19 @@ export interface Quote {
   adjclose: number;
 }
 
+// Quotes entered by hand have the MANUAL data source and are kept by market data syncs
+export const MANUAL_DATA_SOURCE = 'MANUAL';
+
+export interface NewManualQuote {
+  symbol: string;
+  date: string; // YYYY-MM-DD
+  open?: number;
+  high?: number;
+  low?: number;
+  close: number;
+  volume?: number;
+}
+
 export interface AssetData {
   asset: Asset;
   quoteHistory: Quote[];
@@ -271,6 +688,10 @@ export interface Settings {
   theme: string;
   font: string;
   baseCurrency: string;
+  quantityPrecision: number;
+  pricePrecision: number;
+  readOnly: boolean;
+  activityUndoDays: number;
 }
 
 export interface SettingsContextType {
@@ -291,6 +712,13 @@ export interface Goal {
   allocations?: GoalAllocation[];
 }
 
+// Record matching a search, scores go from 0 to 1
+export type SearchResult =
+  | { type: 'ACCOUNT'; score: number; account: Account }
+  | { type: 'ASSET'; score: number; asset: Asset }
+  | { type: 'ACTIVITY'; score: number; activity: Activity }
+  | { type: 'GOAL'; score: number; goal: Goal };
+
 export interface GoalAllocation {
   id: string;
   goalId: string;
@@ -298,6 +726,19 @@ export interface GoalAllocation {
   percentAllocation: number;
 }
 
+export interface GoalProjectionPoint {
+  date: string;
+  value: number;
+}
+
+export interface GoalProjection {
+  goalId: string;
+  targetAmount: number;
+  completionDate?: string;
+  monthsToCompletion?: number;
+  values: GoalProjectionPoint[];
+}
+
 export interface GoalProgress {
   name: string;
   targetValue: number;
@@ -17,15 +17,31 @@ export function formatDate(input: string | number): string {
   return format(date, 'MMM d, yyyy');
 }
 
-export function formatAmount(amount: number, currency: string, displayCurrency = true) {
+export const DEFAULT_QUANTITY_PRECISION = 8;
+export const DEFAULT_PRICE_PRECISION = 2;
+export const DEFAULT_ACTIVITY_UNDO_DAYS = 7;
+
+export function formatAmount(
+  amount: number,
+  currency: string,
+  displayCurrency = true,
+  precision = DEFAULT_PRICE_PRECISION,
+) {
   return new Intl.NumberFormat('en-US', {
     style: displayCurrency ? 'currency' : undefined,
     currency: currency,
-    minimumFractionDigits: 2,
-    maximumFractionDigits: 2,
+    minimumFractionDigits: Math.min(2, precision),
+    maximumFractionDigits: precision,
   }).format(amount);
 }
 
+// Quantities are kept unrounded, only their display is limited to `precision` decimals
+export function formatQuantity(quantity: number, precision = DEFAULT_QUANTITY_PRECISION) {
+  return new Intl.NumberFormat('en-US', {
+    maximumFractionDigits: precision,
+  }).format(quantity);
+}
+
 export function formatPercent(value: number) {
   // return new Intl.NumberFormat('en-US', {
   //   style: 'percent',
@@ -4,9 +4,12 @@ import { Card, CardContent, CardHeader } from '@/components/ui/card';
 import { Separator } from '@/components/ui/separator';
 import { Skeleton } from '@/components/ui/skeleton';
 import { Holding } from '@/lib/types';
-import { formatAmount } from '@/lib/utils';
+import { formatAmount, formatQuantity } from '@/lib/utils';
+import { useSettingsContext } from '@/lib/settings-provider';
 
 const AccountHoldings = ({ holdings, isLoading }: { holdings: Holding[]; isLoading: boolean }) => {
+  const { settings } = useSettingsContext();
+
   if (!isLoading && !holdings.length) {
     return null;
   }
@@ -42,11 +45,18 @@ const AccountHoldings = ({ holdings, isLoading }: { holdings: Holding[]; isLoadi
 
                 <div className="text-right">
                   <p className="">{formatAmount(holding.marketValue, holding.currency)}</p>
-                  <p className="text-sm text-gray-600">{holding.quantity} shares</p>
+                  <p className="text-sm text-gray-600">{formatQuantity(holding.quantity, settings?.quantityPrecision)} shares</p>
                 </div>
 
                 <div className="text-right">
-                  <p className=" ">{formatAmount(holding.marketPrice || 0, holding.currency)}</p>
+                  <p className=" ">
+                    {formatAmount(
+                      holding.marketPrice || 0,
+                      holding.currency,
+                      true,
+                      settings?.pricePrecision,
+                    )}
+                  </p>
                   <p className="text-sm text-gray-600">{holding.currency}</p>
                 </div>
 
@@ -23,12 +23,12 @@ const AccountPage = () => {
     Error
   >({
     queryKey: ['portfolio_history'],
-    queryFn: getHistorical,
+    queryFn: () => getHistorical(),
   });
 
   const { data: holdings, isLoading: isLoadingHoldings } = useQuery<Holding[], Error>({
     queryKey: ['holdings'],
-    queryFn: computeHoldings,
+    queryFn: () => computeHoldings(),
   });
 
   const accountHoldings = holdings
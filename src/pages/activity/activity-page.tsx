@@ -11,8 +11,9 @@ import { Account, ActivityDetails } from '@/lib/types';
 import { getAccounts } from '@/commands/account';
 // import { getActivities } from '@/commands/activity';
 import { ActivityDeleteModal } from './components/activity-delete-modal';
-import { deleteActivity } from '@/commands/activity';
+import { deleteActivity, restoreActivity } from '@/commands/activity';
 import { toast } from '@/components/ui/use-toast';
+import { ToastAction } from '@/components/ui/toast';
 
 const ActivityPage = () => {
   const [showEditModal, setShowEditModal] = useState(false);
@@ -26,15 +27,35 @@ const ActivityPage = () => {
     queryFn: getAccounts,
   });
 
+  const invalidateActivities = () => {
+    queryClient.invalidateQueries({ queryKey: ['activity-data'] });
+    queryClient.invalidateQueries({ queryKey: ['holdings'] });
+    queryClient.invalidateQueries({ queryKey: ['portfolio_history'] });
+  };
+
+  const restoreActivityMutation = useMutation({
+    mutationFn: restoreActivity,
+    onSuccess: () => {
+      invalidateActivities();
+      toast({
+        title: 'Activity restored.',
+        className: 'bg-green-500 text-white border-none',
+      });
+    },
+  });
+
   const deleteActivityMutation = useMutation({
     mutationFn: deleteActivity,
-    onSuccess: () => {
-      queryClient.invalidateQueries({ queryKey: ['activity-data'] });
-      queryClient.invalidateQueries({ queryKey: ['holdings'] });
-      queryClient.invalidateQueries({ queryKey: ['portfolio_history'] });
+    onSuccess: (_, activityId) => {
+      invalidateActivities();
       toast({
-        title: 'Account updated successfully.',
+        title: 'Activity deleted.',
         className: 'bg-green-500 text-white border-none',
+        action: (
+          <ToastAction altText="Undo" onClick={() => restoreActivityMutation.mutate(activityId)}>
+            Undo
+          </ToastAction>
+        ),
       });
     },
   });
@@ -48,7 +48,9 @@ const activityTypes = [
   { label: 'Deposit', value: 'DEPOSIT' },
   { label: 'Withdrawal', value: 'WITHDRAWAL' },
   { label: 'Dividend', value: 'DIVIDEND' },
-  // { label: 'Split', value: 'SPLIT' },
+  { label: 'Split', value: 'SPLIT' },
+  { label: 'Spin-off (Parent)', value: 'SPIN_OFF_OUT' },
+  { label: 'Spin-off (Received)', value: 'SPIN_OFF_IN' },
   // { label: 'Transfer', value: 'TRANSFER' },
   { label: 'Interest', value: 'INTEREST' },
   { label: 'Fee', value: 'FEE' },
@@ -324,6 +326,8 @@ const AssetActivityFields = ({ defaultAssetId }: AssetActivityFieldsProps) => {
   const watchedType = watch('activityType');
 
   const isSplitType = watchedType === 'SPLIT';
+  const isSpinOffParentType = watchedType === 'SPIN_OFF_OUT';
+  const isSpinOffReceivedType = watchedType === 'SPIN_OFF_IN';
 
   return (
     <>
@@ -362,6 +366,59 @@ const AssetActivityFields = ({ defaultAssetId }: AssetActivityFieldsProps) => {
             </FormItem>
           )}
         />
+      ) : isSpinOffParentType ? (
+        <FormField
+          control={control}
+          name="unitPrice"
+          render={({ field }) => (
+            <FormItem>
+              <FormLabel>Basis Allocated</FormLabel>
+              <FormControl>
+                <Input
+                  type="number"
+                  inputMode="decimal"
+                  placeholder="Ex. 0.2 to move 20% of the cost basis to the spun-off company"
+                  {...field}
+                />
+              </FormControl>
+              <FormMessage />
+            </FormItem>
+          )}
+        />
+      ) : isSpinOffReceivedType ? (
+        <div className="flex space-x-4">
+          <FormField
+            control={control}
+            name="quantity"
+            render={({ field }) => (
+              <FormItem>
+                <FormLabel>Shares Received</FormLabel>
+                <FormControl>
+                  <Input type="number" inputMode="decimal" placeholder="Shares" {...field} />
+                </FormControl>
+                <FormMessage />
+              </FormItem>
+            )}
+          />
+          <FormField
+            control={control}
+            name="unitPrice"
+            render={({ field }) => (
+              <FormItem>
+                <FormLabel>Cash in Lieu per Share</FormLabel>
+                <FormControl>
+                  <Input
+                    type="number"
+                    inputMode="decimal"
+                    placeholder="Price paid for the fractional share, if any"
+                    {...field}
+                  />
+                </FormControl>
+                <FormMessage />
+              </FormItem>
+            )}
+          />
+        </div>
       ) : (
         <div className="flex space-x-4">
           <FormField
@@ -2,7 +2,8 @@ import React, { useMemo } from 'react';
 
 import { debounce } from 'lodash';
 import { DataTableColumnHeader } from '@/components/ui/data-table/data-table-column-header';
-import { formatDate, formatAmount } from '@/lib/utils';
+import { formatDate, formatAmount, formatQuantity } from '@/lib/utils';
+import { useSettingsContext } from '@/lib/settings-provider';
 import { Badge } from '@/components/ui/badge';
 import { Account, ActivityDetails, ActivitySearchResponse } from '@/lib/types';
 import { ActivityOperations } from './activity-operations';
@@ -43,6 +44,9 @@ const activityTypeOptions = [
   { label: 'Fee', value: 'FEE' },
   { label: 'Tax', value: 'TAX' },
   { label: 'Interest', value: 'INTEREST' },
+  { label: 'Split', value: 'SPLIT' },
+  { label: 'Spin-off (Parent)', value: 'SPIN_OFF_OUT' },
+  { label: 'Spin-off (Received)', value: 'SPIN_OFF_IN' },
 ];
 
 export const ActivityTable = ({
@@ -57,6 +61,7 @@ export const ActivityTable = ({
   const [columnFilters, setColumnFilters] = React.useState<ColumnFiltersState>([]);
   const [globalFilter, setGlobalFilter] = React.useState('');
   const [sorting, setSorting] = React.useState<SortingState>([]);
+  const { settings } = useSettingsContext();
 
   const columns: ColumnDef<ActivityDetails>[] = useMemo(
     () => [
@@ -132,7 +137,11 @@ export const ActivityTable = ({
             title="Shares"
           />
         ),
-        cell: ({ row }) => <div className="pr-4 text-right">{row.getValue('quantity')}</div>,
+        cell: ({ row }) => (
+          <div className="pr-4 text-right">
+            {formatQuantity(row.getValue('quantity'), settings?.quantityPrecision)}
+          </div>
+        ),
       },
       {
         id: 'unitPrice',
@@ -149,7 +158,11 @@ export const ActivityTable = ({
         cell: ({ row }) => {
           const unitPrice = row.getValue('unitPrice') as number;
           const currency = (row.getValue('currency') as string) || 'USD';
-          return <div className="text-right ">{formatAmount(unitPrice, currency)}</div>;
+          return (
+            <div className="text-right ">
+              {formatAmount(unitPrice, currency, true, settings?.pricePrecision)}
+            </div>
+          );
         },
       },
       {
@@ -226,7 +239,7 @@ export const ActivityTable = ({
         },
       },
     ],
-    [handleEdit, handleDelete],
+    [handleEdit, handleDelete, settings],
   );
 
   const accountOptions =
@@ -2,14 +2,15 @@ import { AlertFeedback } from '@/components/alert-feedback';
 import { ApplicationHeader } from '@/components/header';
 import { Separator } from '@/components/ui/separator';
 import { toast } from '@/components/ui/use-toast';
-import React, { useState } from 'react';
+import React, { useCallback, useEffect, useState } from 'react';
 import { useNavigate } from 'react-router-dom';
-import type { ActivityImport } from '@/lib/types';
+import type { ActivityImport, ActivityImportPreview, ImportProgress } from '@/lib/types';
 import { ActivityImportForm } from './import-form';
 import ValidationAlert from './import-validation-alert';
 import ImportedActivitiesTable from './imported-activity-table';
 import { useMutation, useQueryClient } from '@tanstack/react-query';
-import { createActivities } from '@/commands/activity';
+import { listen } from '@tauri-apps/api/event';
+import { ImportFileRequest, importActivities } from '@/commands/activity';
 import { syncHistoryQuotes } from '@/commands/symbol';
 import { ImportHelpHoverCard } from './import-help';
 
@@ -17,17 +18,31 @@ const ActivityImportPage = () => {
   const navigate = useNavigate();
   const queryClient = useQueryClient();
   const [activities, setActivities] = useState<ActivityImport[]>([]);
+  const [total, setTotal] = useState<number>(0);
+  const [request, setRequest] = useState<ImportFileRequest | null>(null);
   const [error, setError] = useState<string | null>(null);
   const [success, setSuccess] = useState<boolean>(false);
   const [warning, setWarning] = useState<number>(0);
+  const [progress, setProgress] = useState<number>(0);
+
+  useEffect(() => {
+    const unlisten = listen<ImportProgress>('ACTIVITIES_IMPORT_PROGRESS', (event) => {
+      const { processed, total } = event.payload;
+      setProgress(total > 0 ? Math.round((processed / total) * 100) : 0);
+    });
+
+    return () => {
+      unlisten.then((unlistenProgress) => unlistenProgress());
+    };
+  }, []);
 
   const syncQuotesMutation = useMutation({
     mutationFn: syncHistoryQuotes,
   });
 
   const confirmImportMutation = useMutation({
-    mutationFn: createActivities,
-    onSuccess: () => {
+    mutationFn: importActivities,
+    onSuccess: (result) => {
       setError(null);
       setWarning(0);
       queryClient.invalidateQueries({ queryKey: ['activity-data'] });
@@ -36,6 +51,7 @@ const ActivityImportPage = () => {
       syncQuotesMutation.mutate();
       toast({
         title: 'Activities imported successfully',
+        description: `${result.imported} imported, ${result.updated} updated, ${result.skipped} skipped.`,
         className: 'bg-green-500 text-white border-none',
       });
       navigate('/activities');
@@ -52,35 +68,52 @@ const ActivityImportPage = () => {
 
   function cancelImport() {
     setActivities([]);
+    setTotal(0);
+    setRequest(null);
     setSuccess(false);
     setError(null);
     setWarning(0);
   }
 
-  function onImportSuccess(result: ActivityImport[]) {
-    setActivities(result);
+  function onImportSuccess(preview: ActivityImportPreview, importRequest: ImportFileRequest) {
+    // Duplicates and suspicious dates are skipped unless the user opts back in
+    setActivities(
+      preview.activities.map((activity) => ({
+        ...activity,
+        skip: !!activity.duplicate || !!activity.warning,
+      })),
+    );
+    setTotal(preview.total);
+    setRequest(importRequest);
     setSuccess(true);
-    const errors = result.filter((activity) => activity.error).length;
+    const errors = preview.activities.filter((activity) => activity.error).length;
     setWarning(errors);
   }
 
+  const toggleSkip = useCallback((id: string) => {
+    setActivities((current) =>
+      current.map((activity) =>
+        activity.id === id ? { ...activity, skip: !activity.skip } : activity,
+      ),
+    );
+  }, []);
+
   function confirmImport() {
-    //map activities to new activity
-    const newActivities = activities.map((activity) => ({
-      id: activity.id,
-      accountId: activity.accountId || '',
-      activityDate: new Date(activity.date),
-      currency: activity.currency,
-      fee: activity.fee,
-      isDraft: activity?.isDraft === 'true',
-      quantity: activity.quantity,
-      assetId: activity.symbol,
-      activityType: activity.activityType as any,
-      unitPrice: activity.unitPrice,
-      comment: activity.comment,
-    }));
-
-    confirmImportMutation.mutate(newActivities);
+    if (!request) {
+      return;
+    }
+    // The file is imported again by the backend, the choices made on the preview rows
+    // are sent by line number
+    const lineNumbers = (skip: boolean) =>
+      activities
+        .filter((activity) => !!activity.skip === skip && activity.lineNumber !== undefined)
+        .map((activity) => activity.lineNumber as number);
+
+    setProgress(0);
+    confirmImportMutation.mutate({
+      ...request,
+      selection: { skippedLines: lineNumbers(true), includedLines: lineNumbers(false) },
+    });
   }
 
   return (
@@ -103,16 +136,23 @@ const ActivityImportPage = () => {
             <div className="relative h-2 w-full min-w-[200px] rounded-full bg-gray-200">
               <div
                 className="absolute left-0 h-2 animate-pulse rounded-full bg-gray-800"
-                style={{ width: '40%' }}
+                style={{ width: `${progress}%` }}
               ></div>
             </div>
           ) : null}
+          {total > activities.length ? (
+            <p className="mb-4 text-sm text-muted-foreground">
+              Showing the first {activities.length} of {total} activities. The other rows are
+              imported unless they are duplicates or have a warning.
+            </p>
+          ) : null}
           {activities?.length > 0 ? (
             <ImportedActivitiesTable
               accounts={[]}
               activities={activities || []}
               editModalVisible={false}
               toggleEditModal={() => {}}
+              onToggleSkip={toggleSkip}
             />
           ) : (
             <ActivityImportForm onSuccess={onImportSuccess} onError={setError} />
@@ -26,19 +26,27 @@ import {
   SelectValue,
 } from '@/components/ui/select';
 
-import type { Account, ActivityImport } from '@/lib/types';
+import type { Account, ActivityImportPreview, ImportPreset } from '@/lib/types';
 import { getAccounts } from '@/commands/account';
 import { useMutation, useQuery } from '@tanstack/react-query';
-import { checkActivitiesImport } from '@/commands/activity';
+import { checkActivitiesImport, ImportFileRequest } from '@/commands/activity';
 
 const importFormSchema = z.object({
   account_id: z.string({ required_error: 'Please select an account.' }),
   file_path: z.string({ required_error: 'Please select a file.' }),
+  preset: z.string().optional(),
 });
+
+const importPresets: { label: string; value: ImportPreset | 'AUTO' }[] = [
+  { label: 'Auto-detect', value: 'AUTO' },
+  { label: 'Wealthfolio', value: 'WEALTHFOLIO' },
+  { label: 'Charles Schwab', value: 'SCHWAB' },
+  { label: 'DEGIRO', value: 'DEGIRO' },
+];
 type ImportFormInputs = z.infer<typeof importFormSchema>;
 
 type ActivityImportFormProps = {
-  onSuccess: (activities: ActivityImport[]) => void;
+  onSuccess: (preview: ActivityImportPreview, request: ImportFileRequest) => void;
   onError: (error: string) => void;
 };
 
@@ -93,8 +101,8 @@ export const ActivityImportForm = ({ onSuccess, onError }: ActivityImportFormPro
 
   const checkImportMutation = useMutation({
     mutationFn: checkActivitiesImport,
-    onSuccess: (data) => {
-      onSuccess(data);
+    onSuccess: (data, request) => {
+      onSuccess(data, request);
     },
     onError: (error: any) => {
       onError(error);
@@ -106,7 +114,12 @@ export const ActivityImportForm = ({ onSuccess, onError }: ActivityImportFormPro
   });
 
   const openFilePicker = async () => {
-    let filepath = await open({ filters: [{ name: 'CSV', extensions: ['csv'] }] });
+    let filepath = await open({
+      filters: [
+        { name: 'CSV', extensions: ['csv'] },
+        { name: 'OFX/QFX', extensions: ['ofx', 'qfx'] },
+      ],
+    });
     form.setValue('file_path', filepath as string);
   };
 
@@ -114,6 +127,7 @@ export const ActivityImportForm = ({ onSuccess, onError }: ActivityImportFormPro
     await checkImportMutation.mutateAsync({
       account_id: data.account_id,
       file_path: data.file_path,
+      preset: data.preset && data.preset !== 'AUTO' ? (data.preset as ImportPreset) : undefined,
     });
   }
 
@@ -156,6 +170,34 @@ export const ActivityImportForm = ({ onSuccess, onError }: ActivityImportFormPro
             </FormItem>
           )}
         />
+        <FormField
+          control={form.control}
+          name="preset"
+          render={({ field }) => (
+            <FormItem className={isLoading ? 'pointer-events-none opacity-50' : ''}>
+              <FormLabel>Format</FormLabel>
+              <FormControl>
+                <Select
+                  disabled={checkImportMutation.isPending}
+                  onValueChange={field.onChange}
+                  defaultValue={field.value ?? 'AUTO'}
+                >
+                  <SelectTrigger>
+                    <SelectValue placeholder="Select a format" />
+                  </SelectTrigger>
+                  <SelectContent>
+                    {importPresets.map((preset) => (
+                      <SelectItem value={preset.value} key={preset.value}>
+                        {preset.label}
+                      </SelectItem>
+                    ))}
+                  </SelectContent>
+                </Select>
+              </FormControl>
+              <FormMessage />
+            </FormItem>
+          )}
+        />
         <FormField
           control={form.control}
           name="file_path"
@@ -190,7 +232,7 @@ export const ActivityImportForm = ({ onSuccess, onError }: ActivityImportFormPro
                     <>
                       <EmptyPlaceholder.Icon name="Upload" />
                       <EmptyPlaceholder.Title>
-                        Drag and drop your CSV file here
+                        Drag and drop your CSV or OFX file here
                       </EmptyPlaceholder.Title>
                       <EmptyPlaceholder.Description>
                         Or click the button below to choose a file.
@@ -23,6 +23,18 @@ export function ImportHelpHoverCard() {
             Columns should include Date, Symbol, Quantity, Activity Type, Unit Price, Currency, and
             Fee.
           </li>
+          <li>
+            The symbol can also be an ISIN or a CUSIP, it is matched to a listed ticker during the
+            import.
+          </li>
+          <li>
+            Bond prices are a percentage of face value. Add an optional accruedInterest column to
+            record the accrued interest paid or received on a bond trade.
+          </li>
+          <li>
+            OFX and QFX files downloaded from your bank or broker can be imported as they are,
+            securities are matched by the ticker listed in the file.
+          </li>
           <li>Click the 'Import' button and select your CSV file.</li>
           <li>Review the imported activities before confirming.</li>
         </ul>
@@ -41,6 +53,12 @@ export function ImportHelpHoverCard() {
             <li>CONVERSION_OUT</li>
             <li>FEE</li>
             <li>TAX</li>
+            <li>SPLIT (quantity is the split multiple, e.g. 4 for a 4:1 split)</li>
+            <li>SPIN_OFF_OUT (parent symbol, unitPrice is the fraction of basis allocated)</li>
+            <li>
+              SPIN_OFF_IN (spun-off symbol, same date, quantity is the shares received, unitPrice
+              is the cash in lieu per share of the fractional share, if any)
+            </li>
           </ul>
         </pre>
         <p className="mt-2 font-semibold">Example CSV format:</p>
@@ -4,19 +4,29 @@ import { useMemo } from 'react';
 import { Icons } from '@/components/icons';
 import { Badge } from '@/components/ui/badge';
 import { DataTableColumnHeader } from '@/components/ui/data-table/data-table-column-header';
+import { Switch } from '@/components/ui/switch';
 import { Tooltip, TooltipContent, TooltipProvider, TooltipTrigger } from '@/components/ui/tooltip';
 import type { Account, ActivityImport } from '@/lib/types';
-import { formatAmount, formatDate, toPascalCase } from '@/lib/utils';
+import { formatAmount, formatDate, formatQuantity, toPascalCase } from '@/lib/utils';
+import { useSettingsContext } from '@/lib/settings-provider';
 import type { ColumnDef, SortingState } from '@tanstack/react-table';
 
 export const ImportedActivitiesTable = ({
   activities,
+  onToggleSkip,
 }: {
   accounts: Account[];
   activities: ActivityImport[];
   editModalVisible: boolean;
   toggleEditModal: () => void;
+  onToggleSkip: (id: string) => void;
 }) => {
+  const { settings } = useSettingsContext();
+  const columns = useMemo(
+    () => getColumns(onToggleSkip, settings?.quantityPrecision, settings?.pricePrecision),
+    [onToggleSkip, settings],
+  );
+
   const activitiesType = useMemo(() => {
     const uniqueTypesSet = new Set();
     return activities.reduce(
@@ -47,6 +57,14 @@ export const ImportedActivitiesTable = ({
       title: 'Type',
       options: activitiesType,
     },
+    {
+      id: 'duplicate',
+      title: 'Duplicate',
+      options: [
+        { label: 'Exact', value: 'EXACT' },
+        { label: 'Possible', value: 'POSSIBLE' },
+      ],
+    },
   ];
 
   const defaultSorting: SortingState = [
@@ -69,6 +87,7 @@ export const ImportedActivitiesTable = ({
           accountName: false,
           lineNumber: false,
           error: false,
+          warning: false,
         }}
       />
     </div>
@@ -77,7 +96,11 @@ export const ImportedActivitiesTable = ({
 
 export default ImportedActivitiesTable;
 
-export const columns: ColumnDef<ActivityImport>[] = [
+const getColumns = (
+  onToggleSkip: (id: string) => void,
+  quantityPrecision?: number,
+  pricePrecision?: number,
+): ColumnDef<ActivityImport>[] => [
   {
     id: 'isValid',
     accessorKey: 'isValid',
@@ -85,11 +108,23 @@ export const columns: ColumnDef<ActivityImport>[] = [
     cell: ({ row }) => {
       const isValid = row.getValue('isValid') as string;
       const error = row.getValue('error') as string;
+      const warning = row.getValue('warning') as string | undefined;
       const lineNumber = row.getValue('lineNumber') as number;
 
       return (
         <div className="flex items-center">
-          {isValid === 'true' ? (
+          {isValid === 'true' && warning ? (
+            <TooltipProvider>
+              <Tooltip>
+                <TooltipTrigger asChild>
+                  <Icons.AlertTriangle className="h-4 w-4 cursor-help text-yellow-500" />
+                </TooltipTrigger>
+                <TooltipContent>
+                  <p className="text-yellow-600">{warning}</p>
+                </TooltipContent>
+              </Tooltip>
+            </TooltipProvider>
+          ) : isValid === 'true' ? (
             <Icons.CheckCircle className="h-4 w-4 text-green-500" />
           ) : (
             <TooltipProvider>
@@ -156,6 +191,10 @@ export const columns: ColumnDef<ActivityImport>[] = [
     id: 'error',
     accessorKey: 'error',
   },
+  {
+    id: 'warning',
+    accessorKey: 'warning',
+  },
   {
     id: 'symbol',
     accessorKey: 'symbol',
@@ -185,7 +224,11 @@ export const columns: ColumnDef<ActivityImport>[] = [
     header: ({ column }) => (
       <DataTableColumnHeader className="justify-end text-right" column={column} title="Quantity" />
     ),
-    cell: ({ row }) => <div className="text-right">{row.getValue('quantity')}</div>,
+    cell: ({ row }) => (
+      <div className="text-right">
+        {formatQuantity(row.getValue('quantity'), quantityPrecision)}
+      </div>
+    ),
   },
   {
     id: 'unitPrice',
@@ -202,7 +245,9 @@ export const columns: ColumnDef<ActivityImport>[] = [
     cell: ({ row }) => {
       const unitPrice = row.getValue('unitPrice') as number;
       const currency = (row.getValue('currency') as string) || 'USD';
-      return <div className="text-right">{formatAmount(unitPrice, currency)}</div>;
+      return (
+        <div className="text-right">{formatAmount(unitPrice, currency, true, pricePrecision)}</div>
+      );
     },
   },
   {
@@ -252,10 +297,38 @@ export const columns: ColumnDef<ActivityImport>[] = [
       );
     },
   },
+  {
+    id: 'duplicate',
+    accessorKey: 'duplicate',
+    header: ({ column }) => <DataTableColumnHeader column={column} title="Duplicate" />,
+    cell: ({ row }) => {
+      const duplicate = row.getValue('duplicate') as ActivityImport['duplicate'];
+      if (!duplicate) {
+        return null;
+      }
+
+      return (
+        <Badge variant={duplicate === 'EXACT' ? 'error' : 'secondary'}>
+          {duplicate === 'EXACT' ? 'Duplicate' : 'Possible duplicate'}
+        </Badge>
+      );
+    },
+    filterFn: (row, id, value: string) => {
+      return value.includes(row.getValue(id));
+    },
+  },
   {
     id: 'actions',
-    cell: () => {
-      return <div>...</div>;
+    header: ({ column }) => <DataTableColumnHeader column={column} title="Import" />,
+    cell: ({ row }) => {
+      const id = row.original.id;
+      return (
+        <Switch
+          checked={!row.original.skip}
+          onCheckedChange={() => id && onToggleSkip(id)}
+          aria-label="Import this activity"
+        />
+      );
     },
   },
 ];
@@ -29,7 +29,7 @@ function DashboardSkeleton() {
 export default function DashboardPage() {
   const { data: historyData, isLoading } = useQuery<FinancialHistory[], Error>({
     queryKey: ['portfolio_history'],
-    queryFn: getHistorical,
+    queryFn: () => getHistorical(),
   });
 
   if (isLoading) {
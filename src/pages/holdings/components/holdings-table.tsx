@@ -3,7 +3,9 @@ import { Badge } from '@/components/ui/badge';
 import { Button } from '@/components/ui/button';
 import { DataTable } from '@/components/ui/data-table';
 import { DataTableColumnHeader } from '@/components/ui/data-table/data-table-column-header';
-import { formatAmount, formatPercent } from '@/lib/utils';
+import { Tooltip, TooltipContent, TooltipProvider, TooltipTrigger } from '@/components/ui/tooltip';
+import { formatAmount, formatDate, formatPercent, formatQuantity } from '@/lib/utils';
+import { useSettingsContext } from '@/lib/settings-provider';
 import type { ColumnDef } from '@tanstack/react-table';
 import { useNavigate } from 'react-router-dom';
 
@@ -17,6 +19,8 @@ export const HoldingsTable = ({
   holdings: Holding[];
   isLoading: boolean;
 }) => {
+  const { settings } = useSettingsContext();
+
   if (isLoading) {
     return (
       <div className="space-y-4 pt-6">
@@ -53,7 +57,7 @@ export const HoldingsTable = ({
     <div className="pt-6">
       <DataTable
         data={holdings}
-        columns={columns}
+        columns={getColumns(settings?.quantityPrecision, settings?.pricePrecision)}
         searchBy="symbol"
         filters={filters}
         defaultColumnVisibility={{ currency: false, symbolName: false }}
@@ -65,7 +69,10 @@ export const HoldingsTable = ({
 
 export default HoldingsTable;
 
-export const columns: ColumnDef<Holding>[] = [
+const getColumns = (
+  quantityPrecision?: number,
+  pricePrecision?: number,
+): ColumnDef<Holding>[] => [
   {
     id: 'symbol',
     accessorKey: 'symbol',
@@ -114,7 +121,11 @@ export const columns: ColumnDef<Holding>[] = [
     header: ({ column }) => (
       <DataTableColumnHeader className="justify-end text-right" column={column} title="Quantity" />
     ),
-    cell: ({ row }) => <div className="text-right">{row.getValue('quantity')}</div>,
+    cell: ({ row }) => (
+      <div className="text-right">
+        {formatQuantity(row.getValue('quantity'), quantityPrecision)}
+      </div>
+    ),
   },
   {
     id: 'marketValue',
@@ -129,9 +140,28 @@ export const columns: ColumnDef<Holding>[] = [
       const isLoading = performance?.isLoading || false;
       const marketValue = row.getValue('marketValue') as number;
       const currency = row.getValue('currency') as string;
+      const { fxError, fxDerived } = row.original;
 
       return (
-        <div className="items-end pr-4 text-right font-semibold">
+        <div className="flex items-center justify-end gap-1 pr-4 text-right font-semibold">
+          {fxError || fxDerived ? (
+            <TooltipProvider>
+              <Tooltip>
+                <TooltipTrigger asChild>
+                  <Icons.AlertTriangle
+                    className={`h-4 w-4 cursor-help ${
+                      fxError ? 'text-yellow-500' : 'text-muted-foreground'
+                    }`}
+                  />
+                </TooltipTrigger>
+                <TooltipContent>
+                  <p className={fxError ? 'text-yellow-600' : undefined}>
+                    {fxError ?? 'Converted at an exchange rate derived through another currency'}
+                  </p>
+                </TooltipContent>
+              </Tooltip>
+            </TooltipProvider>
+          ) : null}
           {isLoading ? (
             <Icons.Spinner className="ml-auto h-4 w-4 animate-spin" />
           ) : (
@@ -156,7 +186,26 @@ export const columns: ColumnDef<Holding>[] = [
     cell: ({ row }) => {
       const marketPrice = row.getValue('marketPrice') as number;
       const currency = row.getValue('currency') as string;
-      return <div className="text-right">{formatAmount(marketPrice, currency)}</div>;
+      const { quoteDate, isQuoteStale } = row.original;
+      return (
+        <div className="flex items-center justify-end gap-1">
+          {isQuoteStale ? (
+            <TooltipProvider>
+              <Tooltip>
+                <TooltipTrigger asChild>
+                  <Icons.AlertTriangle className="h-4 w-4 cursor-help text-yellow-500" />
+                </TooltipTrigger>
+                <TooltipContent>
+                  <p className="text-yellow-600">
+                    {quoteDate ? `Stale quote from ${formatDate(quoteDate)}` : 'No quote'}
+                  </p>
+                </TooltipContent>
+              </Tooltip>
+            </TooltipProvider>
+          ) : null}
+          <span>{formatAmount(marketPrice, currency, true, pricePrecision)}</span>
+        </div>
+      );
     },
   },
   {
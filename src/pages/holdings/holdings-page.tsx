@@ -19,12 +19,12 @@ export const HoldingsPage = () => {
   const { settings } = useSettingsContext();
   const { data, isLoading } = useQuery<Holding[], Error>({
     queryKey: ['holdings'],
-    queryFn: computeHoldings,
+    queryFn: () => computeHoldings(),
   });
 
   const { data: historyData } = useQuery<FinancialHistory[], Error>({
     queryKey: ['portfolio_history'],
-    queryFn: getHistorical,
+    queryFn: () => getHistorical(),
   });
 
   const portfolio = historyData?.find((history) => history.account?.id === 'TOTAL');
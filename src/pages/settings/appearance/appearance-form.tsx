@@ -2,7 +2,12 @@ import { zodResolver } from '@hookform/resolvers/zod';
 import { useForm } from 'react-hook-form';
 import * as z from 'zod';
 
-import { cn } from '@/lib/utils';
+import {
+  cn,
+  DEFAULT_ACTIVITY_UNDO_DAYS,
+  DEFAULT_PRICE_PRECISION,
+  DEFAULT_QUANTITY_PRECISION,
+} from '@/lib/utils';
 import { useSettingsContext } from '@/lib/settings-provider';
 import { Button, buttonVariants } from '@/components/ui/button';
 import {
@@ -44,6 +49,10 @@ export function AppearanceForm() {
     const updatedSettings = {
       id: settings?.id || 1,
       baseCurrency: settings?.baseCurrency || 'USD',
+      quantityPrecision: settings?.quantityPrecision ?? DEFAULT_QUANTITY_PRECISION,
+      pricePrecision: settings?.pricePrecision ?? DEFAULT_PRICE_PRECISION,
+      readOnly: settings?.readOnly ?? false,
+      activityUndoDays: settings?.activityUndoDays ?? DEFAULT_ACTIVITY_UNDO_DAYS,
       ...data,
     };
     updateSettings(updatedSettings);
@@ -2,7 +2,12 @@ import { zodResolver } from '@hookform/resolvers/zod';
 import { useForm } from 'react-hook-form';
 import * as z from 'zod';
 
-import { cn } from '@/lib/utils';
+import {
+  cn,
+  DEFAULT_ACTIVITY_UNDO_DAYS,
+  DEFAULT_PRICE_PRECISION,
+  DEFAULT_QUANTITY_PRECISION,
+} from '@/lib/utils';
 import { Button } from '@/components/ui/button';
 import {
   Form,
@@ -14,6 +19,7 @@ import {
   FormMessage,
 } from '@/components/ui/form';
 import { Icons } from '@/components/icons';
+import { Input } from '@/components/ui/input';
 import { Popover, PopoverContent, PopoverTrigger } from '@/components/ui/popover';
 
 import { worldCurrencies } from '@/lib/currencies';
@@ -28,6 +34,21 @@ import { useSettingsContext } from '@/lib/settings-provider';
 
 const appearanceFormSchema = z.object({
   baseCurrency: z.string({ required_error: 'Please select a base currency.' }),
+  quantityPrecision: z.coerce
+    .number()
+    .int()
+    .min(0, { message: 'Precision must be between 0 and 20.' })
+    .max(20, { message: 'Precision must be between 0 and 20.' }),
+  pricePrecision: z.coerce
+    .number()
+    .int()
+    .min(0, { message: 'Precision must be between 0 and 20.' })
+    .max(20, { message: 'Precision must be between 0 and 20.' }),
+  activityUndoDays: z.coerce
+    .number()
+    .int()
+    .min(0, { message: 'Undo window must be between 0 and 365 days.' })
+    .max(365, { message: 'Undo window must be between 0 and 365 days.' }),
 });
 
 type GeneralSettingFormValues = z.infer<typeof appearanceFormSchema>;
@@ -36,6 +57,9 @@ export function GeneralSettingForm() {
   const { settings, updateSettings } = useSettingsContext();
   const defaultValues: Partial<GeneralSettingFormValues> = {
     baseCurrency: settings?.baseCurrency || 'USD',
+    quantityPrecision: settings?.quantityPrecision ?? DEFAULT_QUANTITY_PRECISION,
+    pricePrecision: settings?.pricePrecision ?? DEFAULT_PRICE_PRECISION,
+    activityUndoDays: settings?.activityUndoDays ?? DEFAULT_ACTIVITY_UNDO_DAYS,
   };
   const form = useForm<GeneralSettingFormValues>({
     resolver: zodResolver(appearanceFormSchema),
@@ -47,6 +71,7 @@ export function GeneralSettingForm() {
       id: settings?.id || 1,
       theme: settings?.theme || 'light',
       font: settings?.font || 'font-mono',
+      readOnly: settings?.readOnly ?? false,
       ...data,
     };
     updateSettings(updatedSettings);
@@ -108,6 +133,56 @@ export function GeneralSettingForm() {
           )}
         />
 
+        <FormField
+          control={form.control}
+          name="quantityPrecision"
+          render={({ field }) => (
+            <FormItem className="flex flex-col">
+              <FormLabel>Quantity decimals</FormLabel>
+              <FormControl className="w-[300px]">
+                <Input type="number" min={0} max={20} {...field} />
+              </FormControl>
+              <FormDescription>
+                Decimal places shown for share and coin quantities. Quantities are always stored in
+                full.
+              </FormDescription>
+              <FormMessage />
+            </FormItem>
+          )}
+        />
+
+        <FormField
+          control={form.control}
+          name="pricePrecision"
+          render={({ field }) => (
+            <FormItem className="flex flex-col">
+              <FormLabel>Price decimals</FormLabel>
+              <FormControl className="w-[300px]">
+                <Input type="number" min={0} max={20} {...field} />
+              </FormControl>
+              <FormDescription>Decimal places shown for unit and market prices.</FormDescription>
+              <FormMessage />
+            </FormItem>
+          )}
+        />
+
+        <FormField
+          control={form.control}
+          name="activityUndoDays"
+          render={({ field }) => (
+            <FormItem className="flex flex-col">
+              <FormLabel>Undo window for deleted activities</FormLabel>
+              <FormControl className="w-[300px]">
+                <Input type="number" min={0} max={365} {...field} />
+              </FormControl>
+              <FormDescription>
+                Days a deleted activity can be restored before it is removed for good.
+              </FormDescription>
+              <FormMessage />
+            </FormItem>
+          )}
+        />
+
         <Button type="submit">Save</Button>
       </form>
     </Form>
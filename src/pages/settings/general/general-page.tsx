@@ -1,5 +1,6 @@
 import { Separator } from '@/components/ui/separator';
 import { GeneralSettingForm } from './general-form';
+import { ReadOnlySetting } from './read-only-setting';
 import { SettingsHeader } from '../header';
 
 export default function GeneralSettingsPage() {
@@ -11,6 +12,8 @@ export default function GeneralSettingsPage() {
       />
       <Separator />
       <GeneralSettingForm />
+      <Separator />
+      <ReadOnlySetting />
     </div>
   );
 }
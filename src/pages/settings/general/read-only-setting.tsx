@@ -0,0 +1,48 @@
+import { useMutation, useQueryClient } from '@tanstack/react-query';
+
+import { Label } from '@/components/ui/label';
+import { Switch } from '@/components/ui/switch';
+import { toast } from '@/components/ui/use-toast';
+import { updateReadOnly } from '@/commands/settings';
+import { useSettingsContext } from '@/lib/settings-provider';
+
+export function ReadOnlySetting() {
+  const queryClient = useQueryClient();
+  const { settings } = useSettingsContext();
+
+  const readOnlyMutation = useMutation({
+    mutationFn: updateReadOnly,
+    onSuccess: (updatedSettings) => {
+      queryClient.invalidateQueries({ queryKey: ['settings'] });
+      toast({
+        title: updatedSettings.readOnly ? 'Read-only mode enabled.' : 'Read-only mode disabled.',
+        className: 'bg-green-500 text-white border-none',
+      });
+    },
+    onError: () => {
+      toast({
+        title: 'Uh oh! Something went wrong.',
+        description: 'There was a problem updating the read-only mode.',
+        className: 'bg-red-500 text-white border-none',
+      });
+    },
+  });
+
+  return (
+    <div className="flex items-center justify-between space-x-4">
+      <div className="space-y-1">
+        <Label htmlFor="read-only">Read-only mode</Label>
+        <p className="text-sm text-muted-foreground">
+          Block every change to accounts, activities, goals and settings, for demos and
+          screenshots.
+        </p>
+      </div>
+      <Switch
+        id="read-only"
+        checked={settings?.readOnly ?? false}
+        disabled={readOnlyMutation.isPending}
+        onCheckedChange={(checked) => readOnlyMutation.mutate(checked)}
+      />
+    </div>
+  );
+}
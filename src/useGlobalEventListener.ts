@@ -22,13 +22,29 @@ const useGlobalEventListener = () => {
         title: 'Quotes synced successfully',
       });
     };
+
+    const handleActivitiesChanged = () => {
+      queryClient.invalidateQueries({ queryKey: ['activity-data'] });
+      queryClient.invalidateQueries({ queryKey: ['holdings'] });
+      queryClient.invalidateQueries({ queryKey: ['portfolio_history'] });
+    };
+
+    // Every cached query belongs to the previous profile
+    const handleProfileChanged = () => {
+      queryClient.invalidateQueries();
+    };
+
     const setupListeners = async () => {
       const unlistenSyncStart = await listen('QUOTES_SYNC_START', handleQuoteSyncStart);
       const unlistenSyncComplete = await listen('QUOTES_SYNC_COMPLETE', handleQuotesSyncComplete);
+      const unlistenActivitiesChanged = await listen('ACTIVITIES_CHANGED', handleActivitiesChanged);
+      const unlistenProfileChanged = await listen('PROFILE_CHANGED', handleProfileChanged);
 
       return () => {
         unlistenSyncStart();
         unlistenSyncComplete();
+        unlistenActivitiesChanged();
+        unlistenProfileChanged();
       };
     };
 